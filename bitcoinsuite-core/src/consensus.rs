@@ -0,0 +1,312 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+//! Module for [`UpgradeEpoch`] and [`ConsensusParams`], describing which
+//! consensus and policy rules are in effect at each network upgrade.
+
+/// Number of satoshis in one BCH.
+pub const COIN: u64 = 100_000_000;
+
+/// Maximum amount of satoshis any output or tx can carry, 21 million BCH.
+pub const MAX_MONEY: u64 = 21_000_000 * COIN;
+
+/// Network a chain of blocks belongs to; upgrades activate at different times
+/// on each of them.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Net {
+    /// Bitcoin Cash mainnet.
+    Mainnet,
+    /// Testnet4, follows the activation schedule of mainnet.
+    Testnet4,
+    /// Chipnet, activates every upgrade 6 months ahead of mainnet.
+    Chipnet,
+    /// Regtest, every upgrade is active from the genesis block.
+    Regtest,
+}
+
+/// A network upgrade of Bitcoin Cash, ordered by activation.
+///
+/// Every epoch includes the rules of all the epochs before it:
+/// ```
+/// # use bitcoinsuite_core::consensus::UpgradeEpoch;
+/// assert!(UpgradeEpoch::Legacy < UpgradeEpoch::Upgrade9);
+/// assert!(UpgradeEpoch::Upgrade11 < UpgradeEpoch::Upgrade12);
+/// assert_eq!(UpgradeEpoch::LATEST, UpgradeEpoch::Upgrade12);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum UpgradeEpoch {
+    /// Everything before May 2022. Only the rules of the last legacy era
+    /// (November 2018 to May 2022) are modeled.
+    Legacy,
+    /// May 2022: native introspection and 64-bit script integers.
+    Upgrade8,
+    /// May 2023: CashTokens, P2SH32 and a 65 byte minimum tx size.
+    Upgrade9,
+    /// May 2024: adaptive blocksize limit, no tx or script rule changes.
+    Upgrade10,
+    /// May 2025: VM limits (operation cost accounting and 10,000 byte stack
+    /// elements) and arbitrary-precision script integers (BigInt).
+    Upgrade11,
+    /// May 2026: loops, functions, bitwise operations, Pay-to-Script (P2S)
+    /// and 128 byte token commitments.
+    Upgrade12,
+}
+
+/// Consensus and policy parameters in effect at an [`UpgradeEpoch`].
+///
+/// ```
+/// # use bitcoinsuite_core::consensus::{ConsensusParams, UpgradeEpoch};
+/// let params = ConsensusParams::for_epoch(UpgradeEpoch::Upgrade9);
+/// assert!(params.cashtokens);
+/// assert_eq!(params.max_token_commitment_length, 40);
+/// assert_eq!(params.min_tx_size, 65);
+///
+/// let params = ConsensusParams::for_epoch(UpgradeEpoch::Upgrade8);
+/// assert!(!params.cashtokens);
+/// assert_eq!(params.min_tx_size, 100);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ConsensusParams {
+    /// Epoch these parameters belong to.
+    pub epoch: UpgradeEpoch,
+    /// Whether native introspection opcodes are enabled.
+    pub native_introspection: bool,
+    /// Whether outputs can carry CashTokens, i.e. whether locking bytecode
+    /// starting with `PREFIX_TOKEN` (`0xef`) encodes a token prefix.
+    pub cashtokens: bool,
+    /// Whether 32-byte pay-to-script-hash outputs (P2SH32) are enabled.
+    pub p2sh32: bool,
+    /// Whether the VM uses density-based operation cost and hash iteration
+    /// limits instead of the legacy opcode and sigop limits.
+    pub vm_limits: bool,
+    /// Whether script numbers have arbitrary precision (up to
+    /// [`ConsensusParams::max_script_num_length`] bytes).
+    pub bigint: bool,
+    /// Whether `OP_BEGIN`/`OP_UNTIL` loops are enabled.
+    pub loops: bool,
+    /// Whether `OP_DEFINE`/`OP_INVOKE` functions are enabled.
+    pub functions: bool,
+    /// Whether the re-enabled and new bitwise and shift opcodes are enabled.
+    pub bitwise: bool,
+    /// Whether raw (non-template) locking bytecode is standard (P2S).
+    pub p2s: bool,
+    /// Maximum length of an NFT commitment, in bytes.
+    pub max_token_commitment_length: usize,
+    /// Minimum serialized size of a tx, in bytes.
+    pub min_tx_size: usize,
+    /// Maximum serialized size of a tx, in bytes.
+    pub max_tx_size: usize,
+    /// Maximum serialized size of a standard tx, in bytes.
+    pub max_standard_tx_size: usize,
+    /// Maximum size of an element on the VM stack, in bytes.
+    pub max_script_element_size: usize,
+    /// Maximum length of a number in the VM, in bytes.
+    pub max_script_num_length: usize,
+    /// Maximum size of a standard unlocking script (scriptSig), in bytes.
+    pub max_standard_scriptsig_size: usize,
+    /// Maximum size of standard raw locking bytecode, if P2S is enabled.
+    pub max_standard_p2s_size: usize,
+}
+
+impl Net {
+    /// Median-time-past at which the given upgrade activates on this network.
+    ///
+    /// Returns [`None`] for [`UpgradeEpoch::Legacy`], which is active from
+    /// genesis.
+    /// ```
+    /// # use bitcoinsuite_core::consensus::{Net, UpgradeEpoch};
+    /// assert_eq!(
+    ///     Net::Mainnet.activation_time(UpgradeEpoch::Upgrade9),
+    ///     Some(1684152000),
+    /// );
+    /// assert_eq!(
+    ///     Net::Chipnet.activation_time(UpgradeEpoch::Upgrade9),
+    ///     Some(1668513600),
+    /// );
+    /// assert_eq!(Net::Regtest.activation_time(UpgradeEpoch::Upgrade12), Some(0));
+    /// assert_eq!(Net::Mainnet.activation_time(UpgradeEpoch::Legacy), None);
+    /// ```
+    pub fn activation_time(self, epoch: UpgradeEpoch) -> Option<i64> {
+        use self::UpgradeEpoch::*;
+        let mainnet_time = match epoch {
+            Legacy => return None,
+            Upgrade8 => 1_652_616_000,
+            Upgrade9 => 1_684_152_000,
+            Upgrade10 => 1_715_774_400,
+            Upgrade11 => 1_747_310_400,
+            Upgrade12 => 1_778_846_400,
+        };
+        Some(match self {
+            Net::Mainnet | Net::Testnet4 => mainnet_time,
+            // Chipnet was started after Upgrade8 activated on mainnet
+            Net::Chipnet if epoch == Upgrade8 => 0,
+            Net::Chipnet => match epoch {
+                Upgrade9 => 1_668_513_600,
+                Upgrade10 => 1_700_049_600,
+                Upgrade11 => 1_731_672_000,
+                _ => 1_763_208_000,
+            },
+            Net::Regtest => 0,
+        })
+    }
+}
+
+impl UpgradeEpoch {
+    /// The most recent upgrade known to this crate.
+    pub const LATEST: UpgradeEpoch = UpgradeEpoch::Upgrade12;
+
+    /// All upgrades, in order of activation.
+    pub const ALL: [UpgradeEpoch; 6] = [
+        UpgradeEpoch::Legacy,
+        UpgradeEpoch::Upgrade8,
+        UpgradeEpoch::Upgrade9,
+        UpgradeEpoch::Upgrade10,
+        UpgradeEpoch::Upgrade11,
+        UpgradeEpoch::Upgrade12,
+    ];
+
+    /// Epoch in effect for a block whose parent has the given median-time-past
+    /// on the given network.
+    /// ```
+    /// # use bitcoinsuite_core::consensus::{Net, UpgradeEpoch};
+    /// assert_eq!(
+    ///     UpgradeEpoch::at_median_time_past(Net::Mainnet, 1684151999),
+    ///     UpgradeEpoch::Upgrade8,
+    /// );
+    /// assert_eq!(
+    ///     UpgradeEpoch::at_median_time_past(Net::Mainnet, 1684152000),
+    ///     UpgradeEpoch::Upgrade9,
+    /// );
+    /// assert_eq!(
+    ///     UpgradeEpoch::at_median_time_past(Net::Chipnet, 1700049600),
+    ///     UpgradeEpoch::Upgrade10,
+    /// );
+    /// ```
+    pub fn at_median_time_past(net: Net, median_time_past: i64) -> UpgradeEpoch {
+        UpgradeEpoch::ALL
+            .into_iter()
+            .rev()
+            .find(|&epoch| match net.activation_time(epoch) {
+                Some(activation_time) => median_time_past >= activation_time,
+                None => true,
+            })
+            .unwrap_or(UpgradeEpoch::Legacy)
+    }
+
+    /// [`ConsensusParams`] in effect during this epoch.
+    pub fn params(self) -> ConsensusParams {
+        ConsensusParams::for_epoch(self)
+    }
+}
+
+impl ConsensusParams {
+    /// Parameters in effect during the given [`UpgradeEpoch`].
+    pub fn for_epoch(epoch: UpgradeEpoch) -> ConsensusParams {
+        use self::UpgradeEpoch::*;
+        ConsensusParams {
+            epoch,
+            native_introspection: epoch >= Upgrade8,
+            cashtokens: epoch >= Upgrade9,
+            p2sh32: epoch >= Upgrade9,
+            vm_limits: epoch >= Upgrade11,
+            bigint: epoch >= Upgrade11,
+            loops: epoch >= Upgrade12,
+            functions: epoch >= Upgrade12,
+            bitwise: epoch >= Upgrade12,
+            p2s: epoch >= Upgrade12,
+            max_token_commitment_length: match epoch {
+                Legacy | Upgrade8 => 0,
+                Upgrade9 | Upgrade10 | Upgrade11 => 40,
+                Upgrade12 => 128,
+            },
+            min_tx_size: if epoch >= Upgrade9 { 65 } else { 100 },
+            max_tx_size: 1_000_000,
+            max_standard_tx_size: 100_000,
+            max_script_element_size: if epoch >= Upgrade11 { 10_000 } else { 520 },
+            max_script_num_length: match epoch {
+                Legacy => 4,
+                Upgrade8 | Upgrade9 | Upgrade10 => 8,
+                Upgrade11 | Upgrade12 => 10_000,
+            },
+            max_standard_scriptsig_size: if epoch >= Upgrade12 { 10_000 } else { 1_650 },
+            max_standard_p2s_size: if epoch >= Upgrade12 { 201 } else { 0 },
+        }
+    }
+
+    /// Parameters of the most recent upgrade, i.e. today's rules.
+    pub fn latest() -> ConsensusParams {
+        ConsensusParams::for_epoch(UpgradeEpoch::LATEST)
+    }
+
+    /// Parameters in effect for a block whose parent has the given
+    /// median-time-past on the given network.
+    pub fn at_median_time_past(net: Net, median_time_past: i64) -> ConsensusParams {
+        ConsensusParams::for_epoch(UpgradeEpoch::at_median_time_past(
+            net,
+            median_time_past,
+        ))
+    }
+}
+
+impl Default for ConsensusParams {
+    fn default() -> Self {
+        ConsensusParams::latest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::{ConsensusParams, Net, UpgradeEpoch};
+
+    #[test]
+    fn test_epoch_at_median_time_past() {
+        for net in [Net::Mainnet, Net::Testnet4, Net::Chipnet, Net::Regtest] {
+            for epoch in UpgradeEpoch::ALL.into_iter().skip(1) {
+                // Several epochs can activate at the same time, e.g. on regtest
+                let time = net.activation_time(epoch).unwrap();
+                assert!(UpgradeEpoch::at_median_time_past(net, time) >= epoch);
+                if time > 0 {
+                    assert!(UpgradeEpoch::at_median_time_past(net, time - 1) < epoch);
+                }
+            }
+        }
+        assert_eq!(
+            UpgradeEpoch::at_median_time_past(Net::Mainnet, 0),
+            UpgradeEpoch::Legacy,
+        );
+        assert_eq!(
+            UpgradeEpoch::at_median_time_past(Net::Chipnet, 0),
+            UpgradeEpoch::Upgrade8,
+        );
+        assert_eq!(
+            UpgradeEpoch::at_median_time_past(Net::Regtest, 0),
+            UpgradeEpoch::LATEST,
+        );
+    }
+
+    #[test]
+    fn test_params_per_epoch() {
+        let legacy = UpgradeEpoch::Legacy.params();
+        assert!(!legacy.native_introspection);
+        assert_eq!(legacy.max_script_num_length, 4);
+
+        let upgrade9 = UpgradeEpoch::Upgrade9.params();
+        assert!(upgrade9.cashtokens && upgrade9.p2sh32);
+        assert!(!upgrade9.vm_limits && !upgrade9.bigint);
+        assert_eq!(upgrade9.max_script_element_size, 520);
+
+        let upgrade11 = UpgradeEpoch::Upgrade11.params();
+        assert!(upgrade11.vm_limits && upgrade11.bigint);
+        assert!(!upgrade11.loops && !upgrade11.p2s);
+        assert_eq!(upgrade11.max_script_element_size, 10_000);
+        assert_eq!(upgrade11.max_token_commitment_length, 40);
+
+        let upgrade12 = ConsensusParams::latest();
+        assert_eq!(upgrade12, ConsensusParams::default());
+        assert!(upgrade12.loops && upgrade12.functions && upgrade12.bitwise);
+        assert!(upgrade12.p2s);
+        assert_eq!(upgrade12.max_token_commitment_length, 128);
+        assert_eq!(upgrade12.max_standard_p2s_size, 201);
+    }
+}
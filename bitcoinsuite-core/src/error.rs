@@ -7,7 +7,7 @@
 use thiserror::Error;
 
 /// Errors indicating some data doesn't map to some object.
-#[derive(Debug, Error, PartialEq)]
+#[derive(Clone, Debug, Error, PartialEq)]
 pub enum DataError {
     /// Expect a fixed length which was not met.
     #[error("Invalid length, expected {expected} bytes but got {actual} bytes")]
@@ -63,7 +63,7 @@ pub enum DataError {
         /// Returns the CashTokenDecodingError from caller.
         error: String,
     },
-    ///
+    /// Capability is set but the prefix doesn't encode an NFT.
     #[error("Invalid token prefix: capability requires an NFT")]
     CapabilityWithoutNft,
 
@@ -91,6 +91,45 @@ pub enum DataError {
         ///minimum token prefix lengh
         encoded_length: usize,
     },
+
+    /// Commitment exceeds the maximum length of the active upgrade.
+    #[error(
+        "Invalid token prefix: commitment length {actual} exceeds maximum of {max_length} bytes"
+    )]
+    CommitmentTooLong {
+        /// Maximum commitment length, see
+        /// [`crate::consensus::ConsensusParams::max_token_commitment_length`].
+        max_length: usize,
+        /// Actual commitment length.
+        actual: usize,
+    },
+
+    /// Bytes left over after decoding an object that should span all data.
+    #[error("Invalid encoding: {num_bytes} trailing bytes")]
+    TrailingBytes {
+        /// Number of bytes left over.
+        num_bytes: usize,
+    },
+
+    /// Script doesn't match the template of the expected script type.
+    #[error("Script doesn't match the {expected} template")]
+    InvalidScriptTemplate {
+        /// Name of the expected script type.
+        expected: String,
+    },
+
+    /// Serialized data doesn't start with the expected magic bytes.
+    #[error("Invalid magic, expected {expected:?} but got {actual:?}")]
+    InvalidMagic {
+        /// Expected magic bytes.
+        expected: Vec<u8>,
+        /// Actual leading bytes.
+        actual: Vec<u8>,
+    },
+
+    /// Token prefix used before CashTokens activated.
+    #[error("Invalid token prefix: CashTokens are not activated")]
+    TokensNotActivated,
 }
 /// Test
 // TODO: implement
@@ -451,6 +451,17 @@ hash_algo! {
     }
 }
 
+hash_algo! {
+    /// Hash of the SHA-1 algorithm. See [`Hashed`].
+    ///
+    /// Considered broken, only used by `OP_SHA1` in scripts.
+    pub struct Sha1(pub [u8; 20]);
+
+    fn digest(data: impl AsRef<[u8]>) -> Self {
+        Sha1(sha1::Sha1::digest(data).into())
+    }
+}
+
 hash_algo! {
     /// SHA-256 algorithm followed by RIPEMD-160, see [`Sha256`] and
     /// [`Ripemd160`]. See [`Hashed`].
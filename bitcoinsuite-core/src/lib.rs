@@ -8,9 +8,13 @@
 abc_rust_lint::lint! {
 // pub mod block;
 pub mod bytes;
+pub mod consensus;
 pub mod error;
 pub mod hash;
+pub mod policy;
 pub mod script;
 pub mod ser;
 pub mod tx;
+pub mod vm;
+pub mod wallet;
 }
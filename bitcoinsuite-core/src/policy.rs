@@ -0,0 +1,760 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+//! Module for [`PolicyConfig`], checking whether a tx would be relayed by
+//! nodes running the default standardness rules.
+
+use thiserror::Error;
+
+use crate::{
+    consensus::{ConsensusParams, UpgradeEpoch},
+    script::{Script, ScriptVariant},
+    ser::BitcoinSer,
+    tx::{Output, Transaction},
+};
+
+/// Default fee rate (in sats per 1000 bytes) used to compute dust thresholds.
+pub const DEFAULT_DUST_RELAY_FEE: u64 = 1000;
+
+/// Default maximum total size of all OP_RETURN outputs of a tx.
+pub const MAX_OP_RETURN_RELAY: usize = 223;
+
+/// Maximum number of SigChecks in a standard tx.
+pub const MAX_STANDARD_TX_SIGCHECKS: u32 = 3000;
+
+/// Minimum standard tx version.
+pub const MIN_STANDARD_VERSION: i32 = 1;
+
+/// Maximum standard tx version.
+pub const MAX_STANDARD_VERSION: i32 = 2;
+
+/// Maximum number of pubkeys in a standard bare multisig output.
+pub const MAX_STANDARD_BARE_MULTISIG_PUBKEYS: usize = 3;
+
+/// Standardness rules, as enforced by nodes before relaying txs or adding
+/// them to their mempool.
+///
+/// Defaults to the policy of the latest upgrade, and every field can be
+/// adjusted to mirror a node running non-default settings:
+/// ```
+/// # use bitcoinsuite_core::{consensus::UpgradeEpoch, policy::PolicyConfig};
+/// let config = PolicyConfig::default();
+/// assert_eq!(config.max_standard_tx_size, 100_000);
+/// assert_eq!(config.dust_relay_fee, 1000);
+/// assert_eq!(config.max_token_commitment_length, 128);
+/// assert_eq!(config.max_p2s_size, Some(201));
+///
+/// let config = PolicyConfig {
+///     dust_relay_fee: 2000,
+///     ..PolicyConfig::for_epoch(UpgradeEpoch::Upgrade11)
+/// };
+/// assert_eq!(config.max_token_commitment_length, 40);
+/// assert_eq!(config.max_p2s_size, None);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PolicyConfig {
+    /// Maximum serialized size of a standard tx.
+    pub max_standard_tx_size: usize,
+    /// Maximum size of a standard scriptSig.
+    pub max_scriptsig_size: usize,
+    /// Maximum total size of the scripts of all OP_RETURN outputs of a tx.
+    pub max_datacarrier_bytes: usize,
+    /// Fee rate (in sats per 1000 bytes) used to compute dust thresholds.
+    pub dust_relay_fee: u64,
+    /// Maximum number of SigChecks of all inputs of a tx.
+    pub max_tx_sigchecks: u32,
+    /// Maximum length of NFT commitments.
+    pub max_token_commitment_length: usize,
+    /// Whether bare multisig outputs (up to 3 pubkeys) are standard.
+    pub permit_bare_multisig: bool,
+    /// Whether P2SH32 outputs are standard.
+    pub p2sh32: bool,
+    /// Maximum size of standard P2S outputs (raw locking bytecode, without
+    /// token prefix), or `None` if P2S outputs are not standard.
+    pub max_p2s_size: Option<usize>,
+}
+
+/// Violation of a standardness rule, found by [`PolicyConfig::check_tx`].
+#[derive(Clone, Debug, Error, Eq, Hash, PartialEq)]
+pub enum PolicyError {
+    /// Tx version is outside the standard range.
+    #[error("version: tx version {0} is not standard")]
+    Version(i32),
+
+    /// Serialized tx is larger than the standard size.
+    #[error("tx-size: tx has {size} bytes, maximum is {max_size}")]
+    TxTooLarge {
+        /// Serialized size of the tx.
+        size: usize,
+        /// Maximum standard tx size.
+        max_size: usize,
+    },
+
+    /// A scriptSig is larger than the standard size.
+    #[error("scriptsig-size: input {input_idx} has {size} bytes, maximum is {max_size}")]
+    ScriptSigTooLarge {
+        /// Index of the offending input.
+        input_idx: usize,
+        /// Size of the scriptSig.
+        size: usize,
+        /// Maximum standard scriptSig size.
+        max_size: usize,
+    },
+
+    /// A scriptSig contains an op that is not a push.
+    #[error("scriptsig-not-pushonly: input {input_idx}")]
+    ScriptSigNotPushOnly {
+        /// Index of the offending input.
+        input_idx: usize,
+    },
+
+    /// A scriptSig pushes data with a larger opcode than necessary.
+    #[error("scriptsig-non-minimal-push: input {input_idx}")]
+    ScriptSigNonMinimalPush {
+        /// Index of the offending input.
+        input_idx: usize,
+    },
+
+    /// An output script doesn't match any standard template.
+    #[error("scriptpubkey: output {output_idx} has a non-standard script")]
+    NonStandardScript {
+        /// Index of the offending output.
+        output_idx: usize,
+    },
+
+    /// A P2S output's locking bytecode is larger than the standard size.
+    #[error(
+        "scriptpubkey: output {output_idx} has {size} bytes of P2S bytecode, maximum is {max_size}"
+    )]
+    P2STooLarge {
+        /// Index of the offending output.
+        output_idx: usize,
+        /// Size of the locking bytecode, without token prefix.
+        size: usize,
+        /// Maximum standard P2S size.
+        max_size: usize,
+    },
+
+    /// A bare multisig output while those are not permitted.
+    #[error("bare-multisig: output {output_idx}")]
+    BareMultisig {
+        /// Index of the offending output.
+        output_idx: usize,
+    },
+
+    /// An output value is below the dust threshold.
+    #[error("dust: output {output_idx} has {value} sats, minimum is {dust_threshold}")]
+    Dust {
+        /// Index of the offending output.
+        output_idx: usize,
+        /// Value of the output.
+        value: u64,
+        /// Dust threshold of the output, see [`PolicyConfig::dust_threshold`].
+        dust_threshold: u64,
+    },
+
+    /// The OP_RETURN outputs of the tx are too large in total.
+    #[error("oversize-op-return: OP_RETURN outputs have {size} bytes, maximum is {max_size}")]
+    OpReturnTooLarge {
+        /// Total size of all OP_RETURN scripts.
+        size: usize,
+        /// Maximum total size.
+        max_size: usize,
+    },
+
+    /// An NFT commitment is longer than the standard length.
+    #[error("token-commitment-oversized: output {output_idx} has {length} bytes, maximum is {max_length}")]
+    CommitmentTooLong {
+        /// Index of the offending output.
+        output_idx: usize,
+        /// Length of the commitment.
+        length: usize,
+        /// Maximum commitment length.
+        max_length: usize,
+    },
+
+    /// The tx has too many SigChecks.
+    #[error(
+        "bad-txns-too-many-sigchecks: tx has {sigchecks} SigChecks, maximum is {max_sigchecks}"
+    )]
+    TooManySigChecks {
+        /// Total SigChecks of the tx.
+        sigchecks: u64,
+        /// Maximum number of SigChecks.
+        max_sigchecks: u32,
+    },
+
+    /// An input has more SigChecks than its scriptSig size allows.
+    #[error(
+        "input-sigchecks: input {input_idx} has {sigchecks} SigChecks, maximum is {max_sigchecks}"
+    )]
+    SigChecksDensity {
+        /// Index of the offending input.
+        input_idx: usize,
+        /// SigChecks of the input.
+        sigchecks: u32,
+        /// Maximum number of SigChecks for the scriptSig's size.
+        max_sigchecks: u32,
+    },
+
+    /// The number of SigChecks counts doesn't match the number of inputs.
+    #[error("input-sigchecks: got SigChecks of {num_sigchecks} inputs, tx has {num_inputs}")]
+    SigChecksCountMismatch {
+        /// Number of inputs of the tx.
+        num_inputs: usize,
+        /// Number of SigChecks counts given.
+        num_sigchecks: usize,
+    },
+}
+
+impl PolicyConfig {
+    /// Default standardness rules in effect at the given [`UpgradeEpoch`].
+    pub fn for_epoch(epoch: UpgradeEpoch) -> PolicyConfig {
+        PolicyConfig::from(&epoch.params())
+    }
+
+    /// Dust threshold of the output at [`PolicyConfig::dust_relay_fee`], see
+    /// [`Output::dust_threshold`].
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::ShaRmd160,
+    /// #     policy::PolicyConfig,
+    /// #     script::Script,
+    /// #     ser::CompactUint,
+    /// #     tx::{CashToken, Output, TxId},
+    /// # };
+    /// let config = PolicyConfig::default();
+    /// let mut output = Output {
+    ///     value: 0,
+    ///     script: Script::p2pkh(&ShaRmd160([1; 20])),
+    ///     token: None,
+    /// };
+    /// assert_eq!(config.dust_threshold(&output), 546);
+    ///
+    /// output.token = Some(CashToken {
+    ///     amount: CompactUint(1000),
+    ///     category: TxId::from([2; 32]),
+    ///     nft: None,
+    /// });
+    /// assert_eq!(config.dust_threshold(&output), 657);
+    /// ```
+    pub fn dust_threshold(&self, output: &Output) -> u64 {
+        output.dust_threshold(self.dust_relay_fee)
+    }
+
+    /// Check whether the output is standard on its own, i.e. without looking
+    /// at the other outputs of the tx.
+    ///
+    /// As of the May 2026 upgrade, well-formed locking bytecode that doesn't
+    /// match a template (P2S) is standard up to
+    /// [`PolicyConfig::max_p2s_size`] bytes; the token prefix doesn't count.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     consensus::UpgradeEpoch,
+    /// #     policy::{PolicyConfig, PolicyError},
+    /// #     script::Script,
+    /// #     tx::Output,
+    /// # };
+    /// let mut output = Output {
+    ///     value: 2000,
+    ///     script: Script::new(vec![0x51; 201].into()),
+    ///     token: None,
+    /// };
+    /// assert_eq!(PolicyConfig::default().check_output(0, &output), Ok(()));
+    /// assert_eq!(
+    ///     PolicyConfig::for_epoch(UpgradeEpoch::Upgrade11).check_output(0, &output),
+    ///     Err(PolicyError::NonStandardScript { output_idx: 0 }),
+    /// );
+    ///
+    /// output.script = Script::new(vec![0x51; 202].into());
+    /// assert_eq!(
+    ///     PolicyConfig::default().check_output(0, &output),
+    ///     Err(PolicyError::P2STooLarge { output_idx: 0, size: 202, max_size: 201 }),
+    /// );
+    /// ```
+    pub fn check_output(&self, output_idx: usize, output: &Output) -> Result<(), PolicyError> {
+        if output.script.is_opreturn() {
+            let is_push_only = Script::new(output.script.bytecode().slice(1..)).is_push_only();
+            if !is_push_only {
+                return Err(PolicyError::NonStandardScript { output_idx });
+            }
+        } else {
+            match ScriptVariant::from_script(&output.script) {
+                ScriptVariant::P2PKH(_) | ScriptVariant::P2SH(_) | ScriptVariant::P2PK(_) => {}
+                ScriptVariant::P2SH32(_) if self.p2sh32 => {}
+                ScriptVariant::Multisig { pubkeys, .. }
+                    if pubkeys.len() <= MAX_STANDARD_BARE_MULTISIG_PUBKEYS =>
+                {
+                    if !self.permit_bare_multisig {
+                        return Err(PolicyError::BareMultisig { output_idx });
+                    }
+                }
+                ScriptVariant::Other(_) => {
+                    return Err(PolicyError::NonStandardScript { output_idx })
+                }
+                // Any other spendable script is P2S, e.g. larger multisig
+                _ => match self.max_p2s_size {
+                    Some(max_size) if output.script.bytecode().len() > max_size => {
+                        return Err(PolicyError::P2STooLarge {
+                            output_idx,
+                            size: output.script.bytecode().len(),
+                            max_size,
+                        });
+                    }
+                    Some(_) => {}
+                    None => return Err(PolicyError::NonStandardScript { output_idx }),
+                },
+            }
+        }
+        let commitment_len = output
+            .token
+            .as_ref()
+            .map_or(0, |token| token.commitment().0.len());
+        if commitment_len > self.max_token_commitment_length {
+            return Err(PolicyError::CommitmentTooLong {
+                output_idx,
+                length: commitment_len,
+                max_length: self.max_token_commitment_length,
+            });
+        }
+        let dust_threshold = self.dust_threshold(output);
+        if output.value < dust_threshold {
+            return Err(PolicyError::Dust {
+                output_idx,
+                value: output.value,
+                dust_threshold,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check the tx against all standardness rules that don't require
+    /// executing scripts, returning every violation found.
+    ///
+    /// SigChecks can only be counted by running the scripts, see
+    /// [`PolicyConfig::check_sigchecks`].
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::ShaRmd160,
+    /// #     policy::{PolicyConfig, PolicyError},
+    /// #     script::Script,
+    /// #     tx::{Input, Output, Transaction},
+    /// # };
+    /// let tx = Transaction {
+    ///     version: 2,
+    ///     inputs: vec![Input::default()],
+    ///     outputs: vec![
+    ///         Output {
+    ///             value: 545,
+    ///             script: Script::p2pkh(&ShaRmd160([1; 20])),
+    ///             token: None,
+    ///         },
+    ///         Output {
+    ///             value: 0,
+    ///             script: Script::new(vec![0x6a, 0x76].into()),
+    ///             token: None,
+    ///         },
+    ///     ],
+    ///     locktime: 0,
+    /// };
+    /// assert_eq!(
+    ///     PolicyConfig::default().check_tx(&tx),
+    ///     Err(vec![
+    ///         PolicyError::Dust { output_idx: 0, value: 545, dust_threshold: 546 },
+    ///         PolicyError::NonStandardScript { output_idx: 1 },
+    ///     ]),
+    /// );
+    /// ```
+    pub fn check_tx(&self, tx: &Transaction) -> Result<(), Vec<PolicyError>> {
+        let mut errors = Vec::new();
+        if !(MIN_STANDARD_VERSION..=MAX_STANDARD_VERSION).contains(&tx.version) {
+            errors.push(PolicyError::Version(tx.version));
+        }
+        let size = tx.ser_len();
+        if size > self.max_standard_tx_size {
+            errors.push(PolicyError::TxTooLarge {
+                size,
+                max_size: self.max_standard_tx_size,
+            });
+        }
+
+        for (input_idx, input) in tx.inputs.iter().enumerate() {
+            let size = input.script.bytecode().len();
+            if size > self.max_scriptsig_size {
+                errors.push(PolicyError::ScriptSigTooLarge {
+                    input_idx,
+                    size,
+                    max_size: self.max_scriptsig_size,
+                });
+            }
+            if !input.script.is_push_only() {
+                errors.push(PolicyError::ScriptSigNotPushOnly { input_idx });
+            }
+            if !input.script.non_minimal_pushes().is_empty() {
+                errors.push(PolicyError::ScriptSigNonMinimalPush { input_idx });
+            }
+        }
+
+        let mut op_return_size = 0;
+        for (output_idx, output) in tx.outputs.iter().enumerate() {
+            if output.script.is_opreturn() {
+                op_return_size += output.script.bytecode().len();
+            }
+            if let Err(err) = self.check_output(output_idx, output) {
+                errors.push(err);
+            }
+        }
+        if op_return_size > self.max_datacarrier_bytes {
+            errors.push(PolicyError::OpReturnTooLarge {
+                size: op_return_size,
+                max_size: self.max_datacarrier_bytes,
+            });
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Check the SigChecks counted for each input (by executing its scripts)
+    /// against the per-tx limit and the per-input density limit, which
+    /// allows one SigCheck per 43 bytes of scriptSig (plus 60 bytes of
+    /// slack).
+    ///
+    /// `input_sigchecks` must have one entry per input of the tx.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     policy::{PolicyConfig, PolicyError},
+    /// #     script::Script,
+    /// #     tx::{Input, Transaction},
+    /// # };
+    /// let tx = Transaction {
+    ///     inputs: vec![Input {
+    ///         script: Script::new(vec![0x51; 26].into()),
+    ///         ..Default::default()
+    ///     }],
+    ///     ..Default::default()
+    /// };
+    /// let config = PolicyConfig::default();
+    /// assert_eq!(config.check_sigchecks(&tx, &[2]), Ok(()));
+    /// assert_eq!(
+    ///     config.check_sigchecks(&tx, &[3]),
+    ///     Err(vec![PolicyError::SigChecksDensity {
+    ///         input_idx: 0,
+    ///         sigchecks: 3,
+    ///         max_sigchecks: 2,
+    ///     }]),
+    /// );
+    /// ```
+    pub fn check_sigchecks(
+        &self,
+        tx: &Transaction,
+        input_sigchecks: &[u32],
+    ) -> Result<(), Vec<PolicyError>> {
+        if input_sigchecks.len() != tx.inputs.len() {
+            return Err(vec![PolicyError::SigChecksCountMismatch {
+                num_inputs: tx.inputs.len(),
+                num_sigchecks: input_sigchecks.len(),
+            }]);
+        }
+        let mut errors = Vec::new();
+        for (input_idx, (input, &sigchecks)) in tx.inputs.iter().zip(input_sigchecks).enumerate() {
+            let max_sigchecks = ((input.script.bytecode().len() + 60) / 43) as u32;
+            if sigchecks > max_sigchecks {
+                errors.push(PolicyError::SigChecksDensity {
+                    input_idx,
+                    sigchecks,
+                    max_sigchecks,
+                });
+            }
+        }
+        let sigchecks = input_sigchecks
+            .iter()
+            .map(|&sigchecks| u64::from(sigchecks))
+            .sum();
+        if sigchecks > u64::from(self.max_tx_sigchecks) {
+            errors.push(PolicyError::TooManySigChecks {
+                sigchecks,
+                max_sigchecks: self.max_tx_sigchecks,
+            });
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        PolicyConfig::from(&ConsensusParams::latest())
+    }
+}
+
+impl From<&ConsensusParams> for PolicyConfig {
+    fn from(params: &ConsensusParams) -> Self {
+        PolicyConfig {
+            max_standard_tx_size: params.max_standard_tx_size,
+            max_scriptsig_size: params.max_standard_scriptsig_size,
+            max_datacarrier_bytes: MAX_OP_RETURN_RELAY,
+            dust_relay_fee: DEFAULT_DUST_RELAY_FEE,
+            max_tx_sigchecks: MAX_STANDARD_TX_SIGCHECKS,
+            max_token_commitment_length: params.max_token_commitment_length,
+            permit_bare_multisig: true,
+            p2sh32: params.p2sh32,
+            max_p2s_size: params.p2s.then(|| params.max_standard_p2s_size),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        consensus::UpgradeEpoch,
+        hash::{Sha256d, ShaRmd160},
+        policy::{PolicyConfig, PolicyError},
+        script::{PubKey, PubKeyVariant, Script},
+        ser::CompactUint,
+        tx::{Capability, CashToken, Commitment, Input, NonFungibleTokenCapability, TxId, NFT},
+        wallet::test_util::{output, tx},
+    };
+
+    fn p2pkh() -> Script {
+        Script::p2pkh(&ShaRmd160([1; 20]))
+    }
+
+    fn nft(commitment_len: usize) -> Option<CashToken> {
+        Some(CashToken {
+            amount: CompactUint(0),
+            category: TxId::from([2; 32]),
+            nft: Some(NFT {
+                capability: NonFungibleTokenCapability(Capability::None),
+                commitment: Commitment(vec![3; commitment_len].into()),
+            }),
+        })
+    }
+
+    #[test]
+    fn test_dust_threshold() {
+        let config = PolicyConfig::default();
+        assert_eq!(config.dust_threshold(&output(0, p2pkh())), 546);
+        assert_eq!(
+            config.dust_threshold(&output(0, Script::p2sh(&ShaRmd160([1; 20])))),
+            540,
+        );
+        assert_eq!(
+            config.dust_threshold(&output(0, Script::new(vec![0x6a; 100].into()))),
+            0,
+        );
+        // Token prefix counts towards the output size: 1 prefix byte,
+        // 32 category bytes, 1 bitfield, 1 + 40 commitment bytes
+        let mut token_output = output(0, p2pkh());
+        token_output.token = nft(40);
+        assert_eq!(config.dust_threshold(&token_output), 3 * (182 + 75));
+        let config = PolicyConfig {
+            dust_relay_fee: 0,
+            ..Default::default()
+        };
+        assert_eq!(config.dust_threshold(&token_output), 0);
+    }
+
+    #[test]
+    fn test_check_tx_standard() {
+        let config = PolicyConfig::default();
+        let pubkey = PubKey([2; 33]);
+        let tx = tx(
+            vec![Input {
+                script: Script::new(vec![0x01, 0x11, 0x51].into()),
+                ..Default::default()
+            }],
+            vec![
+                output(546, p2pkh()),
+                output(540, Script::p2sh(&ShaRmd160([1; 20]))),
+                output(576, Script::p2sh32(&Sha256d([1; 32]))),
+                output(576, Script::p2pk(&pubkey)),
+                output(0, Script::new(vec![0x6a, 0x01, 0x00].into())),
+                output(
+                    1000,
+                    Script::new(
+                        [[0x51, 0x21].as_ref(), &pubkey.0, &[0x51, 0xae]]
+                            .concat()
+                            .into(),
+                    ),
+                ),
+            ],
+        );
+        assert_eq!(config.check_tx(&tx), Ok(()));
+
+        // P2SH32 isn't standard before Upgrade9, bare multisig can be disabled
+        let config = PolicyConfig {
+            permit_bare_multisig: false,
+            ..PolicyConfig::for_epoch(UpgradeEpoch::Upgrade8)
+        };
+        assert_eq!(
+            config.check_tx(&tx),
+            Err(vec![
+                PolicyError::NonStandardScript { output_idx: 2 },
+                PolicyError::BareMultisig { output_idx: 5 },
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_check_tx_non_standard() {
+        let config = PolicyConfig::default();
+        let mut tx = tx(
+            vec![
+                Input {
+                    script: Script::new(vec![0x51; 10_001].into()),
+                    ..Default::default()
+                },
+                Input {
+                    script: Script::new(vec![0x51, 0x76].into()),
+                    ..Default::default()
+                },
+                Input {
+                    script: Script::new(vec![0x01, 0x05].into()),
+                    ..Default::default()
+                },
+            ],
+            vec![
+                output(545, p2pkh()),
+                output(0, Script::new(vec![0x6a; 100].into())),
+                output(
+                    0,
+                    Script::new([[0x6a, 0x4c, 0xc8].as_ref(), &[0; 200]].concat().into()),
+                ),
+                output(1000, Script::new(vec![0x51; 202].into())),
+            ],
+        );
+        tx.version = 3;
+        tx.outputs[3].token = nft(129);
+        assert_eq!(
+            config.check_tx(&tx),
+            Err(vec![
+                PolicyError::Version(3),
+                PolicyError::ScriptSigTooLarge {
+                    input_idx: 0,
+                    size: 10_001,
+                    max_size: 10_000,
+                },
+                PolicyError::ScriptSigNotPushOnly { input_idx: 1 },
+                PolicyError::ScriptSigNonMinimalPush { input_idx: 2 },
+                PolicyError::Dust {
+                    output_idx: 0,
+                    value: 545,
+                    dust_threshold: 546,
+                },
+                PolicyError::NonStandardScript { output_idx: 1 },
+                PolicyError::P2STooLarge {
+                    output_idx: 3,
+                    size: 202,
+                    max_size: 201,
+                },
+                PolicyError::OpReturnTooLarge {
+                    size: 303,
+                    max_size: 223,
+                },
+            ]),
+        );
+
+        // Commitment is checked for standard scripts
+        tx.outputs[3].script = p2pkh();
+        assert_eq!(
+            config.check_output(3, &tx.outputs[3]),
+            Err(PolicyError::CommitmentTooLong {
+                output_idx: 3,
+                length: 129,
+                max_length: 128,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_check_p2s() {
+        let config = PolicyConfig::default();
+        // Token-carrying covenant, the token prefix doesn't count towards
+        // the P2S size
+        let covenant = Script::new([[0xc0, 0xce].as_ref(), &[0x75; 199]].concat().into());
+        let mut covenant_output = output(10_000, covenant);
+        covenant_output.token = nft(128);
+        assert_eq!(config.check_output(0, &covenant_output), Ok(()));
+        // Bare multisig with more than 3 pubkeys is standard as P2S
+        let pubkey = PubKeyVariant::Compressed(PubKey([2; 33]));
+        let multisig = output(10_000, Script::multisig(1, &vec![pubkey; 4]));
+        assert_eq!(config.check_output(0, &multisig), Ok(()));
+        // Malformed and empty scripts are never standard
+        for bytecode in [vec![0x4c], vec![]] {
+            assert_eq!(
+                config.check_output(0, &output(10_000, Script::new(bytecode.into()))),
+                Err(PolicyError::NonStandardScript { output_idx: 0 }),
+            );
+        }
+
+        let config = PolicyConfig::for_epoch(UpgradeEpoch::Upgrade11);
+        assert_eq!(
+            config.check_output(0, &covenant_output),
+            Err(PolicyError::NonStandardScript { output_idx: 0 }),
+        );
+        assert_eq!(
+            config.check_output(0, &multisig),
+            Err(PolicyError::NonStandardScript { output_idx: 0 }),
+        );
+        let config = PolicyConfig {
+            max_p2s_size: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.check_output(0, &covenant_output),
+            Err(PolicyError::P2STooLarge {
+                output_idx: 0,
+                size: 201,
+                max_size: 100,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_check_sigchecks() {
+        let config = PolicyConfig::default();
+        let tx = tx(
+            vec![
+                Input {
+                    script: Script::new(vec![0x51; 1000].into()),
+                    ..Default::default()
+                };
+                200
+            ],
+            vec![],
+        );
+        assert_eq!(
+            config.check_sigchecks(&tx, &[24; 200]),
+            Err(vec![PolicyError::TooManySigChecks {
+                sigchecks: 4800,
+                max_sigchecks: 3000,
+            },])
+        );
+        assert_eq!(config.check_sigchecks(&tx, &[15; 200]), Ok(()));
+        // Sums beyond u32 don't overflow
+        assert_eq!(
+            config.check_sigchecks(&tx, &[u32::MAX; 200]).unwrap_err()[200],
+            PolicyError::TooManySigChecks {
+                sigchecks: 200 * u64::from(u32::MAX),
+                max_sigchecks: 3000,
+            },
+        );
+        assert_eq!(
+            config.check_sigchecks(&tx, &[15; 199]),
+            Err(vec![PolicyError::SigChecksCountMismatch {
+                num_inputs: 200,
+                num_sigchecks: 199,
+            }]),
+        );
+    }
+}
@@ -0,0 +1,318 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use bytes::Bytes;
+
+use crate::{
+    hash::{Hashed, Sha256, Sha256d, ShaRmd160},
+    script::{opcode::*, Op, PubKey, Script, ScriptMut, ScriptNum},
+};
+
+/// Size of the secret preimage of an [`Htlc`], enforced by the script so
+/// the same secret can unlock HTLCs on other chains.
+pub const HTLC_PREIMAGE_SIZE: usize = 32;
+
+/// Hash-time-locked contract, as used for atomic swaps: the recipient can
+/// claim the coin by revealing the preimage of `secret_hash`, or the refund
+/// key can take it back once `timeout` passed.
+///
+/// The redeem script is:
+/// ```text
+/// OP_IF
+///     OP_SIZE 32 OP_EQUALVERIFY OP_SHA256 <secret_hash> OP_EQUALVERIFY
+///     <recipient>
+/// OP_ELSE
+///     <timeout> OP_CHECKLOCKTIMEVERIFY/OP_CHECKSEQUENCEVERIFY OP_DROP
+///     <refund>
+/// OP_ENDIF
+/// OP_CHECKSIG
+/// ```
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     hash::{Hashed, Sha256},
+/// #     script::{Htlc, HtlcTimeout, PubKey},
+/// # };
+/// let preimage = [7; 32];
+/// let htlc = Htlc {
+///     secret_hash: Sha256::digest(preimage),
+///     recipient: PubKey([2; 33]),
+///     refund: PubKey([3; 33]),
+///     timeout: HtlcTimeout::Absolute(800_000),
+/// };
+/// let sig = [[1; 64].as_ref(), &[0x41]].concat();
+/// let script_sig = htlc.claim_script_sig(&sig, &preimage);
+///
+/// // The counterparty learns the secret from the claiming input
+/// let claim = Htlc::extract_claim(&script_sig).unwrap();
+/// assert_eq!(claim.htlc, htlc);
+/// assert_eq!(claim.preimage.as_ref(), preimage);
+/// assert_eq!(Htlc::extract_claim(&htlc.refund_script_sig(&sig)), None);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Htlc {
+    /// SHA-256 hash of the secret.
+    pub secret_hash: Sha256,
+    /// Key that can claim the coin with the secret.
+    pub recipient: PubKey,
+    /// Key that can take the coin back after the timeout.
+    pub refund: PubKey,
+    /// When the refund branch unlocks.
+    pub timeout: HtlcTimeout,
+}
+
+/// Timeout of the refund branch of an [`Htlc`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HtlcTimeout {
+    /// Absolute locktime, checked with [`OP_CHECKLOCKTIMEVERIFY`]: a block
+    /// height below 500,000,000, otherwise a UNIX timestamp.
+    Absolute(u32),
+    /// Relative locktime, checked with [`OP_CHECKSEQUENCEVERIFY`], encoded
+    /// like the nSequence of the refunding input (BIP68).
+    Relative(u32),
+}
+
+/// Claim of an [`Htlc`], parsed from the scriptSig of the claiming input.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct HtlcClaim {
+    /// HTLC that was claimed.
+    pub htlc: Htlc,
+    /// Signature of the recipient, including the sighash byte.
+    pub sig: Bytes,
+    /// Revealed secret, hashing to [`Htlc::secret_hash`].
+    pub preimage: Bytes,
+}
+
+impl Htlc {
+    /// Redeem script of the HTLC.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::Sha256,
+    /// #     script::{Htlc, HtlcTimeout, PubKey},
+    /// # };
+    /// let htlc = Htlc {
+    ///     secret_hash: Sha256([0x11; 32]),
+    ///     recipient: PubKey([2; 33]),
+    ///     refund: PubKey([3; 33]),
+    ///     timeout: HtlcTimeout::Relative(144),
+    /// };
+    /// assert_eq!(
+    ///     htlc.redeem_script().hex(),
+    ///     "6382012088a8201111111111111111111111111111111111111111111111111111\
+    ///      111111111111882102020202020202020202020202020202020202020202020202\
+    ///      020202020202020267029000b27521030303030303030303030303030303030303\
+    ///      03030303030303030303030303030368ac",
+    /// );
+    /// ```
+    pub fn redeem_script(&self) -> Script {
+        let mut script = ScriptMut::with_capacity(2 * (1 + PubKey::SIZE) + 50);
+        script.put_opcodes([OP_IF, OP_SIZE]);
+        script.push_int(HTLC_PREIMAGE_SIZE as i64);
+        script.put_opcodes([OP_EQUALVERIFY, OP_SHA256]);
+        script.push_data(self.secret_hash.as_le_bytes());
+        script.put_opcodes([OP_EQUALVERIFY]);
+        script.push_data(self.recipient.as_slice());
+        script.put_opcodes([OP_ELSE]);
+        let (timeout, opcode) = match self.timeout {
+            HtlcTimeout::Absolute(locktime) => (locktime, OP_CHECKLOCKTIMEVERIFY),
+            HtlcTimeout::Relative(sequence) => (sequence, OP_CHECKSEQUENCEVERIFY),
+        };
+        script.push_int(timeout.into());
+        script.put_opcodes([opcode, OP_DROP]);
+        script.push_data(self.refund.as_slice());
+        script.put_opcodes([OP_ENDIF, OP_CHECKSIG]);
+        script.freeze()
+    }
+
+    /// P2SH script locking coins to this HTLC.
+    pub fn p2sh(&self) -> Script {
+        Script::p2sh(&ShaRmd160::digest(self.redeem_script()))
+    }
+
+    /// P2SH32 script locking coins to this HTLC.
+    pub fn p2sh32(&self) -> Script {
+        Script::p2sh32(&Sha256d::digest(self.redeem_script()))
+    }
+
+    /// ScriptSig claiming the coin with the recipient's signature and the
+    /// secret: `<sig> <preimage> OP_1 <redeem script>`.
+    pub fn claim_script_sig(&self, sig: &[u8], preimage: &[u8]) -> Script {
+        let mut script = ScriptMut::with_capacity(0);
+        script.push_data(sig);
+        script.push_data(preimage);
+        script.put_opcodes([OP_1]);
+        script.push_data(self.redeem_script().bytecode());
+        script.freeze()
+    }
+
+    /// ScriptSig refunding the coin with the refund key's signature:
+    /// `<sig> OP_0 <redeem script>`. The spending tx must set
+    /// [`HtlcTimeout::locktime`] and [`HtlcTimeout::sequence`].
+    pub fn refund_script_sig(&self, sig: &[u8]) -> Script {
+        let mut script = ScriptMut::with_capacity(0);
+        script.push_data(sig);
+        script.put_opcodes([OP_0]);
+        script.push_data(self.redeem_script().bytecode());
+        script.freeze()
+    }
+
+    /// Parse an HTLC from its redeem script, `None` if it's not exactly the
+    /// script of [`Htlc::redeem_script`].
+    pub fn from_redeem_script(script: &Script) -> Option<Htlc> {
+        let ops = script.iter_ops().collect::<Result<Vec<_>, _>>().ok()?;
+        // Only extract the fields here, the other ops are checked by
+        // comparing with the rebuilt script below
+        let (secret_hash, recipient, timeout, opcode, refund) = match ops.as_slice() {
+            [_, _, _, _, _, hash, _, recipient, _, timeout, Op::Code(opcode), _, refund, _, _] => {
+                (hash, recipient, timeout, *opcode, refund)
+            }
+            _ => return None,
+        };
+        let timeout = ScriptNum::decode(&timeout.pushed_data()?).ok()?;
+        let timeout = u32::try_from(timeout.0).ok()?;
+        let htlc = Htlc {
+            secret_hash: Sha256(push_array(secret_hash)?),
+            recipient: PubKey(push_array(recipient)?),
+            refund: PubKey(push_array(refund)?),
+            timeout: match opcode {
+                OP_CHECKLOCKTIMEVERIFY => HtlcTimeout::Absolute(timeout),
+                OP_CHECKSEQUENCEVERIFY => HtlcTimeout::Relative(timeout),
+                _ => return None,
+            },
+        };
+        if htlc.redeem_script() != *script {
+            return None;
+        }
+        Some(htlc)
+    }
+
+    /// Parse the scriptSig of an input claiming an HTLC, e.g. to learn the
+    /// secret revealed by the counterparty of an atomic swap. Returns `None`
+    /// for refunds, other scripts and preimages not matching the hash.
+    pub fn extract_claim(script_sig: &Script) -> Option<HtlcClaim> {
+        let ops = script_sig.iter_ops().collect::<Result<Vec<_>, _>>().ok()?;
+        let (sig, preimage, redeem_script) = match ops.as_slice() {
+            [Op::Push(_, sig), Op::Push(_, preimage), Op::Code(OP_1), Op::Push(_, script)] => {
+                (sig, preimage, script)
+            }
+            _ => return None,
+        };
+        let htlc = Htlc::from_redeem_script(&Script::new(redeem_script.clone()))?;
+        if preimage.len() != HTLC_PREIMAGE_SIZE || Sha256::digest(preimage) != htlc.secret_hash {
+            return None;
+        }
+        Some(HtlcClaim {
+            htlc,
+            sig: sig.clone(),
+            preimage: preimage.clone(),
+        })
+    }
+}
+
+impl HtlcTimeout {
+    /// Locktime of a tx refunding the HTLC.
+    pub fn locktime(self) -> u32 {
+        match self {
+            HtlcTimeout::Absolute(locktime) => locktime,
+            HtlcTimeout::Relative(_) => 0,
+        }
+    }
+
+    /// nSequence of an input refunding the HTLC. For absolute timeouts, this
+    /// is below `0xffffffff` so the locktime is enforced.
+    pub fn sequence(self) -> u32 {
+        match self {
+            HtlcTimeout::Absolute(_) => 0xffff_fffe,
+            HtlcTimeout::Relative(sequence) => sequence,
+        }
+    }
+}
+
+fn push_array<const N: usize>(op: &Op) -> Option<[u8; N]> {
+    match op {
+        Op::Push(_, data) => data.as_ref().try_into().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        hash::{Hashed, Sha256},
+        script::{opcode::*, Htlc, HtlcTimeout, Op, PubKey, Script, ScriptMut},
+    };
+
+    fn htlc(timeout: HtlcTimeout) -> Htlc {
+        Htlc {
+            secret_hash: Sha256::digest([7; 32]),
+            recipient: PubKey([2; 33]),
+            refund: PubKey([3; 33]),
+            timeout,
+        }
+    }
+
+    #[test]
+    fn test_htlc_timeouts() {
+        for (timeout, encoded) in [
+            (HtlcTimeout::Absolute(0), vec![OP_0::N]),
+            (HtlcTimeout::Absolute(16), vec![OP_16::N]),
+            (HtlcTimeout::Absolute(0x80), vec![2, 0x80, 0]),
+            (HtlcTimeout::Absolute(800_000), vec![3, 0x00, 0x35, 0x0c]),
+            (
+                HtlcTimeout::Absolute(0xffff_ffff),
+                vec![5, 0xff, 0xff, 0xff, 0xff, 0],
+            ),
+            (HtlcTimeout::Relative(0x0040_0001), vec![3, 1, 0, 0x40]),
+        ] {
+            let htlc = htlc(timeout);
+            let script = htlc.redeem_script();
+            let ops = script.iter_ops().collect::<Result<Vec<_>, _>>().unwrap();
+            let mut timeout_op = ScriptMut::with_capacity(0);
+            timeout_op.put_bytecode(&encoded);
+            let expected = timeout_op.freeze().iter_ops().next().unwrap().unwrap();
+            assert_eq!(ops[9], expected);
+            let opcode = match timeout {
+                HtlcTimeout::Absolute(_) => OP_CHECKLOCKTIMEVERIFY,
+                HtlcTimeout::Relative(_) => OP_CHECKSEQUENCEVERIFY,
+            };
+            assert_eq!(ops[10], Op::Code(opcode));
+            assert_eq!(Htlc::from_redeem_script(&script), Some(htlc));
+        }
+        let timeout = HtlcTimeout::Relative(10);
+        assert_eq!((timeout.locktime(), timeout.sequence()), (0, 10));
+        let timeout = HtlcTimeout::Absolute(10);
+        assert_eq!((timeout.locktime(), timeout.sequence()), (10, 0xffff_fffe));
+    }
+
+    #[test]
+    fn test_htlc_claim() {
+        let htlc = htlc(HtlcTimeout::Relative(144));
+        let sig = [[1; 64].as_ref(), &[0x41]].concat();
+        let script_sig = htlc.claim_script_sig(&sig, &[7; 32]);
+        let ops = script_sig
+            .iter_ops()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(ops.len(), 4);
+        assert_eq!(ops[2], Op::Code(OP_1));
+        assert_eq!(
+            ops[3],
+            Op::Push(OP_PUSHDATA1, htlc.redeem_script().bytecode().clone()),
+        );
+        let claim = Htlc::extract_claim(&script_sig).unwrap();
+        assert_eq!(claim.htlc, htlc);
+        assert_eq!(claim.sig.as_ref(), sig);
+        assert_eq!(claim.preimage.as_ref(), [7; 32]);
+
+        // Wrong preimage, refund and unrelated scripts have no claim
+        let script_sig = htlc.claim_script_sig(&sig, &[8; 32]);
+        assert_eq!(Htlc::extract_claim(&script_sig), None);
+        let script_sig = htlc.refund_script_sig(&sig);
+        assert_eq!(Htlc::extract_claim(&script_sig), None);
+        assert_eq!(Htlc::extract_claim(&Script::p2pk(&PubKey([2; 33]))), None);
+        assert_eq!(Htlc::from_redeem_script(&htlc.p2sh()), None);
+        assert_ne!(htlc.p2sh(), htlc.p2sh32());
+    }
+}
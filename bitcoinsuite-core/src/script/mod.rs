@@ -4,7 +4,9 @@
 
 //! Module for structs and definitions regarding Script.
 
+mod htlc;
 mod iter;
+mod multisig;
 mod op;
 pub mod opcode;
 mod pubkey;
@@ -12,14 +14,20 @@ mod pubkey_variant;
 #[allow(clippy::module_inception)]
 mod script;
 mod script_mut;
+mod script_num;
+mod script_sig;
 mod uncompressed_pubkey;
 mod variant;
 
+pub use self::htlc::*;
 pub use self::iter::*;
+pub use self::multisig::*;
 pub use self::op::*;
 pub use self::pubkey::*;
 pub use self::pubkey_variant::*;
 pub use self::script::*;
 pub use self::script_mut::*;
+pub use self::script_num::*;
+pub use self::script_sig::*;
 pub use self::uncompressed_pubkey::*;
 pub use self::variant::*;
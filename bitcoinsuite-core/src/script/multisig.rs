@@ -0,0 +1,219 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use crate::script::{opcode::*, Op, PubKeyVariant, Script, ScriptMut};
+
+/// Maximum number of pubkeys in an `OP_CHECKMULTISIG` script (consensus).
+pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+
+/// How the signatures of a multisig input are checked, determined by the
+/// dummy element `OP_CHECKMULTISIG` pops.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MultisigMode {
+    /// ECDSA signatures, with an empty dummy element (`OP_0`).
+    Legacy,
+    /// Schnorr signatures, with a bitfield of the signing pubkeys as dummy
+    /// element.
+    Schnorr,
+}
+
+impl Script {
+    /// Multisig script requiring `m` signatures of the `pubkeys`:
+    /// `OP_m <pubkey>... OP_n OP_CHECKMULTISIG`. Used as bare multisig
+    /// output, or as redeem script of a P2SH multisig.
+    ///
+    /// Panics unless `1 <= m <= pubkeys.len() <= 20`.
+    /// ```
+    /// # use bitcoinsuite_core::script::{PubKey, PubKeyVariant, Script, ScriptVariant};
+    /// let pubkeys = [
+    ///     PubKeyVariant::Compressed(PubKey([2; 33])),
+    ///     PubKeyVariant::Compressed(PubKey([3; 33])),
+    /// ];
+    /// let script = Script::multisig(1, &pubkeys);
+    /// assert_eq!(
+    ///     script.hex(),
+    ///     "51210202020202020202020202020202020202020202020202020202020202020202\
+    ///      02210303030303030303030303030303030303030303030303030303030303030303\
+    ///      0352ae",
+    /// );
+    /// assert_eq!(
+    ///     ScriptVariant::from_script(&script),
+    ///     ScriptVariant::Multisig { m: 1, pubkeys: pubkeys.to_vec() },
+    /// );
+    /// ```
+    pub fn multisig(m: usize, pubkeys: &[PubKeyVariant]) -> Script {
+        assert!(
+            1 <= m && m <= pubkeys.len() && pubkeys.len() <= MAX_PUBKEYS_PER_MULTISIG,
+            "Invalid {}-of-{} multisig",
+            m,
+            pubkeys.len(),
+        );
+        let mut script = ScriptMut::with_capacity(3 + pubkeys.len() * 66);
+        script.push_data(&[m as u8]);
+        for pubkey in pubkeys {
+            match pubkey {
+                PubKeyVariant::Compressed(pubkey) => script.push_data(pubkey.as_slice()),
+                PubKeyVariant::Uncompressed(pubkey) => script.push_data(pubkey.as_ref()),
+            }
+        }
+        script.push_data(&[pubkeys.len() as u8]);
+        script.put_opcodes([OP_CHECKMULTISIG]);
+        script.freeze()
+    }
+
+    /// ScriptSig unlocking a bare multisig output of `num_pubkeys` pubkeys:
+    /// the dummy element for `mode`, followed by the signatures. `sigs` are
+    /// pairs of the index of the signing pubkey and its signature (including
+    /// the sighash byte); they are put in the order of the pubkeys.
+    ///
+    /// For P2SH multisig, add the redeem script with
+    /// [`Script::p2sh_script_sig`].
+    /// ```
+    /// # use bitcoinsuite_core::script::{MultisigMode, Script};
+    /// let sigs = [(2, vec![0xcc; 65]), (0, vec![0xaa; 65])];
+    /// let script_sig = Script::multisig_script_sig(MultisigMode::Schnorr, 3, &sigs);
+    /// // Bitfield 0b101 is pushed as OP_5
+    /// assert_eq!(&script_sig.bytecode()[..3], [0x55, 65, 0xaa]);
+    ///
+    /// let sigs = [(1, vec![0xbb; 72])];
+    /// let script_sig = Script::multisig_script_sig(MultisigMode::Legacy, 3, &sigs);
+    /// assert_eq!(script_sig.bytecode().as_ref(), [[0, 72].as_ref(), &[0xbb; 72]].concat());
+    /// ```
+    pub fn multisig_script_sig<S: AsRef<[u8]>>(
+        mode: MultisigMode,
+        num_pubkeys: usize,
+        sigs: &[(usize, S)],
+    ) -> Script {
+        let mut sigs = sigs.iter().collect::<Vec<_>>();
+        sigs.sort_by_key(|&(pubkey_idx, _)| *pubkey_idx);
+        let mut script = ScriptMut::with_capacity(0);
+        match mode {
+            MultisigMode::Legacy => script.put_opcodes([OP_0]),
+            MultisigMode::Schnorr => {
+                let mut bitfield = vec![0u8; (num_pubkeys + 7) / 8];
+                for &(pubkey_idx, _) in &sigs {
+                    bitfield[pubkey_idx / 8] |= 1 << (pubkey_idx % 8);
+                }
+                script.push_data(&bitfield);
+            }
+        }
+        for (_, sig) in sigs {
+            script.push_data(sig.as_ref());
+        }
+        script.freeze()
+    }
+
+    /// ScriptSig of a P2SH input: `script_sig` followed by a push of the
+    /// `redeem_script`.
+    /// ```
+    /// # use bitcoinsuite_core::script::Script;
+    /// let script_sig = Script::new(vec![0x00, 0x51].into());
+    /// let redeem_script = Script::new(vec![0x87].into());
+    /// assert_eq!(
+    ///     Script::p2sh_script_sig(&script_sig, &redeem_script).hex(),
+    ///     "00510187",
+    /// );
+    /// ```
+    pub fn p2sh_script_sig(script_sig: &Script, redeem_script: &Script) -> Script {
+        let mut script = ScriptMut::with_capacity(0);
+        script.put_bytecode(script_sig.bytecode());
+        script.push_data(redeem_script.bytecode());
+        script.freeze()
+    }
+}
+
+/// Parse `m` and the pubkeys of a script built by [`Script::multisig`].
+pub(crate) fn parse_multisig(script: &Script) -> Option<(usize, Vec<PubKeyVariant>)> {
+    if script.bytecode().last() != Some(&OP_CHECKMULTISIG::N) {
+        return None;
+    }
+    let ops = script.iter_ops().collect::<Result<Vec<_>, _>>().ok()?;
+    let (m, pubkeys) = match ops.as_slice() {
+        [m, pubkeys @ .., _n, _checkmultisig] => (m, pubkeys),
+        _ => return None,
+    };
+    let m = match m {
+        Op::Code(opcode @ Opcode(OP_1::N..=OP_16::N)) => opcode.number() - OP_1::N + 1,
+        Op::Push(_, data) if data.len() == 1 => data[0],
+        _ => return None,
+    } as usize;
+    let pubkeys = pubkeys
+        .iter()
+        .map(|op| match op {
+            Op::Push(_, pubkey) => PubKeyVariant::try_from(pubkey.as_ref()).ok(),
+            Op::Code(_) => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if m < 1 || m > pubkeys.len() || pubkeys.len() > MAX_PUBKEYS_PER_MULTISIG {
+        return None;
+    }
+    // Rejects non-minimal pushes and a wrong `n`
+    if Script::multisig(m, &pubkeys) != *script {
+        return None;
+    }
+    Some((m, pubkeys))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::script::{
+        multisig::parse_multisig, opcode::*, MultisigMode, PubKey, PubKeyVariant, Script,
+        ScriptMut, UncompressedPubKey,
+    };
+
+    #[test]
+    fn test_parse_multisig() {
+        let pubkeys = (0..20)
+            .map(|idx| match idx % 2 {
+                0 => PubKeyVariant::Compressed(PubKey([idx; 33])),
+                _ => PubKeyVariant::Uncompressed(UncompressedPubKey([idx; 65])),
+            })
+            .collect::<Vec<_>>();
+        for (m, n) in [(1, 1), (2, 3), (16, 17), (20, 20)] {
+            let script = Script::multisig(m, &pubkeys[..n]);
+            assert_eq!(parse_multisig(&script), Some((m, pubkeys[..n].to_vec())));
+        }
+        // n=17 is pushed as number
+        let script = Script::multisig(17, &pubkeys[..17]);
+        assert_eq!(&script.bytecode()[..2], [1, 17]);
+
+        let mut script = ScriptMut::with_capacity(0);
+        script.put_opcodes([OP_2]);
+        script.put_bytecode(&[33]);
+        script.put_bytecode(&[2; 33]);
+        script.put_opcodes([OP_1, OP_CHECKMULTISIG]);
+        // m > n
+        assert_eq!(parse_multisig(&script.freeze()), None);
+        // Non-minimal push of m
+        let script = Script::multisig(1, &pubkeys[..1]);
+        let non_minimal = [[1, 1].as_ref(), &script.bytecode()[1..]].concat();
+        assert_eq!(parse_multisig(&Script::new(non_minimal.into())), None);
+        // Wrong n
+        let mut wrong_n = script.to_vec();
+        wrong_n[35] = OP_2::N;
+        assert_eq!(parse_multisig(&Script::new(wrong_n.into())), None);
+        assert_eq!(parse_multisig(&Script::p2pk(&PubKey([2; 33]))), None);
+    }
+
+    #[test]
+    fn test_multisig_script_sig() {
+        let sigs = (0..10)
+            .map(|idx| (idx, vec![idx as u8; 65]))
+            .collect::<Vec<_>>();
+        let script_sig = Script::multisig_script_sig(MultisigMode::Schnorr, 10, &sigs[8..]);
+        assert_eq!(&script_sig.bytecode()[..3], [2, 0x00, 0x03]);
+        let script_sig = Script::multisig_script_sig(MultisigMode::Schnorr, 3, &sigs[..1]);
+        assert_eq!(&script_sig.bytecode()[..2], [OP_1::N, 65]);
+        let script_sig = Script::multisig_script_sig(MultisigMode::Schnorr, 9, &sigs[1..2]);
+        assert_eq!(&script_sig.bytecode()[..4], [2, 0x02, 0x00, 65]);
+        let script_sig =
+            Script::multisig_script_sig(MultisigMode::Legacy, 3, &[(2, [2; 71]), (0, [1; 71])]);
+        assert_eq!(
+            script_sig.bytecode().as_ref(),
+            [[0, 71].as_ref(), &[1; 71], &[71], &[2; 71]].concat(),
+        );
+    }
+}
@@ -7,7 +7,7 @@ use bytes::Bytes;
 use crate::{
     bytes::{read_array, read_bytes},
     error::DataError,
-    script::opcode::*,
+    script::{opcode::*, script_mut::push_opcode},
 };
 
 /// An operation in a script.
@@ -44,4 +44,54 @@ impl Op {
             otherwise => Op::Code(otherwise),
         })
     }
+
+    /// Data this op pushes onto the stack, including the number pushes
+    /// [`OP_0`], [`OP_1NEGATE`] and [`OP_1`] to [`OP_16`]. `None` for
+    /// non-push opcodes.
+    /// ```
+    /// # use bitcoinsuite_core::script::{opcode::*, Op};
+    /// assert_eq!(Op::Push(Opcode(2), vec![1, 2].into()).pushed_data(), Some(vec![1, 2].into()));
+    /// assert_eq!(Op::Code(OP_0).pushed_data(), Some(vec![].into()));
+    /// assert_eq!(Op::Code(OP_1NEGATE).pushed_data(), Some(vec![0x81].into()));
+    /// assert_eq!(Op::Code(OP_16).pushed_data(), Some(vec![16].into()));
+    /// assert_eq!(Op::Code(OP_RESERVED).pushed_data(), None);
+    /// assert_eq!(Op::Code(OP_CHECKSIG).pushed_data(), None);
+    /// ```
+    pub fn pushed_data(&self) -> Option<Bytes> {
+        match *self {
+            Op::Push(_, ref data) => Some(data.clone()),
+            Op::Code(OP_0) => Some(Bytes::new()),
+            Op::Code(OP_1NEGATE) => Some(Bytes::from_static(&[0x81])),
+            Op::Code(opcode @ Opcode(OP_1::N..=OP_16::N)) => {
+                Some(Bytes::from(vec![opcode.number() - OP_1::N + 1]))
+            }
+            Op::Code(_) => None,
+        }
+    }
+
+    /// Whether this op pushes its data with the smallest possible opcode, as
+    /// required by MINIMALDATA, see [`ScriptMut::push_data`]. Ops that don't
+    /// push data are considered minimal.
+    ///
+    /// [`ScriptMut::push_data`]: crate::script::ScriptMut::push_data
+    /// ```
+    /// # use bitcoinsuite_core::script::{opcode::*, Op};
+    /// assert!(Op::Push(Opcode(2), vec![1, 2].into()).is_minimal_push());
+    /// assert!(Op::Code(OP_5).is_minimal_push());
+    /// // Should be OP_5
+    /// assert!(!Op::Push(Opcode(1), vec![5].into()).is_minimal_push());
+    /// // Should be OP_0
+    /// assert!(!Op::Push(OP_PUSHDATA1, vec![].into()).is_minimal_push());
+    /// // Should be a direct push
+    /// assert!(!Op::Push(OP_PUSHDATA1, vec![1, 2].into()).is_minimal_push());
+    /// ```
+    pub fn is_minimal_push(&self) -> bool {
+        match self {
+            Op::Code(_) => true,
+            Op::Push(opcode, data) => match data.as_ref() {
+                [] | [1..=16] | [0x81] => false,
+                _ => *opcode == push_opcode(data.len()),
+            },
+        }
+    }
 }
@@ -131,20 +131,272 @@ define_opcodes! {
     OP_15 = 0x5f,
     /// Push the number 16 onto the stack.
     OP_16 = 0x60,
+    /// Do nothing.
+    OP_NOP = 0x61,
+    /// Reserved, fails the script if executed.
+    OP_VER = 0x62,
+    /// Execute the following ops if the top stack item is true (must be
+    /// minimally encoded on BCH).
+    OP_IF = 0x63,
+    /// Execute the following ops if the top stack item is false.
+    OP_NOTIF = 0x64,
+    /// Start a loop, which is repeated until [`OP_UNTIL`] pops a true value
+    /// (May 2026).
+    OP_BEGIN = 0x65,
+    /// Reserved before [`OP_BEGIN`], failed the script even if not executed.
+    OP_VERIF = 0x65,
+    /// Pop the top stack item and jump back to the matching [`OP_BEGIN`]
+    /// unless it's true (May 2026).
+    OP_UNTIL = 0x66,
+    /// Reserved before [`OP_UNTIL`], failed the script even if not executed.
+    OP_VERNOTIF = 0x66,
+    /// Execute the following ops if the previous [`OP_IF`] branch wasn't
+    /// executed.
+    OP_ELSE = 0x67,
+    /// End an [`OP_IF`]/[`OP_ELSE`] block.
+    OP_ENDIF = 0x68,
+    /// Fail the script if the top stack item is false, otherwise pop it.
+    OP_VERIFY = 0x69,
     /// Mark transaction as invalid. Used to add data to a tx in an output.
     OP_RETURN = 0x6a,
+    /// Move the top stack item to the alt stack.
+    OP_TOALTSTACK = 0x6b,
+    /// Move the top alt stack item to the stack.
+    OP_FROMALTSTACK = 0x6c,
+    /// Remove the top two stack items.
+    OP_2DROP = 0x6d,
+    /// Duplicate the top two stack items.
+    OP_2DUP = 0x6e,
+    /// Duplicate the top three stack items.
+    OP_3DUP = 0x6f,
+    /// Copy the third and fourth stack items to the top.
+    OP_2OVER = 0x70,
+    /// Move the fifth and sixth stack items to the top.
+    OP_2ROT = 0x71,
+    /// Swap the top two pairs of stack items.
+    OP_2SWAP = 0x72,
+    /// Duplicate the top stack item if it's true.
+    OP_IFDUP = 0x73,
+    /// Push the number of stack items.
+    OP_DEPTH = 0x74,
+    /// Remove the top stack item.
+    OP_DROP = 0x75,
     /// Duplicate the top stack item.
     OP_DUP = 0x76,
+    /// Remove the second stack item.
+    OP_NIP = 0x77,
+    /// Copy the second stack item to the top.
+    OP_OVER = 0x78,
+    /// Pop n and copy the n-th stack item (counted from the top) to the top.
+    OP_PICK = 0x79,
+    /// Pop n and move the n-th stack item (counted from the top) to the top.
+    OP_ROLL = 0x7a,
+    /// Move the third stack item to the top.
+    OP_ROT = 0x7b,
+    /// Swap the top two stack items.
+    OP_SWAP = 0x7c,
+    /// Copy the top stack item below the second item.
+    OP_TUCK = 0x7d,
+    /// Concatenate the top two stack items.
+    OP_CAT = 0x7e,
+    /// Pop n and split the top stack item at byte n into two items.
+    OP_SPLIT = 0x7f,
+    /// Pop size and encode the top stack item as number of that size.
+    OP_NUM2BIN = 0x80,
+    /// Convert the top stack item to a minimally encoded number.
+    OP_BIN2NUM = 0x81,
+    /// Push the byte length of the top stack item, without popping it.
+    OP_SIZE = 0x82,
+    /// Flip all bits of the top stack item (May 2026, disabled before).
+    OP_INVERT = 0x83,
+    /// Bitwise AND of the top two stack items, which must have equal size.
+    OP_AND = 0x84,
+    /// Bitwise OR of the top two stack items, which must have equal size.
+    OP_OR = 0x85,
+    /// Bitwise XOR of the top two stack items, which must have equal size.
+    OP_XOR = 0x86,
     /// If the top two stack items are byte-equal, push 1 onto the stack,
     /// otherwise 0.
     OP_EQUAL = 0x87,
     /// Like [`OP_EQUAL`], but fail the script if the items aren't equal.
     OP_EQUALVERIFY = 0x88,
+    /// Pop a function identifier and the function's bytecode and define the
+    /// function (May 2026).
+    OP_DEFINE = 0x89,
+    /// Reserved before [`OP_DEFINE`], failed the script if executed.
+    OP_RESERVED1 = 0x89,
+    /// Pop a function identifier and evaluate the function defined by
+    /// [`OP_DEFINE`] (May 2026).
+    OP_INVOKE = 0x8a,
+    /// Reserved before [`OP_INVOKE`], failed the script if executed.
+    OP_RESERVED2 = 0x8a,
+    /// Add 1 to the top stack item.
+    OP_1ADD = 0x8b,
+    /// Subtract 1 from the top stack item.
+    OP_1SUB = 0x8c,
+    /// Pop n and shift the number on top of the stack left by n bits
+    /// (May 2026).
+    OP_LSHIFTNUM = 0x8d,
+    /// Multiply the top stack item by 2, disabled before [`OP_LSHIFTNUM`].
+    OP_2MUL = 0x8d,
+    /// Pop n and shift the number on top of the stack right by n bits,
+    /// rounding down (May 2026).
+    OP_RSHIFTNUM = 0x8e,
+    /// Divide the top stack item by 2, disabled before [`OP_RSHIFTNUM`].
+    OP_2DIV = 0x8e,
+    /// Negate the top stack item.
+    OP_NEGATE = 0x8f,
+    /// Replace the top stack item by its absolute value.
+    OP_ABS = 0x90,
+    /// Replace the top stack item by 1 if it's 0, otherwise by 0.
+    OP_NOT = 0x91,
+    /// Replace the top stack item by 0 if it's 0, otherwise by 1.
+    OP_0NOTEQUAL = 0x92,
+    /// Pop a and b and push a + b.
+    OP_ADD = 0x93,
+    /// Pop a and b and push a - b.
+    OP_SUB = 0x94,
+    /// Pop a and b and push a * b.
+    OP_MUL = 0x95,
+    /// Pop a and b and push a / b, rounded towards zero.
+    OP_DIV = 0x96,
+    /// Pop a and b and push the remainder of a / b.
+    OP_MOD = 0x97,
+    /// Pop n and shift the bits of the top stack item left by n, keeping
+    /// its size (May 2026).
+    OP_LSHIFTBIN = 0x98,
+    /// Shift the second stack item left by the top item, disabled before
+    /// [`OP_LSHIFTBIN`].
+    OP_LSHIFT = 0x98,
+    /// Pop n and shift the bits of the top stack item right by n, keeping
+    /// its size (May 2026).
+    OP_RSHIFTBIN = 0x99,
+    /// Shift the second stack item right by the top item, disabled before
+    /// [`OP_RSHIFTBIN`].
+    OP_RSHIFT = 0x99,
+    /// Pop a and b and push 1 if both are non-zero, otherwise 0.
+    OP_BOOLAND = 0x9a,
+    /// Pop a and b and push 1 if either is non-zero, otherwise 0.
+    OP_BOOLOR = 0x9b,
+    /// Pop a and b and push 1 if they're numerically equal, otherwise 0.
+    OP_NUMEQUAL = 0x9c,
+    /// Like [`OP_NUMEQUAL`], but fail the script if they're not equal.
+    OP_NUMEQUALVERIFY = 0x9d,
+    /// Pop a and b and push 1 if they're not numerically equal, otherwise 0.
+    OP_NUMNOTEQUAL = 0x9e,
+    /// Pop a and b and push 1 if a < b, otherwise 0.
+    OP_LESSTHAN = 0x9f,
+    /// Pop a and b and push 1 if a > b, otherwise 0.
+    OP_GREATERTHAN = 0xa0,
+    /// Pop a and b and push 1 if a <= b, otherwise 0.
+    OP_LESSTHANOREQUAL = 0xa1,
+    /// Pop a and b and push 1 if a >= b, otherwise 0.
+    OP_GREATERTHANOREQUAL = 0xa2,
+    /// Pop a and b and push the smaller one.
+    OP_MIN = 0xa3,
+    /// Pop a and b and push the larger one.
+    OP_MAX = 0xa4,
+    /// Pop x, min and max and push 1 if min <= x < max, otherwise 0.
+    OP_WITHIN = 0xa5,
+    /// Hash the top stack item x using RIPEMD-160(x)
+    OP_RIPEMD160 = 0xa6,
+    /// Hash the top stack item x using SHA-1(x)
+    OP_SHA1 = 0xa7,
+    /// Hash the top stack item x using SHA-256(x)
+    OP_SHA256 = 0xa8,
     /// Hash the top stack item x using RIPEMD-160(SHA-256(x))
     OP_HASH160 = 0xa9,
+    /// Hash the top stack item x using SHA-256(SHA-256(x))
+    OP_HASH256 = 0xaa,
+    /// Signatures only sign the script after the last executed
+    /// `OP_CODESEPARATOR`.
+    OP_CODESEPARATOR = 0xab,
     /// Pop pubkey and signature and verify if they sign this input's BIP143
     /// sighash.
     OP_CHECKSIG = 0xac,
+    /// Like [`OP_CHECKSIG`], but fail the script if the signature is invalid.
+    OP_CHECKSIGVERIFY = 0xad,
+    /// Pop N pubkeys, M signatures and a dummy/bitfield and verify that the
+    /// signatures sign this input's sighash.
+    OP_CHECKMULTISIG = 0xae,
+    /// Like [`OP_CHECKMULTISIG`], but fail the script if the signatures are
+    /// invalid.
+    OP_CHECKMULTISIGVERIFY = 0xaf,
+    /// Do nothing, reserved for upgrades.
+    OP_NOP1 = 0xb0,
+    /// Fail the script unless the tx locktime is at least the top stack item
+    /// (BIP65).
+    OP_CHECKLOCKTIMEVERIFY = 0xb1,
+    /// Fail the script unless the input's relative locktime is at least the
+    /// top stack item (BIP112).
+    OP_CHECKSEQUENCEVERIFY = 0xb2,
+    /// Do nothing, reserved for upgrades.
+    OP_NOP4 = 0xb3,
+    /// Do nothing, reserved for upgrades.
+    OP_NOP5 = 0xb4,
+    /// Do nothing, reserved for upgrades.
+    OP_NOP6 = 0xb5,
+    /// Do nothing, reserved for upgrades.
+    OP_NOP7 = 0xb6,
+    /// Do nothing, reserved for upgrades.
+    OP_NOP8 = 0xb7,
+    /// Do nothing, reserved for upgrades.
+    OP_NOP9 = 0xb8,
+    /// Do nothing, reserved for upgrades.
+    OP_NOP10 = 0xb9,
+    /// Pop pubkey, message and signature and verify the signature signs the
+    /// SHA-256 of the message.
+    OP_CHECKDATASIG = 0xba,
+    /// Like [`OP_CHECKDATASIG`], but fail the script if the signature is
+    /// invalid.
+    OP_CHECKDATASIGVERIFY = 0xbb,
+    /// Reverse the bytes of the top stack item.
+    OP_REVERSEBYTES = 0xbc,
+    /// Push the index of the evaluated input.
+    OP_INPUTINDEX = 0xc0,
+    /// Push the bytecode being evaluated, starting after the last executed
+    /// `OP_CODESEPARATOR`.
+    OP_ACTIVEBYTECODE = 0xc1,
+    /// Push the version of the tx.
+    OP_TXVERSION = 0xc2,
+    /// Push the number of inputs of the tx.
+    OP_TXINPUTCOUNT = 0xc3,
+    /// Push the number of outputs of the tx.
+    OP_TXOUTPUTCOUNT = 0xc4,
+    /// Push the locktime of the tx.
+    OP_TXLOCKTIME = 0xc5,
+    /// Pop an input index and push the value of the coin it spends.
+    OP_UTXOVALUE = 0xc6,
+    /// Pop an input index and push the locking bytecode of the coin it
+    /// spends.
+    OP_UTXOBYTECODE = 0xc7,
+    /// Pop an input index and push the txid of its outpoint.
+    OP_OUTPOINTTXHASH = 0xc8,
+    /// Pop an input index and push the output index of its outpoint.
+    OP_OUTPOINTINDEX = 0xc9,
+    /// Pop an input index and push its unlocking bytecode.
+    OP_INPUTBYTECODE = 0xca,
+    /// Pop an input index and push its sequence number.
+    OP_INPUTSEQUENCENUMBER = 0xcb,
+    /// Pop an output index and push its value.
+    OP_OUTPUTVALUE = 0xcc,
+    /// Pop an output index and push its locking bytecode.
+    OP_OUTPUTBYTECODE = 0xcd,
+    /// Pop an input index and push the token category of the coin it spends,
+    /// followed by the NFT capability if it's mutable or minting.
+    OP_UTXOTOKENCATEGORY = 0xce,
+    /// Pop an input index and push the NFT commitment of the coin it spends.
+    OP_UTXOTOKENCOMMITMENT = 0xcf,
+    /// Pop an input index and push the fungible token amount of the coin it
+    /// spends.
+    OP_UTXOTOKENAMOUNT = 0xd0,
+    /// Pop an output index and push its token category, followed by the NFT
+    /// capability if it's mutable or minting.
+    OP_OUTPUTTOKENCATEGORY = 0xd1,
+    /// Pop an output index and push its NFT commitment.
+    OP_OUTPUTTOKENCOMMITMENT = 0xd2,
+    /// Pop an output index and push its fungible token amount.
+    OP_OUTPUTTOKENAMOUNT = 0xd3,
 }
 
 #[cfg(test)]
@@ -163,5 +415,13 @@ mod tests {
         assert_eq!(OP_0.to_string(), "OP_0");
         assert_eq!(OP_1.to_string(), "OP_1");
         assert_eq!(OP_TRUE.to_string(), "OP_1");
+        // Opcodes of the May 2026 upgrade take precedence over legacy names
+        assert_eq!(OP_VERIF.to_string(), "OP_BEGIN");
+        assert_eq!(Opcode(0x66).to_string(), "OP_UNTIL");
+        assert_eq!(Opcode(0x89).to_string(), "OP_DEFINE");
+        assert_eq!(Opcode(0x8a).to_string(), "OP_INVOKE");
+        assert_eq!(Opcode(0x8d).to_string(), "OP_LSHIFTNUM");
+        assert_eq!(Opcode(0x99).to_string(), "OP_RSHIFTBIN");
+        assert_eq!(Opcode(0xff).to_string(), "[unrecognized opcode]");
     }
 }
@@ -5,14 +5,16 @@
 use bytes::Bytes;
 
 use crate::{
-    bytes::read_bytes,
     error::DataError,
-    hash::{Hashed, ShaRmd160},
-    script::{opcode::*, PubKey, ScriptMut, ScriptOpIter, UncompressedPubKey},
-    ser::{read_compact_size, BitcoinSer, BitcoinSerializer},
-    tx::{Bitfield, CashToken, TokenBitfield, WrappedTokenScript},
+    hash::{Hashed, Sha256d, ShaRmd160},
+    script::{opcode::*, Op, PubKey, ScriptMut, ScriptOpIter, UncompressedPubKey},
+    ser::{BitcoinSer, BitcoinSerializer},
+    tx::{CashToken, WrappedTokenScript},
 };
 
+/// Maximum size of a script, in bytes (consensus).
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+
 /// A Bitcoin script.
 ///
 /// This is immutable, and uses [`Bytes`] to store the bytecode, making it cheap
@@ -67,6 +69,26 @@ impl Script {
         script.freeze()
     }
 
+    /// Pay-to-script-hash using a 32-byte hash:
+    /// `OP_HASH256 <script hash> OP_EQUAL`
+    /// ```
+    /// # use bitcoinsuite_core::{script::Script, hash::Sha256d};
+    /// let script = Script::p2sh32(&Sha256d([0x11; 32]));
+    /// assert_eq!(
+    ///     script.hex(),
+    ///     "aa20111111111111111111111111111111111111111111111111111111111111\
+    ///      111187",
+    /// );
+    /// ```
+    pub fn p2sh32(hash: &Sha256d) -> Script {
+        let mut script = ScriptMut::with_capacity(1 + 1 + Sha256d::SIZE + 1);
+        script.put_opcodes([OP_HASH256]);
+        script.put_bytecode(&[Sha256d::SIZE as u8]);
+        script.put_bytecode(hash.as_le_bytes());
+        script.put_opcodes([OP_EQUAL]);
+        script.freeze()
+    }
+
     /// Pay-to-public-key (compressed): `<pubkey> OP_CHECKSIG`
     /// ```
     /// # use bitcoinsuite_core::{script::{PubKey, Script}, hash::ShaRmd160};
@@ -163,6 +185,48 @@ impl Script {
         }
     }
 
+    /// Whether this script only consists of push ops (including
+    /// [`OP_RESERVED`]), as required for standard scriptSigs. Scripts that
+    /// can't be parsed are not push-only.
+    /// ```
+    /// # use bitcoinsuite_core::script::Script;
+    /// assert!(Script::new(vec![0x00, 0x01, 0x02, 0x51, 0x60].into()).is_push_only());
+    /// assert!(Script::new(vec![].into()).is_push_only());
+    /// assert!(!Script::new(vec![0x51, 0x76].into()).is_push_only());
+    /// assert!(!Script::new(vec![0x02, 0x01].into()).is_push_only());
+    /// ```
+    pub fn is_push_only(&self) -> bool {
+        self.iter_ops().all(|op| match op {
+            Ok(Op::Push(..)) => true,
+            Ok(Op::Code(opcode)) => opcode.number() <= OP_16::N,
+            Err(_) => false,
+        })
+    }
+
+    /// Pushes of this script that don't use the smallest possible opcode,
+    /// with their index in [`Script::iter_ops`], see
+    /// [`Op::is_minimal_push`]. Scripts executing them fail with
+    /// MINIMALDATA. Parsing stops at the first invalid op.
+    /// ```
+    /// # use bitcoinsuite_core::{hash::ShaRmd160, script::{opcode::*, Op, Script}};
+    /// let script = Script::new(vec![0x51, 0x01, 0x02, 0x4c, 0x01, 0x60, 0x87].into());
+    /// assert_eq!(
+    ///     script.non_minimal_pushes(),
+    ///     vec![
+    ///         (1, Op::Push(Opcode(1), vec![2].into())),
+    ///         (2, Op::Push(OP_PUSHDATA1, vec![0x60].into())),
+    ///     ],
+    /// );
+    /// assert!(Script::p2pkh(&ShaRmd160([1; 20])).non_minimal_pushes().is_empty());
+    /// ```
+    pub fn non_minimal_pushes(&self) -> Vec<(usize, Op)> {
+        self.iter_ops()
+            .map_while(Result::ok)
+            .enumerate()
+            .filter(|(_, op)| !op.is_minimal_push())
+            .collect()
+    }
+
     /// Iterator over the operations in this script.
     ///
     /// ```
@@ -215,68 +279,32 @@ impl Script {
     pub fn unwrap_prefixed_lockscript(
         data: &mut bytes::Bytes,
     ) -> Result<WrappedTokenScript, DataError> {
-        let token_script_size = data.slice(0..1)[0];
-        let token_size = read_token_prefix_data_size(data)?;
-        //consume script size byte
-        let _eat_script_size = read_bytes(data, 1);
-        //consume token data from script
-        let mut token_bytes = read_bytes(data, token_size as usize)?;
-
-        let token_len = token_bytes.as_ref().to_vec().len();
-
-        let token: CashToken = BitcoinSer::deser(&mut token_bytes)?;
-        let next_readsize = token_script_size as usize - token_len;
-
-        let script_bytes = read_bytes(data, next_readsize)?;
-        let script = Script::new(script_bytes);
-        return Ok(WrappedTokenScript(Some(token), script));
+        let mut bytecode = Bytes::deser(data)?;
+        let token: CashToken = BitcoinSer::deser(&mut bytecode)?;
+        Ok(WrappedTokenScript(Some(token), Script::new(bytecode)))
     }
 }
 
-/// read token prefix, remaining bytes: lockscript data.
+/// Size of the token prefix (including `PREFIX_TOKEN`) of the serialized
+/// locking bytecode at the start of `data`, which begins with the
+/// bytecode's length. `data` is not consumed.
+/// ```
+/// # #![allow(deprecated)]
+/// # use bitcoinsuite_core::script::read_token_prefix_data_size;
+/// # use bytes::Bytes;
+/// // Fungible token prefix of 35 bytes (amount 5) followed by OP_1
+/// let mut data = Bytes::from(
+///     [[36, 0xef].as_ref(), &[1; 32], &[0x10, 5, 0x51]].concat(),
+/// );
+/// assert_eq!(read_token_prefix_data_size(&mut data).unwrap(), 35);
+/// assert_eq!(data.len(), 37);
+/// ```
+#[deprecated(note = "Decode outputs with `Output::deser_with` or `CashToken::decode` instead")]
 pub fn read_token_prefix_data_size(data: &mut Bytes) -> Result<usize, DataError> {
-    let token_bitfield_index = 34;
-    let bitfield = data.slice(token_bitfield_index..token_bitfield_index + 1);
-    let bitfield = TokenBitfield(bitfield[0].into());
-    let commitment_len = if bitfield.has_commitment_length() {
-        let commitment_index = token_bitfield_index + 1;
-        let commitment_len = data.slice(commitment_index..commitment_index + 1)[0];
-        let commitment_len = Bytes::copy_from_slice([commitment_len].as_ref());
-        let size = read_compact_size(&mut Bytes::from(commitment_len));
-        size? + 1
-    } else {
-        0
-    };
-
-    let amount_len = if bitfield.has_amount() {
-        let token_amount_index = token_bitfield_index + commitment_len as usize + 1;
-        let token_amount_size = data.slice(token_amount_index..token_amount_index + 1);
-
-        let size = match token_amount_size[0] as u64 {
-            0..=0xfc => 1,
-            0xfd => 3,
-            0xfe => 5,
-            0xff => 9,
-            _ => 0,
-        };
-
-        size
-    } else {
-        0
-    };
-
-    let prefix_byte = 1;
-    let bitfield_byte = 1;
-    let category_bytes = 32;
-
-    let total = /* total script size */
-          prefix_byte /* magic byte ef */
-        + category_bytes
-        + bitfield_byte
-        + commitment_len as usize
-        + amount_len as usize;
-
-    Ok(total)
+    let mut bytecode = Bytes::deser(&mut data.clone())?;
+    let bytecode_len = bytecode.len();
+    CashToken::deser(&mut bytecode)?;
+    Ok(bytecode_len - bytecode.len())
 }
 
 impl AsRef<[u8]> for Script {
@@ -1,6 +1,6 @@
 use bytes::{BufMut, BytesMut};
 
-use crate::script::{opcode::Opcode, Script};
+use crate::script::{opcode::*, Op, Script, ScriptNum};
 
 /// A mutable version of [`Script`], it allows appending more opcodes/bytecode
 /// etc.
@@ -48,6 +48,79 @@ impl ScriptMut {
         self.0.put_slice(slice);
     }
 
+    /// Push `data` onto the script with the smallest possible opcode, as
+    /// required by MINIMALDATA: [`OP_0`], [`OP_1NEGATE`] and [`OP_1`] to
+    /// [`OP_16`] for the data they push, a direct push for up to 75 bytes,
+    /// or else the smallest of [`OP_PUSHDATA1`], [`OP_PUSHDATA2`] and
+    /// [`OP_PUSHDATA4`].
+    /// ```
+    /// # use bitcoinsuite_core::script::ScriptMut;
+    /// let mut script_mut = ScriptMut::default();
+    /// script_mut.push_data(&[]);
+    /// script_mut.push_data(&[16]);
+    /// script_mut.push_data(&[0x81]);
+    /// script_mut.push_data(&[17, 18]);
+    /// assert_eq!(script_mut.freeze().hex(), "00604f021112");
+    ///
+    /// let mut script_mut = ScriptMut::default();
+    /// script_mut.push_data(&[0xff; 76]);
+    /// assert_eq!(&script_mut.freeze().bytecode()[..3], [0x4c, 76, 0xff]);
+    /// ```
+    pub fn push_data(&mut self, data: &[u8]) {
+        match *data {
+            [] => self.put_opcodes([OP_0]),
+            [num @ 1..=16] => self.put_opcodes([Opcode(OP_1::N + num - 1)]),
+            [0x81] => self.put_opcodes([OP_1NEGATE]),
+            _ => self.put_push(push_opcode(data.len()), data),
+        }
+    }
+
+    /// Push `num` onto the script, encoded as VM number, with the smallest
+    /// possible opcode.
+    /// ```
+    /// # use bitcoinsuite_core::script::ScriptMut;
+    /// let mut script_mut = ScriptMut::default();
+    /// script_mut.push_int(0);
+    /// script_mut.push_int(-1);
+    /// script_mut.push_int(16);
+    /// script_mut.push_int(17);
+    /// script_mut.push_int(-255);
+    /// script_mut.push_int(0x80);
+    /// assert_eq!(script_mut.freeze().hex(), "004f60011102ff80028000");
+    /// ```
+    pub fn push_int(&mut self, num: i64) {
+        self.push_data(&ScriptNum(num).encode());
+    }
+
+    /// Append `op` as it is, keeping the opcode of [`Op::Push`] even if it's
+    /// not minimal, so e.g. ops from [`Script::iter_ops`] are copied
+    /// faithfully.
+    /// ```
+    /// # use bitcoinsuite_core::script::{opcode::*, Op, ScriptMut};
+    /// let mut script_mut = ScriptMut::default();
+    /// script_mut.push_op(&Op::Code(OP_DUP));
+    /// script_mut.push_op(&Op::Push(Opcode(2), vec![1, 2].into()));
+    /// script_mut.push_op(&Op::Push(OP_PUSHDATA2, vec![3].into()));
+    /// assert_eq!(script_mut.freeze().hex(), "760201024d010003");
+    /// ```
+    pub fn push_op(&mut self, op: &Op) {
+        match op {
+            Op::Code(opcode) => self.put_opcodes([*opcode]),
+            Op::Push(opcode, data) => self.put_push(*opcode, data),
+        }
+    }
+
+    fn put_push(&mut self, opcode: Opcode, data: &[u8]) {
+        self.0.put_u8(opcode.number());
+        match opcode {
+            OP_PUSHDATA1 => self.0.put_u8(data.len() as u8),
+            OP_PUSHDATA2 => self.0.put_u16_le(data.len() as u16),
+            OP_PUSHDATA4 => self.0.put_u32_le(data.len() as u32),
+            _ => {}
+        }
+        self.0.put_slice(data);
+    }
+
     /// Turn the given [`ScriptMut`] into a [`Script`], making it immutable.
     /// ```
     /// # use bitcoinsuite_core::script::{Script, ScriptMut};
@@ -60,3 +133,13 @@ impl ScriptMut {
         Script::new(self.0.freeze())
     }
 }
+
+/// Smallest opcode pushing `len` bytes, ignoring the opcodes pushing numbers.
+pub(crate) fn push_opcode(len: usize) -> Opcode {
+    match len {
+        0..=0x4b => Opcode(len as u8),
+        0x4c..=0xff => OP_PUSHDATA1,
+        0x100..=0xffff => OP_PUSHDATA2,
+        _ => OP_PUSHDATA4,
+    }
+}
@@ -0,0 +1,405 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+#[cfg(feature = "bigint")]
+use num_bigint::{BigInt, Sign};
+use thiserror::Error;
+
+/// Number as used by the VM, e.g. by `OP_ADD` or `OP_CHECKLOCKTIMEVERIFY`,
+/// limited to the 8-byte range. Encoded little-endian, with the highest bit
+/// of the last byte as sign (sign-magnitude), and without superfluous bytes.
+///
+/// The range is symmetric, so [`i64::MIN`] is not a valid number; the
+/// arithmetic methods fail instead of producing it.
+///
+/// VM numbers are also common in NFT commitments and covenant parameters:
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     script::{ScriptNum, ScriptNumError},
+/// #     tx::Commitment,
+/// # };
+/// let commitment = Commitment(vec![0xe8, 0x83].into());
+/// assert_eq!(ScriptNum::decode(&commitment.0), Ok(ScriptNum(-1000)));
+/// assert_eq!(ScriptNum(-1000).encode(), [0xe8, 0x83]);
+/// assert_eq!(ScriptNum(128).encode(), [0x80, 0x00]);
+/// assert_eq!(ScriptNum(0).encode(), []);
+///
+/// // Superfluous zero byte
+/// assert_eq!(ScriptNum::decode(&[0x01, 0x00]), Err(ScriptNumError::NonMinimal));
+/// assert_eq!(
+///     ScriptNum::decode(&[1; 9]),
+///     Err(ScriptNumError::TooLong { max_len: 8, actual: 9 }),
+/// );
+/// assert_eq!(
+///     ScriptNum(i64::MAX).checked_add(ScriptNum(1)),
+///     Err(ScriptNumError::Overflow),
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ScriptNum(pub i64);
+
+/// Arbitrary precision VM number, as of the May 2025 upgrade ("BigInt"),
+/// limited to [`BigScriptNum::MAX_LEN`] bytes. Encoded like [`ScriptNum`].
+/// ```
+/// # use bitcoinsuite_core::script::{BigScriptNum, ScriptNum, ScriptNumError};
+/// let num = BigScriptNum::from(ScriptNum(i64::MAX));
+/// let sum = num.checked_add(&num).unwrap();
+/// assert_eq!(sum.encode(), [0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]);
+/// assert_eq!(sum.to_script_num(), Err(ScriptNumError::Overflow));
+/// let diff = sum.checked_sub(&num).unwrap();
+/// assert_eq!(diff.to_script_num(), Ok(ScriptNum(i64::MAX)));
+/// ```
+#[cfg(feature = "bigint")]
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BigScriptNum(pub BigInt);
+
+/// Errors decoding or computing VM numbers.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum ScriptNumError {
+    /// The encoding has more bytes than allowed.
+    #[error("VM number has {actual} bytes, maximum is {max_len}")]
+    TooLong {
+        /// Maximum number of bytes.
+        max_len: usize,
+        /// Number of bytes of the encoding.
+        actual: usize,
+    },
+
+    /// The encoding has a superfluous last byte.
+    #[error("VM number is not minimally encoded")]
+    NonMinimal,
+
+    /// The result of an operation is out of range.
+    #[error("VM number overflow")]
+    Overflow,
+
+    /// Division or modulo by zero.
+    #[error("Division by zero")]
+    DivisionByZero,
+}
+
+impl ScriptNum {
+    /// Maximum number of bytes of a [`ScriptNum`].
+    pub const MAX_LEN: usize = 8;
+    /// Largest number.
+    pub const MAX: ScriptNum = ScriptNum(i64::MAX);
+    /// Smallest number, note that this is `-MAX`, not [`i64::MIN`].
+    pub const MIN: ScriptNum = ScriptNum(-i64::MAX);
+
+    /// Decode a minimally encoded number of up to [`ScriptNum::MAX_LEN`]
+    /// bytes.
+    pub fn decode(bytes: &[u8]) -> Result<ScriptNum, ScriptNumError> {
+        check_encoding(bytes, Self::MAX_LEN)?;
+        let (&last, _) = match bytes.split_last() {
+            Some(split) => split,
+            None => return Ok(ScriptNum(0)),
+        };
+        let mut abs = 0u64;
+        for (idx, &byte) in bytes.iter().enumerate() {
+            abs |= u64::from(byte) << (8 * idx);
+        }
+        if last & 0x80 == 0 {
+            return Ok(ScriptNum(abs as i64));
+        }
+        abs &= !(0x80 << (8 * (bytes.len() - 1)));
+        Ok(ScriptNum(-(abs as i64)))
+    }
+
+    /// Minimal encoding of this number. [`i64::MIN`] is encoded with 9
+    /// bytes, so it can't be decoded again.
+    pub fn encode(self) -> Vec<u8> {
+        let mut abs = self.0.unsigned_abs();
+        let mut bytes = Vec::new();
+        while abs > 0 {
+            bytes.push(abs as u8);
+            abs >>= 8;
+        }
+        add_sign(&mut bytes, self.0 < 0);
+        bytes
+    }
+
+    /// `self + other`, failing outside of the 8-byte range.
+    pub fn checked_add(self, other: ScriptNum) -> Result<ScriptNum, ScriptNumError> {
+        Self::in_range(self.0.checked_add(other.0))
+    }
+
+    /// `self - other`, failing outside of the 8-byte range.
+    pub fn checked_sub(self, other: ScriptNum) -> Result<ScriptNum, ScriptNumError> {
+        Self::in_range(self.0.checked_sub(other.0))
+    }
+
+    /// `self * other`, failing outside of the 8-byte range.
+    pub fn checked_mul(self, other: ScriptNum) -> Result<ScriptNum, ScriptNumError> {
+        Self::in_range(self.0.checked_mul(other.0))
+    }
+
+    /// `self / other`, rounded towards zero like `OP_DIV`.
+    pub fn checked_div(self, other: ScriptNum) -> Result<ScriptNum, ScriptNumError> {
+        if other.0 == 0 {
+            return Err(ScriptNumError::DivisionByZero);
+        }
+        Self::in_range(self.0.checked_div(other.0))
+    }
+
+    /// Remainder of `self / other`, with the sign of `self` like `OP_MOD`.
+    pub fn checked_rem(self, other: ScriptNum) -> Result<ScriptNum, ScriptNumError> {
+        if other.0 == 0 {
+            return Err(ScriptNumError::DivisionByZero);
+        }
+        Self::in_range(self.0.checked_rem(other.0))
+    }
+
+    /// `-self`, failing for [`i64::MIN`].
+    pub fn checked_neg(self) -> Result<ScriptNum, ScriptNumError> {
+        Self::in_range(self.0.checked_neg())
+    }
+
+    fn in_range(num: Option<i64>) -> Result<ScriptNum, ScriptNumError> {
+        match num {
+            Some(num) if num != i64::MIN => Ok(ScriptNum(num)),
+            _ => Err(ScriptNumError::Overflow),
+        }
+    }
+}
+
+impl From<i64> for ScriptNum {
+    fn from(num: i64) -> Self {
+        ScriptNum(num)
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl BigScriptNum {
+    /// Maximum number of bytes of a [`BigScriptNum`], the stack element
+    /// limit of the May 2025 upgrade.
+    pub const MAX_LEN: usize = 10_000;
+
+    /// Decode a minimally encoded number of up to
+    /// [`BigScriptNum::MAX_LEN`] bytes.
+    pub fn decode(bytes: &[u8]) -> Result<BigScriptNum, ScriptNumError> {
+        check_encoding(bytes, Self::MAX_LEN)?;
+        let (&last, rest) = match bytes.split_last() {
+            Some(split) => split,
+            None => return Ok(BigScriptNum::default()),
+        };
+        let mut magnitude = rest.to_vec();
+        magnitude.push(last & 0x7f);
+        let sign = if last & 0x80 != 0 {
+            Sign::Minus
+        } else {
+            Sign::Plus
+        };
+        Ok(BigScriptNum(BigInt::from_bytes_le(sign, &magnitude)))
+    }
+
+    /// Minimal encoding of this number.
+    pub fn encode(&self) -> Vec<u8> {
+        let (sign, mut bytes) = self.0.to_bytes_le();
+        if sign == Sign::NoSign {
+            return Vec::new();
+        }
+        add_sign(&mut bytes, sign == Sign::Minus);
+        bytes
+    }
+
+    /// Number of bytes of the encoding.
+    pub fn encoded_len(&self) -> usize {
+        match self.0.bits() {
+            0 => 0,
+            // The sign takes one bit of the last byte
+            bits => bits as usize / 8 + 1,
+        }
+    }
+
+    /// Convert to a [`ScriptNum`], failing outside of the 8-byte range.
+    pub fn to_script_num(&self) -> Result<ScriptNum, ScriptNumError> {
+        match i64::try_from(&self.0) {
+            Ok(num) if num != i64::MIN => Ok(ScriptNum(num)),
+            _ => Err(ScriptNumError::Overflow),
+        }
+    }
+
+    /// `self + other`, failing above [`BigScriptNum::MAX_LEN`] bytes.
+    pub fn checked_add(&self, other: &BigScriptNum) -> Result<BigScriptNum, ScriptNumError> {
+        Self::in_range(&self.0 + &other.0)
+    }
+
+    /// `self - other`, failing above [`BigScriptNum::MAX_LEN`] bytes.
+    pub fn checked_sub(&self, other: &BigScriptNum) -> Result<BigScriptNum, ScriptNumError> {
+        Self::in_range(&self.0 - &other.0)
+    }
+
+    /// `self * other`, failing above [`BigScriptNum::MAX_LEN`] bytes.
+    pub fn checked_mul(&self, other: &BigScriptNum) -> Result<BigScriptNum, ScriptNumError> {
+        // The product has at least `bits - 1` bits, avoid computing it if
+        // that's already too many
+        if self.0.bits() + other.0.bits() > Self::MAX_LEN as u64 * 8 + 1 {
+            return Err(ScriptNumError::Overflow);
+        }
+        Self::in_range(&self.0 * &other.0)
+    }
+
+    /// `self / other`, rounded towards zero like `OP_DIV`.
+    pub fn checked_div(&self, other: &BigScriptNum) -> Result<BigScriptNum, ScriptNumError> {
+        if other.0.sign() == Sign::NoSign {
+            return Err(ScriptNumError::DivisionByZero);
+        }
+        Ok(BigScriptNum(&self.0 / &other.0))
+    }
+
+    /// Remainder of `self / other`, with the sign of `self` like `OP_MOD`.
+    pub fn checked_rem(&self, other: &BigScriptNum) -> Result<BigScriptNum, ScriptNumError> {
+        if other.0.sign() == Sign::NoSign {
+            return Err(ScriptNumError::DivisionByZero);
+        }
+        Ok(BigScriptNum(&self.0 % &other.0))
+    }
+
+    fn in_range(num: BigInt) -> Result<BigScriptNum, ScriptNumError> {
+        let num = BigScriptNum(num);
+        if num.encoded_len() > Self::MAX_LEN {
+            return Err(ScriptNumError::Overflow);
+        }
+        Ok(num)
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl From<ScriptNum> for BigScriptNum {
+    fn from(num: ScriptNum) -> Self {
+        BigScriptNum(num.0.into())
+    }
+}
+
+/// Check the size and minimality of an encoded VM number.
+fn check_encoding(bytes: &[u8], max_len: usize) -> Result<(), ScriptNumError> {
+    if bytes.len() > max_len {
+        return Err(ScriptNumError::TooLong {
+            max_len,
+            actual: bytes.len(),
+        });
+    }
+    match *bytes {
+        // Zero must be encoded as empty bytes
+        [last] if last & 0x7f == 0 => Err(ScriptNumError::NonMinimal),
+        // The last byte only holds the sign, but the previous byte has room
+        [.., prev, last] if last & 0x7f == 0 && prev & 0x80 == 0 => Err(ScriptNumError::NonMinimal),
+        _ => Ok(()),
+    }
+}
+
+/// Add the sign bit to the little-endian magnitude `bytes`, adding a byte if
+/// the highest bit is taken.
+fn add_sign(bytes: &mut Vec<u8>, is_negative: bool) {
+    match bytes.last_mut() {
+        Some(last) if *last & 0x80 != 0 => bytes.push(if is_negative { 0x80 } else { 0 }),
+        Some(last) if is_negative => *last |= 0x80,
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::script::{ScriptNum, ScriptNumError};
+
+    #[test]
+    fn test_script_num_encoding() {
+        for (num, encoded) in [
+            (0, vec![]),
+            (1, vec![0x01]),
+            (-1, vec![0x81]),
+            (127, vec![0x7f]),
+            (-127, vec![0xff]),
+            (128, vec![0x80, 0x00]),
+            (-128, vec![0x80, 0x80]),
+            (255, vec![0xff, 0x00]),
+            (256, vec![0x00, 0x01]),
+            (-32768, vec![0x00, 0x80, 0x80]),
+            (
+                i64::MAX,
+                vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+            ),
+            (-i64::MAX, vec![0xff; 8]),
+        ] {
+            assert_eq!(ScriptNum(num).encode(), encoded);
+            assert_eq!(ScriptNum::decode(&encoded), Ok(ScriptNum(num)));
+        }
+        assert_eq!(ScriptNum(i64::MIN).encode().len(), 9);
+        for non_minimal in [[0x00].as_ref(), &[0x80], &[0x05, 0x00], &[0x05, 0x80]] {
+            assert_eq!(
+                ScriptNum::decode(non_minimal),
+                Err(ScriptNumError::NonMinimal),
+            );
+        }
+    }
+
+    #[test]
+    fn test_script_num_arithmetic() {
+        let num = ScriptNum;
+        assert_eq!(num(7).checked_sub(num(10)), Ok(num(-3)));
+        assert_eq!(num(-7).checked_mul(num(3)), Ok(num(-21)));
+        assert_eq!(num(-7).checked_div(num(2)), Ok(num(-3)));
+        assert_eq!(num(-7).checked_rem(num(2)), Ok(num(-1)));
+        assert_eq!(num(7).checked_rem(num(-2)), Ok(num(1)));
+        assert_eq!(
+            num(1).checked_div(num(0)),
+            Err(ScriptNumError::DivisionByZero)
+        );
+        assert_eq!(
+            num(1).checked_rem(num(0)),
+            Err(ScriptNumError::DivisionByZero)
+        );
+        assert_eq!(
+            ScriptNum::MIN.checked_sub(num(1)),
+            Err(ScriptNumError::Overflow)
+        );
+        assert_eq!(
+            ScriptNum::MAX.checked_mul(num(2)),
+            Err(ScriptNumError::Overflow)
+        );
+        assert_eq!(ScriptNum::MIN.checked_neg(), Ok(ScriptNum::MAX));
+        assert_eq!(num(i64::MIN).checked_neg(), Err(ScriptNumError::Overflow));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_big_script_num() {
+        use crate::script::BigScriptNum;
+
+        let max = BigScriptNum::decode(&[[0xff; 9999].as_ref(), &[0x7f]].concat()).unwrap();
+        assert_eq!(max.encoded_len(), BigScriptNum::MAX_LEN);
+        assert_eq!(max.checked_add(&max), Err(ScriptNumError::Overflow));
+        let min = BigScriptNum::decode(&[0xff; 10_000]).unwrap();
+        assert_eq!(max.checked_add(&min), Ok(BigScriptNum::default()));
+        assert_eq!(max.checked_mul(&max), Err(ScriptNumError::Overflow));
+        assert_eq!(
+            BigScriptNum::decode(&[0; 10_001]),
+            Err(ScriptNumError::TooLong {
+                max_len: 10_000,
+                actual: 10_001,
+            }),
+        );
+        assert_eq!(
+            BigScriptNum::decode(&[0x05, 0x80]),
+            Err(ScriptNumError::NonMinimal),
+        );
+
+        for num in [0, 1, -1, 127, -128, 255, i64::MAX, -i64::MAX] {
+            let big = BigScriptNum::from(ScriptNum(num));
+            assert_eq!(big.encode(), ScriptNum(num).encode());
+            assert_eq!(big.encoded_len(), big.encode().len());
+            assert_eq!(BigScriptNum::decode(&big.encode()), Ok(big.clone()));
+            assert_eq!(big.to_script_num(), Ok(ScriptNum(num)));
+        }
+        let big = |num: i64| BigScriptNum::from(ScriptNum(num));
+        assert_eq!(big(-7).checked_div(&big(2)), Ok(big(-3)));
+        assert_eq!(big(-7).checked_rem(&big(2)), Ok(big(-1)));
+        assert_eq!(
+            big(1).checked_div(&big(0)),
+            Err(ScriptNumError::DivisionByZero)
+        );
+    }
+}
@@ -0,0 +1,411 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use bytes::Bytes;
+
+use crate::{
+    hash::{Hashed, Sha256d, ShaRmd160},
+    script::{opcode::*, MultisigMode, Op, PubKeyVariant, Script, ScriptVariant},
+    tx::{Signature, TxSignature},
+};
+
+/// What a scriptSig reveals when spending an output, see
+/// [`ScriptSigVariant::analyze`].
+///
+/// Signatures of P2PKH, P2PK and multisig spends are parsed with
+/// [`TxSignature::parse_lenient`], so spends from before FORKID or with
+/// high S are still recognized.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ScriptSigVariant {
+    /// Spend of a P2PKH output: `<sig> <pubkey>`.
+    P2PKH {
+        /// Signature of the spender.
+        sig: TxSignature,
+        /// Pubkey of the spender, hashing to the hash of the output.
+        pubkey: PubKeyVariant,
+    },
+    /// Spend of a P2PK output: `<sig>`.
+    P2PK {
+        /// Signature of the pubkey of the output.
+        sig: TxSignature,
+    },
+    /// Spend of a multisig script: `<dummy> <sig>...`.
+    Multisig {
+        /// Mode of the signatures, determined by the dummy element.
+        mode: MultisigMode,
+        /// Indices of the signing pubkeys, from the bitfield; `None` in
+        /// legacy mode, where only verifying the signatures tells them.
+        signer_indices: Option<Vec<usize>>,
+        /// Signatures, in the order of the pubkeys.
+        sigs: Vec<TxSignature>,
+    },
+    /// Spend of a P2SH or P2SH32 output: `<push>... <redeem script>`.
+    P2SH {
+        /// Revealed redeem script, hashing to the hash of the output.
+        redeem_script: Box<Script>,
+        /// The other pushes, analyzed as spend of the redeem script.
+        spend: Box<ScriptSigVariant>,
+    },
+    /// Spend of a script using [`OP_CHECKDATASIG`] or
+    /// [`OP_CHECKDATASIGVERIFY`]. Pushes are classified as signatures by
+    /// their encoding, so e.g. a 64 byte message counts as data signature.
+    CheckDataSig {
+        /// Pushes that are encoded like signatures without sighash byte.
+        data_sigs: Vec<Signature>,
+        /// Pushes that are encoded like signatures with sighash byte.
+        tx_sigs: Vec<TxSignature>,
+        /// All pushes of the scriptSig.
+        pushes: Vec<Bytes>,
+    },
+    /// ScriptSig that doesn't match the spent script, or spends a script
+    /// this doesn't know.
+    Other(Script),
+}
+
+impl ScriptSigVariant {
+    /// Analyze `script_sig`, which spends an output with `spent_script`.
+    ///
+    /// P2SH and P2SH32 spends are analyzed recursively, using the redeem
+    /// script as the spent script.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::{Hashed, ShaRmd160},
+    /// #     script::{PubKey, PubKeyVariant, Script, ScriptMut, ScriptSigVariant},
+    /// # };
+    /// let pubkey = PubKey([2; 33]);
+    /// let spent_script = Script::p2pkh(&ShaRmd160::digest(pubkey.as_slice()));
+    /// let mut script_sig = ScriptMut::with_capacity(100);
+    /// script_sig.put_bytecode(&[65]);
+    /// script_sig.put_bytecode(&[[3; 64].as_ref(), &[0x41]].concat());
+    /// script_sig.put_bytecode(&[33]);
+    /// script_sig.put_bytecode(pubkey.as_slice());
+    /// match ScriptSigVariant::analyze(&script_sig.freeze(), &spent_script) {
+    ///     ScriptSigVariant::P2PKH { pubkey: spender, .. } => {
+    ///         assert_eq!(spender, PubKeyVariant::Compressed(pubkey));
+    ///     }
+    ///     _ => panic!("Expected P2PKH"),
+    /// }
+    /// ```
+    pub fn analyze(script_sig: &Script, spent_script: &Script) -> ScriptSigVariant {
+        let ops = match script_sig.iter_ops().collect::<Result<Vec<_>, _>>() {
+            Ok(ops) => ops,
+            Err(_) => return ScriptSigVariant::Other(script_sig.clone()),
+        };
+        let pushes = match ops.iter().map(Op::pushed_data).collect::<Option<Vec<_>>>() {
+            Some(pushes) => pushes,
+            None => return ScriptSigVariant::Other(script_sig.clone()),
+        };
+        let variant = ScriptVariant::from_script(spent_script);
+        match variant {
+            ScriptVariant::P2SH(_) | ScriptVariant::P2SH32(_) => {
+                let (redeem_script, inner_pushes) = match pushes.split_last() {
+                    Some((redeem_script, inner_pushes)) => {
+                        (Script::new(redeem_script.clone()), inner_pushes)
+                    }
+                    None => return ScriptSigVariant::Other(script_sig.clone()),
+                };
+                let expected_script = match variant {
+                    ScriptVariant::P2SH(_) => Script::p2sh(&ShaRmd160::digest(&redeem_script)),
+                    _ => Script::p2sh32(&Sha256d::digest(&redeem_script)),
+                };
+                if expected_script != *spent_script {
+                    return ScriptSigVariant::Other(script_sig.clone());
+                }
+                let redeem_push_len = op_len(ops.last().unwrap());
+                let bytecode = script_sig.bytecode();
+                let inner_script_sig =
+                    Script::new(bytecode.slice(..bytecode.len() - redeem_push_len));
+                let spend = analyze_pushes(&inner_script_sig, inner_pushes, &redeem_script);
+                ScriptSigVariant::P2SH {
+                    redeem_script: Box::new(redeem_script),
+                    spend: Box::new(spend),
+                }
+            }
+            _ => analyze_pushes(script_sig, &pushes, spent_script),
+        }
+    }
+}
+
+/// Analyze a spend of `script`, which is not P2SH.
+fn analyze_pushes(script_sig: &Script, pushes: &[Bytes], script: &Script) -> ScriptSigVariant {
+    let analyzed = match ScriptVariant::from_script(script) {
+        ScriptVariant::P2PKH(hash) => match pushes {
+            [sig, pubkey] => (|| {
+                let pubkey = PubKeyVariant::try_from(pubkey.as_ref()).ok()?;
+                let pubkey_bytes = match &pubkey {
+                    PubKeyVariant::Compressed(pubkey) => pubkey.as_slice(),
+                    PubKeyVariant::Uncompressed(pubkey) => pubkey.as_ref(),
+                };
+                if ShaRmd160::digest(pubkey_bytes) != hash {
+                    return None;
+                }
+                Some(ScriptSigVariant::P2PKH {
+                    sig: TxSignature::parse_lenient(sig).ok()?,
+                    pubkey,
+                })
+            })(),
+            _ => None,
+        },
+        ScriptVariant::P2PK(_) => match pushes {
+            [sig] => TxSignature::parse_lenient(sig)
+                .ok()
+                .map(|sig| ScriptSigVariant::P2PK { sig }),
+            _ => None,
+        },
+        ScriptVariant::Multisig { m, pubkeys } => analyze_multisig(pushes, m, pubkeys.len()),
+        _ => {
+            let uses_checkdatasig = script.iter_ops().any(|op| {
+                matches!(
+                    op,
+                    Ok(Op::Code(OP_CHECKDATASIG)) | Ok(Op::Code(OP_CHECKDATASIGVERIFY))
+                )
+            });
+            uses_checkdatasig.then(|| ScriptSigVariant::CheckDataSig {
+                data_sigs: pushes
+                    .iter()
+                    .filter_map(|push| Signature::try_from(push.as_ref()).ok())
+                    .collect(),
+                tx_sigs: pushes
+                    .iter()
+                    .filter_map(|push| TxSignature::try_from(push.as_ref()).ok())
+                    .collect(),
+                pushes: pushes.to_vec(),
+            })
+        }
+    };
+    analyzed.unwrap_or_else(|| ScriptSigVariant::Other(script_sig.clone()))
+}
+
+fn analyze_multisig(pushes: &[Bytes], m: usize, num_pubkeys: usize) -> Option<ScriptSigVariant> {
+    let (dummy, sigs) = pushes.split_first()?;
+    if sigs.len() != m {
+        return None;
+    }
+    let sigs = sigs
+        .iter()
+        .map(|sig| TxSignature::parse_lenient(sig))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    if dummy.is_empty() {
+        return Some(ScriptSigVariant::Multisig {
+            mode: MultisigMode::Legacy,
+            signer_indices: None,
+            sigs,
+        });
+    }
+    if dummy.len() != (num_pubkeys + 7) / 8 {
+        return None;
+    }
+    let signer_indices = (0..dummy.len() * 8)
+        .filter(|&idx| dummy[idx / 8] & (1 << (idx % 8)) != 0)
+        .collect::<Vec<_>>();
+    let all_schnorr = sigs.iter().all(|sig| sig.sig.is_schnorr());
+    if signer_indices.len() != m
+        || signer_indices.iter().any(|&idx| idx >= num_pubkeys)
+        || !all_schnorr
+    {
+        return None;
+    }
+    Some(ScriptSigVariant::Multisig {
+        mode: MultisigMode::Schnorr,
+        signer_indices: Some(signer_indices),
+        sigs,
+    })
+}
+
+/// Number of bytes of the op in the bytecode.
+fn op_len(op: &Op) -> usize {
+    match op {
+        Op::Code(_) => 1,
+        Op::Push(OP_PUSHDATA1, data) => 2 + data.len(),
+        Op::Push(OP_PUSHDATA2, data) => 3 + data.len(),
+        Op::Push(OP_PUSHDATA4, data) => 5 + data.len(),
+        Op::Push(_, data) => 1 + data.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        hash::{Hashed, Sha256, Sha256d, ShaRmd160},
+        script::{
+            opcode::*, Htlc, HtlcTimeout, MultisigMode, PubKey, PubKeyVariant, Script, ScriptMut,
+            ScriptSigVariant, UncompressedPubKey,
+        },
+        tx::{Signature, TxSignature},
+    };
+
+    fn der_sig() -> Vec<u8> {
+        let mut sig = vec![0x30, 68, 0x02, 32];
+        sig.extend_from_slice(&[0x11; 32]);
+        sig.extend_from_slice(&[0x02, 32]);
+        sig.extend_from_slice(&[0x22; 32]);
+        sig
+    }
+
+    fn schnorr_tx_sig(byte: u8) -> Vec<u8> {
+        [[byte; 64].as_ref(), &[0x41]].concat()
+    }
+
+    fn pubkeys() -> Vec<PubKeyVariant> {
+        (2..5)
+            .map(|byte| PubKeyVariant::Compressed(PubKey([byte; 33])))
+            .collect()
+    }
+
+    #[test]
+    fn test_analyze_multisig() {
+        let redeem_script = Script::multisig(2, &pubkeys());
+        let p2sh32 = Script::p2sh32(&Sha256d::digest(&redeem_script));
+        let sigs = [(0, schnorr_tx_sig(1)), (2, schnorr_tx_sig(2))];
+        let script_sig = Script::multisig_script_sig(MultisigMode::Schnorr, 3, &sigs);
+        let p2sh_script_sig = Script::p2sh_script_sig(&script_sig, &redeem_script);
+        let expected_spend = ScriptSigVariant::Multisig {
+            mode: MultisigMode::Schnorr,
+            signer_indices: Some(vec![0, 2]),
+            sigs: sigs
+                .iter()
+                .map(|(_, sig)| TxSignature::try_from(sig.as_slice()).unwrap())
+                .collect(),
+        };
+        assert_eq!(
+            ScriptSigVariant::analyze(&p2sh_script_sig, &p2sh32),
+            ScriptSigVariant::P2SH {
+                redeem_script: Box::new(redeem_script.clone()),
+                spend: Box::new(expected_spend.clone()),
+            },
+        );
+        // Bare multisig
+        assert_eq!(
+            ScriptSigVariant::analyze(&script_sig, &redeem_script),
+            expected_spend,
+        );
+        // Legacy mode
+        let ecdsa_sig = [der_sig(), vec![0x41]].concat();
+        let sigs = [(0, ecdsa_sig.clone()), (1, ecdsa_sig.clone())];
+        let script_sig = Script::multisig_script_sig(MultisigMode::Legacy, 3, &sigs);
+        assert_eq!(
+            ScriptSigVariant::analyze(&script_sig, &redeem_script),
+            ScriptSigVariant::Multisig {
+                mode: MultisigMode::Legacy,
+                signer_indices: None,
+                sigs: vec![TxSignature::try_from(ecdsa_sig.as_slice()).unwrap(); 2],
+            },
+        );
+        // Bitfield with only one signer but two sigs
+        let mut script_sig = ScriptMut::with_capacity(0);
+        script_sig.put_opcodes([OP_1]);
+        for _ in 0..2 {
+            script_sig.put_bytecode(&[65]);
+            script_sig.put_bytecode(&schnorr_tx_sig(1));
+        }
+        let script_sig = script_sig.freeze();
+        assert_eq!(
+            ScriptSigVariant::analyze(&script_sig, &redeem_script),
+            ScriptSigVariant::Other(script_sig),
+        );
+        // Redeem script not matching the output
+        let other_p2sh32 = Script::p2sh32(&Sha256d([0; 32]));
+        assert_eq!(
+            ScriptSigVariant::analyze(&p2sh_script_sig, &other_p2sh32),
+            ScriptSigVariant::Other(p2sh_script_sig),
+        );
+    }
+
+    #[test]
+    fn test_analyze_historical() {
+        // Pre-fork SIGHASH_ALL signature with high S, e.g. from 2015
+        let mut high_s_sig = vec![0x30, 69, 0x02, 32];
+        high_s_sig.extend_from_slice(&[0x11; 32]);
+        high_s_sig.extend_from_slice(&[0x02, 33, 0]);
+        high_s_sig.extend_from_slice(&[0xff; 32]);
+        high_s_sig.push(0x01);
+        let sig = TxSignature::parse_lenient(&high_s_sig).unwrap();
+        assert!(TxSignature::try_from(high_s_sig.as_slice()).is_err());
+
+        let pubkey = PubKeyVariant::Uncompressed(UncompressedPubKey([4; 65]));
+        let p2pkh = Script::p2pkh(&ShaRmd160::digest([4; 65]));
+        let mut script_sig = ScriptMut::with_capacity(0);
+        script_sig.push_data(&high_s_sig);
+        script_sig.push_data(&[4; 65]);
+        assert_eq!(
+            ScriptSigVariant::analyze(&script_sig.freeze(), &p2pkh),
+            ScriptSigVariant::P2PKH {
+                sig: sig.clone(),
+                pubkey,
+            },
+        );
+
+        let sigs = [(0, high_s_sig.clone()), (2, high_s_sig)];
+        let script_sig = Script::multisig_script_sig(MultisigMode::Legacy, 3, &sigs);
+        assert_eq!(
+            ScriptSigVariant::analyze(&script_sig, &Script::multisig(2, &pubkeys())),
+            ScriptSigVariant::Multisig {
+                mode: MultisigMode::Legacy,
+                signer_indices: None,
+                sigs: vec![sig; 2],
+            },
+        );
+    }
+
+    #[test]
+    fn test_analyze_contracts() {
+        // HTLC spends reveal the redeem script, the inner spend is unknown
+        let htlc = Htlc {
+            secret_hash: Sha256::digest([7; 32]),
+            recipient: PubKey([2; 33]),
+            refund: PubKey([3; 33]),
+            timeout: HtlcTimeout::Absolute(100),
+        };
+        let script_sig = htlc.claim_script_sig(&schnorr_tx_sig(1), &[7; 32]);
+        let mut inner_script_sig = ScriptMut::with_capacity(0);
+        inner_script_sig.put_bytecode(&[65]);
+        inner_script_sig.put_bytecode(&schnorr_tx_sig(1));
+        inner_script_sig.put_bytecode(&[32]);
+        inner_script_sig.put_bytecode(&[7; 32]);
+        inner_script_sig.put_opcodes([OP_1]);
+        assert_eq!(
+            ScriptSigVariant::analyze(&script_sig, &htlc.p2sh()),
+            ScriptSigVariant::P2SH {
+                redeem_script: Box::new(htlc.redeem_script()),
+                spend: Box::new(ScriptSigVariant::Other(inner_script_sig.freeze())),
+            },
+        );
+
+        // OP_CHECKDATASIG oracle contract
+        let mut redeem_script = ScriptMut::with_capacity(0);
+        redeem_script.put_bytecode(&[33]);
+        redeem_script.put_bytecode(&[2; 33]);
+        redeem_script.put_opcodes([OP_CHECKDATASIGVERIFY, OP_CHECKSIG]);
+        let redeem_script = redeem_script.freeze();
+        let mut script_sig = ScriptMut::with_capacity(0);
+        script_sig.put_bytecode(&[65]);
+        script_sig.put_bytecode(&schnorr_tx_sig(9));
+        script_sig.put_bytecode(&[64]);
+        script_sig.put_bytecode(&[8; 64]);
+        script_sig.put_bytecode(&[3, 1, 2, 3]);
+        let script_sig = script_sig.freeze();
+        assert_eq!(
+            ScriptSigVariant::analyze(&script_sig, &redeem_script),
+            ScriptSigVariant::CheckDataSig {
+                data_sigs: vec![Signature::Schnorr([8; 64])],
+                tx_sigs: vec![TxSignature::try_from(schnorr_tx_sig(9).as_slice()).unwrap()],
+                pushes: vec![
+                    Bytes::from(schnorr_tx_sig(9)),
+                    Bytes::from(vec![8; 64]),
+                    Bytes::from(vec![1, 2, 3]),
+                ],
+            },
+        );
+        // Non-push scriptSig
+        let script_sig = Script::new(vec![OP_DUP::N].into());
+        assert_eq!(
+            ScriptSigVariant::analyze(&script_sig, &redeem_script),
+            ScriptSigVariant::Other(script_sig),
+        );
+    }
+}
@@ -8,8 +8,8 @@ use thiserror::Error;
 
 use crate::{
     error::DataError,
-    hash::ShaRmd160,
-    script::{PubKeyVariant, Script},
+    hash::{Hashed, Sha256d, ShaRmd160},
+    script::{multisig::parse_multisig, opcode::*, PubKeyVariant, Script, MAX_SCRIPT_SIZE},
 };
 
 /// Errors indicating a script type couldn't be parsed.
@@ -29,14 +29,29 @@ pub enum ScriptType {
     /// Pay-to-script-hash.
     /// Script: `OP_HASH160 <hash> OP_EQUAL`
     P2SH,
+    /// Pay-to-script-hash with a 32-byte hash, enabled by CashTokens.
+    /// Script: `OP_HASH256 <hash> OP_EQUAL`
+    P2SH32,
     /// Pay-to-public-key.
     /// Script: `<pubkey> OP_CHECKSIG`
     P2PK,
+    /// Bare multisig.
+    /// Script: `OP_m <pubkey>... OP_n OP_CHECKMULTISIG`
+    Multisig,
+    /// Pay-to-script, raw locking bytecode of a contract, standard as of the
+    /// May 2026 upgrade up to a size limit.
+    /// Script: any well-formed script not matching another template
+    P2S,
     /// Other kinds of script.
     Other,
 }
 
 /// Script variant, with the script's payload.
+///
+/// Breaking change: well-formed scripts that don't match a template, e.g.
+/// `OP_1`, used to be [`ScriptVariant::Other`] and are now
+/// [`ScriptVariant::P2S`], in every epoch. `Other` is left for scripts that
+/// can't be spent: malformed, empty, OP_RETURN or too large.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum ScriptVariant {
     /// Pay-to-public-key-hash.
@@ -45,16 +60,33 @@ pub enum ScriptVariant {
     /// Pay-to-script-hash.
     /// Script: `OP_HASH160 <hash> OP_EQUAL`
     P2SH(ShaRmd160),
+    /// Pay-to-script-hash with a 32-byte hash, enabled by CashTokens.
+    /// Script: `OP_HASH256 <hash> OP_EQUAL`
+    P2SH32(Sha256d),
     /// Pay-to-public-key.
     /// Script: `<pubkey> OP_CHECKSIG`
     P2PK(PubKeyVariant),
-    /// Other kinds of script.
+    /// Bare multisig, requiring `m` signatures of the `pubkeys`.
+    /// Script: `OP_m <pubkey>... OP_n OP_CHECKMULTISIG`
+    Multisig {
+        /// Number of required signatures.
+        m: usize,
+        /// Pubkeys that can sign, in the order of the script.
+        pubkeys: Vec<PubKeyVariant>,
+    },
+    /// Pay-to-script, raw locking bytecode of a contract.
+    /// Script: any well-formed script not matching another template
+    P2S(Script),
+    /// Other kinds of script: malformed, empty, OP_RETURN or larger than
+    /// [`MAX_SCRIPT_SIZE`], i.e. unspendable.
     Other(Script),
 }
 
 impl ScriptVariant {
     /// Try to interpret the payload as the given [`ScriptType`], and return the
-    /// corresponding variant. ```
+    /// corresponding variant. The payload of multisig and P2S is the whole
+    /// script.
+    /// ```
     /// # use bitcoinsuite_core::{
     /// #     hash::ShaRmd160,
     /// #     script::{
@@ -85,6 +117,18 @@ impl ScriptVariant {
     ///         UncompressedPubKey([4; 65])
     ///     )),
     /// );
+    /// let multisig = Script::multisig(1, &[PubKeyVariant::Compressed(PubKey([2; 33]))]);
+    /// assert_eq!(
+    ///     ScriptVariant::from_type_and_payload(ScriptType::Multisig, multisig.bytecode())?,
+    ///     ScriptVariant::Multisig {
+    ///         m: 1,
+    ///         pubkeys: vec![PubKeyVariant::Compressed(PubKey([2; 33]))],
+    ///     },
+    /// );
+    /// assert_eq!(
+    ///     ScriptVariant::from_type_and_payload(ScriptType::P2S, &[0x51, 0x87])?,
+    ///     ScriptVariant::P2S(Script::new(vec![0x51, 0x87].into())),
+    /// );
     /// assert_eq!(
     ///     ScriptVariant::from_type_and_payload(ScriptType::Other, &[1, 2])?,
     ///     ScriptVariant::Other(Script::new(vec![1, 2].into())),
@@ -100,11 +144,118 @@ impl ScriptVariant {
         Ok(match script_type {
             P2PKH => ScriptVariant::P2PKH(ShaRmd160(parse_array(payload)?)),
             P2SH => ScriptVariant::P2SH(ShaRmd160(parse_array(payload)?)),
+            P2SH32 => ScriptVariant::P2SH32(Sha256d(parse_array(payload)?)),
             P2PK => ScriptVariant::P2PK(payload.try_into()?),
+            Multisig => {
+                let script = Script::new(payload.to_vec().into());
+                let (m, pubkeys) =
+                    parse_multisig(&script).ok_or_else(|| DataError::InvalidScriptTemplate {
+                        expected: "multisig".to_string(),
+                    })?;
+                ScriptVariant::Multisig { m, pubkeys }
+            }
+            P2S => {
+                let script = Script::new(payload.to_vec().into());
+                if !is_p2s(&script) {
+                    return Err(DataError::InvalidScriptTemplate {
+                        expected: "p2s".to_string(),
+                    });
+                }
+                ScriptVariant::P2S(script)
+            }
             Other => ScriptVariant::Other(Script::new(payload.to_vec().into())),
         })
     }
 
+    /// Detect the variant of the given locking script (without token prefix);
+    /// well-formed scripts that don't match any template exactly are
+    /// [`ScriptVariant::P2S`], whether they are standard depends on their size
+    /// and the upgrade epoch, see
+    /// [`PolicyConfig::max_p2s_size`](crate::policy::PolicyConfig::max_p2s_size).
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::{Sha256d, ShaRmd160},
+    /// #     script::{PubKey, PubKeyVariant, Script, ScriptVariant},
+    /// # };
+    /// let variants = [
+    ///     ScriptVariant::P2PKH(ShaRmd160([1; 20])),
+    ///     ScriptVariant::P2SH(ShaRmd160([2; 20])),
+    ///     ScriptVariant::P2SH32(Sha256d([3; 32])),
+    ///     ScriptVariant::P2PK(PubKeyVariant::Compressed(PubKey([2; 33]))),
+    ///     ScriptVariant::Multisig {
+    ///         m: 2,
+    ///         pubkeys: vec![PubKeyVariant::Compressed(PubKey([2; 33])); 3],
+    ///     },
+    /// ];
+    /// for variant in variants {
+    ///     assert_eq!(ScriptVariant::from_script(&variant.to_script()), variant);
+    /// }
+    /// // Not exactly P2SH, trailing OP_1
+    /// let script = Script::new(hex::decode("a91402020202020202020202020202020202020202028751").unwrap().into());
+    /// assert_eq!(ScriptVariant::from_script(&script), ScriptVariant::P2S(script));
+    /// // Malformed, push op cut short
+    /// let script = Script::new(hex::decode("a914020202").unwrap().into());
+    /// assert_eq!(ScriptVariant::from_script(&script), ScriptVariant::Other(script));
+    /// ```
+    pub fn from_script(script: &Script) -> ScriptVariant {
+        let bytecode = script.bytecode().as_ref();
+        match bytecode {
+            [OP_DUP::N, OP_HASH160::N, 20, hash @ .., OP_EQUALVERIFY::N, OP_CHECKSIG::N]
+                if hash.len() == ShaRmd160::SIZE =>
+            {
+                ScriptVariant::P2PKH(ShaRmd160(parse_array(hash).unwrap()))
+            }
+            [OP_HASH160::N, 20, hash @ .., OP_EQUAL::N] if hash.len() == ShaRmd160::SIZE => {
+                ScriptVariant::P2SH(ShaRmd160(parse_array(hash).unwrap()))
+            }
+            [OP_HASH256::N, 32, hash @ .., OP_EQUAL::N] if hash.len() == Sha256d::SIZE => {
+                ScriptVariant::P2SH32(Sha256d(parse_array(hash).unwrap()))
+            }
+            [33 | 65, pubkey @ .., OP_CHECKSIG::N] if pubkey.len() == bytecode[0] as usize => {
+                match PubKeyVariant::try_from(pubkey) {
+                    Ok(pubkey) => ScriptVariant::P2PK(pubkey),
+                    Err(_) => ScriptVariant::raw(script),
+                }
+            }
+            [.., OP_CHECKMULTISIG::N] => match parse_multisig(script) {
+                Some((m, pubkeys)) => ScriptVariant::Multisig { m, pubkeys },
+                None => ScriptVariant::raw(script),
+            },
+            _ => ScriptVariant::raw(script),
+        }
+    }
+
+    /// Script not matching any template, P2S if well-formed.
+    fn raw(script: &Script) -> ScriptVariant {
+        match is_p2s(script) {
+            true => ScriptVariant::P2S(script.clone()),
+            false => ScriptVariant::Other(script.clone()),
+        }
+    }
+
+    /// [`ScriptType`] of this variant.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::ShaRmd160,
+    /// #     script::{ScriptType, ScriptVariant},
+    /// # };
+    /// assert_eq!(
+    ///     ScriptVariant::P2SH(ShaRmd160([2; 20])).script_type(),
+    ///     ScriptType::P2SH,
+    /// );
+    /// ```
+    pub fn script_type(&self) -> ScriptType {
+        match self {
+            ScriptVariant::P2PKH(_) => ScriptType::P2PKH,
+            ScriptVariant::P2SH(_) => ScriptType::P2SH,
+            ScriptVariant::P2SH32(_) => ScriptType::P2SH32,
+            ScriptVariant::P2PK(_) => ScriptType::P2PK,
+            ScriptVariant::Multisig { .. } => ScriptType::Multisig,
+            ScriptVariant::P2S(_) => ScriptType::P2S,
+            ScriptVariant::Other(_) => ScriptType::Other,
+        }
+    }
+
     /// ```
     /// # use bitcoinsuite_core::{
     /// #     hash::ShaRmd160,
@@ -123,9 +274,11 @@ impl ScriptVariant {
         match self {
             ScriptVariant::P2PKH(hash) => Script::p2pkh(hash),
             ScriptVariant::P2SH(hash) => Script::p2sh(hash),
+            ScriptVariant::P2SH32(hash) => Script::p2sh32(hash),
             ScriptVariant::P2PK(PubKeyVariant::Compressed(pk)) => Script::p2pk(pk),
             ScriptVariant::P2PK(PubKeyVariant::Uncompressed(pk)) => Script::p2pk_uncompressed(pk),
-            ScriptVariant::Other(script) => script.clone(),
+            ScriptVariant::Multisig { m, pubkeys } => Script::multisig(*m, pubkeys),
+            ScriptVariant::P2S(script) | ScriptVariant::Other(script) => script.clone(),
         }
     }
 }
@@ -137,13 +290,27 @@ impl FromStr for ScriptType {
         match s {
             "p2pkh" => Ok(ScriptType::P2PKH),
             "p2sh" => Ok(ScriptType::P2SH),
+            "p2sh32" => Ok(ScriptType::P2SH32),
             "p2pk" => Ok(ScriptType::P2PK),
+            "multisig" => Ok(ScriptType::Multisig),
+            "p2s" => Ok(ScriptType::P2S),
             "other" => Ok(ScriptType::Other),
             _ => Err(ScriptTypeError::UnknownScriptType(s.to_string())),
         }
     }
 }
 
+/// Whether the script has the shape of a P2S locking script: non-empty,
+/// not OP_RETURN, all ops well-formed and at most [`MAX_SCRIPT_SIZE`] bytes,
+/// so it could be spent.
+fn is_p2s(script: &Script) -> bool {
+    let bytecode = script.bytecode();
+    !bytecode.is_empty()
+        && bytecode.len() <= MAX_SCRIPT_SIZE
+        && !script.is_opreturn()
+        && script.iter_ops().all(|op| op.is_ok())
+}
+
 fn parse_array<const N: usize>(payload: &[u8]) -> Result<[u8; N], DataError> {
     payload.try_into().map_err(|_| DataError::InvalidLength {
         expected: N,
@@ -155,14 +322,17 @@ fn parse_array<const N: usize>(payload: &[u8]) -> Result<[u8; N], DataError> {
 mod tests {
     use crate::{
         error::DataError,
-        script::{ScriptType, ScriptTypeError, ScriptVariant},
+        script::{Script, ScriptType, ScriptTypeError, ScriptVariant},
     };
 
     #[test]
     fn test_script_type() -> Result<(), ScriptTypeError> {
         assert_eq!("p2pkh".parse::<ScriptType>()?, ScriptType::P2PKH);
         assert_eq!("p2sh".parse::<ScriptType>()?, ScriptType::P2SH);
+        assert_eq!("p2sh32".parse::<ScriptType>()?, ScriptType::P2SH32);
         assert_eq!("p2pk".parse::<ScriptType>()?, ScriptType::P2PK);
+        assert_eq!("multisig".parse::<ScriptType>()?, ScriptType::Multisig);
+        assert_eq!("p2s".parse::<ScriptType>()?, ScriptType::P2S);
         assert_eq!("other".parse::<ScriptType>()?, ScriptType::Other);
         assert_eq!(
             "foobar".parse::<ScriptType>(),
@@ -201,6 +371,58 @@ mod tests {
                 actual: 64,
             },
         );
+        assert_eq!(
+            ScriptVariant::from_type_and_payload(ScriptType::Multisig, &[0x51, 0xae]).unwrap_err(),
+            DataError::InvalidScriptTemplate {
+                expected: "multisig".to_string(),
+            },
+        );
+        for payload in [
+            vec![],
+            vec![0x6a, 0x51],
+            vec![0x02, 0x51],
+            vec![0x51; 10_001],
+        ] {
+            assert_eq!(
+                ScriptVariant::from_type_and_payload(ScriptType::P2S, &payload).unwrap_err(),
+                DataError::InvalidScriptTemplate {
+                    expected: "p2s".to_string(),
+                },
+            );
+        }
         Ok(())
     }
+
+    #[test]
+    fn test_script_variant_p2s() {
+        // Covenant too large for P2SH's 520 byte redeem script limit
+        let covenant = Script::new([[0x51].as_ref(), &[0x75; 600]].concat().into());
+        assert_eq!(
+            ScriptVariant::from_script(&covenant),
+            ScriptVariant::P2S(covenant.clone()),
+        );
+        assert_eq!(
+            ScriptVariant::from_script(&covenant).script_type(),
+            ScriptType::P2S
+        );
+        // Invalid multisig is raw bytecode
+        let multisig = Script::new(vec![0x51, 0x51, 0xae].into());
+        assert_eq!(
+            ScriptVariant::from_script(&multisig),
+            ScriptVariant::P2S(multisig.clone()),
+        );
+        // Unspendable scripts are other
+        for bytecode in [
+            vec![],
+            vec![0x6a, 0x01, 0x00],
+            vec![0x4c],
+            vec![0x51; 10_001],
+        ] {
+            let script = Script::new(bytecode.into());
+            assert_eq!(
+                ScriptVariant::from_script(&script),
+                ScriptVariant::Other(script.clone()),
+            );
+        }
+    }
 }
@@ -192,9 +192,6 @@ impl CompactUint {
 
 impl BitcoinSer for CompactUint {
     fn ser_to<S: BitcoinSerializer>(&self, bytes: &mut S) {
-        if self.0 == 0 {
-            ()
-        }
         self.encode_minimal(bytes)
     }
     fn deser(data: &mut bytes::Bytes) -> Result<Self, DataError> {
@@ -203,19 +200,33 @@ impl BitcoinSer for CompactUint {
 }
 
 /// Like [`read_compact_size`], checks value is encoded canonically: (value length should be encoded minimally [`compact_uint_len`])
+///
+/// ```
+/// # use bitcoinsuite_core::{error::DataError, ser::read_compact_uint_minimal};
+/// # use bytes::Bytes;
+/// assert_eq!(read_compact_uint_minimal(&mut Bytes::from(vec![0x00])), Ok(0));
+/// assert_eq!(read_compact_uint_minimal(&mut Bytes::from(vec![0xfd, 0xfd, 0x00])), Ok(0xfd));
+/// assert_eq!(
+///     read_compact_uint_minimal(&mut Bytes::from(vec![0xfd, 0x01, 0x00])),
+///     Err(DataError::InvalidCompactUint {
+///         canonical_length: 1,
+///         encoded_length: 3,
+///     }),
+/// );
+/// ```
 pub fn read_compact_uint_minimal(bytes: &mut Bytes) -> Result<u64, DataError> {
     let encoded_length_byte = read_array::<1>(&mut bytes.clone())?[0];
-    let value = &read_compact_size(bytes)?;
-
-    if CompactUint(*value).ser_len() as u64
-        != CompactUint(encoded_length_byte as u64).canonical_len() as u64
-    {
+    let value = read_compact_size(bytes)?;
+    let mut canonical_length = 0usize;
+    write_compact_size(&mut canonical_length, value);
+    let encoded_length = CompactUint(encoded_length_byte as u64).canonical_len() as usize;
+    if canonical_length != encoded_length {
         return Err(DataError::InvalidCompactUint {
-            canonical_length: CompactUint(*value).ser_len() as usize,
-            encoded_length: CompactUint(encoded_length_byte as u64).canonical_len() as usize,
+            canonical_length,
+            encoded_length,
         });
     }
-    Ok(*value as u64)
+    Ok(value)
 }
 
 /// Read Byte length, returns minimally-encoded [`CompactUint`] size from the provided [`Bytes`]
@@ -0,0 +1,760 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use std::collections::{BTreeMap, HashSet};
+
+use thiserror::Error;
+
+use crate::{
+    consensus::{ConsensusParams, MAX_MONEY},
+    error::DataError,
+    ser::BitcoinSer,
+    tx::{
+        Capability, Commitment, OutPoint, Output, Transaction, TxId, MAXIMUM_TOKEN_AMOUNT,
+        TOKEN_PREFIX,
+    },
+};
+
+/// Minimum size of the scriptSig of a coinbase tx.
+pub const MIN_COINBASE_SCRIPTSIG_SIZE: usize = 2;
+
+/// Maximum size of the scriptSig of a coinbase tx.
+pub const MAX_COINBASE_SCRIPTSIG_SIZE: usize = 100;
+
+/// Violation of a context-free consensus rule, found by
+/// [`Transaction::check`].
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum TxCheckError {
+    /// Tx has no inputs.
+    #[error("bad-txns-vin-empty: tx has no inputs")]
+    NoInputs,
+
+    /// Tx has no outputs.
+    #[error("bad-txns-vout-empty: tx has no outputs")]
+    NoOutputs,
+
+    /// Serialized tx is smaller than allowed.
+    #[error("bad-txns-undersize: tx has {size} bytes, minimum is {min_size}")]
+    TxTooSmall {
+        /// Serialized size of the tx.
+        size: usize,
+        /// Minimum size, see [`ConsensusParams::min_tx_size`].
+        min_size: usize,
+    },
+
+    /// Serialized tx is larger than allowed.
+    #[error("bad-txns-oversize: tx has {size} bytes, maximum is {max_size}")]
+    TxTooLarge {
+        /// Serialized size of the tx.
+        size: usize,
+        /// Maximum size, see [`ConsensusParams::max_tx_size`].
+        max_size: usize,
+    },
+
+    /// An output has a value above [`MAX_MONEY`].
+    #[error("bad-txns-vout-toolarge: output {output_idx} has value {value}")]
+    OutputValueTooLarge {
+        /// Index of the offending output.
+        output_idx: usize,
+        /// Value of the output.
+        value: u64,
+    },
+
+    /// The sum of all output values is above [`MAX_MONEY`].
+    #[error("bad-txns-txouttotal-toolarge: outputs total {total} sats")]
+    TotalOutputValueTooLarge {
+        /// Sum of all output values, saturated at [`u64::MAX`].
+        total: u64,
+    },
+
+    /// The same [`OutPoint`] is spent by more than one input.
+    #[error("bad-txns-inputs-duplicate: input {input_idx} spends {prev_out:?} again")]
+    DuplicateInput {
+        /// Index of the input spending the [`OutPoint`] a second time.
+        input_idx: usize,
+        /// The [`OutPoint`] spent more than once.
+        prev_out: OutPoint,
+    },
+
+    /// The scriptSig of a coinbase tx is too short or too long.
+    #[error(
+        "bad-cb-length: coinbase scriptSig has {size} bytes, must be \
+         {MIN_COINBASE_SCRIPTSIG_SIZE} to {MAX_COINBASE_SCRIPTSIG_SIZE}"
+    )]
+    CoinbaseScriptSigSize {
+        /// Size of the coinbase scriptSig.
+        size: usize,
+    },
+
+    /// A non-coinbase input spends the null [`OutPoint`].
+    #[error("bad-txns-prevout-null: input {input_idx} spends the null outpoint")]
+    NullPrevOut {
+        /// Index of the offending input.
+        input_idx: usize,
+    },
+
+    /// An output has a malformed or out-of-range token prefix.
+    #[error("bad-txns-vout-invalid-token-prefix: output {output_idx}: {error}")]
+    InvalidTokenPrefix {
+        /// Index of the offending output.
+        output_idx: usize,
+        /// Why the token prefix is invalid.
+        error: DataError,
+    },
+
+    /// Number of spent outputs doesn't match the number of inputs.
+    #[error("Expected {num_inputs} spent outputs, got {num_spent_outputs}")]
+    SpentOutputsMismatch {
+        /// Number of inputs of the tx.
+        num_inputs: usize,
+        /// Number of spent outputs given.
+        num_spent_outputs: usize,
+    },
+
+    /// Outputs have more fungible tokens of a category than the inputs.
+    #[error(
+        "bad-txns-token-in-exceeds: outputs have {output_amount} tokens of category \
+         {category}, inputs only {input_amount}"
+    )]
+    TokenAmountInflated {
+        /// Token category.
+        category: TxId,
+        /// Sum of fungible tokens of the category in the inputs.
+        input_amount: u64,
+        /// Sum of fungible tokens of the category in the outputs.
+        output_amount: u64,
+    },
+
+    /// Sum of fungible tokens of a category in the outputs exceeds the
+    /// maximum token amount.
+    #[error("bad-txns-token-amount-overflow: outputs of category {category} overflow")]
+    TokenAmountOverflow {
+        /// Token category.
+        category: TxId,
+    },
+
+    /// An NFT was created without a minting or mutable token of its category
+    /// in the inputs (or an identical immutable token).
+    #[error("bad-txns-token-nft-ex-nihilo: output {output_idx} of category {category}")]
+    NftWithoutAuthority {
+        /// Index of the offending output.
+        output_idx: usize,
+        /// Token category.
+        category: TxId,
+    },
+}
+
+/// Tokens of a category in the inputs of a tx.
+#[derive(Default)]
+struct InputTokens {
+    amount: u64,
+    has_minting: bool,
+    num_mutable: usize,
+    immutable: Vec<Commitment>,
+}
+
+impl Transaction {
+    /// Run the context-free checks of the node (`CheckTransaction`) using
+    /// the latest [`ConsensusParams`], see [`Transaction::check_with`].
+    pub fn check(&self) -> Result<(), Vec<TxCheckError>> {
+        self.check_with(&ConsensusParams::latest())
+    }
+
+    /// Run the checks that don't need the UTXO set or the chain tip, under
+    /// the given [`ConsensusParams`].
+    ///
+    /// Unlike the node, this doesn't stop at the first failed check but
+    /// returns every violation it finds, in the order inputs and outputs
+    /// appear in the tx.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     consensus::MAX_MONEY,
+    /// #     tx::{Input, OutPoint, Output, Transaction, TxCheckError, TxId},
+    /// # };
+    /// let input = Input {
+    ///     prev_out: OutPoint { txid: TxId::from([1; 32]), outpoint_index: 0 },
+    ///     ..Default::default()
+    /// };
+    /// let tx = Transaction {
+    ///     version: 2,
+    ///     inputs: vec![input.clone(), input.clone()],
+    ///     outputs: vec![Output { value: MAX_MONEY + 1, ..Default::default() }],
+    ///     locktime: 0,
+    /// };
+    /// assert_eq!(
+    ///     tx.check(),
+    ///     Err(vec![
+    ///         TxCheckError::OutputValueTooLarge { output_idx: 0, value: MAX_MONEY + 1 },
+    ///         TxCheckError::TotalOutputValueTooLarge { total: MAX_MONEY + 1 },
+    ///         TxCheckError::DuplicateInput { input_idx: 1, prev_out: input.prev_out },
+    ///     ]),
+    /// );
+    /// ```
+    pub fn check_with(&self, params: &ConsensusParams) -> Result<(), Vec<TxCheckError>> {
+        let mut errors = Vec::new();
+        if self.inputs.is_empty() {
+            errors.push(TxCheckError::NoInputs);
+        }
+        if self.outputs.is_empty() {
+            errors.push(TxCheckError::NoOutputs);
+        }
+
+        let size = self.ser_len();
+        if size < params.min_tx_size {
+            errors.push(TxCheckError::TxTooSmall {
+                size,
+                min_size: params.min_tx_size,
+            });
+        }
+        if size > params.max_tx_size {
+            errors.push(TxCheckError::TxTooLarge {
+                size,
+                max_size: params.max_tx_size,
+            });
+        }
+
+        let mut total: u64 = 0;
+        for (output_idx, output) in self.outputs.iter().enumerate() {
+            if output.value > MAX_MONEY {
+                errors.push(TxCheckError::OutputValueTooLarge {
+                    output_idx,
+                    value: output.value,
+                });
+            }
+            total = total.saturating_add(output.value);
+            if let Some(token) = &output.token {
+                if let Err(error) = token.validate(params) {
+                    errors.push(TxCheckError::InvalidTokenPrefix { output_idx, error });
+                }
+            } else if params.cashtokens && output.script.bytecode().first() == Some(&TOKEN_PREFIX) {
+                // Only possible for outputs decoded leniently
+                errors.push(TxCheckError::InvalidTokenPrefix {
+                    output_idx,
+                    error: DataError::InvalidPrefixEncoding {
+                        error: "locking script starts with PREFIX_TOKEN".to_string(),
+                    },
+                });
+            }
+        }
+        if total > MAX_MONEY {
+            errors.push(TxCheckError::TotalOutputValueTooLarge { total });
+        }
+
+        let mut spent = HashSet::with_capacity(self.inputs.len());
+        for (input_idx, input) in self.inputs.iter().enumerate() {
+            if !spent.insert(input.prev_out) {
+                errors.push(TxCheckError::DuplicateInput {
+                    input_idx,
+                    prev_out: input.prev_out,
+                });
+            }
+        }
+
+        if self.is_coinbase() {
+            let size = self.inputs[0].script.bytecode().len();
+            if !(MIN_COINBASE_SCRIPTSIG_SIZE..=MAX_COINBASE_SCRIPTSIG_SIZE).contains(&size) {
+                errors.push(TxCheckError::CoinbaseScriptSigSize { size });
+            }
+        } else {
+            for (input_idx, input) in self.inputs.iter().enumerate() {
+                if input.prev_out.is_null() {
+                    errors.push(TxCheckError::NullPrevOut { input_idx });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl Transaction {
+    /// Check that the tokens in the outputs are covered by the tokens of
+    /// `spent_outputs` (the outputs spent by each input), following the
+    /// token-aware validation rules of CashTokens:
+    /// - outputs may not have more fungible tokens of a category than the
+    ///   inputs,
+    /// - each output NFT needs a minting NFT of the category in the inputs,
+    ///   an identical immutable NFT, or a mutable NFT (which is consumed),
+    /// - categories created by this tx (the txid of an input spending
+    ///   output 0 of its tx) are exempt.
+    ///
+    /// Returns every violation found. Tokens of the inputs that aren't in
+    /// any output are burned, which is valid.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     ser::CompactUint,
+    /// #     tx::{CashToken, Input, OutPoint, Output, Transaction, TxCheckError, TxId},
+    /// # };
+    /// let category = TxId::from([1; 32]);
+    /// let token_output = |amount| Output {
+    ///     token: Some(CashToken { amount: CompactUint(amount), category, nft: None }),
+    ///     ..Default::default()
+    /// };
+    /// let tx = Transaction {
+    ///     inputs: vec![Input {
+    ///         prev_out: OutPoint { txid: TxId::from([2; 32]), outpoint_index: 1 },
+    ///         ..Default::default()
+    ///     }],
+    ///     outputs: vec![token_output(60), token_output(50)],
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(
+    ///     tx.check_tokens(&[token_output(100)]),
+    ///     Err(vec![TxCheckError::TokenAmountInflated {
+    ///         category,
+    ///         input_amount: 100,
+    ///         output_amount: 110,
+    ///     }]),
+    /// );
+    /// assert_eq!(tx.check_tokens(&[token_output(110)]), Ok(()));
+    /// ```
+    pub fn check_tokens(&self, spent_outputs: &[Output]) -> Result<(), Vec<TxCheckError>> {
+        if spent_outputs.len() != self.inputs.len() {
+            return Err(vec![TxCheckError::SpentOutputsMismatch {
+                num_inputs: self.inputs.len(),
+                num_spent_outputs: spent_outputs.len(),
+            }]);
+        }
+        let genesis_categories = self
+            .inputs
+            .iter()
+            .filter(|input| input.prev_out.outpoint_index == 0)
+            .map(|input| input.prev_out.txid)
+            .collect::<HashSet<_>>();
+
+        let mut inputs = BTreeMap::<TxId, InputTokens>::new();
+        for token in spent_outputs
+            .iter()
+            .filter_map(|output| output.token.as_ref())
+        {
+            let category = inputs.entry(token.category).or_default();
+            category.amount = category.amount.saturating_add(token.amount.0);
+            if let Some(nft) = &token.nft {
+                match nft.capability.0 {
+                    Capability::Minting => category.has_minting = true,
+                    Capability::Mutable => category.num_mutable += 1,
+                    Capability::None => category.immutable.push(nft.commitment.clone()),
+                }
+            }
+        }
+
+        let mut errors = Vec::new();
+        let mut output_amounts = BTreeMap::<TxId, u64>::new();
+        // Immutable NFTs matching an input NFT first, so they don't use up
+        // mutable NFTs needed by the other outputs.
+        let mut needs_mutable = Vec::new();
+        for (output_idx, output) in self.outputs.iter().enumerate() {
+            let token = match &output.token {
+                Some(token) => token,
+                None => continue,
+            };
+            let amount = output_amounts.entry(token.category).or_default();
+            *amount = amount.saturating_add(token.amount.0);
+            // Genesis can create any NFTs, but not more than the max amount
+            if genesis_categories.contains(&token.category) {
+                continue;
+            }
+            let nft = match &token.nft {
+                Some(nft) => nft,
+                None => continue,
+            };
+            let input = inputs.entry(token.category).or_default();
+            if input.has_minting {
+                continue;
+            }
+            match nft.capability.0 {
+                Capability::Minting => errors.push(TxCheckError::NftWithoutAuthority {
+                    output_idx,
+                    category: token.category,
+                }),
+                Capability::Mutable => needs_mutable.push((output_idx, token.category)),
+                Capability::None => {
+                    match input.immutable.iter().position(|c| *c == nft.commitment) {
+                        Some(pos) => {
+                            input.immutable.swap_remove(pos);
+                        }
+                        None => needs_mutable.push((output_idx, token.category)),
+                    }
+                }
+            }
+        }
+        for (output_idx, category) in needs_mutable {
+            let input = inputs.entry(category).or_default();
+            if input.num_mutable == 0 {
+                errors.push(TxCheckError::NftWithoutAuthority {
+                    output_idx,
+                    category,
+                });
+            } else {
+                input.num_mutable -= 1;
+            }
+        }
+        for (category, output_amount) in output_amounts {
+            if output_amount > MAXIMUM_TOKEN_AMOUNT {
+                errors.push(TxCheckError::TokenAmountOverflow { category });
+                continue;
+            }
+            if genesis_categories.contains(&category) {
+                continue;
+            }
+            let input_amount = inputs.get(&category).map_or(0, |input| input.amount);
+            if output_amount > input_amount {
+                errors.push(TxCheckError::TokenAmountInflated {
+                    category,
+                    input_amount,
+                    output_amount,
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        consensus::{UpgradeEpoch, MAX_MONEY},
+        error::DataError,
+        script::Script,
+        ser::CompactUint,
+        tx::{
+            Capability, CashToken, Commitment, DecodeContext, Input, NonFungibleTokenCapability,
+            OutPoint, Output, Transaction, TxCheckError, TxId, MAXIMUM_TOKEN_AMOUNT, NFT,
+        },
+        wallet::test_util::{output, script, tx},
+    };
+
+    fn input(idx: u32) -> Input {
+        Input {
+            prev_out: OutPoint {
+                txid: TxId::from([1; 32]),
+                outpoint_index: idx,
+            },
+            script: Script::new(vec![0x51; 20].into()),
+            sequence: 0xffff_ffff,
+        }
+    }
+
+    #[test]
+    fn test_check_valid() {
+        assert_eq!(
+            tx(vec![input(0), input(1)], vec![output(1000, script())]).check(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_check_empty() {
+        let empty = Transaction::default();
+        assert_eq!(
+            empty.check(),
+            Err(vec![
+                TxCheckError::NoInputs,
+                TxCheckError::NoOutputs,
+                TxCheckError::TxTooSmall {
+                    size: 10,
+                    min_size: 65,
+                },
+            ]),
+        );
+        assert_eq!(
+            empty
+                .check_with(&UpgradeEpoch::Upgrade8.params())
+                .unwrap_err()[2],
+            TxCheckError::TxTooSmall {
+                size: 10,
+                min_size: 100,
+            },
+        );
+    }
+
+    #[test]
+    fn test_check_size() {
+        let mut big_input = input(0);
+        big_input.script = Script::new(vec![0x51; 1_000_000].into());
+        let size = 1_000_000 + 89;
+        assert_eq!(
+            tx(vec![big_input], vec![output(1000, script())]).check(),
+            Err(vec![TxCheckError::TxTooLarge {
+                size,
+                max_size: 1_000_000,
+            }]),
+        );
+    }
+
+    #[test]
+    fn test_check_money_range() {
+        assert_eq!(
+            tx(
+                vec![input(0)],
+                vec![output(MAX_MONEY, script()), output(MAX_MONEY, script())]
+            )
+            .check(),
+            Err(vec![TxCheckError::TotalOutputValueTooLarge {
+                total: 2 * MAX_MONEY,
+            }]),
+        );
+        assert_eq!(
+            tx(
+                vec![input(0)],
+                vec![output(u64::MAX, script()), output(1, script())]
+            )
+            .check(),
+            Err(vec![
+                TxCheckError::OutputValueTooLarge {
+                    output_idx: 0,
+                    value: u64::MAX,
+                },
+                TxCheckError::TotalOutputValueTooLarge { total: u64::MAX },
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_check_duplicate_inputs() {
+        assert_eq!(
+            tx(
+                vec![input(0), input(1), input(0), input(0)],
+                vec![output(1000, script())]
+            )
+            .check(),
+            Err(vec![
+                TxCheckError::DuplicateInput {
+                    input_idx: 2,
+                    prev_out: input(0).prev_out,
+                },
+                TxCheckError::DuplicateInput {
+                    input_idx: 3,
+                    prev_out: input(0).prev_out,
+                },
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_check_coinbase() {
+        let mut coinbase = input(0);
+        coinbase.prev_out = OutPoint::NULL;
+        for (size, expected) in [
+            (
+                1,
+                Err(vec![TxCheckError::CoinbaseScriptSigSize { size: 1 }]),
+            ),
+            (2, Ok(())),
+            (100, Ok(())),
+            (
+                101,
+                Err(vec![TxCheckError::CoinbaseScriptSigSize { size: 101 }]),
+            ),
+        ] {
+            coinbase.script = Script::new(vec![0x51; size].into());
+            let tx = tx(
+                vec![coinbase.clone()],
+                vec![output(1000, script()), output(1000, script())],
+            );
+            assert!(tx.is_coinbase());
+            assert_eq!(tx.check(), expected);
+        }
+
+        // Null prevout outside of a coinbase tx
+        let mut null_input = input(0);
+        null_input.prev_out = OutPoint::NULL;
+        assert_eq!(
+            tx(vec![input(0), null_input], vec![output(1000, script())]).check(),
+            Err(vec![TxCheckError::NullPrevOut { input_idx: 1 }]),
+        );
+    }
+
+    #[test]
+    fn test_check_tokens() {
+        let mut token_output = output(1000, script());
+        token_output.token = Some(CashToken {
+            amount: CompactUint(0),
+            category: TxId::from([2; 32]),
+            nft: None,
+        });
+        let tx1 = tx(vec![input(0)], vec![output(1000, script()), token_output]);
+        assert_eq!(
+            tx1.check(),
+            Err(vec![TxCheckError::InvalidTokenPrefix {
+                output_idx: 1,
+                error: DataError::NoTokens {
+                    error: "Invalid token prefix: must encode at least one token".to_string(),
+                },
+            }]),
+        );
+
+        // Invalid token prefix, kept in the script by lenient decoding
+        let mut ser = hex::decode("02000000").unwrap();
+        ser.extend_from_slice(&hex::decode("01").unwrap());
+        ser.extend_from_slice(&[1; 32]);
+        ser.extend_from_slice(&hex::decode("00000000").unwrap());
+        ser.extend_from_slice(&hex::decode("14").unwrap());
+        ser.extend_from_slice(&[0x51; 20]);
+        ser.extend_from_slice(&hex::decode("ffffffff").unwrap());
+        ser.extend_from_slice(&hex::decode("01e80300000000000002ef5100000000").unwrap());
+        let ser = Bytes::from(ser);
+        let decoded = Transaction::decode(ser, &DecodeContext::lenient()).unwrap();
+        assert_eq!(decoded.value.outputs[0].script.hex(), "ef51");
+        assert_eq!(
+            decoded.value.check(),
+            Err(vec![TxCheckError::InvalidTokenPrefix {
+                output_idx: 0,
+                error: DataError::InvalidPrefixEncoding {
+                    error: "locking script starts with PREFIX_TOKEN".to_string(),
+                },
+            }]),
+        );
+        // Before CashTokens, the same script is fine (but the tx is too small)
+        assert_eq!(
+            decoded.value.check_with(&UpgradeEpoch::Upgrade8.params()),
+            Err(vec![TxCheckError::TxTooSmall {
+                size: 82,
+                min_size: 100,
+            }]),
+        );
+    }
+
+    fn nft_output(capability: Capability, commitment: &[u8], amount: u64) -> Output {
+        let mut output = output(1000, script());
+        output.token = Some(CashToken {
+            amount: CompactUint(amount),
+            category: TxId::from([2; 32]),
+            nft: Some(NFT {
+                capability: NonFungibleTokenCapability(capability),
+                commitment: Commitment(Bytes::copy_from_slice(commitment)),
+            }),
+        });
+        output
+    }
+
+    #[test]
+    fn test_check_tokens_nfts() {
+        use Capability::{Minting, Mutable};
+        let immutable = Capability::None;
+        let category = TxId::from([2; 32]);
+        let tx = |outputs| tx(vec![input(1), input(2)], outputs);
+
+        // Minting NFT can create any NFT
+        let spent = [nft_output(Minting, b"", 0), output(1000, script())];
+        let outputs = vec![
+            nft_output(Minting, b"", 0),
+            nft_output(Mutable, b"a", 0),
+            nft_output(immutable, b"b", 0),
+        ];
+        assert_eq!(tx(outputs.clone()).check_tokens(&spent), Ok(()));
+
+        // Without it, only the mutable NFT can be re-created once
+        let spent = [nft_output(Mutable, b"", 0), output(1000, script())];
+        assert_eq!(
+            tx(outputs).check_tokens(&spent),
+            Err(vec![
+                TxCheckError::NftWithoutAuthority {
+                    output_idx: 0,
+                    category,
+                },
+                TxCheckError::NftWithoutAuthority {
+                    output_idx: 2,
+                    category,
+                },
+            ]),
+        );
+
+        // Identical immutable NFTs are matched before using mutable NFTs
+        let spent = [nft_output(Mutable, b"", 0), nft_output(immutable, b"x", 0)];
+        let outputs = vec![nft_output(Mutable, b"y", 0), nft_output(immutable, b"x", 0)];
+        assert_eq!(tx(outputs).check_tokens(&spent), Ok(()));
+        let outputs = vec![
+            nft_output(immutable, b"x", 0),
+            nft_output(immutable, b"x", 0),
+        ];
+        assert_eq!(tx(outputs).check_tokens(&spent), Ok(()));
+        let outputs = vec![nft_output(immutable, b"x", 0); 3];
+        assert_eq!(
+            tx(outputs).check_tokens(&spent),
+            Err(vec![TxCheckError::NftWithoutAuthority {
+                output_idx: 2,
+                category,
+            }]),
+        );
+
+        // Fungible tokens are summed per category
+        let spent = [
+            nft_output(immutable, b"", 100),
+            nft_output(immutable, b"", 50),
+        ];
+        let outputs = vec![
+            nft_output(immutable, b"", 150),
+            nft_output(immutable, b"", 1),
+        ];
+        assert_eq!(
+            tx(outputs).check_tokens(&spent),
+            Err(vec![TxCheckError::TokenAmountInflated {
+                category,
+                input_amount: 150,
+                output_amount: 151,
+            }]),
+        );
+        let outputs = vec![nft_output(immutable, b"", i64::MAX as u64); 2];
+        assert_eq!(
+            tx(outputs).check_tokens(&spent),
+            Err(vec![TxCheckError::TokenAmountOverflow { category }]),
+        );
+
+        assert_eq!(
+            tx(vec![]).check_tokens(&spent[..1]),
+            Err(vec![TxCheckError::SpentOutputsMismatch {
+                num_inputs: 2,
+                num_spent_outputs: 1,
+            }]),
+        );
+    }
+
+    #[test]
+    fn test_check_tokens_genesis() {
+        // Spending output 0 of tx [2; 32] allows creating category [2; 32]
+        let mut genesis_input = input(0);
+        genesis_input.prev_out.txid = TxId::from([2; 32]);
+        let genesis_tx = tx(
+            vec![genesis_input, input(1)],
+            vec![
+                nft_output(Capability::Minting, b"", 1000),
+                nft_output(Capability::None, b"abc", 0),
+            ],
+        );
+        assert_eq!(
+            genesis_tx.check_tokens(&[output(1000, script()), output(1000, script())]),
+            Ok(())
+        );
+
+        // Genesis can't create more than the maximum amount in total
+        let mut genesis_input = input(0);
+        genesis_input.prev_out.txid = TxId::from([2; 32]);
+        let overflow_tx = tx(
+            vec![genesis_input, input(1)],
+            vec![
+                nft_output(Capability::None, b"", MAXIMUM_TOKEN_AMOUNT),
+                nft_output(Capability::None, b"", 1),
+            ],
+        );
+        assert_eq!(
+            overflow_tx.check_tokens(&[output(1000, script()), output(1000, script())]),
+            Err(vec![TxCheckError::TokenAmountOverflow {
+                category: TxId::from([2; 32]),
+            }]),
+        );
+    }
+}
@@ -0,0 +1,626 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use bytes::Bytes;
+
+use crate::{
+    bytes::read_bytes,
+    consensus::{ConsensusParams, Net, UpgradeEpoch},
+    error::DataError,
+    script::Script,
+    ser::{read_compact_size, read_compact_uint_minimal, BitcoinSer, CompactUint},
+    tx::{
+        Bitfield, CashToken, Input, Output, TokenBitfield, Transaction, MAXIMUM_TOKEN_AMOUNT,
+        MINIMUM_PREFIX_LENGTH, TOKEN_PREFIX,
+    },
+};
+
+/// Rules to apply when decoding a [`Transaction`](crate::tx::Transaction),
+/// for txs that were mined under different consensus rules than today's.
+///
+/// Before CashTokens activated, locking bytecode starting with `0xef` was
+/// arbitrary script, not a token prefix:
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     consensus::UpgradeEpoch,
+/// #     tx::{DecodeContext, Output},
+/// # };
+/// # use bytes::Bytes;
+/// let ser = hex::decode("e80300000000000002ef51").unwrap();
+/// let ctx = DecodeContext::from(&UpgradeEpoch::Upgrade8.params());
+/// let output = Output::deser_with(&mut Bytes::from(ser.clone()), &ctx).unwrap();
+/// assert_eq!(output.script.hex(), "ef51");
+/// assert_eq!(output.token, None);
+///
+/// // With CashTokens, this is an invalid token prefix
+/// let ctx = DecodeContext::default();
+/// assert!(Output::deser_with(&mut Bytes::from(ser), &ctx).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct DecodeContext {
+    /// Whether locking bytecode starting with `PREFIX_TOKEN` (`0xef`) is
+    /// parsed as a token prefix.
+    pub cashtokens: bool,
+    /// Maximum length of NFT commitments, longer commitments are out of range.
+    pub max_token_commitment_length: usize,
+    /// Whether to reject or to report encoding issues.
+    pub mode: DecodeMode,
+}
+
+/// How to handle data that the node wouldn't accept but which can still be
+/// decoded into a structure.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DecodeMode {
+    /// Reject non-minimal CompactSize encodings, trailing bytes and
+    /// out-of-range token values, like the node does.
+    Strict,
+    /// Recover as much structure as possible, and report any issues as
+    /// [`DecodeWarning`]s. Token prefixes that can't be decoded are kept as
+    /// part of the locking script.
+    Lenient,
+}
+
+/// Issue found while decoding in [`DecodeMode::Lenient`].
+///
+/// `offset` is the position in the decoded bytes where the issue starts.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodeWarning {
+    /// CompactSize or CompactUint is not minimally encoded.
+    NonMinimalCompactSize {
+        /// Position of the CompactSize.
+        offset: usize,
+        /// Value of the CompactSize.
+        value: u64,
+        /// Number of bytes used to encode the value.
+        encoded_length: usize,
+    },
+    /// There are bytes left after the decoded object.
+    TrailingBytes {
+        /// Position of the first trailing byte.
+        offset: usize,
+        /// Number of trailing bytes.
+        num_bytes: usize,
+    },
+    /// Locking bytecode starts with `PREFIX_TOKEN` but isn't a valid token
+    /// prefix; it is kept as the output's script.
+    InvalidTokenPrefix {
+        /// Position of the locking bytecode.
+        offset: usize,
+        /// Why the token prefix is invalid.
+        error: DataError,
+    },
+    /// Fungible token amount exceeds the maximum of 9223372036854775807.
+    TokenAmountOutOfRange {
+        /// Position of the amount.
+        offset: usize,
+        /// Encoded amount.
+        amount: u64,
+    },
+    /// NFT commitment exceeds
+    /// [`DecodeContext::max_token_commitment_length`].
+    CommitmentTooLong {
+        /// Position of the commitment length.
+        offset: usize,
+        /// Maximum commitment length.
+        max_length: usize,
+        /// Actual commitment length.
+        actual: usize,
+    },
+}
+
+/// Value decoded with a [`DecodeContext`], plus all the [`DecodeWarning`]s
+/// encountered (always empty in [`DecodeMode::Strict`]).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decoded<T> {
+    /// Decoded value.
+    pub value: T,
+    /// Issues found while decoding.
+    pub warnings: Vec<DecodeWarning>,
+}
+
+impl DecodeContext {
+    /// Context decoding txs the way the node did before CashTokens activated.
+    pub fn pre_cashtokens() -> Self {
+        DecodeContext::from(&UpgradeEpoch::Upgrade8.params())
+    }
+
+    /// Context for a tx in a block whose parent has the given
+    /// median-time-past on the given network.
+    /// ```
+    /// # use bitcoinsuite_core::{consensus::Net, tx::DecodeContext};
+    /// assert!(!DecodeContext::at_median_time_past(Net::Mainnet, 1684151999).cashtokens);
+    /// assert!(DecodeContext::at_median_time_past(Net::Mainnet, 1684152000).cashtokens);
+    /// ```
+    pub fn at_median_time_past(net: Net, median_time_past: i64) -> Self {
+        DecodeContext::from(&UpgradeEpoch::at_median_time_past(net, median_time_past).params())
+    }
+
+    /// Same context, but decoding in the given [`DecodeMode`].
+    /// ```
+    /// # use bitcoinsuite_core::tx::{DecodeContext, DecodeMode};
+    /// let ctx = DecodeContext::default().with_mode(DecodeMode::Lenient);
+    /// assert_eq!(ctx.mode, DecodeMode::Lenient);
+    /// assert!(ctx.cashtokens);
+    /// ```
+    pub fn with_mode(self, mode: DecodeMode) -> Self {
+        DecodeContext { mode, ..self }
+    }
+
+    /// Context recovering as much as possible under today's rules.
+    pub fn lenient() -> Self {
+        DecodeContext::default().with_mode(DecodeMode::Lenient)
+    }
+}
+
+impl Default for DecodeContext {
+    fn default() -> Self {
+        DecodeContext::from(&ConsensusParams::latest())
+    }
+}
+
+impl From<&ConsensusParams> for DecodeContext {
+    fn from(params: &ConsensusParams) -> Self {
+        DecodeContext {
+            cashtokens: params.cashtokens,
+            max_token_commitment_length: params.max_token_commitment_length,
+            mode: DecodeMode::Strict,
+        }
+    }
+}
+
+/// Decodes txs, outputs and tokens according to a [`DecodeContext`],
+/// collecting [`DecodeWarning`]s along the way.
+#[derive(Debug)]
+pub(crate) struct Decoder<'a> {
+    ctx: &'a DecodeContext,
+    total_len: usize,
+    warnings: Vec<DecodeWarning>,
+}
+
+impl<'a> Decoder<'a> {
+    /// Decoder for the given bytes; offsets in warnings are relative to the
+    /// current start of `data`.
+    pub(crate) fn new(ctx: &'a DecodeContext, data: &Bytes) -> Self {
+        Decoder {
+            ctx,
+            total_len: data.len(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Finish decoding `value`; all of `data` must be consumed.
+    pub(crate) fn finish<T>(mut self, value: T, data: &Bytes) -> Result<Decoded<T>, DataError> {
+        if !data.is_empty() {
+            let offset = self.offset(data);
+            match self.ctx.mode {
+                DecodeMode::Strict => {
+                    return Err(DataError::TrailingBytes {
+                        num_bytes: data.len(),
+                    })
+                }
+                DecodeMode::Lenient => self.warnings.push(DecodeWarning::TrailingBytes {
+                    offset,
+                    num_bytes: data.len(),
+                }),
+            }
+        }
+        Ok(Decoded {
+            value,
+            warnings: self.warnings,
+        })
+    }
+
+    fn offset(&self, data: &Bytes) -> usize {
+        self.total_len - data.len()
+    }
+
+    fn read_compact_size(&mut self, data: &mut Bytes, offset: usize) -> Result<u64, DataError> {
+        match read_compact_uint_minimal(&mut data.clone()) {
+            Err(DataError::InvalidCompactUint { encoded_length, .. })
+                if self.ctx.mode == DecodeMode::Lenient =>
+            {
+                let value = read_compact_size(data)?;
+                self.warnings.push(DecodeWarning::NonMinimalCompactSize {
+                    offset,
+                    value,
+                    encoded_length,
+                });
+                Ok(value)
+            }
+            _ => read_compact_uint_minimal(data),
+        }
+    }
+
+    fn read_var_bytes(&mut self, data: &mut Bytes) -> Result<Bytes, DataError> {
+        let offset = self.offset(data);
+        let size = self.read_compact_size(data, offset)?;
+        read_bytes(data, size as usize)
+    }
+
+    pub(crate) fn transaction(&mut self, data: &mut Bytes) -> Result<Transaction, DataError> {
+        let version = BitcoinSer::deser(data)?;
+        let offset = self.offset(data);
+        let num_inputs = self.read_compact_size(data, offset)? as usize;
+        let mut inputs = Vec::with_capacity(num_inputs.min(0x10000));
+        for _ in 0..num_inputs {
+            inputs.push(self.input(data)?);
+        }
+        let offset = self.offset(data);
+        let num_outputs = self.read_compact_size(data, offset)? as usize;
+        let mut outputs = Vec::with_capacity(num_outputs.min(0x10000));
+        for _ in 0..num_outputs {
+            outputs.push(self.output(data)?);
+        }
+        Ok(Transaction {
+            version,
+            inputs,
+            outputs,
+            locktime: BitcoinSer::deser(data)?,
+        })
+    }
+
+    fn input(&mut self, data: &mut Bytes) -> Result<Input, DataError> {
+        Ok(Input {
+            prev_out: BitcoinSer::deser(data)?,
+            script: Script::new(self.read_var_bytes(data)?),
+            sequence: BitcoinSer::deser(data)?,
+        })
+    }
+
+    pub(crate) fn output(&mut self, data: &mut Bytes) -> Result<Output, DataError> {
+        let value = BitcoinSer::deser(data)?;
+        let mut bytecode = self.read_var_bytes(data)?;
+        if !self.ctx.cashtokens || bytecode.first() != Some(&TOKEN_PREFIX) {
+            return Ok(Output {
+                value,
+                script: Script::new(bytecode),
+                token: None,
+            });
+        }
+        let offset = self.offset(data) - bytecode.len();
+        match self.ctx.mode {
+            DecodeMode::Strict => {
+                let token = self.token(&mut bytecode, offset)?;
+                Ok(Output {
+                    value,
+                    script: Script::new(bytecode),
+                    token: Some(token),
+                })
+            }
+            DecodeMode::Lenient => {
+                let num_warnings = self.warnings.len();
+                let mut token_bytecode = bytecode.clone();
+                match self.token(&mut token_bytecode, offset) {
+                    Ok(token) => Ok(Output {
+                        value,
+                        script: Script::new(token_bytecode),
+                        token: Some(token),
+                    }),
+                    Err(error) => {
+                        // Warnings of the discarded token don't apply
+                        self.warnings.truncate(num_warnings);
+                        self.warnings
+                            .push(DecodeWarning::InvalidTokenPrefix { offset, error });
+                        Ok(Output {
+                            value,
+                            script: Script::new(bytecode),
+                            token: None,
+                        })
+                    }
+                }
+            }
+        }
+    }
+
+    /// Decode a token prefix at the start of `data`, which is at `offset` in
+    /// the decoded bytes.
+    pub(crate) fn token(
+        &mut self,
+        data: &mut Bytes,
+        offset: usize,
+    ) -> Result<CashToken, DataError> {
+        match self.ctx.mode {
+            DecodeMode::Strict => {
+                let token = CashToken::deser(data)?;
+                let commitment_len = token.commitment().0.len();
+                if commitment_len > self.ctx.max_token_commitment_length {
+                    return Err(DataError::CommitmentTooLong {
+                        max_length: self.ctx.max_token_commitment_length,
+                        actual: commitment_len,
+                    });
+                }
+                Ok(token)
+            }
+            DecodeMode::Lenient => self.token_lenient(data, offset),
+        }
+    }
+
+    /// Decode a token prefix, tolerating non-minimal CompactUints and
+    /// out-of-range values, which are reported as warnings. All other rules
+    /// are checked by the strict parser, on the prefix re-encoded minimally.
+    fn token_lenient(&mut self, data: &mut Bytes, offset: usize) -> Result<CashToken, DataError> {
+        let mut remaining = data.clone();
+        let (canonical, amount) = match self.canonical_token_prefix(&mut remaining, offset) {
+            Ok(canonical) => canonical,
+            // Prefix is cut short, report it like the strict parser does
+            Err(_) => return CashToken::deser(data),
+        };
+        let mut token = CashToken::deser(&mut Bytes::from(canonical))?;
+        token.amount = amount;
+        *data = remaining;
+        Ok(token)
+    }
+
+    /// Read the token prefix at the start of `data` and re-encode it with
+    /// minimal CompactUints and the amount capped at
+    /// [`MAXIMUM_TOKEN_AMOUNT`], warning about both and about long
+    /// commitments. Returns the re-encoded prefix and the actual amount.
+    fn canonical_token_prefix(
+        &mut self,
+        data: &mut Bytes,
+        offset: usize,
+    ) -> Result<(Vec<u8>, CompactUint), DataError> {
+        let start_len = data.len();
+        let mut canonical = read_bytes(data, MINIMUM_PREFIX_LENGTH as usize)?.to_vec();
+        let bitfield = TokenBitfield(canonical[canonical.len() - 1]);
+        if bitfield.has_commitment_length() {
+            let commitment_offset = offset + start_len - data.len();
+            let commitment_len = self.read_compact_size(data, commitment_offset)?;
+            let max_length = self.ctx.max_token_commitment_length;
+            if commitment_len > max_length as u64 {
+                self.warnings.push(DecodeWarning::CommitmentTooLong {
+                    offset: commitment_offset,
+                    max_length,
+                    actual: commitment_len as usize,
+                });
+            }
+            canonical.extend_from_slice(&CompactUint(commitment_len).ser());
+            canonical.extend_from_slice(&read_bytes(data, commitment_len as usize)?);
+        }
+        let mut amount = CompactUint(0);
+        if bitfield.has_amount() {
+            let amount_offset = offset + start_len - data.len();
+            amount = CompactUint(self.read_compact_size(data, amount_offset)?);
+            if amount.0 > MAXIMUM_TOKEN_AMOUNT {
+                self.warnings.push(DecodeWarning::TokenAmountOutOfRange {
+                    offset: amount_offset,
+                    amount: amount.0,
+                });
+            }
+            canonical.extend_from_slice(&CompactUint(amount.0.min(MAXIMUM_TOKEN_AMOUNT)).ser());
+        }
+        Ok((canonical, amount))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        error::DataError,
+        script::{Script, ScriptVariant},
+        ser::{BitcoinSer, CompactUint},
+        tx::{
+            Capability, CashToken, Commitment, DecodeContext, DecodeMode, DecodeWarning, Input,
+            NonFungibleTokenCapability, Output, Transaction, TxId, NFT,
+        },
+    };
+
+    fn token_tx() -> Transaction {
+        Transaction {
+            version: 2,
+            inputs: vec![Input {
+                script: Script::new(vec![0x51].into()),
+                ..Default::default()
+            }],
+            outputs: vec![Output {
+                value: 1000,
+                script: Script::new(vec![0x51].into()),
+                token: Some(CashToken {
+                    amount: CompactUint(100),
+                    category: TxId::from([1; 32]),
+                    nft: Some(NFT {
+                        capability: NonFungibleTokenCapability(Capability::Mutable),
+                        commitment: Commitment(vec![0xcc; 3].into()),
+                    }),
+                }),
+            }],
+            locktime: 0,
+        }
+    }
+
+    #[test]
+    fn test_decode_valid() -> Result<(), DataError> {
+        let tx = token_tx();
+        for ctx in [DecodeContext::default(), DecodeContext::lenient()] {
+            let decoded = Transaction::decode(tx.ser(), &ctx)?;
+            assert_eq!(decoded.value, tx);
+            assert_eq!(decoded.warnings, vec![]);
+            let decoded = Output::decode(tx.outputs[0].ser(), &ctx)?;
+            assert_eq!(decoded.value, tx.outputs[0]);
+            let decoded = CashToken::decode(tx.outputs[0].token.ser(), &ctx)?;
+            assert_eq!(Some(decoded.value), tx.outputs[0].token);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_p2s_token_output() -> Result<(), DataError> {
+        // Large raw contract carrying a token, the prefix and bytecode
+        // together need a 3 byte CompactSize
+        let mut output = token_tx().outputs.remove(0);
+        output.script = Script::new([[0x51].as_ref(), &[0x75; 600]].concat().into());
+        let ser = output.ser();
+        assert_eq!(&ser[8..11], &[0xfd, 0x80, 0x02]);
+        for ctx in [DecodeContext::default(), DecodeContext::lenient()] {
+            let decoded = Output::decode(ser.clone(), &ctx)?;
+            assert_eq!(decoded.value, output);
+            assert_eq!(decoded.warnings, vec![]);
+            assert_eq!(
+                ScriptVariant::from_script(&decoded.value.script),
+                ScriptVariant::P2S(output.script.clone()),
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_trailing_bytes() -> Result<(), DataError> {
+        let tx = token_tx();
+        let ser = Bytes::from([tx.ser().as_ref(), &[0xab, 0xcd]].concat());
+        assert_eq!(
+            Transaction::decode(ser.clone(), &DecodeContext::default()),
+            Err(DataError::TrailingBytes { num_bytes: 2 }),
+        );
+        let decoded = Transaction::decode(ser.clone(), &DecodeContext::lenient())?;
+        assert_eq!(decoded.value, tx);
+        assert_eq!(
+            decoded.warnings,
+            vec![DecodeWarning::TrailingBytes {
+                offset: ser.len() - 2,
+                num_bytes: 2,
+            }],
+        );
+        // Streaming deserialization leaves the trailing bytes for the caller
+        let mut data = ser;
+        assert_eq!(Transaction::deser(&mut data)?, tx);
+        assert_eq!(data.as_ref(), &[0xab, 0xcd]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_non_minimal_script_len() -> Result<(), DataError> {
+        // Input script length encoded as 0xfd0100 instead of 0x01
+        let ser = hex::decode(
+            "02000000\
+             01\
+             0000000000000000000000000000000000000000000000000000000000000000\
+             00000000\
+             fd0100\
+             51\
+             00000000\
+             00\
+             00000000",
+        )
+        .unwrap();
+        let ser = Bytes::from(ser);
+        assert_eq!(
+            Transaction::decode(ser.clone(), &DecodeContext::default()),
+            Err(DataError::InvalidCompactUint {
+                canonical_length: 1,
+                encoded_length: 3,
+            }),
+        );
+        // Streaming deserialization accepts it, like before decoding modes
+        let tx = Transaction::deser(&mut ser.clone())?;
+        assert_eq!(tx.inputs[0].script.hex(), "51");
+        let decoded = Transaction::decode(ser, &DecodeContext::lenient())?;
+        assert_eq!(decoded.value.inputs[0].script.hex(), "51");
+        assert_eq!(
+            decoded.warnings,
+            vec![DecodeWarning::NonMinimalCompactSize {
+                offset: 41,
+                value: 1,
+                encoded_length: 3,
+            }],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_invalid_token_prefix() -> Result<(), DataError> {
+        // 0xef followed by too few bytes for a token prefix
+        let output = Output {
+            value: 1000,
+            script: Script::new(vec![0xef, 0x51].into()),
+            token: None,
+        };
+        let ser = output.ser();
+        let error = DataError::InvalidTokenPrefixLength {
+            minimum_length: 34,
+            actual: 2,
+        };
+        assert_eq!(
+            Output::decode(ser.clone(), &DecodeContext::default()),
+            Err(error.clone()),
+        );
+        // Lenient decoding reports the same error as strict decoding
+        let decoded = Output::decode(ser, &DecodeContext::lenient())?;
+        assert_eq!(decoded.value, output);
+        assert_eq!(
+            decoded.warnings,
+            vec![DecodeWarning::InvalidTokenPrefix { offset: 9, error }],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_token_out_of_range() -> Result<(), DataError> {
+        // Amount 0x8000000000000000 and a 41 byte commitment
+        let prefix = Bytes::from(
+            [
+                [0xef].as_ref(),
+                &[0xbb; 32],
+                &[0x70, 41],
+                &[0xcc; 41],
+                &[0xff, 0, 0, 0, 0, 0, 0, 0, 0x80],
+            ]
+            .concat(),
+        );
+        let upgrade11 = DecodeContext::from(&crate::consensus::UpgradeEpoch::Upgrade11.params());
+        assert_eq!(
+            CashToken::decode(prefix.clone(), &upgrade11),
+            Err(DataError::InvalidAmountEncoding {
+                error: "Invalid token prefix: exceeds maximum fungible token amount of 9223372036854775807".to_string(),
+            }),
+        );
+        let decoded = CashToken::decode(prefix, &upgrade11.with_mode(DecodeMode::Lenient))?;
+        assert_eq!(decoded.value.amount, CompactUint(0x8000_0000_0000_0000));
+        assert_eq!(
+            decoded.value.commitment(),
+            Commitment(vec![0xcc; 41].into())
+        );
+        assert_eq!(
+            decoded.warnings,
+            vec![
+                DecodeWarning::CommitmentTooLong {
+                    offset: 34,
+                    max_length: 40,
+                    actual: 41,
+                },
+                DecodeWarning::TokenAmountOutOfRange {
+                    offset: 76,
+                    amount: 0x8000_0000_0000_0000,
+                },
+            ],
+        );
+
+        // Commitment longer than 40 bytes was invalid before Upgrade12
+        let prefix = Bytes::from([[0xef].as_ref(), &[0xbb; 32], &[0x60, 41], &[0xcc; 41]].concat());
+        assert_eq!(
+            CashToken::decode(prefix.clone(), &upgrade11),
+            Err(DataError::CommitmentTooLong {
+                max_length: 40,
+                actual: 41,
+            }),
+        );
+        assert!(CashToken::decode(prefix, &DecodeContext::default()).is_ok());
+
+        // Non-minimal amount
+        let prefix =
+            Bytes::from([[0xef].as_ref(), &[0xbb; 32], &[0x10, 0xfd, 0x01, 0x00]].concat());
+        let decoded = CashToken::decode(prefix, &DecodeContext::lenient())?;
+        assert_eq!(decoded.value.amount, CompactUint(1));
+        assert_eq!(
+            decoded.warnings,
+            vec![DecodeWarning::NonMinimalCompactSize {
+                offset: 34,
+                value: 1,
+                encoded_length: 3,
+            }],
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use thiserror::Error;
+
+use crate::{
+    script::{opcode::*, push_opcode, ScriptNum},
+    ser::{write_compact_size, BitcoinSer},
+    tx::Transaction,
+};
+
+/// Size of a compressed pubkey push: `<33 bytes>`.
+const PUBKEY_PUSH_SIZE: usize = 1 + 33;
+
+/// Fee rate, in satoshis per 1000 bytes.
+///
+/// Fees are computed like the node does: rounded down, but at least 1 sat for
+/// a non-zero rate.
+/// ```
+/// # use bitcoinsuite_core::tx::FeeRate;
+/// assert_eq!(FeeRate::from_sats_per_byte(1).fee(219), 219);
+/// assert_eq!(FeeRate::from_sats_per_kb(1500).fee(219), 328);
+/// assert_eq!(FeeRate::from_sats_per_kb(1).fee(219), 1);
+/// assert_eq!(FeeRate::from_sats_per_kb(0).fee(219), 0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FeeRate {
+    sats_per_kb: u64,
+}
+
+/// How an input will be unlocked, used to estimate the size of its scriptSig
+/// before it is signed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum UnlockingTemplate {
+    /// P2PKH with a compressed pubkey and an ECDSA signature:
+    /// `<DER sig + sighash byte> <pubkey>`. Low-S DER signatures are at most
+    /// 71 bytes, so the size is an upper bound.
+    P2pkhEcdsa,
+    /// P2PKH with a compressed pubkey and a Schnorr signature:
+    /// `<64 byte sig + sighash byte> <pubkey>`, size is exact.
+    P2pkhSchnorr,
+    /// P2SH `m`-of-`n` multisig with compressed pubkeys:
+    /// `<dummy/bitfield> <sig>... <redeem script>`. Size is an upper bound.
+    P2shMultisig {
+        /// Number of required signatures.
+        m: usize,
+        /// Number of pubkeys.
+        n: usize,
+        /// Whether the signatures are Schnorr (with a bitfield instead of the
+        /// dummy element) or ECDSA.
+        schnorr: bool,
+    },
+    /// Any other scriptSig, e.g. for covenants, with a known size.
+    Custom {
+        /// Exact size of the scriptSig.
+        script_sig_size: usize,
+    },
+}
+
+/// Estimated serialized size of a tx once signed, see
+/// [`Transaction::estimate_size`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SizeEstimate {
+    /// Serialized size of the signed tx, or an upper bound of it.
+    pub size: usize,
+    /// Whether `size` is exact, i.e. no template has a variable size.
+    pub is_exact: bool,
+}
+
+/// Errors when estimating the size of a tx.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum FeeEstimateError {
+    /// The number of unlocking templates doesn't match the number of inputs.
+    #[error("Expected {num_inputs} unlocking templates, got {num_templates}")]
+    TemplateCountMismatch {
+        /// Number of inputs of the tx.
+        num_inputs: usize,
+        /// Number of templates given.
+        num_templates: usize,
+    },
+}
+
+impl FeeRate {
+    /// Fee rate of `sats_per_kb` sats per 1000 bytes.
+    pub const fn from_sats_per_kb(sats_per_kb: u64) -> Self {
+        FeeRate { sats_per_kb }
+    }
+
+    /// Fee rate of `sats_per_byte` sats per byte.
+    pub const fn from_sats_per_byte(sats_per_byte: u64) -> Self {
+        FeeRate {
+            sats_per_kb: sats_per_byte * 1000,
+        }
+    }
+
+    /// Sats per 1000 bytes of this fee rate.
+    pub const fn sats_per_kb(self) -> u64 {
+        self.sats_per_kb
+    }
+
+    /// Fee for `size` bytes at this rate.
+    pub fn fee(self, size: usize) -> u64 {
+        let fee = self.sats_per_kb * size as u64 / 1000;
+        if fee == 0 && self.sats_per_kb > 0 && size > 0 {
+            return 1;
+        }
+        fee
+    }
+}
+
+impl UnlockingTemplate {
+    /// Size of the scriptSig produced by this template; for templates with
+    /// variable size, the largest possible size.
+    /// ```
+    /// # use bitcoinsuite_core::tx::UnlockingTemplate;
+    /// assert_eq!(UnlockingTemplate::P2pkhEcdsa.script_sig_size(), 107);
+    /// assert_eq!(UnlockingTemplate::P2pkhSchnorr.script_sig_size(), 100);
+    /// // OP_0 <sig> <sig> <OP_2 <pubkey> <pubkey> <pubkey> OP_3 OP_CHECKMULTISIG>
+    /// let template = UnlockingTemplate::P2shMultisig { m: 2, n: 3, schnorr: false };
+    /// assert_eq!(template.script_sig_size(), 1 + 2 * 73 + 2 + 105);
+    /// ```
+    pub fn script_sig_size(&self) -> usize {
+        match *self {
+            UnlockingTemplate::P2pkhEcdsa => 1 + 72 + PUBKEY_PUSH_SIZE,
+            UnlockingTemplate::P2pkhSchnorr => 1 + 65 + PUBKEY_PUSH_SIZE,
+            UnlockingTemplate::P2shMultisig { m, n, schnorr } => {
+                let redeem_script_size =
+                    num_push_size(m) + n * PUBKEY_PUSH_SIZE + num_push_size(n) + 1;
+                let (dummy_size, sig_size) = if schnorr {
+                    // Bitfield with one bit per pubkey
+                    (1 + (n + 7) / 8, 1 + 65)
+                } else {
+                    (1, 1 + 72)
+                };
+                dummy_size + m * sig_size + push_size(redeem_script_size) + redeem_script_size
+            }
+            UnlockingTemplate::Custom { script_sig_size } => script_sig_size,
+        }
+    }
+
+    /// Serialized size of an input unlocked by this template: outpoint,
+    /// scriptSig with its length and sequence.
+    /// ```
+    /// # use bitcoinsuite_core::tx::UnlockingTemplate;
+    /// assert_eq!(UnlockingTemplate::P2pkhSchnorr.input_size(), 141);
+    /// let template = UnlockingTemplate::Custom { script_sig_size: 300 };
+    /// assert_eq!(template.input_size(), 36 + 3 + 300 + 4);
+    /// ```
+    pub fn input_size(&self) -> usize {
+        let script_sig_size = self.script_sig_size();
+        36 + compact_size_len(script_sig_size) + script_sig_size + 4
+    }
+
+    /// Whether [`UnlockingTemplate::script_sig_size`] is exact.
+    pub fn is_exact(&self) -> bool {
+        matches!(
+            self,
+            UnlockingTemplate::P2pkhSchnorr | UnlockingTemplate::Custom { .. }
+        )
+    }
+}
+
+impl SizeEstimate {
+    /// Fee to pay for a tx of the estimated size at the given rate.
+    pub fn fee(&self, fee_rate: FeeRate) -> u64 {
+        fee_rate.fee(self.size)
+    }
+}
+
+impl Transaction {
+    /// Estimate the serialized size of this tx once all inputs are signed,
+    /// with one [`UnlockingTemplate`] per input; any scriptSig already in the
+    /// tx is replaced by the template's size.
+    ///
+    /// Outputs are counted as serialized, including token prefixes, so the
+    /// fee can be set before signing.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::ShaRmd160,
+    /// #     script::Script,
+    /// #     tx::{FeeRate, Input, Output, SizeEstimate, Transaction, UnlockingTemplate},
+    /// # };
+    /// let tx = Transaction {
+    ///     version: 2,
+    ///     inputs: vec![Input::default(); 2],
+    ///     outputs: vec![Output {
+    ///         value: 10_000,
+    ///         script: Script::p2pkh(&ShaRmd160([1; 20])),
+    ///         token: None,
+    ///     }],
+    ///     locktime: 0,
+    /// };
+    /// let estimate = tx.estimate_size(&[UnlockingTemplate::P2pkhSchnorr; 2]).unwrap();
+    /// assert_eq!(estimate, SizeEstimate { size: 10 + 2 * 141 + 34, is_exact: true });
+    /// assert_eq!(estimate.fee(FeeRate::from_sats_per_byte(1)), 326);
+    ///
+    /// let templates = [UnlockingTemplate::P2pkhSchnorr, UnlockingTemplate::P2pkhEcdsa];
+    /// let estimate = tx.estimate_size(&templates).unwrap();
+    /// assert_eq!(estimate, SizeEstimate { size: 333, is_exact: false });
+    /// ```
+    pub fn estimate_size(
+        &self,
+        templates: &[UnlockingTemplate],
+    ) -> Result<SizeEstimate, FeeEstimateError> {
+        if templates.len() != self.inputs.len() {
+            return Err(FeeEstimateError::TemplateCountMismatch {
+                num_inputs: self.inputs.len(),
+                num_templates: templates.len(),
+            });
+        }
+        let mut size = self.ser_len();
+        let mut is_exact = true;
+        for (input, template) in self.inputs.iter().zip(templates) {
+            let script_sig_size = template.script_sig_size();
+            size -= input.script.ser_len();
+            size += compact_size_len(script_sig_size) + script_sig_size;
+            is_exact &= template.is_exact();
+        }
+        Ok(SizeEstimate { size, is_exact })
+    }
+}
+
+/// Number of bytes used to encode `size` as CompactSize.
+fn compact_size_len(size: usize) -> usize {
+    let mut len = 0;
+    write_compact_size(&mut len, size as u64);
+    len
+}
+
+/// Number of bytes of the opcode and length used to minimally push `len`
+/// bytes of data.
+fn push_size(len: usize) -> usize {
+    match push_opcode(len) {
+        OP_PUSHDATA1 => 2,
+        OP_PUSHDATA2 => 3,
+        OP_PUSHDATA4 => 5,
+        _ => 1,
+    }
+}
+
+/// Number of bytes used to minimally push the number `num`.
+fn num_push_size(num: usize) -> usize {
+    match num {
+        0..=16 => 1,
+        _ => {
+            let len = ScriptNum(num as i64).encode().len();
+            push_size(len) + len
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        script::Script,
+        ser::{BitcoinSer, CompactUint},
+        tx::{
+            CashToken, FeeEstimateError, FeeRate, Input, Output, SizeEstimate, Transaction, TxId,
+            UnlockingTemplate,
+        },
+    };
+
+    #[test]
+    fn test_multisig_script_sig_size() {
+        // 1-of-1 Schnorr: bitfield push, sig, push of 37 byte redeem script
+        let template = UnlockingTemplate::P2shMultisig {
+            m: 1,
+            n: 1,
+            schnorr: true,
+        };
+        assert_eq!(template.script_sig_size(), 2 + 66 + 1 + 37);
+        assert!(!template.is_exact());
+        // 11-of-15 ECDSA: redeem script needs OP_PUSHDATA2
+        let template = UnlockingTemplate::P2shMultisig {
+            m: 11,
+            n: 15,
+            schnorr: false,
+        };
+        assert_eq!(template.script_sig_size(), 1 + 11 * 73 + 3 + 513);
+        // 20 pubkeys need a 3 byte bitfield and a 2 byte number push
+        let template = UnlockingTemplate::P2shMultisig {
+            m: 1,
+            n: 20,
+            schnorr: true,
+        };
+        assert_eq!(template.script_sig_size(), 4 + 66 + 3 + 684);
+    }
+
+    #[test]
+    fn test_estimate_size() {
+        let mut tx = Transaction {
+            version: 2,
+            inputs: vec![Input::default()],
+            outputs: vec![Output {
+                value: 1000,
+                script: Script::new(vec![0x51; 250].into()),
+                token: None,
+            }],
+            locktime: 0,
+        };
+        assert_eq!(
+            tx.estimate_size(&[]),
+            Err(FeeEstimateError::TemplateCountMismatch {
+                num_inputs: 1,
+                num_templates: 0,
+            }),
+        );
+
+        // Estimate matches a tx with a scriptSig of the exact size
+        let template = UnlockingTemplate::Custom {
+            script_sig_size: 300,
+        };
+        let estimate = tx.estimate_size(&[template]).unwrap();
+        let mut signed_tx = tx.clone();
+        signed_tx.inputs[0].script = Script::new(vec![0x51; 300].into());
+        assert_eq!(
+            estimate,
+            SizeEstimate {
+                size: signed_tx.ser_len(),
+                is_exact: true,
+            },
+        );
+        // Existing scriptSigs are replaced
+        assert_eq!(signed_tx.estimate_size(&[template]).unwrap(), estimate);
+
+        // Token prefix counts, pushing the script length CompactSize to 3 bytes
+        tx.outputs[0].token = Some(CashToken {
+            amount: CompactUint(1000),
+            category: TxId::from([1; 32]),
+            nft: None,
+        });
+        let token_estimate = tx.estimate_size(&[template]).unwrap();
+        assert_eq!(token_estimate.size, estimate.size + 37 + 2);
+        assert_eq!(
+            token_estimate.fee(FeeRate::from_sats_per_byte(2)),
+            2 * token_estimate.size as u64,
+        );
+    }
+}
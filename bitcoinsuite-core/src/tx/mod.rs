@@ -4,10 +4,20 @@
 
 //! Module for data referring to txs, e.g. [`TxId`].
 
+mod check;
+mod decode;
+mod fee;
+mod sighash;
+mod signature;
 mod token;
 #[allow(clippy::module_inception)]
 mod transaction;
 mod txid;
+pub use self::check::*;
+pub use self::decode::*;
+pub use self::fee::*;
+pub use self::sighash::*;
+pub use self::signature::*;
 pub use self::token::*;
 pub use self::transaction::*;
 pub use self::txid::*;
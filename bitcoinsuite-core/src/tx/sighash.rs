@@ -0,0 +1,405 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use bytes::{Bytes, BytesMut};
+use thiserror::Error;
+
+use crate::{
+    hash::{Hashed, Sha256d},
+    script::Script,
+    ser::{BitcoinSer, BitcoinSerializer},
+    tx::{Output, Transaction},
+};
+
+/// Sighash type, the byte appended to signatures determining which parts of
+/// the tx are signed.
+/// ```
+/// # use bitcoinsuite_core::tx::SigHashType;
+/// assert_eq!(SigHashType::ALL_FORKID.0, 0x41);
+/// let single_acp = SigHashType::SINGLE_FORKID.with_anyonecanpay();
+/// assert_eq!(single_acp.0, 0xc3);
+/// assert_eq!(single_acp.base_type(), SigHashType::SINGLE);
+/// assert!(single_acp.has_anyonecanpay());
+/// assert!(single_acp.has_forkid());
+/// assert!(!single_acp.has_utxos());
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SigHashType(pub u8);
+
+/// Errors computing a sighash, see [`Transaction::sighash`].
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum SigHashError {
+    /// Signed input doesn't exist.
+    #[error("Input index {input_idx} out of range, tx has {num_inputs} inputs")]
+    InputIndexOutOfRange {
+        /// Index of the signed input.
+        input_idx: usize,
+        /// Number of inputs of the tx.
+        num_inputs: usize,
+    },
+
+    /// There must be one spent output per input.
+    #[error("Expected {num_inputs} spent outputs, got {num_spent_outputs}")]
+    SpentOutputsMismatch {
+        /// Number of inputs of the tx.
+        num_inputs: usize,
+        /// Number of spent outputs given.
+        num_spent_outputs: usize,
+    },
+
+    /// Sighash type doesn't have `SIGHASH_FORKID` set, which is required.
+    #[error("Sighash type {0:#04x} doesn't have SIGHASH_FORKID")]
+    MissingForkId(u8),
+
+    /// `SIGHASH_UTXOS` can't be combined with `SIGHASH_ANYONECANPAY`.
+    #[error("Sighash type {0:#04x} has both SIGHASH_UTXOS and SIGHASH_ANYONECANPAY")]
+    UtxosWithAnyoneCanPay(u8),
+
+    /// Base type is not ALL, NONE or SINGLE.
+    #[error("Sighash type {0:#04x} has an invalid base type")]
+    InvalidBaseType(u8),
+}
+
+impl SigHashType {
+    /// Sign all inputs and outputs.
+    pub const ALL: SigHashType = SigHashType(0x01);
+    /// Sign all inputs, but no outputs.
+    pub const NONE: SigHashType = SigHashType(0x02);
+    /// Sign all inputs, and the output with the same index as the input.
+    pub const SINGLE: SigHashType = SigHashType(0x03);
+    /// Flag to also sign the outputs spent by all inputs (CashTokens).
+    pub const UTXOS: SigHashType = SigHashType(0x20);
+    /// Flag marking the BCH sighash algorithm, required for all signatures.
+    pub const FORKID: SigHashType = SigHashType(0x40);
+    /// Flag to only sign the input itself, allowing others to add inputs.
+    pub const ANYONECANPAY: SigHashType = SigHashType(0x80);
+
+    /// `SIGHASH_ALL | SIGHASH_FORKID`, the default.
+    pub const ALL_FORKID: SigHashType = SigHashType(0x41);
+    /// `SIGHASH_NONE | SIGHASH_FORKID`.
+    pub const NONE_FORKID: SigHashType = SigHashType(0x42);
+    /// `SIGHASH_SINGLE | SIGHASH_FORKID`.
+    pub const SINGLE_FORKID: SigHashType = SigHashType(0x43);
+
+    const BASE_TYPE_MASK: u8 = 0x1f;
+
+    /// Base type, i.e. [`SigHashType::ALL`], [`SigHashType::NONE`] or
+    /// [`SigHashType::SINGLE`] for valid sighash types.
+    pub fn base_type(self) -> SigHashType {
+        SigHashType(self.0 & Self::BASE_TYPE_MASK)
+    }
+
+    /// Whether `SIGHASH_ANYONECANPAY` is set.
+    pub fn has_anyonecanpay(self) -> bool {
+        self.0 & Self::ANYONECANPAY.0 != 0
+    }
+
+    /// Whether `SIGHASH_UTXOS` is set.
+    pub fn has_utxos(self) -> bool {
+        self.0 & Self::UTXOS.0 != 0
+    }
+
+    /// Whether `SIGHASH_FORKID` is set.
+    pub fn has_forkid(self) -> bool {
+        self.0 & Self::FORKID.0 != 0
+    }
+
+    /// This sighash type with `SIGHASH_ANYONECANPAY` set.
+    pub fn with_anyonecanpay(self) -> SigHashType {
+        SigHashType(self.0 | Self::ANYONECANPAY.0)
+    }
+
+    /// This sighash type with `SIGHASH_UTXOS` set.
+    pub fn with_utxos(self) -> SigHashType {
+        SigHashType(self.0 | Self::UTXOS.0)
+    }
+
+    /// Check whether this sighash type is valid on BCH.
+    pub fn validate(self) -> Result<(), SigHashError> {
+        if !self.has_forkid() {
+            return Err(SigHashError::MissingForkId(self.0));
+        }
+        if self.has_utxos() && self.has_anyonecanpay() {
+            return Err(SigHashError::UtxosWithAnyoneCanPay(self.0));
+        }
+        let base_type = self.base_type();
+        if base_type != Self::ALL && base_type != Self::NONE && base_type != Self::SINGLE {
+            return Err(SigHashError::InvalidBaseType(self.0));
+        }
+        Ok(())
+    }
+}
+
+impl Transaction {
+    /// Preimage signed by signatures of input `input_idx`, using the BCH
+    /// sighash algorithm (BIP143 with `SIGHASH_FORKID`), including the
+    /// CashTokens extensions: the token prefix of the spent output, and
+    /// the hash of all spent outputs for `SIGHASH_UTXOS`.
+    ///
+    /// `spent_outputs` are the outputs spent by each input of this tx, and
+    /// `script_code` is the script executed (e.g. the redeem script for
+    /// P2SH inputs).
+    pub fn sighash_preimage(
+        &self,
+        input_idx: usize,
+        spent_outputs: &[Output],
+        script_code: &Script,
+        sig_hash_type: SigHashType,
+    ) -> Result<Bytes, SigHashError> {
+        sig_hash_type.validate()?;
+        if spent_outputs.len() != self.inputs.len() {
+            return Err(SigHashError::SpentOutputsMismatch {
+                num_inputs: self.inputs.len(),
+                num_spent_outputs: spent_outputs.len(),
+            });
+        }
+        let input = self
+            .inputs
+            .get(input_idx)
+            .ok_or(SigHashError::InputIndexOutOfRange {
+                input_idx,
+                num_inputs: self.inputs.len(),
+            })?;
+        let spent_output = &spent_outputs[input_idx];
+        let base_type = sig_hash_type.base_type();
+        let anyonecanpay = sig_hash_type.has_anyonecanpay();
+
+        let hash_prevouts = if anyonecanpay {
+            [0; 32]
+        } else {
+            hash_items(self.inputs.iter().map(|input| &input.prev_out))
+        };
+        let hash_sequence = if anyonecanpay || base_type != SigHashType::ALL {
+            [0; 32]
+        } else {
+            hash_items(self.inputs.iter().map(|input| &input.sequence))
+        };
+        let hash_outputs = if base_type == SigHashType::ALL {
+            hash_items(self.outputs.iter())
+        } else if base_type == SigHashType::SINGLE && input_idx < self.outputs.len() {
+            hash_items([&self.outputs[input_idx]])
+        } else {
+            [0; 32]
+        };
+
+        let mut preimage = BytesMut::new();
+        self.version.ser_to(&mut preimage);
+        preimage.put(&hash_prevouts);
+        if sig_hash_type.has_utxos() {
+            preimage.put(&hash_items(spent_outputs.iter()));
+        }
+        preimage.put(&hash_sequence);
+        input.prev_out.ser_to(&mut preimage);
+        if spent_output.token.is_some() {
+            spent_output.token.ser_to(&mut preimage);
+        }
+        script_code.ser_to(&mut preimage);
+        spent_output.value.ser_to(&mut preimage);
+        input.sequence.ser_to(&mut preimage);
+        preimage.put(&hash_outputs);
+        self.locktime.ser_to(&mut preimage);
+        u32::from(sig_hash_type.0).ser_to(&mut preimage);
+        Ok(preimage.freeze())
+    }
+
+    /// Sighash signed by signatures of input `input_idx`, the double SHA-256
+    /// of [`Transaction::sighash_preimage`].
+    pub fn sighash(
+        &self,
+        input_idx: usize,
+        spent_outputs: &[Output],
+        script_code: &Script,
+        sig_hash_type: SigHashType,
+    ) -> Result<Sha256d, SigHashError> {
+        let preimage =
+            self.sighash_preimage(input_idx, spent_outputs, script_code, sig_hash_type)?;
+        Ok(Sha256d::digest(preimage))
+    }
+}
+
+fn hash_items<'a, T: BitcoinSer + 'a>(items: impl IntoIterator<Item = &'a T>) -> [u8; 32] {
+    let mut data = BytesMut::new();
+    for item in items {
+        item.ser_to(&mut data);
+    }
+    Sha256d::digest(data).to_le_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        hash::{Hashed, Sha256d},
+        script::Script,
+        ser::{BitcoinSer, CompactUint},
+        tx::{CashToken, Input, OutPoint, Output, SigHashError, SigHashType, Transaction, TxId},
+    };
+
+    fn tx() -> (Transaction, Vec<Output>) {
+        let tx = Transaction {
+            version: 2,
+            inputs: (0..2)
+                .map(|idx| Input {
+                    prev_out: OutPoint {
+                        txid: TxId::from([idx + 1; 32]),
+                        outpoint_index: idx as u32,
+                    },
+                    script: Script::default(),
+                    sequence: 0xffff_fffe - idx as u32,
+                })
+                .collect(),
+            outputs: (0..2)
+                .map(|idx| Output {
+                    value: 1000 * (idx + 1),
+                    script: Script::new(vec![0x51; idx as usize + 1].into()),
+                    token: None,
+                })
+                .collect(),
+            locktime: 123,
+        };
+        let spent_outputs = vec![
+            Output {
+                value: 5000,
+                script: Script::new(vec![0x52].into()),
+                token: None,
+            },
+            Output {
+                value: 6000,
+                script: Script::new(vec![0x53].into()),
+                token: Some(CashToken {
+                    amount: CompactUint(100),
+                    category: TxId::from([9; 32]),
+                    nft: None,
+                }),
+            },
+        ];
+        (tx, spent_outputs)
+    }
+
+    fn hash(data: &[u8]) -> Vec<u8> {
+        Sha256d::digest(data).to_le_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_sighash_preimage_all() -> Result<(), SigHashError> {
+        let (tx, spent_outputs) = tx();
+        let script_code = Script::new(vec![0x52].into());
+        let preimage =
+            tx.sighash_preimage(0, &spent_outputs, &script_code, SigHashType::ALL_FORKID)?;
+        let prevouts = [tx.inputs[0].prev_out.ser(), tx.inputs[1].prev_out.ser()].concat();
+        let sequences = [tx.inputs[0].sequence.ser(), tx.inputs[1].sequence.ser()].concat();
+        let outputs = [tx.outputs[0].ser(), tx.outputs[1].ser()].concat();
+        let expected = [
+            hex::decode("02000000").unwrap(),
+            hash(&prevouts),
+            hash(&sequences),
+            tx.inputs[0].prev_out.ser().to_vec(),
+            hex::decode("0152").unwrap(),
+            5000u64.to_le_bytes().to_vec(),
+            hex::decode("feffffff").unwrap(),
+            hash(&outputs),
+            hex::decode("7b000000").unwrap(),
+            hex::decode("41000000").unwrap(),
+        ]
+        .concat();
+        assert_eq!(preimage.as_ref(), expected.as_slice());
+        assert_eq!(
+            tx.sighash(0, &spent_outputs, &script_code, SigHashType::ALL_FORKID)?,
+            Sha256d::digest(&expected),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sighash_preimage_token_utxos() -> Result<(), SigHashError> {
+        let (tx, spent_outputs) = tx();
+        let script_code = Script::new(vec![0x53].into());
+        let sig_hash_type = SigHashType::ALL_FORKID.with_utxos();
+        let preimage = tx.sighash_preimage(1, &spent_outputs, &script_code, sig_hash_type)?;
+        let prevouts = [tx.inputs[0].prev_out.ser(), tx.inputs[1].prev_out.ser()].concat();
+        let sequences = [tx.inputs[0].sequence.ser(), tx.inputs[1].sequence.ser()].concat();
+        let utxos = [spent_outputs[0].ser(), spent_outputs[1].ser()].concat();
+        let outputs = [tx.outputs[0].ser(), tx.outputs[1].ser()].concat();
+        let expected = [
+            hex::decode("02000000").unwrap(),
+            hash(&prevouts),
+            hash(&utxos),
+            hash(&sequences),
+            tx.inputs[1].prev_out.ser().to_vec(),
+            spent_outputs[1].token.ser().to_vec(),
+            hex::decode("0153").unwrap(),
+            6000u64.to_le_bytes().to_vec(),
+            hex::decode("fdffffff").unwrap(),
+            hash(&outputs),
+            hex::decode("7b000000").unwrap(),
+            hex::decode("61000000").unwrap(),
+        ]
+        .concat();
+        assert_eq!(preimage.as_ref(), expected.as_slice());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sighash_preimage_single_anyonecanpay() -> Result<(), SigHashError> {
+        let (tx, spent_outputs) = tx();
+        let script_code = Script::new(vec![0x53].into());
+        let sig_hash_type = SigHashType::SINGLE_FORKID.with_anyonecanpay();
+        let preimage = tx.sighash_preimage(1, &spent_outputs, &script_code, sig_hash_type)?;
+        let expected = [
+            hex::decode("02000000").unwrap(),
+            vec![0; 32],
+            vec![0; 32],
+            tx.inputs[1].prev_out.ser().to_vec(),
+            spent_outputs[1].token.ser().to_vec(),
+            hex::decode("0153").unwrap(),
+            6000u64.to_le_bytes().to_vec(),
+            hex::decode("fdffffff").unwrap(),
+            hash(&tx.outputs[1].ser()),
+            hex::decode("7b000000").unwrap(),
+            hex::decode("c3000000").unwrap(),
+        ]
+        .concat();
+        assert_eq!(preimage.as_ref(), expected.as_slice());
+
+        // No output at the input's index: hashOutputs is zero
+        let mut tx = tx;
+        tx.outputs.truncate(1);
+        let preimage = tx.sighash_preimage(1, &spent_outputs, &script_code, sig_hash_type)?;
+        assert_eq!(preimage[preimage.len() - 40..preimage.len() - 8], [0; 32]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sighash_errors() {
+        let (tx, spent_outputs) = tx();
+        let script = Script::default();
+        assert_eq!(
+            tx.sighash(0, &spent_outputs, &script, SigHashType::ALL),
+            Err(SigHashError::MissingForkId(0x01)),
+        );
+        assert_eq!(
+            tx.sighash(0, &spent_outputs, &script, SigHashType(0xe1)),
+            Err(SigHashError::UtxosWithAnyoneCanPay(0xe1)),
+        );
+        assert_eq!(
+            tx.sighash(0, &spent_outputs, &script, SigHashType(0x44)),
+            Err(SigHashError::InvalidBaseType(0x44)),
+        );
+        assert_eq!(
+            tx.sighash(2, &spent_outputs, &script, SigHashType::ALL_FORKID),
+            Err(SigHashError::InputIndexOutOfRange {
+                input_idx: 2,
+                num_inputs: 2,
+            }),
+        );
+        assert_eq!(
+            tx.sighash(0, &spent_outputs[..1], &script, SigHashType::ALL_FORKID),
+            Err(SigHashError::SpentOutputsMismatch {
+                num_inputs: 2,
+                num_spent_outputs: 1,
+            }),
+        );
+    }
+}
@@ -0,0 +1,382 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use bytes::{Bytes, BytesMut};
+use thiserror::Error;
+
+use crate::{
+    script::{opcode::Opcode, Op},
+    tx::{SigHashError, SigHashType},
+};
+
+/// Half of the order of the secp256k1 curve, the largest S value allowed in
+/// ECDSA signatures (LOW_S).
+const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// ECDSA or Schnorr signature, without sighash byte, as checked by
+/// `OP_CHECKDATASIG`. The algorithm is determined by the size: Schnorr
+/// signatures have 64 bytes, which a DER encoding can't have.
+/// ```
+/// # use bitcoinsuite_core::tx::{Signature, SignatureError};
+/// assert_eq!(Signature::try_from([1; 64].as_ref()), Ok(Signature::Schnorr([1; 64])));
+/// let der = hex::decode("3006020101020102").unwrap();
+/// let sig = Signature::try_from(der.as_ref()).unwrap();
+/// assert_eq!(sig, Signature::Ecdsa(der.clone().into()));
+/// assert_eq!(sig.as_slice(), der);
+/// assert_eq!(Signature::try_from([1; 73].as_ref()), Err(SignatureError::InvalidSize(73)));
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Signature {
+    /// Strictly DER encoded ECDSA signature with low S, unless parsed with
+    /// [`TxSignature::parse_lenient`].
+    Ecdsa(Bytes),
+    /// Schnorr signature.
+    Schnorr([u8; Signature::SCHNORR_SIZE]),
+}
+
+/// Signature of a tx input: a [`Signature`] followed by the
+/// [`SigHashType`], as checked by `OP_CHECKSIG`.
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     script::{Op, ScriptMut},
+/// #     tx::{SigHashError, SigHashType, Signature, SignatureError, TxSignature},
+/// # };
+/// let sig = TxSignature {
+///     sig: Signature::Schnorr([7; 64]),
+///     sig_hash_type: SigHashType::ALL_FORKID,
+/// };
+/// let bytes = sig.to_bytes();
+/// assert_eq!(bytes.as_ref(), [[7; 64].as_ref(), &[0x41]].concat());
+/// assert_eq!(TxSignature::try_from(bytes.as_ref()), Ok(sig.clone()));
+///
+/// // Signatures are pushed directly
+/// let op = Op::from(sig.clone());
+/// let mut script = ScriptMut::with_capacity(66);
+/// script.put_bytecode(&[65]);
+/// script.put_bytecode(&bytes);
+/// assert_eq!(script.freeze().iter_ops().next().unwrap(), Ok(op.clone()));
+/// assert_eq!(TxSignature::try_from(&op), Ok(sig));
+///
+/// assert_eq!(
+///     TxSignature::try_from([[7; 64].as_ref(), &[0x01]].concat().as_slice()),
+///     Err(SignatureError::SigHashType(SigHashError::MissingForkId(0x01))),
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TxSignature {
+    /// The signature.
+    pub sig: Signature,
+    /// Sighash type, the last byte of the signature.
+    pub sig_hash_type: SigHashType,
+}
+
+/// Errors parsing a [`Signature`] or [`TxSignature`].
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum SignatureError {
+    /// Tx signatures need at least the sighash byte.
+    #[error("Empty signature")]
+    Empty,
+
+    /// Neither a Schnorr signature nor a possible DER encoding.
+    #[error(
+        "Invalid signature size {0}, expected {} (Schnorr) or {} to {} (ECDSA) bytes",
+        Signature::SCHNORR_SIZE,
+        Signature::MIN_DER_SIZE,
+        Signature::MAX_DER_SIZE
+    )]
+    InvalidSize(usize),
+
+    /// ECDSA signature violates strict DER encoding (BIP66).
+    #[error("Invalid DER encoding: {0}")]
+    InvalidDer(&'static str),
+
+    /// ECDSA signature has an S value above half the curve order (LOW_S).
+    #[error("ECDSA signature has high S")]
+    HighS,
+
+    /// Sighash byte is not valid on BCH.
+    #[error("Invalid sighash type: {0}")]
+    SigHashType(#[from] SigHashError),
+
+    /// Signatures must be pushed.
+    #[error("Expected a push op, got {0}")]
+    NotAPush(Opcode),
+}
+
+impl Signature {
+    /// Number of bytes of a Schnorr signature.
+    pub const SCHNORR_SIZE: usize = 64;
+    /// Minimum number of bytes of a DER encoded ECDSA signature.
+    pub const MIN_DER_SIZE: usize = 8;
+    /// Maximum number of bytes of a DER encoded ECDSA signature.
+    pub const MAX_DER_SIZE: usize = 72;
+
+    /// Encoded signature.
+    pub fn as_slice(&self) -> &[u8] {
+        match self {
+            Signature::Ecdsa(der) => der,
+            Signature::Schnorr(sig) => sig,
+        }
+    }
+
+    /// Whether this is a Schnorr signature.
+    pub fn is_schnorr(&self) -> bool {
+        matches!(self, Signature::Schnorr(_))
+    }
+}
+
+impl TryFrom<&'_ [u8]> for Signature {
+    type Error = SignatureError;
+
+    fn try_from(value: &'_ [u8]) -> Result<Self, Self::Error> {
+        if let Ok(sig) = <[u8; Signature::SCHNORR_SIZE]>::try_from(value) {
+            return Ok(Signature::Schnorr(sig));
+        }
+        if value.len() < Signature::MIN_DER_SIZE || value.len() > Signature::MAX_DER_SIZE {
+            return Err(SignatureError::InvalidSize(value.len()));
+        }
+        let s = parse_der(value)?;
+        if !is_low_s(s) {
+            return Err(SignatureError::HighS);
+        }
+        Ok(Signature::Ecdsa(Bytes::copy_from_slice(value)))
+    }
+}
+
+impl TxSignature {
+    /// Signature followed by the sighash byte.
+    pub fn to_bytes(&self) -> Bytes {
+        let sig = self.sig.as_slice();
+        let mut bytes = BytesMut::with_capacity(sig.len() + 1);
+        bytes.extend_from_slice(sig);
+        bytes.extend_from_slice(&[self.sig_hash_type.0]);
+        bytes.freeze()
+    }
+
+    /// Parse a signature as historical consensus rules accepted it: the
+    /// sighash byte is kept as is, without requiring FORKID, and ECDSA
+    /// signatures are checked for neither strict DER nor low S.
+    ///
+    /// Use this to analyze spends of any age; check signatures against the
+    /// current rules with [`TxSignature::try_from`].
+    /// ```
+    /// # use bitcoinsuite_core::tx::{Signature, SigHashType, SignatureError, TxSignature};
+    /// // Pre-fork ECDSA signature with SIGHASH_ALL, S is not strictly DER encoded
+    /// let der = hex::decode("3006020101020180").unwrap();
+    /// let bytes = [der.as_slice(), &[0x01]].concat();
+    /// assert_eq!(
+    ///     TxSignature::parse_lenient(&bytes),
+    ///     Ok(TxSignature {
+    ///         sig: Signature::Ecdsa(der.into()),
+    ///         sig_hash_type: SigHashType(0x01),
+    ///     }),
+    /// );
+    /// assert!(TxSignature::try_from(bytes.as_slice()).is_err());
+    /// assert_eq!(TxSignature::parse_lenient(&[0x41]), Err(SignatureError::InvalidSize(0)));
+    /// ```
+    pub fn parse_lenient(value: &[u8]) -> Result<TxSignature, SignatureError> {
+        let (&sig_hash_type, sig) = value.split_last().ok_or(SignatureError::Empty)?;
+        let sig = match <[u8; Signature::SCHNORR_SIZE]>::try_from(sig) {
+            Ok(sig) => Signature::Schnorr(sig),
+            Err(_) if sig.is_empty() => return Err(SignatureError::InvalidSize(0)),
+            Err(_) => Signature::Ecdsa(Bytes::copy_from_slice(sig)),
+        };
+        Ok(TxSignature {
+            sig,
+            sig_hash_type: SigHashType(sig_hash_type),
+        })
+    }
+}
+
+impl TryFrom<&'_ [u8]> for TxSignature {
+    type Error = SignatureError;
+
+    fn try_from(value: &'_ [u8]) -> Result<Self, Self::Error> {
+        let (&sig_hash_type, sig) = value.split_last().ok_or(SignatureError::Empty)?;
+        let sig_hash_type = SigHashType(sig_hash_type);
+        sig_hash_type.validate()?;
+        Ok(TxSignature {
+            sig: Signature::try_from(sig)?,
+            sig_hash_type,
+        })
+    }
+}
+
+impl TryFrom<&'_ Op> for TxSignature {
+    type Error = SignatureError;
+
+    fn try_from(value: &'_ Op) -> Result<Self, Self::Error> {
+        match value {
+            Op::Push(_, data) => TxSignature::try_from(data.as_ref()),
+            Op::Code(opcode) => Err(SignatureError::NotAPush(*opcode)),
+        }
+    }
+}
+
+impl From<TxSignature> for Op {
+    fn from(sig: TxSignature) -> Self {
+        let bytes = sig.to_bytes();
+        // Signatures are shorter than OP_PUSHDATA1, so the opcode is the size
+        Op::Push(Opcode(bytes.len() as u8), bytes)
+    }
+}
+
+/// Check the strict DER encoding of BIP66 and return the S value.
+fn parse_der(sig: &[u8]) -> Result<&[u8], SignatureError> {
+    if sig[0] != 0x30 {
+        return Err(SignatureError::InvalidDer("Must start with 0x30"));
+    }
+    if sig[1] as usize != sig.len() - 2 {
+        return Err(SignatureError::InvalidDer("Wrong total length"));
+    }
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return Err(SignatureError::InvalidDer("R exceeds signature"));
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 6 != sig.len() {
+        return Err(SignatureError::InvalidDer(
+            "Lengths of R and S don't add up",
+        ));
+    }
+    if sig[2] != 0x02 || sig[4 + len_r] != 0x02 {
+        return Err(SignatureError::InvalidDer("R and S must be integers"));
+    }
+    let check_int = |int: &[u8]| match int {
+        [] => Err(SignatureError::InvalidDer("Zero-length integer")),
+        [first, ..] if first & 0x80 != 0 => Err(SignatureError::InvalidDer("Negative integer")),
+        [0, second, ..] if second & 0x80 == 0 => {
+            Err(SignatureError::InvalidDer("Integer has excess padding"))
+        }
+        _ => Ok(()),
+    };
+    let s = &sig[6 + len_r..];
+    check_int(&sig[4..4 + len_r])?;
+    check_int(s)?;
+    Ok(s)
+}
+
+/// Whether the (DER checked) S value is at most half the curve order.
+fn is_low_s(s: &[u8]) -> bool {
+    let s = match s {
+        [0, rest @ ..] => rest,
+        _ => s,
+    };
+    match s.len() {
+        len if len < HALF_ORDER.len() => true,
+        len if len == HALF_ORDER.len() => s <= HALF_ORDER.as_ref(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::tx::{SigHashError, SigHashType, Signature, SignatureError, TxSignature};
+
+    fn der_sig(r: &[u8], s: &[u8]) -> Vec<u8> {
+        let mut sig = vec![0x30, (r.len() + s.len() + 4) as u8, 0x02, r.len() as u8];
+        sig.extend_from_slice(r);
+        sig.extend_from_slice(&[0x02, s.len() as u8]);
+        sig.extend_from_slice(s);
+        sig
+    }
+
+    #[test]
+    fn test_parse_ecdsa() {
+        let sig = der_sig(&[0x11; 32], &[0x22; 32]);
+        assert_eq!(
+            Signature::try_from(sig.as_ref()),
+            Ok(Signature::Ecdsa(sig.clone().into())),
+        );
+        let padded_r = [[0].as_ref(), &[0x80; 32]].concat();
+        let max_sig = der_sig(&padded_r, &[0x7f; 32]);
+        // Low S limits ECDSA signatures to one byte below the DER maximum
+        assert_eq!(max_sig.len(), Signature::MAX_DER_SIZE - 1);
+        assert!(Signature::try_from(max_sig.as_ref()).is_ok());
+
+        let check_err = |sig: &[u8], err: &'static str| {
+            assert_eq!(
+                Signature::try_from(sig),
+                Err(SignatureError::InvalidDer(err))
+            );
+        };
+        let mut wrong_tag = sig.clone();
+        wrong_tag[0] = 0x31;
+        check_err(&wrong_tag, "Must start with 0x30");
+        let mut wrong_len = sig.clone();
+        wrong_len[1] = 67;
+        check_err(&wrong_len, "Wrong total length");
+        let mut long_r = sig.clone();
+        long_r[3] = 66;
+        check_err(&long_r, "R exceeds signature");
+        let mut short_s = sig.clone();
+        short_s[37] = 31;
+        check_err(&short_s, "Lengths of R and S don't add up");
+        let mut not_int = sig.clone();
+        not_int[36] = 0x03;
+        check_err(&not_int, "R and S must be integers");
+        check_err(&der_sig(&[], &[1; 33]), "Zero-length integer");
+        check_err(&der_sig(&[0x80], &[1]), "Negative integer");
+        check_err(&der_sig(&[0, 0x7f], &[1]), "Integer has excess padding");
+        assert_eq!(
+            Signature::try_from([0x30; 73].as_ref()),
+            Err(SignatureError::InvalidSize(73)),
+        );
+    }
+
+    #[test]
+    fn test_low_s() {
+        let half_order =
+            hex::decode("7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0")
+                .unwrap();
+        assert!(Signature::try_from(der_sig(&[1], &half_order).as_ref()).is_ok());
+        let mut above_half = half_order.clone();
+        above_half[31] += 1;
+        assert_eq!(
+            Signature::try_from(der_sig(&[1], &above_half).as_ref()),
+            Err(SignatureError::HighS),
+        );
+        let padded_high = [[0].as_ref(), &[0x80; 32]].concat();
+        assert_eq!(
+            Signature::try_from(der_sig(&[1], &padded_high).as_ref()),
+            Err(SignatureError::HighS),
+        );
+    }
+
+    #[test]
+    fn test_tx_signature() {
+        let der = der_sig(&[1], &[2]);
+        let bytes = [der.clone(), vec![0xc1]].concat();
+        let sig = TxSignature::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(
+            sig,
+            TxSignature {
+                sig: Signature::Ecdsa(der.into()),
+                sig_hash_type: SigHashType::ALL_FORKID.with_anyonecanpay(),
+            },
+        );
+        assert_eq!(sig.to_bytes().as_ref(), bytes);
+        assert_eq!(
+            TxSignature::try_from([].as_ref()),
+            Err(SignatureError::Empty)
+        );
+        assert_eq!(
+            TxSignature::try_from([[1; 64].as_ref(), &[0x44]].concat().as_slice()),
+            Err(SignatureError::SigHashType(SigHashError::InvalidBaseType(
+                0x44
+            ))),
+        );
+        assert_eq!(
+            TxSignature::try_from([1; 66].as_ref()),
+            Err(SignatureError::SigHashType(SigHashError::MissingForkId(
+                0x01
+            ))),
+        );
+    }
+}
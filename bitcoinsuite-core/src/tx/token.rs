@@ -7,17 +7,18 @@ use std::ops::BitAnd;
 use bytes::Bytes;
 
 use crate::{
+    consensus::ConsensusParams,
     error::DataError,
     script::Script,
     ser::{BitcoinSer, BitcoinSerializer, CompactUint, read_compact_uint_minimal},
-    tx::TxId, bytes::read_bytes,
+    tx::{decode::Decoder, DecodeContext, Decoded, TxId}, bytes::read_bytes,
 };
 
 /// PREFIX_TOKEN is defined at codepoint 0xef (239) and indicates the presence of a token prefix
-const TOKEN_PREFIX: u8 = 0xef;
+pub const TOKEN_PREFIX: u8 = 0xef;
 
 /// Maximum fungible token amount
-const MAXIMUM_TOKEN_AMOUNT: u64 = 9223372036854775807;
+pub const MAXIMUM_TOKEN_AMOUNT: u64 = 9223372036854775807;
 
 /// The minimum possible length is 34
 pub const MINIMUM_PREFIX_LENGTH: u8 = 34;
@@ -112,7 +113,7 @@ pub const MINIMUM_PREFIX_LENGTH: u8 = 34;
 /// };
 /// assert_eq!(single_fungible_token_out ,Transaction::deser(&mut Bytes::copy_from_slice(&tx_hex)).unwrap() )
 /// ```
-
+///
 /// The CashToken contents of an [`Output`]. This property is only defined if the
 /// output contains one or more tokens. For details, see
 /// <https://cashtokens.org/docs/spec/chip#transaction-output-data-model>
@@ -121,7 +122,7 @@ pub struct CashToken {
     /// The number of fungible tokens held in this output (an integer between 1 and 9223372036854775807).
     /// can be 0 only if NFT is present.
     pub amount: CompactUint,
-    ///	The 32-byte ID [`TxId`]of the token category to which the token(s) in this output belong.
+    /// The 32-byte ID [`TxId`] of the token category to which the token(s) in this output belong.
     pub category: TxId,
     /// Optional nft field.
     pub nft: Option<NFT>,
@@ -150,15 +151,75 @@ impl CashToken {
         };
         Ok(token_data)
     }
+
+    /// Decode a token prefix spanning all of `data`, see
+    /// [`DecodeMode`](crate::tx::DecodeMode) for how encoding issues are
+    /// handled.
+    pub fn decode(mut data: Bytes, ctx: &DecodeContext) -> Result<Decoded<Self>, DataError> {
+        let mut decoder = Decoder::new(ctx, &data);
+        let token = decoder.token(&mut data, 0)?;
+        decoder.finish(token, &data)
+    }
+
+    /// Check this token against the token rules of the given
+    /// [`ConsensusParams`], e.g. the maximum commitment length at that epoch.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     consensus::{ConsensusParams, UpgradeEpoch},
+    /// #     error::DataError,
+    /// #     ser::CompactUint,
+    /// #     tx::{Capability, CashToken, Commitment, NonFungibleTokenCapability, NFT},
+    /// # };
+    /// let token = CashToken {
+    ///     amount: CompactUint(0),
+    ///     category: Default::default(),
+    ///     nft: Some(NFT {
+    ///         capability: NonFungibleTokenCapability(Capability::None),
+    ///         commitment: Commitment(vec![0; 100].into()),
+    ///     }),
+    /// };
+    /// assert_eq!(
+    ///     token.validate(&UpgradeEpoch::Upgrade11.params()),
+    ///     Err(DataError::CommitmentTooLong { max_length: 40, actual: 100 }),
+    /// );
+    /// assert_eq!(token.validate(&UpgradeEpoch::Upgrade12.params()), Ok(()));
+    /// assert_eq!(
+    ///     token.validate(&UpgradeEpoch::Upgrade8.params()),
+    ///     Err(DataError::TokensNotActivated),
+    /// );
+    /// ```
+    pub fn validate(&self, params: &ConsensusParams) -> Result<(), DataError> {
+        if !params.cashtokens {
+            return Err(DataError::TokensNotActivated);
+        }
+        let commitment_len = self.commitment().0.len();
+        if commitment_len > params.max_token_commitment_length {
+            return Err(DataError::CommitmentTooLong {
+                max_length: params.max_token_commitment_length,
+                actual: commitment_len,
+            });
+        }
+        if self.amount.0 > MAXIMUM_TOKEN_AMOUNT {
+            return Err(DataError::InvalidAmountEncoding {
+                error: "Invalid token prefix: exceeds maximum fungible token amount of 9223372036854775807".to_string(),
+            });
+        }
+        if self.nft.is_none() && self.amount.0 == 0 {
+            return Err(DataError::NoTokens {
+                error: "Invalid token prefix: must encode at least one token".to_string(),
+            });
+        }
+        Ok(())
+    }
 }
 
 /// Invalidates incorrect token format and capability token data.
 pub fn validate_prefix_format_and_capability(data: &mut Bytes) -> Result<(), DataError> {
-    let prefix_len = data.len() as usize;
+    let prefix_len = data.len();
     if prefix_len < MINIMUM_PREFIX_LENGTH as usize {
         return Err(DataError::InvalidTokenPrefixLength {
             minimum_length: MINIMUM_PREFIX_LENGTH as usize,
-            actual: prefix_len as usize,
+            actual: prefix_len,
         });
     }
     let token_bitfield_index = 33;
@@ -186,15 +247,14 @@ pub fn validate_prefix_format_and_capability(data: &mut Bytes) -> Result<(), Dat
         });
     }
 
-    if bitfield.has_nft() {
-        if bitfield.has_commitment_length()
+    if bitfield.has_nft()
+        && bitfield.has_commitment_length()
             && data.len() == MINIMUM_PREFIX_LENGTH as usize
         {
             return Err(DataError::CommitmentLengthZero {
                 error: "Error reading CompactUint: requires at least one byte".to_owned(),
             });
         }
-    }
     if !bitfield.has_nft() && !bitfield.has_amount() {
         return Err(DataError::NoTokens {
             error: "Invalid token prefix: must encode at least one token".to_string(),
@@ -206,20 +266,17 @@ pub fn validate_prefix_format_and_capability(data: &mut Bytes) -> Result<(), Dat
 
 impl BitcoinSer for Option<CashToken> {
     fn ser_to<S: BitcoinSerializer>(&self, bytes: &mut S) {
-        match self {
-            Some(token) => {
-                let tokendata = token.encode().unwrap();
-                tokendata.prefix.ser_to(bytes);
-                tokendata.category.ser_to(bytes);
-                tokendata.bitfield.ser_to(bytes);
-                if tokendata.has_commitment_length() {
-                    tokendata.commitment.ser_to(bytes);
-                    tokendata.amount.ser_to(bytes);
-                } else {
-                    tokendata.amount.ser_to(bytes);
-                }
+        if let Some(token) = self {
+            let tokendata = token.encode().unwrap();
+            tokendata.prefix.ser_to(bytes);
+            tokendata.category.ser_to(bytes);
+            tokendata.bitfield.ser_to(bytes);
+            if tokendata.has_commitment_length() {
+                tokendata.commitment.ser_to(bytes);
+                tokendata.amount.ser_to(bytes);
+            } else {
+                tokendata.amount.ser_to(bytes);
             }
-            None => (),
         }
     }
 
@@ -245,7 +302,7 @@ impl BitcoinSer for CashToken {
 
     fn deser(data: &mut bytes::Bytes) -> Result<Self, DataError> {
         let token_data: TokenData = BitcoinSer::deser(data)?;
-        Ok(token_data.decode()?)
+        token_data.decode()
     }
 }
 
@@ -314,8 +371,9 @@ impl TokenData {
         })
     }
     fn decode(&self) -> Result<CashToken, DataError> {
-        let token = if self.has_nft() {
-            let nft = Some(TokenData::decode_nft(&self)?);
+        
+        if self.has_nft() {
+            let nft = Some(TokenData::decode_nft(self)?);
             Ok(CashToken {
                 amount: self.amount,
                 category: self.category,
@@ -327,8 +385,7 @@ impl TokenData {
                 category: self.category,
                 nft: None,
             })
-        };
-        token
+        }
     }
 }
 
@@ -370,6 +427,11 @@ impl BitcoinSer for TokenData {
             }
             if bitfield.has_amount() {
                 let amount: CompactUint = BitcoinSer::deser(data)?;
+                if amount == CompactUint(0) {
+                    return Err(DataError::InvalidAmountEncoding {
+                        error: "fungible token amount must be greater than 0".to_string(),
+                    });
+                }
                 if amount.0 > MAXIMUM_TOKEN_AMOUNT {
                     return Err(DataError::InvalidAmountEncoding { 
                         error: "Invalid token prefix: exceeds maximum fungible token amount of 9223372036854775807".to_string(),
@@ -392,12 +454,12 @@ impl BitcoinSer for TokenData {
             });
         } else if bitfield.has_nft() && !bitfield.has_commitment_length() {
             if bitfield.has_amount() {
-                if data.len() == 0 {
+                if data.is_empty() {
                     return Err(DataError::InvalidAmountEncoding {
                         error: "Error reading CompactUint: requires at least one byte".to_string(),
                     });
                 };
-                if data[0] as usize == 0 as usize {
+                if data[0] as usize == 0_usize {
                     return Err(DataError::InvalidAmountEncoding {
                         error: "fungible token amount must be greater than 0".to_string(),
                     });
@@ -428,12 +490,12 @@ impl BitcoinSer for TokenData {
         if bitfield.capability() != Capability::None as u8 {
             return Err(DataError::CapabilityWithoutNft);
         }
-        if data.len() == 0 {
+        if data.is_empty() {
             return Err(DataError::InvalidAmountEncoding {
                 error: "Error reading CompactUint: requires at least one byte".to_string(),
             });
         };
-        if data[0] as usize == 0 as usize {
+        if data[0] as usize == 0_usize {
             return Err(DataError::InvalidAmountEncoding {
                 error: "fungible token amount must be greater than 0".to_string(),
             });
@@ -450,13 +512,13 @@ impl BitcoinSer for TokenData {
          });
         }
    
-        return Ok(TokenData {
+        Ok(TokenData {
             prefix,
             category,
             bitfield,
             amount,
             commitment: Commitment(vec![].into()),
-        });
+        })
     }
 }
 
@@ -578,8 +640,8 @@ impl Bitfield for TokenBitfield {
         } else {
             TokenBitFlags::UnSet as u8
         };
-        let token_bitfiled = nft_bit | has_commitment_len | amount | capability as u8;
-        token_bitfiled
+        
+        nft_bit | has_commitment_len | amount | capability
     }
     fn capability(&self) -> u8 {
         self.0 & TokenBitFlags::NftCapabilityMask as u8
@@ -617,8 +679,8 @@ impl Bitfield for CashToken {
         } else {
             TokenBitFlags::UnSet as u8
         };
-        let token_bitfiled = nft_bit | has_commitment_len | amount | capability as u8;
-        token_bitfiled
+        
+        nft_bit | has_commitment_len | amount | capability
     }
     fn capability(&self) -> u8 {
         self.nft.as_ref().unwrap().capability.0 as u8
@@ -628,7 +690,7 @@ impl Bitfield for CashToken {
     }
     fn has_commitment_length(&self) -> bool {
         if self.nft.is_some() {
-            self.commitment().0.len() > 0
+            !self.commitment().0.is_empty()
         } else {
             false
         }
@@ -665,7 +727,7 @@ pub struct Commitment(pub Bytes);
 impl BitcoinSer for Commitment {
     fn ser_to<S: BitcoinSerializer>(&self, bytes: &mut S) {
         if self.0.is_empty() {
-            ()
+            
         } else {
             self.0.ser_to(bytes)
         }
@@ -705,12 +767,9 @@ impl BitcoinSer for NonFungibleTokenCapability {
 
 impl BitcoinSer for Option<NFT> {
     fn ser_to<S: BitcoinSerializer>(&self, bytes: &mut S) {
-        match self {
-            Some(token) => {
-                token.capability.ser_to(bytes);
-                token.commitment.ser_to(bytes);
-            }
-            None => (),
+        if let Some(token) = self {
+            token.capability.ser_to(bytes);
+            token.commitment.ser_to(bytes);
         }
     }
 
@@ -817,4 +876,19 @@ mod tests {
         assert_eq!(token_pre, Err(DataError::InvalidTokenPrefixLength { minimum_length: MINIMUM_PREFIX_LENGTH as usize,actual:17 }));
      
     }
+
+    #[test]
+    fn test_zero_amount_after_commitment() {
+        // NFT with a 1 byte commitment, followed by an amount of 0
+        let prefix = hex::decode(
+            "efbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb7001cc00",
+        )
+        .unwrap();
+        assert_eq!(
+            CashToken::deser(&mut prefix.into()),
+            Err(DataError::InvalidAmountEncoding {
+                error: "fungible token amount must be greater than 0".to_string(),
+            }),
+        );
+    }
 }
@@ -5,9 +5,10 @@
 
 use crate::{
     error::DataError,
+    policy::DEFAULT_DUST_RELAY_FEE,
     script::Script,
-    ser::{BitcoinSer, BitcoinSerializer,},
-    tx::{TxId,CashToken,  WrappedTokenScript},
+    ser::{BitcoinSer, BitcoinSerializer},
+    tx::{decode::Decoder, CashToken, DecodeContext, Decoded, FeeRate, TxId, TOKEN_PREFIX},
 };
 
 
@@ -106,6 +107,11 @@ pub struct Output {
 
 
 
+/// Size of a typical input spending an output, used to compute how much it
+/// costs to spend a coin: outpoint (36), scriptSig length (1), P2PKH
+/// scriptSig (107) and nSequence (4).
+const DUST_SPEND_INPUT_SIZE: usize = 32 + 4 + 1 + 107 + 4;
+
 /// Coin, can be spent by providing a valid unlocking script.
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Coin {
@@ -143,6 +149,27 @@ impl std::ops::Deref for Tx {
     }
 }
 
+impl Transaction {
+    /// Whether this is a coinbase tx, i.e. it has exactly one input spending
+    /// the null [`OutPoint`].
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].prev_out.is_null()
+    }
+}
+
+impl OutPoint {
+    /// The null [`OutPoint`], spent by the input of coinbase txs.
+    pub const NULL: OutPoint = OutPoint {
+        txid: TxId::ZERO,
+        outpoint_index: u32::MAX,
+    };
+
+    /// Whether this is the null [`OutPoint`], see [`OutPoint::NULL`].
+    pub fn is_null(&self) -> bool {
+        *self == OutPoint::NULL
+    }
+}
+
 impl BitcoinSer for Transaction {
     fn ser_to<S: BitcoinSerializer>(&self, bytes: &mut S) {
         self.version.ser_to(bytes);
@@ -208,37 +235,177 @@ impl BitcoinSer for Output {
     fn ser_to<S: BitcoinSerializer>(&self, bytes: &mut S) {
         self.value.ser_to(bytes);
         if self.token.is_some() {
-           vec![ self.token.ser().to_vec(), self.script.to_vec()].concat().ser_to(bytes);
+            [self.token.ser().as_ref(), self.script.as_ref()]
+                .concat()
+                .ser_to(bytes);
         } else {
             self.script.ser_to(bytes);
         }
     }
 
     fn deser(data: &mut bytes::Bytes) -> Result<Self, DataError> {
+        let value = BitcoinSer::deser(data)?;
+        let mut bytecode = bytes::Bytes::deser(data)?;
+        let token = match bytecode.first() {
+            Some(&TOKEN_PREFIX) => Some(CashToken::deser(&mut bytecode)?),
+            _ => None,
+        };
+        Ok(Output {
+            value,
+            script: Script::new(bytecode),
+            token,
+        })
+    }
+}
 
-        let token_prefix_index = 9;
-        let prefix_token = data.slice(token_prefix_index..token_prefix_index + 1);
-     
-          if prefix_token[0] == 0xef {
-            let value:u64 = BitcoinSer::deser(data)?;
-            let  WrappedTokenScript(token, script) = Script::unwrap_prefixed_lockscript(data)?;
-         
-           Ok(Output { value, script, token:Some(token.unwrap()) })
-           
-        } else {
-            let value:u64 = BitcoinSer::deser(data)?;
-            let script:Script = BitcoinSer::deser(data)?; 
-            let output = Output {
-                value,
-                script,
-                token:None
-            };
-            Ok(output)
-        }
-     
+impl Output {
+    /// Dust threshold of this output at `dust_relay_fee` (in sats per 1000
+    /// bytes): the value below which spending the output would cost more
+    /// than a third of its value, like `GetDustThreshold` of the node.
+    ///
+    /// The size is computed from the full serialized output using
+    /// [`BitcoinSer::ser_len`], so token outputs need more sats to carry
+    /// their token prefix: category, bitfield, commitment and amount.
+    /// OP_RETURN outputs are unspendable and have no dust threshold.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::ShaRmd160,
+    /// #     script::Script,
+    /// #     ser::CompactUint,
+    /// #     tx::{Capability, CashToken, Commitment, NonFungibleTokenCapability, Output, TxId, NFT},
+    /// # };
+    /// let mut output = Output {
+    ///     value: 0,
+    ///     script: Script::p2pkh(&ShaRmd160([1; 20])),
+    ///     token: None,
+    /// };
+    /// assert_eq!(output.dust_threshold(1000), 546);
+    /// assert_eq!(output.dust_threshold(2000), 1092);
+    /// assert_eq!(output.dust_threshold(0), 0);
+    ///
+    /// // Token prefix: 0xef, 32 byte category, bitfield, 40 byte commitment
+    /// // with 1 byte length, 3 byte amount
+    /// output.token = Some(CashToken {
+    ///     amount: CompactUint(1000),
+    ///     category: TxId::from([2; 32]),
+    ///     nft: Some(NFT {
+    ///         capability: NonFungibleTokenCapability(Capability::Mutable),
+    ///         commitment: Commitment(vec![3; 40].into()),
+    ///     }),
+    /// });
+    /// assert_eq!(output.dust_threshold(1000), 3 * (182 + 78));
+    ///
+    /// let output = Output {
+    ///     value: 0,
+    ///     script: Script::new(vec![0x6a, 0x01, 0x00].into()),
+    ///     token: None,
+    /// };
+    /// assert_eq!(output.dust_threshold(1000), 0);
+    /// ```
+    pub fn dust_threshold(&self, dust_relay_fee: u64) -> u64 {
+        if self.script.is_opreturn() {
+            return 0;
         }
+        let spend_size = self.ser_len() + DUST_SPEND_INPUT_SIZE;
+        3 * FeeRate::from_sats_per_kb(dust_relay_fee).fee(spend_size)
+    }
+
+    /// Minimum value this output needs to be relayed by nodes with the
+    /// default dust relay fee of 1000 sats/kB, see
+    /// [`Output::dust_threshold`].
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::ShaRmd160,
+    /// #     script::Script,
+    /// #     ser::CompactUint,
+    /// #     tx::{CashToken, Output, TxId},
+    /// # };
+    /// let output = Output {
+    ///     value: 0,
+    ///     script: Script::p2pkh(&ShaRmd160([1; 20])),
+    ///     token: Some(CashToken {
+    ///         amount: CompactUint(1),
+    ///         category: TxId::from([2; 32]),
+    ///         nft: None,
+    ///     }),
+    /// };
+    /// // 1 more byte for 0xef, 32 for the category, 1 for the bitfield and 1
+    /// // for the amount
+    /// assert_eq!(output.min_value(), 3 * (182 + 35));
+    /// ```
+    pub fn min_value(&self) -> u64 {
+        self.dust_threshold(DEFAULT_DUST_RELAY_FEE)
+    }
+
+    /// Deserialize an [`Output`] using the rules of the given
+    /// [`DecodeContext`]. If the context has CashTokens enabled, locking
+    /// bytecode starting with `PREFIX_TOKEN` (`0xef`) is split into the token
+    /// prefix and the actual locking script.
+    ///
+    /// Unlike [`BitcoinSer::deser`], which always parses token prefixes but
+    /// accepts non-minimal CompactSizes and commitments of any length, this
+    /// applies the [`DecodeMode`](crate::tx::DecodeMode) of the context.
+    /// Any [`DecodeWarning`](crate::tx::DecodeWarning)s are dropped, use
+    /// [`Output::decode`] to get them.
+    pub fn deser_with(data: &mut bytes::Bytes, ctx: &DecodeContext) -> Result<Self, DataError> {
+        Decoder::new(ctx, data).output(data)
+    }
+
+    /// Decode an [`Output`] spanning all of `data`, see [`DecodeMode`](crate::tx::DecodeMode) for
+    /// how encoding issues are handled.
+    /// ```
+    /// # use bitcoinsuite_core::tx::{DecodeContext, DecodeWarning, Output};
+    /// # use bytes::Bytes;
+    /// // Script length encoded as 0xfd0100 instead of 0x01
+    /// let ser = Bytes::from(hex::decode("e803000000000000fd010051").unwrap());
+    /// assert!(Output::decode(ser.clone(), &DecodeContext::default()).is_err());
+    /// let decoded = Output::decode(ser, &DecodeContext::lenient()).unwrap();
+    /// assert_eq!(decoded.value.script.hex(), "51");
+    /// assert_eq!(
+    ///     decoded.warnings,
+    ///     vec![DecodeWarning::NonMinimalCompactSize {
+    ///         offset: 8,
+    ///         value: 1,
+    ///         encoded_length: 3,
+    ///     }],
+    /// );
+    /// ```
+    pub fn decode(mut data: bytes::Bytes, ctx: &DecodeContext) -> Result<Decoded<Self>, DataError> {
+        let mut decoder = Decoder::new(ctx, &data);
+        let output = decoder.output(&mut data)?;
+        decoder.finish(output, &data)
+    }
+}
+
+impl Transaction {
+    /// Deserialize a [`Transaction`] using the rules of the given
+    /// [`DecodeContext`], e.g. to parse txs mined before CashTokens activated.
+    ///
+    /// Like [`BitcoinSer::deser`], this only consumes the tx from `data`, but
+    /// applies the [`DecodeMode`] of the context; `deser` accepts non-minimal
+    /// CompactSizes and commitments of any length. Any
+    /// [`DecodeWarning`](crate::tx::DecodeWarning)s are dropped, use
+    /// [`Transaction::decode`] to get them.
+    pub fn deser_with(data: &mut bytes::Bytes, ctx: &DecodeContext) -> Result<Self, DataError> {
+        Decoder::new(ctx, data).transaction(data)
+    }
+
+    /// Decode a [`Transaction`] spanning all of `data`, see [`DecodeMode`]
+    /// for how encoding issues are handled.
+    pub fn decode(mut data: bytes::Bytes, ctx: &DecodeContext) -> Result<Decoded<Self>, DataError> {
+        let mut decoder = Decoder::new(ctx, &data);
+        let tx = decoder.transaction(&mut data)?;
+        decoder.finish(tx, &data)
+    }
+}
+
+impl Tx {
+    /// Like [`Transaction::deser_with`], but also computes the [`TxId`].
+    pub fn deser_with(data: &mut bytes::Bytes, ctx: &DecodeContext) -> Result<Self, DataError> {
+        let tx = Transaction::deser_with(data, ctx)?;
+        Ok(Tx::with_txid(TxId::from_tx(&tx), tx))
+    }
 }
-    
 
 #[cfg(test)]
 mod tests {
@@ -246,9 +413,10 @@ mod tests {
     use crate::hash::{Hashed, Sha256d};
     use crate::ser::CompactUint;
     use crate::{
+        error::DataError,
         script::Script,
         ser::BitcoinSer,
-        tx::{OutPoint,token::*, TxId, Input, Transaction, Output},
+        tx::{OutPoint,token::*, DecodeContext, TxId, Input, Transaction, Output, Tx},
     };
     use bytes::Bytes;
     fn verify_ser(tx: Transaction, ser: &[u8]) {
@@ -569,5 +737,80 @@ mod tests {
         Ok(())
     }
 
-    
+    #[test]
+    fn test_deser_with_context() -> Result<(), DataError> {
+        let ef_script = Script::new(hex::decode("ef51").unwrap().into());
+        let tx = Transaction {
+            version: 1,
+            inputs: vec![Input::default()],
+            outputs: vec![
+                Output {
+                    value: 1000,
+                    script: ef_script.clone(),
+                    token: None,
+                },
+                // Empty script followed by an output value starting with 0xef
+                Output {
+                    value: 0xef,
+                    script: Script::default(),
+                    token: None,
+                },
+            ],
+            locktime: 0,
+        };
+        let ser = tx.ser();
+        let pre_tokens = DecodeContext::pre_cashtokens();
+        assert_eq!(Transaction::deser_with(&mut ser.clone(), &pre_tokens)?, tx);
+        assert_eq!(
+            Tx::deser_with(&mut ser.clone(), &pre_tokens)?.txid(),
+            TxId::from_tx(&tx),
+        );
+        assert!(Transaction::deser_with(&mut ser.clone(), &DecodeContext::default()).is_err());
+        assert!(Transaction::deser(&mut ser.clone()).is_err());
+
+        // Token outputs with locking bytecode longer than 252 bytes
+        let token_tx = Transaction {
+            outputs: vec![Output {
+                value: 1000,
+                script: Script::new(vec![0x51; 300].into()),
+                token: Some(CashToken {
+                    amount: CompactUint(1),
+                    category: TxId::from([7; 32]),
+                    nft: None,
+                }),
+            }],
+            ..tx
+        };
+        let ser = token_tx.ser();
+        assert_eq!(Transaction::deser(&mut ser.clone())?, token_tx);
+        let pre_tokens_tx = Transaction::deser_with(&mut ser.clone(), &pre_tokens)?;
+        assert_eq!(pre_tokens_tx.outputs[0].token, None);
+        assert_eq!(
+            pre_tokens_tx.outputs[0].script.as_ref(),
+            [token_tx.outputs[0].token.ser().as_ref(), &[0x51; 300]].concat(),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dust_threshold() {
+        // Token prefix pushes the locking bytecode length over 252 bytes, so
+        // its CompactSize takes 3 bytes instead of 1.
+        let mut output = Output {
+            value: 0,
+            script: Script::new(vec![0x51; 220].into()),
+            token: None,
+        };
+        assert_eq!(output.ser_len(), 8 + 1 + 220);
+        assert_eq!(output.min_value(), 3 * (229 + 148));
+        output.token = Some(CashToken {
+            amount: CompactUint(0x1_0000_0000),
+            category: TxId::from([7; 32]),
+            nft: None,
+        });
+        assert_eq!(output.ser_len(), 8 + 3 + 34 + 9 + 220);
+        assert_eq!(output.min_value(), 3 * (274 + 148));
+        assert_eq!(output.dust_threshold(1), 3);
+        assert_eq!(output.dust_threshold(10_000), 3 * 4220);
+    }
 }   
\ No newline at end of file
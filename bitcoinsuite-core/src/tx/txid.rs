@@ -29,6 +29,9 @@ impl std::fmt::Display for TxId {
 }
 
 impl TxId {
+    /// [`TxId`] with all bytes zero, e.g. used in [`OutPoint::NULL`](crate::tx::OutPoint::NULL).
+    pub const ZERO: TxId = TxId(Sha256d([0; 32]));
+
     /// Return the [`TxId`] for the given [`Transaction`] (or `Tx`).
     ///
     /// This is done by hashing the serialized tx using [`Sha256d`].
@@ -0,0 +1,553 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use bytes::Bytes;
+use thiserror::Error;
+
+use crate::{
+    script::{opcode::*, Op, Script, ScriptNum, ScriptVariant},
+    tx::{Capability, CashToken, Coin, Input, Output, Transaction},
+};
+
+/// Everything the introspection opcodes (`OP_INPUTINDEX` to
+/// `OP_OUTPUTTOKENAMOUNT`) can see when evaluating an input of a tx.
+///
+/// Each accessor returns exactly what the corresponding opcode pushes onto
+/// the stack, which allows testing covenants without running a VM.
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     script::{opcode::*, Script, ScriptNum},
+/// #     tx::{Coin, Input, Output, Transaction},
+/// #     vm::ScriptExecutionContext,
+/// # };
+/// let coin = Coin {
+///     output: Output { value: 5000, script: Script::new(vec![0x51].into()), token: None },
+///     height: 100,
+///     is_coinbase: false,
+/// };
+/// let tx = Transaction {
+///     version: 2,
+///     inputs: vec![Input { sequence: 0xffff_fffe, ..Default::default() }],
+///     outputs: vec![Output { value: 4000, ..Default::default() }],
+///     locktime: 0,
+/// };
+/// let spent_coins = [coin];
+/// let ctx = ScriptExecutionContext::new(&tx, &spent_coins, 0)?;
+/// assert_eq!(ctx.tx_version(), ScriptNum(2));
+/// assert_eq!(ctx.active_bytecode().hex(), "51");
+/// assert_eq!(ctx.introspect(OP_UTXOVALUE, Some(0))?.as_ref(), [0x88, 0x13]);
+/// assert_eq!(ctx.introspect(OP_OUTPUTVALUE, Some(0))?.as_ref(), [0xa0, 0x0f]);
+/// assert_eq!(ctx.introspect(OP_UTXOTOKENCATEGORY, Some(0))?.as_ref(), []);
+/// # Ok::<_, bitcoinsuite_core::vm::IntrospectionError>(())
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScriptExecutionContext<'a> {
+    tx: &'a Transaction,
+    spent_coins: &'a [Coin],
+    input_idx: usize,
+    active_bytecode: Script,
+}
+
+/// Errors when building a [`ScriptExecutionContext`] or introspecting it.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum IntrospectionError {
+    /// Input index is out of range.
+    #[error("Invalid input index {idx}, tx only has {num_inputs} inputs")]
+    InvalidInputIndex {
+        /// Requested input index.
+        idx: usize,
+        /// Number of inputs of the tx.
+        num_inputs: usize,
+    },
+
+    /// Output index is out of range.
+    #[error("Invalid output index {idx}, tx only has {num_outputs} outputs")]
+    InvalidOutputIndex {
+        /// Requested output index.
+        idx: usize,
+        /// Number of outputs of the tx.
+        num_outputs: usize,
+    },
+
+    /// There must be exactly one spent coin per input.
+    #[error("Tx has {num_inputs} inputs, but got {num_spent_coins} spent coins")]
+    SpentCoinsMismatch {
+        /// Number of inputs of the tx.
+        num_inputs: usize,
+        /// Number of spent coins provided.
+        num_spent_coins: usize,
+    },
+
+    /// The opcode isn't an introspection opcode.
+    #[error("{0} is not an introspection opcode")]
+    NotIntrospection(Opcode),
+
+    /// The opcode pops an input or output index, but none was given.
+    #[error("{0} requires an index")]
+    MissingIndex(Opcode),
+}
+
+use self::IntrospectionError::*;
+
+impl<'a> ScriptExecutionContext<'a> {
+    /// Context evaluating input `input_idx` of `tx`, which spends
+    /// `spent_coins[input_idx]`.
+    ///
+    /// The active bytecode is the script of the spent coin, or the redeem
+    /// script (the last push of the scriptSig) if the coin is P2SH.
+    pub fn new(
+        tx: &'a Transaction,
+        spent_coins: &'a [Coin],
+        input_idx: usize,
+    ) -> Result<Self, IntrospectionError> {
+        if tx.inputs.len() != spent_coins.len() {
+            return Err(SpentCoinsMismatch {
+                num_inputs: tx.inputs.len(),
+                num_spent_coins: spent_coins.len(),
+            });
+        }
+        if input_idx >= tx.inputs.len() {
+            return Err(InvalidInputIndex {
+                idx: input_idx,
+                num_inputs: tx.inputs.len(),
+            });
+        }
+        let spent_script = &spent_coins[input_idx].output.script;
+        let active_bytecode = match ScriptVariant::from_script(spent_script) {
+            ScriptVariant::P2SH(_) | ScriptVariant::P2SH32(_) => {
+                redeem_script(&tx.inputs[input_idx].script).unwrap_or_else(|| spent_script.clone())
+            }
+            _ => spent_script.clone(),
+        };
+        Ok(ScriptExecutionContext {
+            tx,
+            spent_coins,
+            input_idx,
+            active_bytecode,
+        })
+    }
+
+    /// Override the active bytecode, e.g. to simulate an executed
+    /// `OP_CODESEPARATOR`.
+    pub fn with_active_bytecode(mut self, active_bytecode: Script) -> Self {
+        self.active_bytecode = active_bytecode;
+        self
+    }
+
+    /// Tx being evaluated.
+    pub fn tx(&self) -> &'a Transaction {
+        self.tx
+    }
+
+    /// Coins spent by the tx, one for each input.
+    pub fn spent_coins(&self) -> &'a [Coin] {
+        self.spent_coins
+    }
+
+    /// What [`OP_INPUTINDEX`] pushes.
+    pub fn input_index(&self) -> ScriptNum {
+        ScriptNum(self.input_idx as i64)
+    }
+
+    /// What [`OP_ACTIVEBYTECODE`] pushes.
+    pub fn active_bytecode(&self) -> &Script {
+        &self.active_bytecode
+    }
+
+    /// What [`OP_TXVERSION`] pushes.
+    pub fn tx_version(&self) -> ScriptNum {
+        ScriptNum(self.tx.version.into())
+    }
+
+    /// What [`OP_TXINPUTCOUNT`] pushes.
+    pub fn tx_input_count(&self) -> ScriptNum {
+        ScriptNum(self.tx.inputs.len() as i64)
+    }
+
+    /// What [`OP_TXOUTPUTCOUNT`] pushes.
+    pub fn tx_output_count(&self) -> ScriptNum {
+        ScriptNum(self.tx.outputs.len() as i64)
+    }
+
+    /// What [`OP_TXLOCKTIME`] pushes.
+    pub fn tx_locktime(&self) -> ScriptNum {
+        ScriptNum(self.tx.locktime.into())
+    }
+
+    /// What [`OP_UTXOVALUE`] pushes for input `idx`.
+    pub fn utxo_value(&self, idx: usize) -> Result<ScriptNum, IntrospectionError> {
+        Ok(ScriptNum(self.utxo(idx)?.value as i64))
+    }
+
+    /// What [`OP_UTXOBYTECODE`] pushes for input `idx`.
+    pub fn utxo_bytecode(&self, idx: usize) -> Result<&'a Script, IntrospectionError> {
+        Ok(&self.utxo(idx)?.script)
+    }
+
+    /// What [`OP_OUTPOINTTXHASH`] pushes for input `idx`: the txid in
+    /// little-endian byte order, like in the serialized outpoint.
+    pub fn outpoint_tx_hash(&self, idx: usize) -> Result<[u8; 32], IntrospectionError> {
+        Ok(self.input(idx)?.prev_out.txid.to_bytes())
+    }
+
+    /// What [`OP_OUTPOINTINDEX`] pushes for input `idx`.
+    pub fn outpoint_index(&self, idx: usize) -> Result<ScriptNum, IntrospectionError> {
+        Ok(ScriptNum(self.input(idx)?.prev_out.outpoint_index.into()))
+    }
+
+    /// What [`OP_INPUTBYTECODE`] pushes for input `idx`.
+    pub fn input_bytecode(&self, idx: usize) -> Result<&'a Script, IntrospectionError> {
+        Ok(&self.input(idx)?.script)
+    }
+
+    /// What [`OP_INPUTSEQUENCENUMBER`] pushes for input `idx`.
+    pub fn input_sequence_number(&self, idx: usize) -> Result<ScriptNum, IntrospectionError> {
+        Ok(ScriptNum(self.input(idx)?.sequence.into()))
+    }
+
+    /// What [`OP_OUTPUTVALUE`] pushes for output `idx`.
+    pub fn output_value(&self, idx: usize) -> Result<ScriptNum, IntrospectionError> {
+        Ok(ScriptNum(self.output(idx)?.value as i64))
+    }
+
+    /// What [`OP_OUTPUTBYTECODE`] pushes for output `idx`.
+    pub fn output_bytecode(&self, idx: usize) -> Result<&'a Script, IntrospectionError> {
+        Ok(&self.output(idx)?.script)
+    }
+
+    /// What [`OP_UTXOTOKENCATEGORY`] pushes for input `idx`, see
+    /// [`token_category`].
+    pub fn utxo_token_category(&self, idx: usize) -> Result<Vec<u8>, IntrospectionError> {
+        Ok(token_category(self.utxo(idx)?.token.as_ref()))
+    }
+
+    /// What [`OP_UTXOTOKENCOMMITMENT`] pushes for input `idx`, see
+    /// [`token_commitment`].
+    pub fn utxo_token_commitment(&self, idx: usize) -> Result<Bytes, IntrospectionError> {
+        Ok(token_commitment(self.utxo(idx)?.token.as_ref()))
+    }
+
+    /// What [`OP_UTXOTOKENAMOUNT`] pushes for input `idx`, see
+    /// [`token_amount`].
+    pub fn utxo_token_amount(&self, idx: usize) -> Result<ScriptNum, IntrospectionError> {
+        Ok(token_amount(self.utxo(idx)?.token.as_ref()))
+    }
+
+    /// What [`OP_OUTPUTTOKENCATEGORY`] pushes for output `idx`, see
+    /// [`token_category`].
+    pub fn output_token_category(&self, idx: usize) -> Result<Vec<u8>, IntrospectionError> {
+        Ok(token_category(self.output(idx)?.token.as_ref()))
+    }
+
+    /// What [`OP_OUTPUTTOKENCOMMITMENT`] pushes for output `idx`, see
+    /// [`token_commitment`].
+    pub fn output_token_commitment(&self, idx: usize) -> Result<Bytes, IntrospectionError> {
+        Ok(token_commitment(self.output(idx)?.token.as_ref()))
+    }
+
+    /// What [`OP_OUTPUTTOKENAMOUNT`] pushes for output `idx`, see
+    /// [`token_amount`].
+    pub fn output_token_amount(&self, idx: usize) -> Result<ScriptNum, IntrospectionError> {
+        Ok(token_amount(self.output(idx)?.token.as_ref()))
+    }
+
+    /// The stack item the introspection `opcode` pushes. Opcodes popping an
+    /// input or output index require `idx`, the others ignore it.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     script::opcode::*,
+    /// #     tx::{Coin, Input, Transaction},
+    /// #     vm::{IntrospectionError, ScriptExecutionContext},
+    /// # };
+    /// let tx = Transaction { inputs: vec![Input::default()], ..Default::default() };
+    /// let spent_coins = [Coin::default()];
+    /// let ctx = ScriptExecutionContext::new(&tx, &spent_coins, 0)?;
+    /// assert_eq!(ctx.introspect(OP_TXINPUTCOUNT, None)?.as_ref(), [1]);
+    /// assert_eq!(ctx.introspect(OP_INPUTINDEX, None)?.as_ref(), []);
+    /// assert_eq!(
+    ///     ctx.introspect(OP_OUTPUTVALUE, Some(0)),
+    ///     Err(IntrospectionError::InvalidOutputIndex { idx: 0, num_outputs: 0 }),
+    /// );
+    /// assert_eq!(
+    ///     ctx.introspect(OP_UTXOVALUE, None),
+    ///     Err(IntrospectionError::MissingIndex(OP_UTXOVALUE)),
+    /// );
+    /// assert_eq!(
+    ///     ctx.introspect(OP_CHECKSIG, None),
+    ///     Err(IntrospectionError::NotIntrospection(OP_CHECKSIG)),
+    /// );
+    /// # Ok::<_, IntrospectionError>(())
+    /// ```
+    pub fn introspect(
+        &self,
+        opcode: Opcode,
+        idx: Option<usize>,
+    ) -> Result<Bytes, IntrospectionError> {
+        let num = |num: ScriptNum| Bytes::from(num.encode());
+        match opcode {
+            OP_INPUTINDEX => return Ok(num(self.input_index())),
+            OP_ACTIVEBYTECODE => return Ok(self.active_bytecode.bytecode().clone()),
+            OP_TXVERSION => return Ok(num(self.tx_version())),
+            OP_TXINPUTCOUNT => return Ok(num(self.tx_input_count())),
+            OP_TXOUTPUTCOUNT => return Ok(num(self.tx_output_count())),
+            OP_TXLOCKTIME => return Ok(num(self.tx_locktime())),
+            Opcode(OP_UTXOVALUE::N..=OP_OUTPUTTOKENAMOUNT::N) => {}
+            _ => return Err(NotIntrospection(opcode)),
+        }
+        let idx = idx.ok_or(MissingIndex(opcode))?;
+        Ok(match opcode {
+            OP_UTXOVALUE => num(self.utxo_value(idx)?),
+            OP_UTXOBYTECODE => self.utxo_bytecode(idx)?.bytecode().clone(),
+            OP_OUTPOINTTXHASH => Bytes::copy_from_slice(&self.outpoint_tx_hash(idx)?),
+            OP_OUTPOINTINDEX => num(self.outpoint_index(idx)?),
+            OP_INPUTBYTECODE => self.input_bytecode(idx)?.bytecode().clone(),
+            OP_INPUTSEQUENCENUMBER => num(self.input_sequence_number(idx)?),
+            OP_OUTPUTVALUE => num(self.output_value(idx)?),
+            OP_OUTPUTBYTECODE => self.output_bytecode(idx)?.bytecode().clone(),
+            OP_UTXOTOKENCATEGORY => self.utxo_token_category(idx)?.into(),
+            OP_UTXOTOKENCOMMITMENT => self.utxo_token_commitment(idx)?,
+            OP_UTXOTOKENAMOUNT => num(self.utxo_token_amount(idx)?),
+            OP_OUTPUTTOKENCATEGORY => self.output_token_category(idx)?.into(),
+            OP_OUTPUTTOKENCOMMITMENT => self.output_token_commitment(idx)?,
+            _ => num(self.output_token_amount(idx)?),
+        })
+    }
+
+    fn input(&self, idx: usize) -> Result<&'a Input, IntrospectionError> {
+        self.tx.inputs.get(idx).ok_or(InvalidInputIndex {
+            idx,
+            num_inputs: self.tx.inputs.len(),
+        })
+    }
+
+    fn utxo(&self, idx: usize) -> Result<&'a Output, IntrospectionError> {
+        self.input(idx)?;
+        Ok(&self.spent_coins[idx].output)
+    }
+
+    fn output(&self, idx: usize) -> Result<&'a Output, IntrospectionError> {
+        self.tx.outputs.get(idx).ok_or(InvalidOutputIndex {
+            idx,
+            num_outputs: self.tx.outputs.len(),
+        })
+    }
+}
+
+/// Token category as pushed by the introspection opcodes: the 32-byte
+/// category ID in little-endian byte order, followed by `0x01` for mutable
+/// and `0x02` for minting NFTs. Empty if there's no token.
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     ser::CompactUint,
+/// #     tx::{Capability, CashToken, Commitment, NonFungibleTokenCapability, TxId, NFT},
+/// #     vm::token_category,
+/// # };
+/// let mut token = CashToken {
+///     amount: CompactUint(0),
+///     category: TxId::from([7; 32]),
+///     nft: Some(NFT {
+///         capability: NonFungibleTokenCapability(Capability::Minting),
+///         commitment: Commitment::default(),
+///     }),
+/// };
+/// assert_eq!(token_category(Some(&token)), [[7; 32].as_ref(), &[2]].concat());
+/// token.nft = None;
+/// assert_eq!(token_category(Some(&token)), [7; 32]);
+/// assert_eq!(token_category(None), []);
+/// ```
+pub fn token_category(token: Option<&CashToken>) -> Vec<u8> {
+    let token = match token {
+        Some(token) => token,
+        None => return vec![],
+    };
+    let mut category = token.category.to_vec();
+    if let Some(nft) = &token.nft {
+        match nft.capability.0 {
+            Capability::None => {}
+            capability => category.push(capability as u8),
+        }
+    }
+    category
+}
+
+/// NFT commitment as pushed by the introspection opcodes. Empty if there's
+/// no NFT.
+pub fn token_commitment(token: Option<&CashToken>) -> Bytes {
+    token
+        .and_then(|token| token.nft.as_ref())
+        .map(|nft| nft.commitment.0.clone())
+        .unwrap_or_default()
+}
+
+/// Fungible token amount as pushed by the introspection opcodes. Zero if
+/// there's no token.
+pub fn token_amount(token: Option<&CashToken>) -> ScriptNum {
+    ScriptNum(token.map_or(0, |token| token.amount.0 as i64))
+}
+
+/// Last push of a push-only scriptSig, which is the redeem script for P2SH.
+fn redeem_script(script_sig: &Script) -> Option<Script> {
+    let last_op = script_sig.iter_ops().last()?.ok()?;
+    if !script_sig.is_push_only() {
+        return None;
+    }
+    match last_op {
+        Op::Push(_, data) => Some(Script::new(data)),
+        Op::Code(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        hash::{Hashed, ShaRmd160},
+        script::{opcode::*, Script, ScriptNum},
+        tx::{Capability, Coin, Input, OutPoint, Output, Transaction, TxId},
+        vm::{IntrospectionError, ScriptExecutionContext},
+        wallet::test_util::token,
+    };
+
+    #[test]
+    fn test_script_execution_context() -> Result<(), IntrospectionError> {
+        let redeem_script = Script::new(vec![OP_INPUTINDEX::N, OP_UTXOVALUE::N].into());
+        let p2sh = Script::p2sh(&ShaRmd160::digest(&redeem_script));
+        let spent_coins = [
+            Coin {
+                output: Output {
+                    value: 10_000,
+                    script: p2sh,
+                    token: token(1000, Some((Capability::Mutable, b"abc"))),
+                },
+                ..Default::default()
+            },
+            Coin {
+                output: Output {
+                    value: 546,
+                    script: Script::new(vec![0x51].into()),
+                    token: token(0, Some((Capability::None, b""))),
+                },
+                ..Default::default()
+            },
+        ];
+        let tx = Transaction {
+            version: 2,
+            inputs: vec![
+                Input {
+                    prev_out: OutPoint {
+                        txid: TxId::from([1; 32]),
+                        outpoint_index: 300,
+                    },
+                    script: Script::p2sh_script_sig(&Script::default(), &redeem_script),
+                    sequence: 0xffff_ffff,
+                },
+                Input::default(),
+            ],
+            outputs: vec![Output {
+                value: 9000,
+                script: Script::new(vec![0x6a].into()),
+                token: token(1000, Some((Capability::Minting, b"xyz"))),
+            }],
+            locktime: 800_000,
+        };
+        let ctx = ScriptExecutionContext::new(&tx, &spent_coins, 0)?;
+        let introspect = |opcode, idx| ctx.introspect(opcode, idx).map(|data| data.to_vec());
+
+        assert_eq!(ctx.active_bytecode(), &redeem_script);
+        assert_eq!(introspect(OP_ACTIVEBYTECODE, None)?, redeem_script.to_vec());
+        assert_eq!(introspect(OP_INPUTINDEX, None)?, Vec::<u8>::new());
+        assert_eq!(introspect(OP_TXVERSION, None)?, vec![2]);
+        assert_eq!(introspect(OP_TXINPUTCOUNT, None)?, vec![2]);
+        assert_eq!(introspect(OP_TXOUTPUTCOUNT, None)?, vec![1]);
+        assert_eq!(introspect(OP_TXLOCKTIME, None)?, vec![0x00, 0x35, 0x0c]);
+        assert_eq!(introspect(OP_UTXOVALUE, Some(0))?, vec![0x10, 0x27]);
+        assert_eq!(introspect(OP_UTXOVALUE, Some(1))?, vec![0x22, 0x02]);
+        assert_eq!(
+            introspect(OP_UTXOBYTECODE, Some(0))?,
+            spent_coins[0].output.script.to_vec(),
+        );
+        assert_eq!(introspect(OP_OUTPOINTTXHASH, Some(0))?, vec![1; 32]);
+        assert_eq!(introspect(OP_OUTPOINTINDEX, Some(0))?, vec![0x2c, 0x01]);
+        assert_eq!(
+            introspect(OP_INPUTBYTECODE, Some(0))?,
+            tx.inputs[0].script.to_vec()
+        );
+        assert_eq!(
+            introspect(OP_INPUTSEQUENCENUMBER, Some(0))?,
+            vec![0xff, 0xff, 0xff, 0xff, 0x00],
+        );
+        assert_eq!(
+            introspect(OP_INPUTSEQUENCENUMBER, Some(1))?,
+            Vec::<u8>::new()
+        );
+        assert_eq!(introspect(OP_OUTPUTVALUE, Some(0))?, vec![0x28, 0x23]);
+        assert_eq!(introspect(OP_OUTPUTBYTECODE, Some(0))?, vec![0x6a]);
+
+        let category = |capability: &[u8]| [[7; 32].as_ref(), capability].concat();
+        assert_eq!(
+            introspect(OP_UTXOTOKENCATEGORY, Some(0))?,
+            category(&[0x01])
+        );
+        assert_eq!(introspect(OP_UTXOTOKENCATEGORY, Some(1))?, category(&[]));
+        assert_eq!(
+            introspect(OP_UTXOTOKENCOMMITMENT, Some(0))?,
+            b"abc".to_vec()
+        );
+        assert_eq!(
+            introspect(OP_UTXOTOKENCOMMITMENT, Some(1))?,
+            Vec::<u8>::new()
+        );
+        assert_eq!(introspect(OP_UTXOTOKENAMOUNT, Some(0))?, vec![0xe8, 0x03]);
+        assert_eq!(ctx.utxo_token_amount(1)?, ScriptNum(0));
+        assert_eq!(
+            introspect(OP_OUTPUTTOKENCATEGORY, Some(0))?,
+            category(&[0x02])
+        );
+        assert_eq!(
+            introspect(OP_OUTPUTTOKENCOMMITMENT, Some(0))?,
+            b"xyz".to_vec()
+        );
+        assert_eq!(introspect(OP_OUTPUTTOKENAMOUNT, Some(0))?, vec![0xe8, 0x03]);
+
+        assert_eq!(
+            introspect(OP_UTXOVALUE, Some(2)),
+            Err(IntrospectionError::InvalidInputIndex {
+                idx: 2,
+                num_inputs: 2
+            }),
+        );
+        assert_eq!(
+            introspect(OP_OUTPUTTOKENAMOUNT, Some(1)),
+            Err(IntrospectionError::InvalidOutputIndex {
+                idx: 1,
+                num_outputs: 1
+            }),
+        );
+
+        // Non-P2SH input evaluates the coin's script
+        let ctx = ScriptExecutionContext::new(&tx, &spent_coins, 1)?;
+        assert_eq!(ctx.active_bytecode().to_vec(), vec![0x51]);
+        assert_eq!(ctx.input_index(), ScriptNum(1));
+        let ctx = ctx.with_active_bytecode(Script::default());
+        assert_eq!(ctx.introspect(OP_ACTIVEBYTECODE, None)?, Bytes::new());
+
+        assert_eq!(
+            ScriptExecutionContext::new(&tx, &spent_coins, 2),
+            Err(IntrospectionError::InvalidInputIndex {
+                idx: 2,
+                num_inputs: 2
+            }),
+        );
+        assert_eq!(
+            ScriptExecutionContext::new(&tx, &spent_coins[..1], 0),
+            Err(IntrospectionError::SpentCoinsMismatch {
+                num_inputs: 2,
+                num_spent_coins: 1,
+            }),
+        );
+        Ok(())
+    }
+}
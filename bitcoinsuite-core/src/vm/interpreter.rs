@@ -0,0 +1,1739 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use thiserror::Error;
+
+use crate::{
+    consensus::{ConsensusParams, UpgradeEpoch},
+    hash::{Hashed, Ripemd160, Sha1, Sha256, Sha256d, ShaRmd160},
+    script::{
+        opcode::*, Op, PubKeyVariant, Script, ScriptNum, ScriptNumError, ScriptVariant,
+        MAX_PUBKEYS_PER_MULTISIG,
+    },
+    tx::{Output, SigHashError, Signature, SignatureError, TxSignature},
+    vm::{
+        hash_iterations, num::VmNum, IntrospectionError, ScriptExecutionContext, VmLimits,
+        VmMetrics, BASE_INSTRUCTION_COST, MAX_CONTROL_STACK_DEPTH, MAX_FUNCTION_ID_LENGTH,
+        MAX_SCRIPT_SIZE, MAX_STACK_SIZE,
+    },
+};
+
+/// Locktimes below this are block heights, otherwise UNIX timestamps.
+const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+
+/// Set in a sequence number to disable its relative locktime (BIP68).
+const SEQUENCE_DISABLE_FLAG: i64 = 1 << 31;
+
+/// Set in a sequence number for relative locktimes in units of 512 seconds.
+const SEQUENCE_TYPE_FLAG: i64 = 1 << 22;
+
+/// Bits of a sequence number holding the relative locktime.
+const SEQUENCE_LOCKTIME_MASK: i64 = 0xffff;
+
+/// Numbers popped by `OP_CHECKLOCKTIMEVERIFY` and `OP_CHECKSEQUENCEVERIFY`
+/// may have up to 5 bytes.
+const MAX_LOCKTIME_NUM_LENGTH: usize = 5;
+
+/// Entry of the control stack of an evaluated bytecode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Control {
+    /// `OP_IF`/`OP_NOTIF` branch, and whether it's executed.
+    If(bool),
+    /// `OP_BEGIN` loop, with the position of the first op of the loop body.
+    Loop(usize),
+}
+
+/// Verifies ECDSA and Schnorr signatures for [`ScriptInterpreter`].
+///
+/// This crate doesn't implement elliptic curve cryptography, so the actual
+/// verification is left to the caller, e.g. using libsecp256k1. The
+/// interpreter takes care of everything else, including encoding rules,
+/// computing the sighash and NULLFAIL.
+pub trait SignatureVerifier: std::fmt::Debug {
+    /// Whether `sig` is a valid signature of the 32-byte `msg` by `pubkey`.
+    fn verify(&self, sig: &Signature, msg: &[u8; 32], pubkey: &PubKeyVariant) -> bool;
+}
+
+/// Script interpreter evaluating an input of a tx, under the rules of an
+/// [`UpgradeEpoch`].
+///
+/// Enforces the VM limits of the epoch: before the May 2025 upgrade, 520
+/// byte stack elements and 201 opcodes per script; afterwards, 10,000 byte
+/// stack elements, the operation cost and the hash iteration limits (see
+/// [`VmLimits`]). Numbers are limited to the length allowed in the epoch,
+/// so pre-2025 overflows can be reproduced. Numbers longer than 8 bytes, as
+/// allowed since May 2025, require the `bigint` feature.
+///
+/// Signatures are checked by a [`SignatureVerifier`].
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     consensus::UpgradeEpoch,
+/// #     script::{opcode::*, PubKeyVariant, ScriptMut, ScriptNumError},
+/// #     tx::{Coin, Input, Signature, Transaction},
+/// #     vm::{ScriptError, ScriptExecutionContext, ScriptInterpreter, SignatureVerifier},
+/// # };
+/// #[derive(Debug)]
+/// struct NoSigs;
+/// impl SignatureVerifier for NoSigs {
+///     fn verify(&self, _: &Signature, _: &[u8; 32], _: &PubKeyVariant) -> bool {
+///         false
+///     }
+/// }
+/// let tx = Transaction { inputs: vec![Input::default()], ..Default::default() };
+/// let spent_coins = [Coin::default()];
+/// let ctx = ScriptExecutionContext::new(&tx, &spent_coins, 0)?;
+///
+/// // (2^63 - 1) * 2 overflows 64-bit numbers
+/// let mut script = ScriptMut::with_capacity(0);
+/// script.push_int(i64::MAX);
+/// script.push_int(2);
+/// script.put_opcodes([OP_MUL, OP_SIZE, OP_NIP]);
+/// let script = script.freeze();
+///
+/// let params = UpgradeEpoch::Upgrade11.params();
+/// let mut interpreter = ScriptInterpreter::new(&ctx, &params, &NoSigs, true);
+/// if cfg!(feature = "bigint") {
+///     interpreter.eval(&script)?;
+///     assert_eq!(interpreter.stack(), [vec![9]]);
+/// } else {
+///     assert_eq!(interpreter.eval(&script), Err(ScriptError::BigIntRequired));
+/// }
+///
+/// let params = UpgradeEpoch::Upgrade10.params();
+/// let mut interpreter = ScriptInterpreter::new(&ctx, &params, &NoSigs, true);
+/// assert_eq!(
+///     interpreter.eval(&script),
+///     Err(ScriptError::ScriptNum(ScriptNumError::Overflow)),
+/// );
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct ScriptInterpreter<'a> {
+    ctx: &'a ScriptExecutionContext<'a>,
+    params: &'a ConsensusParams,
+    verifier: &'a dyn SignatureVerifier,
+    is_standard: bool,
+    limits: VmLimits,
+    metrics: VmMetrics,
+    stack: Vec<Vec<u8>>,
+    alt_stack: Vec<Vec<u8>>,
+    num_ops: usize,
+    functions: HashMap<Vec<u8>, Bytes>,
+}
+
+/// Errors when evaluating a script with [`ScriptInterpreter`].
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum ScriptError {
+    /// Script is larger than [`MAX_SCRIPT_SIZE`].
+    #[error("Script has {0} bytes, maximum is {}", MAX_SCRIPT_SIZE)]
+    ScriptSize(usize),
+
+    /// Bytecode ends in the middle of a push op.
+    #[error("Invalid push op at byte {0}")]
+    InvalidBytecode(usize),
+
+    /// Data isn't pushed with the smallest possible opcode (MINIMALDATA).
+    #[error("Non-minimal push")]
+    NonMinimalPush,
+
+    /// Stack element is larger than allowed in the epoch.
+    #[error("Stack element has {size} bytes, maximum is {max_size}")]
+    ElementSize {
+        /// Size of the element.
+        size: usize,
+        /// Maximum size of stack elements.
+        max_size: usize,
+    },
+
+    /// Stack and alt stack have more than [`MAX_STACK_SIZE`] items.
+    #[error("Stack has more than {} items", MAX_STACK_SIZE)]
+    StackSize,
+
+    /// Script has too many non-push opcodes (before May 2025).
+    #[error("Script has more than {0} opcodes")]
+    TooManyOps(usize),
+
+    /// Operation cost exceeds the budget of the input (May 2025).
+    #[error("Operation cost {op_cost} exceeds limit {max_op_cost}")]
+    OpCost {
+        /// Composite operation cost so far.
+        op_cost: u64,
+        /// Maximum operation cost of the input.
+        max_op_cost: u64,
+    },
+
+    /// Too many hash digest iterations for the input (May 2025).
+    #[error("{hash_iterations} hash iterations exceed limit {max_hash_iterations}")]
+    HashIterations {
+        /// Hash iterations so far.
+        hash_iterations: u64,
+        /// Maximum hash iterations of the input.
+        max_hash_iterations: u64,
+    },
+
+    /// Opcode needs more stack items than available.
+    #[error("Invalid stack operation")]
+    InvalidStackOperation,
+
+    /// Index or size operand is negative or out of range.
+    #[error("Invalid index for {0}")]
+    InvalidIndex(Opcode),
+
+    /// `OP_ELSE`/`OP_ENDIF` without `OP_IF`, `OP_IF` without `OP_ENDIF`,
+    /// or the same for `OP_BEGIN`/`OP_UNTIL`.
+    #[error("Unbalanced conditional")]
+    UnbalancedConditional,
+
+    /// Conditionals, loops and function invocations are nested deeper than
+    /// [`MAX_CONTROL_STACK_DEPTH`].
+    #[error("Control stack depth exceeds {}", MAX_CONTROL_STACK_DEPTH)]
+    ControlStackDepth,
+
+    /// Function identifier is longer than [`MAX_FUNCTION_ID_LENGTH`].
+    #[error(
+        "Function identifier has {0} bytes, maximum is {}",
+        MAX_FUNCTION_ID_LENGTH
+    )]
+    InvalidFunctionId(usize),
+
+    /// `OP_DEFINE` of an already defined function.
+    #[error("Function {} is already defined", hex::encode(.0))]
+    FunctionRedefined(Vec<u8>),
+
+    /// `OP_INVOKE` of a function that isn't defined.
+    #[error("Function {} is not defined", hex::encode(.0))]
+    UndefinedFunction(Vec<u8>),
+
+    /// `OP_IF`/`OP_NOTIF` argument must be empty or 1 (MINIMALIF, policy).
+    #[error("OP_IF argument must be minimal")]
+    MinimalIf,
+
+    /// Opcode is unknown, reserved or not enabled in the epoch.
+    #[error("Bad opcode {0}")]
+    BadOpcode(Opcode),
+
+    /// Opcode is disabled and fails the script even if not executed.
+    #[error("Disabled opcode {0}")]
+    DisabledOpcode(Opcode),
+
+    /// `OP_RETURN` was executed.
+    #[error("OP_RETURN executed")]
+    OpReturn,
+
+    /// A `VERIFY` opcode failed.
+    #[error("{0} failed")]
+    Verify(Opcode),
+
+    /// Script evaluated without error, but left false on the stack.
+    #[error("Script evaluated to false")]
+    EvalFalse,
+
+    /// Stack must have exactly one element after evaluation (CLEANSTACK,
+    /// policy).
+    #[error("Stack not clean after evaluation")]
+    CleanStack,
+
+    /// ScriptSig contains non-push opcodes.
+    #[error("ScriptSig is not push-only")]
+    ScriptSigNotPushOnly,
+
+    /// Invalid VM number operand or result.
+    #[error("Invalid number: {0}")]
+    ScriptNum(#[from] ScriptNumError),
+
+    /// VM number operand or result is valid in the epoch, but longer than
+    /// 8 bytes, which requires the `bigint` feature.
+    #[error("Number longer than 8 bytes requires the bigint feature")]
+    BigIntRequired,
+
+    /// Operands of a bitwise opcode have different sizes.
+    #[error("Invalid operand size for {0}")]
+    InvalidOperandSize(Opcode),
+
+    /// `OP_NUM2BIN` can't encode the number in the requested size.
+    #[error("Number doesn't fit the requested size")]
+    ImpossibleEncoding,
+
+    /// Signature is encoded incorrectly.
+    #[error("Invalid signature: {0}")]
+    Signature(#[from] SignatureError),
+
+    /// Sighash can't be computed.
+    #[error("Invalid sighash: {0}")]
+    SigHash(#[from] SigHashError),
+
+    /// Pubkey is neither a compressed nor an uncompressed pubkey.
+    #[error("Invalid pubkey encoding")]
+    InvalidPubKey,
+
+    /// Multisig has more than [`MAX_PUBKEYS_PER_MULTISIG`] pubkeys, or more
+    /// signatures than pubkeys.
+    #[error("Invalid multisig count")]
+    MultisigCount,
+
+    /// Schnorr multisig bitfield has the wrong size or number of bits set.
+    #[error("Invalid multisig bitfield")]
+    InvalidBitfield,
+
+    /// Legacy multisig requires ECDSA, Schnorr multisig Schnorr signatures.
+    #[error("Wrong signature algorithm for multisig mode")]
+    MultisigSigAlgorithm,
+
+    /// Failed signature checks must use an empty signature (NULLFAIL).
+    #[error("Signature check failed with a non-empty signature")]
+    NullFail,
+
+    /// Locktime operand is negative.
+    #[error("Negative locktime")]
+    NegativeLocktime,
+
+    /// Tx doesn't satisfy the locktime required by the script.
+    #[error("Unsatisfied locktime")]
+    UnsatisfiedLocktime,
+
+    /// Introspection failed, e.g. due to an invalid index.
+    #[error("Introspection failed: {0}")]
+    Introspection(#[from] IntrospectionError),
+}
+
+use self::ScriptError::*;
+
+impl<'a> ScriptInterpreter<'a> {
+    /// Interpreter for the input of `ctx`, enforcing the rules of `params`.
+    ///
+    /// If `is_standard`, relay policy is enforced too: MINIMALIF,
+    /// CLEANSTACK and the stricter hashing limits.
+    pub fn new(
+        ctx: &'a ScriptExecutionContext<'a>,
+        params: &'a ConsensusParams,
+        verifier: &'a dyn SignatureVerifier,
+        is_standard: bool,
+    ) -> Self {
+        let input = &ctx.tx().inputs[ctx.input_index().0 as usize];
+        ScriptInterpreter {
+            ctx,
+            params,
+            verifier,
+            is_standard,
+            limits: VmLimits::new(params, input.script.bytecode().len(), is_standard),
+            metrics: VmMetrics::default(),
+            stack: Vec::new(),
+            alt_stack: Vec::new(),
+            num_ops: 0,
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Verify the input like a node does: evaluate the scriptSig, then the
+    /// spent script, and for P2SH the redeem script. Returns the resources
+    /// used by the input.
+    pub fn verify_input(mut self) -> Result<VmMetrics, ScriptError> {
+        let input_idx = self.ctx.input_index().0 as usize;
+        let script_sig = self.ctx.input_bytecode(input_idx)?;
+        let spent_script = self.ctx.utxo_bytecode(input_idx)?;
+        if !script_sig.is_push_only() {
+            return Err(ScriptSigNotPushOnly);
+        }
+        self.eval(script_sig)?;
+        let script_sig_stack = self.stack.clone();
+        self.eval(spent_script)?;
+        self.check_top_true()?;
+        let is_p2sh = match ScriptVariant::from_script(spent_script) {
+            ScriptVariant::P2SH(_) => true,
+            ScriptVariant::P2SH32(_) => self.params.p2sh32,
+            _ => false,
+        };
+        if is_p2sh {
+            self.stack = script_sig_stack;
+            // Spent script checked the redeem script hash, so it's there
+            let redeem_script = Script::new(self.pop()?.into());
+            self.eval(&redeem_script)?;
+            self.check_top_true()?;
+        }
+        if self.is_standard && self.stack.len() != 1 {
+            return Err(CleanStack);
+        }
+        Ok(self.metrics)
+    }
+
+    /// Evaluate `script` on the current stack. The operation cost and
+    /// hashing budgets are shared by all scripts evaluated by this
+    /// interpreter, functions defined with `OP_DEFINE` are not.
+    pub fn eval(&mut self, script: &Script) -> Result<(), ScriptError> {
+        let bytecode = script.bytecode();
+        if bytecode.len() > MAX_SCRIPT_SIZE {
+            return Err(ScriptSize(bytecode.len()));
+        }
+        self.alt_stack.clear();
+        self.num_ops = 0;
+        self.functions.clear();
+        self.eval_bytecode(bytecode, 0)
+    }
+
+    /// Evaluate the script or function body `bytecode`, with its own control
+    /// stack. `depth` is the control stack depth of the invoking bytecode.
+    fn eval_bytecode(&mut self, bytecode: &Bytes, depth: usize) -> Result<(), ScriptError> {
+        let mut remaining = bytecode.clone();
+        let mut control_stack = Vec::<Control>::new();
+        let mut code_start = 0;
+        while !remaining.is_empty() {
+            let op_start = bytecode.len() - remaining.len();
+            let op = Op::read_op(&mut remaining).map_err(|_| InvalidBytecode(op_start))?;
+            let is_executed = !control_stack.contains(&Control::If(false));
+            self.metrics.op_cost += BASE_INSTRUCTION_COST;
+            let opcode = match op {
+                Op::Code(opcode) | Op::Push(opcode, _) => opcode,
+            };
+            if opcode > OP_16 {
+                self.add_ops(1)?;
+            }
+            if self.is_disabled(opcode) {
+                return Err(DisabledOpcode(opcode));
+            }
+            if let Op::Push(_, data) = &op {
+                self.check_element_size(data.len())?;
+            }
+            match opcode {
+                OP_IF | OP_NOTIF => {
+                    let mut is_true = false;
+                    if is_executed {
+                        let top = self.pop()?;
+                        if self.is_standard && !matches!(top.as_slice(), [] | [1]) {
+                            return Err(MinimalIf);
+                        }
+                        is_true = cast_to_bool(&top) == (opcode == OP_IF);
+                    }
+                    control_stack.push(Control::If(is_true));
+                    self.check_control_depth(depth + control_stack.len())?;
+                }
+                OP_ELSE => match control_stack.last_mut() {
+                    Some(Control::If(is_true)) => *is_true = !*is_true,
+                    _ => return Err(UnbalancedConditional),
+                },
+                OP_ENDIF => match control_stack.pop() {
+                    Some(Control::If(_)) => {}
+                    _ => return Err(UnbalancedConditional),
+                },
+                OP_BEGIN if self.params.loops => {
+                    control_stack.push(Control::Loop(bytecode.len() - remaining.len()));
+                    self.check_control_depth(depth + control_stack.len())?;
+                }
+                OP_UNTIL if self.params.loops => {
+                    let loop_start = match control_stack.pop() {
+                        Some(Control::Loop(loop_start)) => loop_start,
+                        _ => return Err(UnbalancedConditional),
+                    };
+                    if is_executed && !cast_to_bool(&self.pop()?) {
+                        remaining = bytecode.slice(loop_start..);
+                        control_stack.push(Control::Loop(loop_start));
+                    }
+                }
+                _ if !is_executed => {}
+                OP_CODESEPARATOR => code_start = bytecode.len() - remaining.len(),
+                OP_INVOKE if self.params.functions => {
+                    let id = self.pop()?;
+                    let body = match self.functions.get(&id) {
+                        Some(body) => body.clone(),
+                        None => return Err(UndefinedFunction(id)),
+                    };
+                    let depth = depth + control_stack.len() + 1;
+                    self.check_control_depth(depth)?;
+                    self.eval_bytecode(&body, depth)?;
+                }
+                _ => match op.pushed_data() {
+                    Some(data) => {
+                        if !op.is_minimal_push() {
+                            return Err(NonMinimalPush);
+                        }
+                        self.push(data.to_vec())?;
+                    }
+                    None => {
+                        let script_code = Script::new(bytecode.slice(code_start..));
+                        self.exec_opcode(opcode, &script_code)?;
+                    }
+                },
+            }
+            self.check_limits()?;
+        }
+        if !control_stack.is_empty() {
+            return Err(UnbalancedConditional);
+        }
+        Ok(())
+    }
+
+    /// Items on the stack, the top item last.
+    pub fn stack(&self) -> &[Vec<u8>] {
+        &self.stack
+    }
+
+    /// Limits enforced by this interpreter.
+    pub fn limits(&self) -> &VmLimits {
+        &self.limits
+    }
+
+    /// Resources used by the scripts evaluated so far.
+    pub fn metrics(&self) -> &VmMetrics {
+        &self.metrics
+    }
+
+    fn exec_opcode(&mut self, opcode: Opcode, script_code: &Script) -> Result<(), ScriptError> {
+        match opcode {
+            OP_NOP | OP_NOP1 | Opcode(OP_NOP4::N..=OP_NOP10::N) => {}
+            OP_VERIFY => self.verify(opcode)?,
+            OP_RETURN => return Err(OpReturn),
+
+            OP_TOALTSTACK => {
+                let top = self.pop()?;
+                self.alt_stack.push(top);
+            }
+            OP_FROMALTSTACK => {
+                let top = self.alt_stack.pop().ok_or(InvalidStackOperation)?;
+                self.push(top)?;
+            }
+            OP_2DROP => {
+                self.pop_n(2)?;
+            }
+            OP_2DUP | OP_3DUP | OP_2OVER => {
+                let (num, depth) = match opcode {
+                    OP_2DUP => (2, 0),
+                    OP_3DUP => (3, 0),
+                    _ => (2, 2),
+                };
+                for _ in 0..num {
+                    let item = self.top(depth + num - 1)?.clone();
+                    self.push(item)?;
+                }
+            }
+            OP_2ROT => {
+                let items = self.pop_n(6)?;
+                self.push_all(items[2..].iter().chain(&items[..2]))?;
+            }
+            OP_2SWAP => {
+                let items = self.pop_n(4)?;
+                self.push_all(items[2..].iter().chain(&items[..2]))?;
+            }
+            OP_IFDUP => {
+                let top = self.top(0)?.clone();
+                if cast_to_bool(&top) {
+                    self.push(top)?;
+                }
+            }
+            OP_DEPTH => self.push_int(self.stack.len() as i64)?,
+            OP_DROP => {
+                self.pop()?;
+            }
+            OP_DUP => self.push(self.top(0)?.clone())?,
+            OP_NIP => {
+                let top = self.pop()?;
+                self.pop()?;
+                self.push(top)?;
+            }
+            OP_OVER => self.push(self.top(1)?.clone())?,
+            OP_PICK | OP_ROLL => {
+                let depth = self.pop_index(opcode)?;
+                if depth >= self.stack.len() {
+                    return Err(InvalidIndex(opcode));
+                }
+                let idx = self.stack.len() - depth - 1;
+                let item = match opcode {
+                    OP_PICK => self.stack[idx].clone(),
+                    _ => self.stack.remove(idx),
+                };
+                self.push(item)?;
+            }
+            OP_ROT => {
+                let items = self.pop_n(3)?;
+                self.push_all([&items[1], &items[2], &items[0]])?;
+            }
+            OP_SWAP => {
+                let items = self.pop_n(2)?;
+                self.push_all([&items[1], &items[0]])?;
+            }
+            OP_TUCK => {
+                let items = self.pop_n(2)?;
+                self.push_all([&items[1], &items[0], &items[1]])?;
+            }
+
+            OP_CAT => {
+                let mut items = self.pop_n(2)?;
+                let b = items.pop().unwrap();
+                let mut a = items.pop().unwrap();
+                self.check_element_size(a.len() + b.len())?;
+                a.extend_from_slice(&b);
+                self.push(a)?;
+            }
+            OP_SPLIT => {
+                let pos = self.pop_index(opcode)?;
+                let mut data = self.pop()?;
+                if pos > data.len() {
+                    return Err(InvalidIndex(opcode));
+                }
+                let right = data.split_off(pos);
+                self.push_all([&data, &right])?;
+            }
+            OP_NUM2BIN => {
+                let size = self.pop_index(opcode)?;
+                self.check_element_size(size)?;
+                let mut data = minimally_encode(self.pop()?);
+                if data.len() > size {
+                    return Err(ImpossibleEncoding);
+                }
+                let sign_bit = match data.last_mut() {
+                    Some(last) => {
+                        let sign_bit = *last & 0x80;
+                        *last &= 0x7f;
+                        sign_bit
+                    }
+                    None => 0,
+                };
+                data.resize(size, 0);
+                if let Some(last) = data.last_mut() {
+                    *last |= sign_bit;
+                }
+                self.push(data)?;
+            }
+            OP_BIN2NUM => {
+                let data = minimally_encode(self.pop()?);
+                let num = self.decode_num(&data)?;
+                self.push_num(num)?;
+            }
+            OP_SIZE => self.push_int(self.top(0)?.len() as i64)?,
+            OP_REVERSEBYTES => {
+                let mut data = self.pop()?;
+                data.reverse();
+                self.push(data)?;
+            }
+
+            OP_AND | OP_OR | OP_XOR => {
+                let mut items = self.pop_n(2)?;
+                let b = items.pop().unwrap();
+                let mut a = items.pop().unwrap();
+                if a.len() != b.len() {
+                    return Err(InvalidOperandSize(opcode));
+                }
+                for (a, b) in a.iter_mut().zip(b) {
+                    match opcode {
+                        OP_AND => *a &= b,
+                        OP_OR => *a |= b,
+                        _ => *a ^= b,
+                    }
+                }
+                self.push(a)?;
+            }
+            OP_INVERT => {
+                let data = self.pop()?;
+                self.push(data.iter().map(|byte| !byte).collect())?;
+            }
+            OP_LSHIFTBIN | OP_RSHIFTBIN => {
+                let shift = self.pop_index(opcode)?;
+                let data = self.pop()?;
+                self.push(shift_bin(&data, shift, opcode == OP_LSHIFTBIN))?;
+            }
+            OP_EQUAL | OP_EQUALVERIFY => {
+                let items = self.pop_n(2)?;
+                self.push_bool(items[0] == items[1])?;
+                if opcode == OP_EQUALVERIFY {
+                    self.verify(opcode)?;
+                }
+            }
+
+            OP_1ADD | OP_1SUB | OP_NEGATE | OP_ABS | OP_NOT | OP_0NOTEQUAL => {
+                let num = self.pop_num()?;
+                let one = VmNum::from(1);
+                let zero = VmNum::default();
+                match opcode {
+                    OP_1ADD => self.push_result(num.checked_add(&one))?,
+                    OP_1SUB => self.push_result(num.checked_sub(&one))?,
+                    OP_NEGATE => self.push_result(zero.checked_sub(&num))?,
+                    OP_ABS if num < zero => self.push_result(zero.checked_sub(&num))?,
+                    OP_ABS => self.push_num(num)?,
+                    OP_NOT => self.push_bool(num == zero)?,
+                    _ => self.push_bool(num != zero)?,
+                }
+            }
+            Opcode(OP_ADD::N..=OP_MOD::N) | Opcode(OP_BOOLAND::N..=OP_MAX::N) => {
+                let b_bytes = self.pop()?;
+                let a_bytes = self.pop()?;
+                let b = self.decode_num(&b_bytes)?;
+                let a = self.decode_num(&a_bytes)?;
+                let zero = VmNum::default();
+                if let OP_MUL | OP_DIV | OP_MOD = opcode {
+                    // Quadratic in the size of the operands
+                    self.metrics.op_cost += (a_bytes.len() * b_bytes.len()) as u64;
+                }
+                match opcode {
+                    OP_ADD => self.push_result(a.checked_add(&b))?,
+                    OP_SUB => self.push_result(a.checked_sub(&b))?,
+                    OP_MUL => self.push_result(a.checked_mul(&b))?,
+                    OP_DIV => self.push_result(a.checked_div(&b))?,
+                    OP_MOD => self.push_result(a.checked_rem(&b))?,
+                    OP_BOOLAND => self.push_bool(a != zero && b != zero)?,
+                    OP_BOOLOR => self.push_bool(a != zero || b != zero)?,
+                    OP_NUMEQUAL => self.push_bool(a == b)?,
+                    OP_NUMEQUALVERIFY => {
+                        if a != b {
+                            return Err(Verify(opcode));
+                        }
+                    }
+                    OP_NUMNOTEQUAL => self.push_bool(a != b)?,
+                    OP_LESSTHAN => self.push_bool(a < b)?,
+                    OP_GREATERTHAN => self.push_bool(a > b)?,
+                    OP_LESSTHANOREQUAL => self.push_bool(a <= b)?,
+                    OP_GREATERTHANOREQUAL => self.push_bool(a >= b)?,
+                    OP_MIN => self.push_num(a.min(b))?,
+                    _ => self.push_num(a.max(b))?,
+                }
+            }
+            OP_LSHIFTNUM => {
+                let shift = self.pop_index(opcode)?;
+                let num = self.pop_num()?;
+                // Check the size before shifting to avoid huge allocations
+                let max_bits = self.limits.max_script_num_length as u64 * 8;
+                if num.bits() > 0 && num.bits() + shift as u64 > max_bits {
+                    return Err(ScriptNumError::Overflow.into());
+                }
+                self.push_result(num.checked_shl(shift))?;
+            }
+            OP_RSHIFTNUM => {
+                let shift = self.pop_index(opcode)?;
+                let num = self.pop_num()?;
+                // Rounds towards negative infinity, like an arithmetic shift
+                self.push_num(num.shr(shift))?;
+            }
+            OP_WITHIN => {
+                let max = self.pop_num()?;
+                let min = self.pop_num()?;
+                let num = self.pop_num()?;
+                self.push_bool(min <= num && num < max)?;
+            }
+
+            OP_RIPEMD160 | OP_SHA1 | OP_SHA256 | OP_HASH160 | OP_HASH256 => {
+                let data = self.pop()?;
+                let is_two_round = matches!(opcode, OP_HASH160 | OP_HASH256);
+                self.metrics.hash_iterations += hash_iterations(data.len(), is_two_round);
+                let hash = match opcode {
+                    OP_RIPEMD160 => Ripemd160::digest(data).0.to_vec(),
+                    OP_SHA1 => Sha1::digest(data).0.to_vec(),
+                    OP_SHA256 => Sha256::digest(data).0.to_vec(),
+                    OP_HASH160 => ShaRmd160::digest(data).0.to_vec(),
+                    _ => Sha256d::digest(data).0.to_vec(),
+                };
+                self.push(hash)?;
+            }
+            OP_CHECKSIG | OP_CHECKSIGVERIFY => {
+                let pubkey = self.pop()?;
+                let sig = self.pop()?;
+                let is_valid = self.check_tx_sig(&sig, &pubkey, script_code, None)?;
+                self.push_bool(is_valid)?;
+                if opcode == OP_CHECKSIGVERIFY {
+                    self.verify(opcode)?;
+                }
+            }
+            OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
+                let is_valid = self.check_multisig(script_code)?;
+                self.push_bool(is_valid)?;
+                if opcode == OP_CHECKMULTISIGVERIFY {
+                    self.verify(opcode)?;
+                }
+            }
+            OP_CHECKDATASIG | OP_CHECKDATASIGVERIFY => {
+                let items = self.pop_n(3)?;
+                let is_valid = self.check_data_sig(&items[0], &items[1], &items[2])?;
+                self.push_bool(is_valid)?;
+                if opcode == OP_CHECKDATASIGVERIFY {
+                    self.verify(opcode)?;
+                }
+            }
+            OP_CHECKLOCKTIMEVERIFY => self.check_locktime()?,
+            OP_CHECKSEQUENCEVERIFY => self.check_sequence()?,
+
+            OP_DEFINE if self.params.functions => {
+                let id = self.pop()?;
+                let body = self.pop()?;
+                if id.len() > MAX_FUNCTION_ID_LENGTH {
+                    return Err(InvalidFunctionId(id.len()));
+                }
+                if self.functions.contains_key(&id) {
+                    return Err(FunctionRedefined(id));
+                }
+                self.functions.insert(id, body.into());
+            }
+
+            OP_ACTIVEBYTECODE if self.params.native_introspection => {
+                self.push(script_code.to_vec())?;
+            }
+            Opcode(OP_INPUTINDEX::N..=OP_TXLOCKTIME::N) if self.params.native_introspection => {
+                let item = self.ctx.introspect(opcode, None)?;
+                self.push(item.to_vec())?;
+            }
+            Opcode(OP_UTXOVALUE::N..=OP_OUTPUTTOKENAMOUNT::N)
+                if self.params.native_introspection
+                    && (opcode <= OP_OUTPUTBYTECODE || self.params.cashtokens) =>
+            {
+                let idx = self.pop_index(opcode)?;
+                let item = self.ctx.introspect(opcode, Some(idx))?;
+                self.push(item.to_vec())?;
+            }
+
+            _ => return Err(BadOpcode(opcode)),
+        }
+        Ok(())
+    }
+
+    fn is_disabled(&self, opcode: Opcode) -> bool {
+        match opcode {
+            OP_BEGIN | OP_UNTIL => !self.params.loops,
+            OP_INVERT | OP_LSHIFTNUM | OP_RSHIFTNUM | OP_LSHIFTBIN | OP_RSHIFTBIN => {
+                !self.params.bitwise
+            }
+            OP_MUL => self.params.epoch < UpgradeEpoch::Upgrade8,
+            _ => false,
+        }
+    }
+
+    /// Since May 2025, conditionals are limited to a depth of
+    /// [`MAX_CONTROL_STACK_DEPTH`], which loops and invocations count
+    /// towards too.
+    fn check_control_depth(&self, depth: usize) -> Result<(), ScriptError> {
+        if self.params.vm_limits && depth > MAX_CONTROL_STACK_DEPTH {
+            return Err(ControlStackDepth);
+        }
+        Ok(())
+    }
+
+    fn add_ops(&mut self, num_ops: usize) -> Result<(), ScriptError> {
+        self.num_ops += num_ops;
+        match self.limits.max_ops_per_script {
+            Some(max_ops) if self.num_ops > max_ops => Err(TooManyOps(max_ops)),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_limits(&self) -> Result<(), ScriptError> {
+        if self.stack.len() + self.alt_stack.len() > MAX_STACK_SIZE {
+            return Err(StackSize);
+        }
+        if let Some(max_hash_iterations) = self.limits.max_hash_iterations {
+            if self.metrics.hash_iterations > max_hash_iterations {
+                return Err(HashIterations {
+                    hash_iterations: self.metrics.hash_iterations,
+                    max_hash_iterations,
+                });
+            }
+        }
+        if let Some(max_op_cost) = self.limits.max_op_cost {
+            let op_cost = self.metrics.composite_op_cost(&self.limits);
+            if op_cost > max_op_cost {
+                return Err(OpCost {
+                    op_cost,
+                    max_op_cost,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_element_size(&self, size: usize) -> Result<(), ScriptError> {
+        let max_size = self.limits.max_script_element_size;
+        if size > max_size {
+            return Err(ElementSize { size, max_size });
+        }
+        Ok(())
+    }
+
+    fn check_top_true(&self) -> Result<(), ScriptError> {
+        match self.stack.last() {
+            Some(top) if cast_to_bool(top) => Ok(()),
+            _ => Err(EvalFalse),
+        }
+    }
+
+    /// Push an item, every pushed byte adds to the operation cost.
+    fn push(&mut self, item: Vec<u8>) -> Result<(), ScriptError> {
+        self.check_element_size(item.len())?;
+        self.metrics.op_cost += item.len() as u64;
+        self.stack.push(item);
+        Ok(())
+    }
+
+    fn push_all<'b>(
+        &mut self,
+        items: impl IntoIterator<Item = &'b Vec<u8>>,
+    ) -> Result<(), ScriptError> {
+        for item in items {
+            self.push(item.clone())?;
+        }
+        Ok(())
+    }
+
+    fn push_bool(&mut self, value: bool) -> Result<(), ScriptError> {
+        self.push(if value { vec![1] } else { vec![] })
+    }
+
+    fn push_int(&mut self, num: i64) -> Result<(), ScriptError> {
+        self.push(ScriptNum(num).encode())
+    }
+
+    /// Push the result of an arithmetic opcode. Since May 2022, results
+    /// must fit the number length of the epoch.
+    fn push_num(&mut self, num: VmNum) -> Result<(), ScriptError> {
+        if self.params.epoch >= UpgradeEpoch::Upgrade8
+            && num.encoded_len() > self.limits.max_script_num_length
+        {
+            return Err(ScriptNumError::Overflow.into());
+        }
+        self.push(num.encode())
+    }
+
+    /// Push the result of a checked operation. Without the `bigint`
+    /// feature, numbers are limited to 8 bytes, so results beyond that fail
+    /// with [`ScriptError::BigIntRequired`] in epochs allowing them.
+    fn push_result(&mut self, result: Result<VmNum, ScriptNumError>) -> Result<(), ScriptError> {
+        match result {
+            Ok(num) => self.push_num(num),
+            Err(ScriptNumError::Overflow) if self.is_bigint_required() => Err(BigIntRequired),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn pop(&mut self) -> Result<Vec<u8>, ScriptError> {
+        self.stack.pop().ok_or(InvalidStackOperation)
+    }
+
+    /// Pop the top `num` items, in stack order.
+    fn pop_n(&mut self, num: usize) -> Result<Vec<Vec<u8>>, ScriptError> {
+        if num > self.stack.len() {
+            return Err(InvalidStackOperation);
+        }
+        Ok(self.stack.split_off(self.stack.len() - num))
+    }
+
+    fn pop_num(&mut self) -> Result<VmNum, ScriptError> {
+        let data = self.pop()?;
+        self.decode_num(&data)
+    }
+
+    /// Pop a non-negative number used as index or size.
+    fn pop_index(&mut self, opcode: Opcode) -> Result<usize, ScriptError> {
+        let num = self.pop_num()?.to_script_num()?;
+        usize::try_from(num.0).map_err(|_| InvalidIndex(opcode))
+    }
+
+    /// Item at `depth` from the top of the stack.
+    fn top(&self, depth: usize) -> Result<&Vec<u8>, ScriptError> {
+        match self.stack.len().checked_sub(depth + 1) {
+            Some(idx) => Ok(&self.stack[idx]),
+            None => Err(InvalidStackOperation),
+        }
+    }
+
+    fn decode_num(&self, data: &[u8]) -> Result<VmNum, ScriptError> {
+        if data.len() > ScriptNum::MAX_LEN && self.is_bigint_required() {
+            return Err(BigIntRequired);
+        }
+        decode_num(data, self.limits.max_script_num_length)
+    }
+
+    /// Whether the epoch allows numbers longer than 8 bytes, but the
+    /// `bigint` feature to compute them is off.
+    fn is_bigint_required(&self) -> bool {
+        !cfg!(feature = "bigint") && self.limits.max_script_num_length > ScriptNum::MAX_LEN
+    }
+
+    fn verify(&mut self, opcode: Opcode) -> Result<(), ScriptError> {
+        if !cast_to_bool(&self.pop()?) {
+            return Err(Verify(opcode));
+        }
+        Ok(())
+    }
+
+    fn check_locktime(&mut self) -> Result<(), ScriptError> {
+        let locktime = self.top_locktime()?;
+        let tx = self.ctx.tx();
+        let tx_locktime = i64::from(tx.locktime);
+        let input = &tx.inputs[self.ctx.input_index().0 as usize];
+        if (locktime < LOCKTIME_THRESHOLD) != (tx_locktime < LOCKTIME_THRESHOLD)
+            || locktime > tx_locktime
+            || input.sequence == 0xffff_ffff
+        {
+            return Err(UnsatisfiedLocktime);
+        }
+        Ok(())
+    }
+
+    fn check_sequence(&mut self) -> Result<(), ScriptError> {
+        let sequence = self.top_locktime()?;
+        if sequence & SEQUENCE_DISABLE_FLAG != 0 {
+            return Ok(());
+        }
+        let tx = self.ctx.tx();
+        let tx_sequence = i64::from(tx.inputs[self.ctx.input_index().0 as usize].sequence);
+        let mask = SEQUENCE_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK;
+        if tx.version < 2
+            || tx_sequence & SEQUENCE_DISABLE_FLAG != 0
+            || (sequence & SEQUENCE_TYPE_FLAG) != (tx_sequence & SEQUENCE_TYPE_FLAG)
+            || sequence & mask > tx_sequence & mask
+        {
+            return Err(UnsatisfiedLocktime);
+        }
+        Ok(())
+    }
+
+    /// Top stack item as locktime for `OP_CHECKLOCKTIMEVERIFY` and
+    /// `OP_CHECKSEQUENCEVERIFY`, which leave it on the stack.
+    fn top_locktime(&self) -> Result<i64, ScriptError> {
+        let max_len = self
+            .limits
+            .max_script_num_length
+            .max(MAX_LOCKTIME_NUM_LENGTH);
+        let locktime = decode_num(self.top(0)?, max_len)?.to_script_num()?.0;
+        if locktime < 0 {
+            return Err(NegativeLocktime);
+        }
+        Ok(locktime)
+    }
+
+    /// Check a tx signature, returns whether it's valid. Empty signatures
+    /// are invalid, all other failures must fail the script (NULLFAIL).
+    ///
+    /// `requires_schnorr` restricts the algorithm for multisig.
+    fn check_tx_sig(
+        &mut self,
+        sig: &[u8],
+        pubkey: &[u8],
+        script_code: &Script,
+        requires_schnorr: Option<bool>,
+    ) -> Result<bool, ScriptError> {
+        if sig.is_empty() {
+            return Ok(false);
+        }
+        let sig = TxSignature::try_from(sig)?;
+        if requires_schnorr.map_or(false, |is_schnorr| is_schnorr != sig.sig.is_schnorr()) {
+            return Err(MultisigSigAlgorithm);
+        }
+        let pubkey = parse_pubkey(pubkey)?;
+        let tx = self.ctx.tx();
+        let spent_outputs = self
+            .ctx
+            .spent_coins()
+            .iter()
+            .map(|coin| coin.output.clone())
+            .collect::<Vec<Output>>();
+        let preimage = tx.sighash_preimage(
+            self.ctx.input_index().0 as usize,
+            &spent_outputs,
+            script_code,
+            sig.sig_hash_type,
+        )?;
+        self.metrics.hash_iterations += hash_iterations(preimage.len(), true);
+        self.metrics.sig_checks += 1;
+        let sighash = Sha256d::digest(preimage);
+        if !self.verifier.verify(&sig.sig, &sighash.0, &pubkey) {
+            return Err(NullFail);
+        }
+        Ok(true)
+    }
+
+    fn check_data_sig(
+        &mut self,
+        sig: &[u8],
+        msg: &[u8],
+        pubkey: &[u8],
+    ) -> Result<bool, ScriptError> {
+        if sig.is_empty() {
+            return Ok(false);
+        }
+        let sig = Signature::try_from(sig)?;
+        let pubkey = parse_pubkey(pubkey)?;
+        self.metrics.hash_iterations += hash_iterations(msg.len(), false);
+        self.metrics.sig_checks += 1;
+        if !self.verifier.verify(&sig, &Sha256::digest(msg).0, &pubkey) {
+            return Err(NullFail);
+        }
+        Ok(true)
+    }
+
+    /// Pop the operands of `OP_CHECKMULTISIG` and check the signatures,
+    /// in legacy (ECDSA) or Schnorr mode, depending on the dummy element.
+    fn check_multisig(&mut self, script_code: &Script) -> Result<bool, ScriptError> {
+        let num_pubkeys = self.pop_index(OP_CHECKMULTISIG)?;
+        if num_pubkeys > MAX_PUBKEYS_PER_MULTISIG {
+            return Err(MultisigCount);
+        }
+        self.add_ops(num_pubkeys)?;
+        let pubkeys = self.pop_n(num_pubkeys)?;
+        let num_sigs = self.pop_index(OP_CHECKMULTISIG)?;
+        if num_sigs > num_pubkeys {
+            return Err(MultisigCount);
+        }
+        let sigs = self.pop_n(num_sigs)?;
+        let dummy = self.pop()?;
+
+        if !dummy.is_empty() {
+            // Schnorr mode: the dummy is a bitfield of the signing pubkeys
+            if dummy.len() != (num_pubkeys + 7) / 8 {
+                return Err(InvalidBitfield);
+            }
+            let bitfield = dummy
+                .iter()
+                .enumerate()
+                .fold(0u32, |bitfield, (idx, &byte)| {
+                    bitfield | u32::from(byte) << (8 * idx)
+                });
+            if bitfield >> num_pubkeys != 0 || bitfield.count_ones() as usize != num_sigs {
+                return Err(InvalidBitfield);
+            }
+            let signing_pubkeys = (0..num_pubkeys).filter(|idx| bitfield & (1 << idx) != 0);
+            for (sig, pubkey_idx) in sigs.iter().zip(signing_pubkeys) {
+                if !self.check_tx_sig(sig, &pubkeys[pubkey_idx], script_code, Some(true))? {
+                    return Err(NullFail);
+                }
+            }
+            return Ok(true);
+        }
+
+        // Legacy mode: match signatures to pubkeys in order, starting with
+        // the last ones like the reference implementation
+        if sigs.iter().all(|sig| sig.is_empty()) {
+            return Ok(num_sigs == 0);
+        }
+        let sig_checks = self.metrics.sig_checks;
+        let mut sigs_left = sigs.iter().rev().peekable();
+        let mut pubkeys_left = pubkeys.iter().rev();
+        while let Some(&sig) = sigs_left.peek() {
+            if sigs_left.len() > pubkeys_left.len() {
+                // Not all signatures are valid, but some aren't empty
+                return Err(NullFail);
+            }
+            let pubkey = pubkeys_left.next().unwrap();
+            let is_valid = match self.check_tx_sig(sig, pubkey, script_code, Some(false)) {
+                Err(NullFail) => false,
+                result => result?,
+            };
+            if is_valid {
+                sigs_left.next();
+            }
+        }
+        // Every pubkey counts as signature check
+        self.metrics.sig_checks = sig_checks + num_pubkeys as u64;
+        Ok(true)
+    }
+}
+
+/// Whether a stack item is true: any non-zero bytes, except negative zero.
+fn cast_to_bool(item: &[u8]) -> bool {
+    match item.split_last() {
+        Some((&last, rest)) => rest.iter().any(|&byte| byte != 0) || (last & 0x7f) != 0,
+        None => false,
+    }
+}
+
+fn decode_num(data: &[u8], max_len: usize) -> Result<VmNum, ScriptError> {
+    if data.len() > max_len {
+        return Err(ScriptNumError::TooLong {
+            max_len,
+            actual: data.len(),
+        }
+        .into());
+    }
+    Ok(VmNum::decode(data)?)
+}
+
+/// Shift the bits of `data` by `shift`, keeping its size; bits shifted out
+/// are dropped and zeros are shifted in.
+fn shift_bin(data: &[u8], shift: usize, is_left: bool) -> Vec<u8> {
+    let len = data.len();
+    let shift = shift.min(len * 8);
+    let (byte_shift, bit_shift) = (shift / 8, shift % 8);
+    let mut result = vec![0; len];
+    for (idx, byte) in result.iter_mut().enumerate() {
+        // Byte of `data` this byte gets its high (left) or low (right) bits from
+        let (src, carry) = match is_left {
+            true => (idx + byte_shift, idx + byte_shift + 1),
+            false => match idx.checked_sub(byte_shift) {
+                Some(src) => (src, src.wrapping_sub(1)),
+                None => continue,
+            },
+        };
+        let src_byte = data.get(src).copied().unwrap_or_default();
+        let carry_byte = data.get(carry).copied().unwrap_or_default();
+        *byte = match (is_left, bit_shift) {
+            (_, 0) => src_byte,
+            (true, _) => src_byte << bit_shift | carry_byte >> (8 - bit_shift),
+            (false, _) => src_byte >> bit_shift | carry_byte << (8 - bit_shift),
+        };
+    }
+    result
+}
+
+/// Remove superfluous zero bytes of a number, keeping its sign.
+fn minimally_encode(mut data: Vec<u8>) -> Vec<u8> {
+    let sign_bit = match data.last() {
+        Some(&last) => last & 0x80,
+        None => return data,
+    };
+    *data.last_mut().unwrap() &= 0x7f;
+    while data.last() == Some(&0) {
+        data.pop();
+    }
+    match data.last_mut() {
+        Some(last) if *last & 0x80 != 0 => data.push(sign_bit),
+        Some(last) => *last |= sign_bit,
+        None => {}
+    }
+    data
+}
+
+/// Parse a pubkey with a valid prefix byte (STRICTENC).
+fn parse_pubkey(pubkey: &[u8]) -> Result<PubKeyVariant, ScriptError> {
+    match (pubkey.first(), PubKeyVariant::try_from(pubkey)) {
+        (Some(0x02 | 0x03), Ok(pubkey @ PubKeyVariant::Compressed(_))) => Ok(pubkey),
+        (Some(0x04), Ok(pubkey @ PubKeyVariant::Uncompressed(_))) => Ok(pubkey),
+        _ => Err(InvalidPubKey),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        consensus::UpgradeEpoch,
+        hash::{Hashed, ShaRmd160},
+        script::{
+            opcode::*, MultisigMode, PubKey, PubKeyVariant, Script, ScriptMut, ScriptNumError,
+        },
+        tx::{Coin, Input, Output, Signature, Transaction},
+        vm::{ScriptError, ScriptExecutionContext, ScriptInterpreter, SignatureVerifier},
+    };
+
+    /// Accepts signatures ending with the same byte as the pubkey
+    #[derive(Debug)]
+    struct FakeVerifier;
+
+    impl SignatureVerifier for FakeVerifier {
+        fn verify(&self, sig: &Signature, _: &[u8; 32], pubkey: &PubKeyVariant) -> bool {
+            let pubkey_byte = match pubkey {
+                PubKeyVariant::Compressed(pubkey) => pubkey.0[32],
+                PubKeyVariant::Uncompressed(pubkey) => pubkey.0[64],
+            };
+            sig.as_slice().last() == Some(&pubkey_byte)
+        }
+    }
+
+    fn script(build: impl FnOnce(&mut ScriptMut)) -> Script {
+        let mut script = ScriptMut::with_capacity(0);
+        build(&mut script);
+        script.freeze()
+    }
+
+    fn eval(epoch: UpgradeEpoch, script: &Script) -> Result<Vec<Vec<u8>>, ScriptError> {
+        let tx = Transaction {
+            version: 2,
+            inputs: vec![Input::default()],
+            ..Default::default()
+        };
+        let spent_coins = [Coin::default()];
+        let ctx = ScriptExecutionContext::new(&tx, &spent_coins, 0)?;
+        let params = epoch.params();
+        let mut interpreter = ScriptInterpreter::new(&ctx, &params, &FakeVerifier, false);
+        interpreter.eval(script)?;
+        Ok(interpreter.stack().to_vec())
+    }
+
+    fn verify(
+        script_sig: Script,
+        spent_script: Script,
+        is_standard: bool,
+    ) -> Result<u64, ScriptError> {
+        let tx = Transaction {
+            version: 2,
+            inputs: vec![Input {
+                script: script_sig,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let spent_coins = [Coin {
+            output: Output {
+                value: 10_000,
+                script: spent_script,
+                token: None,
+            },
+            ..Default::default()
+        }];
+        let ctx = ScriptExecutionContext::new(&tx, &spent_coins, 0)?;
+        let params = UpgradeEpoch::LATEST.params();
+        let interpreter = ScriptInterpreter::new(&ctx, &params, &FakeVerifier, is_standard);
+        Ok(interpreter.verify_input()?.sig_checks)
+    }
+
+    /// Strict DER ECDSA signature with SIGHASH_ALL|FORKID, S ends with `s`
+    fn ecdsa_sig(s: u8) -> Vec<u8> {
+        [
+            [0x30, 0x44, 0x02, 0x20].as_ref(),
+            &[0x11; 32],
+            &[0x02, 0x20],
+            &[s; 32],
+            &[0x41],
+        ]
+        .concat()
+    }
+
+    fn schnorr_sig(s: u8) -> Vec<u8> {
+        [[s; 64].as_ref(), &[0x41]].concat()
+    }
+
+    #[test]
+    fn test_arithmetic() -> Result<(), ScriptError> {
+        use self::UpgradeEpoch::*;
+        let calc = |a: i64, b: i64, opcode: Opcode| {
+            script(|script| {
+                script.push_int(a);
+                script.push_int(b);
+                script.put_opcodes([opcode]);
+            })
+        };
+        assert_eq!(eval(Upgrade11, &calc(-7, 2, OP_DIV))?, [vec![0x83]]);
+        assert_eq!(eval(Upgrade11, &calc(-7, 2, OP_MOD))?, [vec![0x81]]);
+        assert_eq!(eval(Upgrade11, &calc(7, -2, OP_MOD))?, [vec![0x01]]);
+        assert_eq!(eval(Upgrade11, &calc(3, 5, OP_MIN))?, [vec![0x03]]);
+        assert_eq!(eval(Upgrade11, &calc(3, 5, OP_LESSTHAN))?, [vec![0x01]]);
+        assert_eq!(
+            eval(Upgrade11, &calc(3, 3, OP_NUMNOTEQUAL))?,
+            [Vec::<u8>::new()]
+        );
+        assert_eq!(
+            eval(Upgrade11, &calc(1, 0, OP_DIV)),
+            Err(ScriptError::ScriptNum(ScriptNumError::DivisionByZero)),
+        );
+        assert_eq!(
+            eval(Legacy, &calc(2, 3, OP_MUL)),
+            Err(ScriptError::DisabledOpcode(OP_MUL)),
+        );
+        assert_eq!(eval(Upgrade8, &calc(2, 3, OP_MUL))?, [vec![0x06]]);
+
+        // 20 byte operands are only valid as of BigInt
+        let big = script(|script| {
+            script.push_data(&[0xff; 20]);
+            script.put_opcodes([OP_DUP, OP_MUL, OP_SIZE, OP_NIP]);
+        });
+        if cfg!(feature = "bigint") {
+            assert_eq!(eval(Upgrade11, &big)?, [vec![40]]);
+        } else {
+            assert_eq!(eval(Upgrade11, &big), Err(ScriptError::BigIntRequired));
+        }
+        assert_eq!(
+            eval(Upgrade10, &big),
+            Err(ScriptError::ScriptNum(ScriptNumError::TooLong {
+                max_len: 8,
+                actual: 20,
+            })),
+        );
+
+        // Legacy results may exceed the 4 byte operand range
+        let add = calc(0x7fff_ffff, 1, OP_ADD);
+        assert_eq!(eval(Legacy, &add)?, [vec![0x00, 0x00, 0x00, 0x80, 0x00]]);
+        let add_twice = script(|script| {
+            script.put_bytecode(add.bytecode());
+            script.put_opcodes([OP_1ADD]);
+        });
+        assert_eq!(
+            eval(Legacy, &add_twice),
+            Err(ScriptError::ScriptNum(ScriptNumError::TooLong {
+                max_len: 4,
+                actual: 5,
+            })),
+        );
+        let overflow = calc(i64::MAX, 1, OP_ADD);
+        assert_eq!(
+            eval(Upgrade8, &overflow),
+            Err(ScriptError::ScriptNum(ScriptNumError::Overflow)),
+        );
+        if cfg!(feature = "bigint") {
+            assert_eq!(
+                eval(Upgrade11, &overflow)?,
+                [vec![0, 0, 0, 0, 0, 0, 0, 0x80, 0]]
+            );
+        } else {
+            assert_eq!(eval(Upgrade11, &overflow), Err(ScriptError::BigIntRequired));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_stack_and_splice_ops() -> Result<(), ScriptError> {
+        let epoch = UpgradeEpoch::LATEST;
+        let ops = |opcodes: &[Opcode]| {
+            script(|script| {
+                script.put_opcodes([OP_1, OP_2, OP_3, OP_4]);
+                script.put_opcodes(opcodes.iter().copied());
+            })
+        };
+        let items = |nums: &[u8]| nums.iter().map(|&num| vec![num]).collect::<Vec<_>>();
+        assert_eq!(eval(epoch, &ops(&[OP_ROT]))?, items(&[1, 3, 4, 2]));
+        assert_eq!(eval(epoch, &ops(&[OP_2SWAP]))?, items(&[3, 4, 1, 2]));
+        assert_eq!(eval(epoch, &ops(&[OP_2OVER]))?, items(&[1, 2, 3, 4, 1, 2]));
+        assert_eq!(
+            eval(epoch, &ops(&[OP_3DUP]))?,
+            items(&[1, 2, 3, 4, 2, 3, 4])
+        );
+        assert_eq!(eval(epoch, &ops(&[OP_TUCK]))?, items(&[1, 2, 4, 3, 4]));
+        assert_eq!(
+            eval(epoch, &ops(&[OP_2, OP_PICK]))?,
+            items(&[1, 2, 3, 4, 2])
+        );
+        assert_eq!(eval(epoch, &ops(&[OP_3, OP_ROLL]))?, items(&[2, 3, 4, 1]));
+        assert_eq!(
+            eval(epoch, &ops(&[OP_4, OP_ROLL])),
+            Err(ScriptError::InvalidIndex(OP_ROLL)),
+        );
+        assert_eq!(
+            eval(epoch, &ops(&[OP_TOALTSTACK, OP_DEPTH, OP_FROMALTSTACK]))?,
+            items(&[1, 2, 3, 3, 4]),
+        );
+        assert_eq!(
+            eval(epoch, &ops(&[OP_CAT, OP_CAT, OP_CAT, OP_1, OP_SPLIT]))?,
+            [vec![1], vec![2, 3, 4]],
+        );
+        assert_eq!(
+            eval(epoch, &ops(&[OP_CAT, OP_REVERSEBYTES]))?,
+            [vec![1], vec![2], vec![4, 3]],
+        );
+
+        let num2bin = script(|script| {
+            script.push_int(-5);
+            script.push_int(4);
+            script.put_opcodes([OP_NUM2BIN, OP_DUP, OP_BIN2NUM]);
+        });
+        assert_eq!(
+            eval(epoch, &num2bin)?,
+            [vec![0x05, 0x00, 0x00, 0x80], vec![0x85]],
+        );
+        assert_eq!(
+            eval(epoch, &Script::new(vec![OP_DROP::N].into())),
+            Err(ScriptError::InvalidStackOperation),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_conditionals() -> Result<(), ScriptError> {
+        let epoch = UpgradeEpoch::LATEST;
+        let run = |opcodes: &[Opcode]| {
+            eval(
+                epoch,
+                &script(|script| script.put_opcodes(opcodes.iter().copied())),
+            )
+        };
+        assert_eq!(
+            run(&[OP_0, OP_IF, OP_RETURN, OP_ELSE, OP_7, OP_ENDIF])?,
+            [vec![7]],
+        );
+        assert_eq!(
+            run(&[OP_1, OP_NOTIF, OP_RESERVED, OP_0, OP_IF, OP_ENDIF, OP_ENDIF])?,
+            Vec::<Vec<u8>>::new(),
+        );
+        assert_eq!(
+            eval(
+                UpgradeEpoch::Upgrade11,
+                &script(|script| script.put_opcodes([OP_0, OP_IF, OP_VERIF, OP_ENDIF])),
+            ),
+            Err(ScriptError::DisabledOpcode(OP_VERIF)),
+        );
+        assert_eq!(run(&[OP_1, OP_IF]), Err(ScriptError::UnbalancedConditional));
+        assert_eq!(run(&[OP_ENDIF]), Err(ScriptError::UnbalancedConditional));
+        assert_eq!(
+            run(&[OP_RESERVED]),
+            Err(ScriptError::BadOpcode(OP_RESERVED))
+        );
+        assert_eq!(run(&[OP_0, OP_VERIFY]), Err(ScriptError::Verify(OP_VERIFY)));
+        assert_eq!(run(&[OP_INPUTINDEX]).map(|_| ()), Ok(()),);
+        assert_eq!(
+            eval(
+                UpgradeEpoch::Legacy,
+                &Script::new(vec![OP_INPUTINDEX::N].into())
+            ),
+            Err(ScriptError::BadOpcode(OP_INPUTINDEX)),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_loops_and_functions() -> Result<(), ScriptError> {
+        use self::UpgradeEpoch::*;
+        // Sum 1..=5: <sum> <i>, loop until i reaches 0
+        let sum = script(|script| {
+            script.put_opcodes([OP_0, OP_5, OP_BEGIN, OP_TUCK, OP_ADD, OP_SWAP, OP_1SUB]);
+            script.put_opcodes([OP_DUP, OP_NOT, OP_UNTIL, OP_DROP]);
+        });
+        assert_eq!(eval(Upgrade12, &sum)?, [vec![15]]);
+        assert_eq!(
+            eval(Upgrade11, &sum),
+            Err(ScriptError::DisabledOpcode(OP_BEGIN))
+        );
+        let run = |opcodes: &[Opcode]| {
+            eval(
+                Upgrade12,
+                &script(|script| script.put_opcodes(opcodes.iter().copied())),
+            )
+        };
+        assert_eq!(
+            run(&[OP_0, OP_IF, OP_BEGIN, OP_ENDIF, OP_UNTIL]),
+            Err(ScriptError::UnbalancedConditional),
+        );
+        assert_eq!(run(&[OP_BEGIN]), Err(ScriptError::UnbalancedConditional));
+        // Infinite loops run out of operation cost
+        assert!(matches!(
+            run(&[OP_BEGIN, OP_0, OP_UNTIL]),
+            Err(ScriptError::OpCost { .. }),
+        ));
+        assert_eq!(run(&[OP_BEGIN; 101]), Err(ScriptError::ControlStackDepth));
+
+        // Define a function squaring the top item, and invoke it twice
+        let square = script(|script| {
+            script.push_data(&[OP_DUP::N, OP_MUL::N]);
+            script.put_opcodes([OP_7, OP_DEFINE, OP_3, OP_7, OP_INVOKE, OP_7, OP_INVOKE]);
+        });
+        assert_eq!(eval(Upgrade12, &square)?, [vec![81]]);
+        assert_eq!(
+            eval(Upgrade11, &square),
+            Err(ScriptError::BadOpcode(OP_DEFINE))
+        );
+        assert_eq!(
+            run(&[OP_0, OP_7, OP_DEFINE, OP_0, OP_7, OP_DEFINE]),
+            Err(ScriptError::FunctionRedefined(vec![7])),
+        );
+        assert_eq!(
+            run(&[OP_7, OP_INVOKE]),
+            Err(ScriptError::UndefinedFunction(vec![7])),
+        );
+        let long_id = script(|script| {
+            script.put_opcodes([OP_0]);
+            script.push_data(&[1; 8]);
+            script.put_opcodes([OP_DEFINE]);
+        });
+        assert_eq!(
+            eval(Upgrade12, &long_id),
+            Err(ScriptError::InvalidFunctionId(8)),
+        );
+        // Function bodies must balance their conditionals
+        let unbalanced = script(|script| {
+            script.push_data(&[OP_IF::N]);
+            script.put_opcodes([OP_0, OP_DEFINE, OP_1, OP_0, OP_INVOKE, OP_ENDIF]);
+        });
+        assert_eq!(
+            eval(Upgrade12, &unbalanced),
+            Err(ScriptError::UnbalancedConditional),
+        );
+        // Recursion is limited by the control stack depth
+        let recursion = script(|script| {
+            script.push_data(&[OP_0::N, OP_INVOKE::N]);
+            script.put_opcodes([OP_0, OP_DEFINE, OP_0, OP_INVOKE]);
+        });
+        assert_eq!(
+            eval(Upgrade12, &recursion),
+            Err(ScriptError::ControlStackDepth),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitwise() -> Result<(), ScriptError> {
+        use self::UpgradeEpoch::*;
+        let shift = |data: &[u8], n: i64, opcode: Opcode| {
+            script(|script| {
+                script.push_data(data);
+                script.push_int(n);
+                script.put_opcodes([opcode]);
+            })
+        };
+        let invert = script(|script| {
+            script.push_data(&[0x0f, 0xa5]);
+            script.put_opcodes([OP_INVERT]);
+        });
+        assert_eq!(eval(Upgrade12, &invert)?, [vec![0xf0, 0x5a]]);
+        assert_eq!(
+            eval(Upgrade11, &invert),
+            Err(ScriptError::DisabledOpcode(OP_INVERT))
+        );
+
+        let bin = [0x81, 0x42];
+        assert_eq!(
+            eval(Upgrade12, &shift(&bin, 0, OP_LSHIFTBIN))?,
+            [bin.to_vec()]
+        );
+        assert_eq!(
+            eval(Upgrade12, &shift(&bin, 1, OP_LSHIFTBIN))?,
+            [vec![0x02, 0x84]]
+        );
+        assert_eq!(
+            eval(Upgrade12, &shift(&bin, 9, OP_LSHIFTBIN))?,
+            [vec![0x84, 0x00]]
+        );
+        assert_eq!(
+            eval(Upgrade12, &shift(&bin, 3, OP_RSHIFTBIN))?,
+            [vec![0x10, 0x28]]
+        );
+        assert_eq!(
+            eval(Upgrade12, &shift(&bin, 8, OP_RSHIFTBIN))?,
+            [vec![0x00, 0x81]]
+        );
+        assert_eq!(
+            eval(Upgrade12, &shift(&bin, 99, OP_RSHIFTBIN))?,
+            [vec![0, 0]]
+        );
+        assert_eq!(
+            eval(Upgrade12, &shift(&bin, -1, OP_RSHIFTBIN)),
+            Err(ScriptError::InvalidIndex(OP_RSHIFTBIN)),
+        );
+        assert_eq!(
+            eval(Upgrade11, &shift(&bin, 1, OP_LSHIFTBIN)),
+            Err(ScriptError::DisabledOpcode(OP_LSHIFT)),
+        );
+
+        // 5 << 4 = 80, -5 >> 1 = -3 (rounding down)
+        assert_eq!(eval(Upgrade12, &shift(&[5], 4, OP_LSHIFTNUM))?, [vec![80]]);
+        assert_eq!(
+            eval(Upgrade12, &shift(&[0x85], 1, OP_RSHIFTNUM))?,
+            [vec![0x83]]
+        );
+        assert_eq!(
+            eval(Upgrade12, &shift(&[0x85], 9, OP_RSHIFTNUM))?,
+            [vec![0x81]]
+        );
+        assert_eq!(
+            eval(Upgrade12, &shift(&[1], 80_000, OP_LSHIFTNUM)),
+            Err(ScriptNumError::Overflow.into()),
+        );
+        assert_eq!(
+            eval(Upgrade12, &shift(&[], 80_000, OP_LSHIFTNUM))?,
+            [Vec::<u8>::new()],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_vm_limits() -> Result<(), ScriptError> {
+        use self::UpgradeEpoch::*;
+        let large_push = script(|script| script.push_data(&[1; 521]));
+        assert_eq!(
+            eval(Upgrade10, &large_push),
+            Err(ScriptError::ElementSize {
+                size: 521,
+                max_size: 520,
+            }),
+        );
+        assert_eq!(eval(Upgrade11, &large_push)?, [vec![1; 521]]);
+
+        let nops = script(|script| script.put_opcodes([OP_NOP; 202]));
+        assert_eq!(eval(Upgrade10, &nops), Err(ScriptError::TooManyOps(201)));
+        assert_eq!(eval(Upgrade11, &nops)?, Vec::<Vec<u8>>::new());
+
+        // Empty scriptSig: budget of 41 * 800, 100 per instruction
+        let nops = script(|script| script.put_opcodes([OP_NOP; 329]));
+        assert_eq!(
+            eval(Upgrade11, &nops),
+            Err(ScriptError::OpCost {
+                op_cost: 32_900,
+                max_op_cost: 32_800,
+            }),
+        );
+        assert_eq!(eval(Upgrade10, &nops), Err(ScriptError::TooManyOps(201)));
+
+        // Non-standard: limit of 41 * 7 / 2 = 143 hash iterations
+        let hashes = script(|script| {
+            script.put_opcodes([OP_0]);
+            script.put_opcodes([OP_SHA256; 144]);
+        });
+        assert_eq!(
+            eval(Upgrade11, &hashes),
+            Err(ScriptError::HashIterations {
+                hash_iterations: 144,
+                max_hash_iterations: 143,
+            }),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_input() -> Result<(), ScriptError> {
+        let pubkey = [[0x02].as_ref(), &[0x77; 32]].concat();
+        let p2pkh = Script::p2pkh(&ShaRmd160::digest(&pubkey));
+        let script_sig = |sig: &[u8]| {
+            script(|script| {
+                script.push_data(sig);
+                script.push_data(&pubkey);
+            })
+        };
+        assert_eq!(
+            verify(script_sig(&schnorr_sig(0x77)), p2pkh.clone(), true)?,
+            1
+        );
+        assert_eq!(
+            verify(script_sig(&ecdsa_sig(0x77)), p2pkh.clone(), true)?,
+            1
+        );
+        assert_eq!(
+            verify(script_sig(&schnorr_sig(0x66)), p2pkh.clone(), true),
+            Err(ScriptError::NullFail),
+        );
+        assert_eq!(
+            verify(script_sig(&[]), p2pkh.clone(), true),
+            Err(ScriptError::EvalFalse),
+        );
+        let extra_push = script(|script| {
+            script.put_opcodes([OP_1]);
+            script.put_bytecode(script_sig(&schnorr_sig(0x77)).bytecode());
+        });
+        assert_eq!(verify(extra_push.clone(), p2pkh.clone(), false)?, 1);
+        assert_eq!(
+            verify(extra_push, p2pkh, true),
+            Err(ScriptError::CleanStack),
+        );
+
+        // 2-of-3 P2SH multisig
+        let pubkeys = (1..=3)
+            .map(|idx| {
+                let mut pubkey = [idx; 33];
+                pubkey[0] = 0x02;
+                PubKeyVariant::Compressed(PubKey(pubkey))
+            })
+            .collect::<Vec<_>>();
+        let redeem_script = Script::multisig(2, &pubkeys);
+        let p2sh = Script::p2sh(&ShaRmd160::digest(&redeem_script));
+        let spend = |mode, sigs: &[(usize, Vec<u8>)]| {
+            let script_sig = Script::multisig_script_sig(mode, 3, sigs);
+            verify(
+                Script::p2sh_script_sig(&script_sig, &redeem_script),
+                p2sh.clone(),
+                true,
+            )
+        };
+        let schnorr = [(0, schnorr_sig(1)), (2, schnorr_sig(3))];
+        assert_eq!(spend(MultisigMode::Schnorr, &schnorr)?, 2);
+        let ecdsa = [(0, ecdsa_sig(1)), (2, ecdsa_sig(3))];
+        assert_eq!(spend(MultisigMode::Legacy, &ecdsa)?, 3);
+        // Signatures in the wrong order
+        let ecdsa = [(0, ecdsa_sig(3)), (2, ecdsa_sig(1))];
+        assert_eq!(
+            spend(MultisigMode::Legacy, &ecdsa),
+            Err(ScriptError::NullFail)
+        );
+        assert_eq!(
+            spend(MultisigMode::Legacy, &schnorr),
+            Err(ScriptError::MultisigSigAlgorithm),
+        );
+        // Bitfield doesn't match the signing pubkeys
+        let schnorr = [(0, schnorr_sig(1)), (1, schnorr_sig(3))];
+        assert_eq!(
+            spend(MultisigMode::Schnorr, &schnorr),
+            Err(ScriptError::NullFail)
+        );
+        Ok(())
+    }
+}
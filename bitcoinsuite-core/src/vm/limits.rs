@@ -0,0 +1,151 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+pub use crate::script::MAX_SCRIPT_SIZE;
+
+use crate::consensus::ConsensusParams;
+
+/// Maximum number of items on the stack and alt stack combined (consensus).
+pub const MAX_STACK_SIZE: usize = 1_000;
+
+/// Maximum number of non-push opcodes per script, before the May 2025
+/// upgrade replaced it by the operation cost limit.
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+
+/// Maximum nesting depth of conditionals, as of the May 2025 upgrade; as of
+/// May 2026, loops and function invocations count towards it too.
+pub const MAX_CONTROL_STACK_DEPTH: usize = 100;
+
+/// Maximum size of a function identifier of `OP_DEFINE`/`OP_INVOKE`, in
+/// bytes (May 2026).
+pub const MAX_FUNCTION_ID_LENGTH: usize = 7;
+
+/// Operation cost of every evaluated instruction.
+pub const BASE_INSTRUCTION_COST: u64 = 100;
+
+/// Operation cost of every signature check.
+pub const SIG_CHECK_COST: u64 = 26_000;
+
+/// Operation cost of every hash digest iteration in standard txs.
+pub const HASH_ITERATION_COST_STANDARD: u64 = 192;
+
+/// Operation cost of every hash digest iteration in non-standard txs.
+pub const HASH_ITERATION_COST_NONSTANDARD: u64 = 64;
+
+/// Added to the unlocking bytecode length to get the density control length.
+const DENSITY_CONTROL_BASE_LENGTH: u64 = 41;
+
+/// Operation cost budget per byte of density control length.
+const OP_COST_BUDGET_PER_BYTE: u64 = 800;
+
+/// Limits the VM enforces when evaluating an input, see [`VmLimits::new`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct VmLimits {
+    /// Maximum size of an element on the stack, in bytes.
+    pub max_script_element_size: usize,
+    /// Maximum length of a number in the VM, in bytes.
+    pub max_script_num_length: usize,
+    /// Maximum number of non-push opcodes per script, before May 2025.
+    pub max_ops_per_script: Option<usize>,
+    /// Maximum composite operation cost of the input, as of May 2025, see
+    /// [`VmMetrics::composite_op_cost`].
+    pub max_op_cost: Option<u64>,
+    /// Maximum number of hash digest iterations of the input, as of May
+    /// 2025.
+    pub max_hash_iterations: Option<u64>,
+    /// Operation cost of a hash digest iteration.
+    pub hash_iteration_cost: u64,
+}
+
+/// Resources used by the evaluation of an input, accounted against
+/// [`VmLimits`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct VmMetrics {
+    /// Operation cost of instructions, pushed bytes and arithmetic, without
+    /// hashing and signature checks.
+    pub op_cost: u64,
+    /// Number of hash digest iterations, see [`hash_iterations`].
+    pub hash_iterations: u64,
+    /// Number of signature checks.
+    pub sig_checks: u64,
+}
+
+impl VmLimits {
+    /// Limits for an input with `unlocking_len` bytes of unlocking bytecode
+    /// (scriptSig), under the rules of `params`.
+    ///
+    /// As of the May 2025 upgrade, the operation cost and hashing budgets
+    /// grow with the unlocking bytecode ("density control length"), and
+    /// hashing is limited and priced more strictly in standard txs.
+    /// ```
+    /// # use bitcoinsuite_core::{consensus::UpgradeEpoch, vm::VmLimits};
+    /// let limits = VmLimits::new(&UpgradeEpoch::Upgrade11.params(), 100, true);
+    /// assert_eq!(limits.max_script_element_size, 10_000);
+    /// assert_eq!(limits.max_op_cost, Some(141 * 800));
+    /// assert_eq!(limits.max_hash_iterations, Some(70));
+    /// assert_eq!(limits.max_ops_per_script, None);
+    ///
+    /// let limits = VmLimits::new(&UpgradeEpoch::Upgrade11.params(), 100, false);
+    /// assert_eq!(limits.max_hash_iterations, Some(493));
+    ///
+    /// let limits = VmLimits::new(&UpgradeEpoch::Upgrade10.params(), 100, true);
+    /// assert_eq!(limits.max_script_element_size, 520);
+    /// assert_eq!(limits.max_script_num_length, 8);
+    /// assert_eq!(limits.max_ops_per_script, Some(201));
+    /// assert_eq!(limits.max_op_cost, None);
+    /// ```
+    pub fn new(params: &ConsensusParams, unlocking_len: usize, is_standard: bool) -> VmLimits {
+        let density_control_length = DENSITY_CONTROL_BASE_LENGTH + unlocking_len as u64;
+        let max_hash_iterations = match is_standard {
+            true => density_control_length / 2,
+            false => density_control_length * 7 / 2,
+        };
+        VmLimits {
+            max_script_element_size: params.max_script_element_size,
+            max_script_num_length: params.max_script_num_length,
+            max_ops_per_script: (!params.vm_limits).then(|| MAX_OPS_PER_SCRIPT),
+            max_op_cost: params
+                .vm_limits
+                .then(|| density_control_length * OP_COST_BUDGET_PER_BYTE),
+            max_hash_iterations: params.vm_limits.then(|| max_hash_iterations),
+            hash_iteration_cost: match is_standard {
+                true => HASH_ITERATION_COST_STANDARD,
+                false => HASH_ITERATION_COST_NONSTANDARD,
+            },
+        }
+    }
+}
+
+impl VmMetrics {
+    /// Total operation cost, including hashing and signature checks, which
+    /// must not exceed [`VmLimits::max_op_cost`].
+    /// ```
+    /// # use bitcoinsuite_core::{consensus::ConsensusParams, vm::{VmLimits, VmMetrics}};
+    /// let limits = VmLimits::new(&ConsensusParams::latest(), 0, true);
+    /// let metrics = VmMetrics { op_cost: 300, hash_iterations: 2, sig_checks: 1 };
+    /// assert_eq!(metrics.composite_op_cost(&limits), 300 + 2 * 192 + 26_000);
+    /// ```
+    pub fn composite_op_cost(&self, limits: &VmLimits) -> u64 {
+        self.op_cost
+            .saturating_add(
+                self.hash_iterations
+                    .saturating_mul(limits.hash_iteration_cost),
+            )
+            .saturating_add(self.sig_checks.saturating_mul(SIG_CHECK_COST))
+    }
+}
+
+/// Number of digest iterations (64 byte blocks) to hash a message of
+/// `message_len` bytes with SHA-256, SHA-1 or RIPEMD-160, plus one if the
+/// result is hashed again (e.g. for `OP_HASH160` and `OP_HASH256`).
+/// ```
+/// # use bitcoinsuite_core::vm::hash_iterations;
+/// assert_eq!(hash_iterations(0, false), 1);
+/// assert_eq!(hash_iterations(55, false), 1);
+/// assert_eq!(hash_iterations(56, false), 2);
+/// assert_eq!(hash_iterations(56, true), 3);
+/// ```
+pub fn hash_iterations(message_len: usize, is_two_round: bool) -> u64 {
+    1 + (message_len as u64 + 8) / 64 + u64::from(is_two_round)
+}
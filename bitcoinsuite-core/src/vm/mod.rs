@@ -0,0 +1,13 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+//! Module for evaluating scripts, e.g. [`ScriptExecutionContext`].
+
+mod context;
+mod interpreter;
+mod limits;
+mod num;
+pub use self::context::*;
+pub use self::interpreter::*;
+pub use self::limits::*;
@@ -0,0 +1,150 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+#[cfg(feature = "bigint")]
+use crate::script::BigScriptNum;
+use crate::script::{ScriptNum, ScriptNumError};
+
+/// Number operated on by the arithmetic opcodes of the interpreter.
+///
+/// With the `bigint` feature, this has arbitrary precision, so the numbers
+/// of every epoch can be evaluated. Otherwise, it's a [`ScriptNum`], which
+/// covers all epochs before the May 2025 upgrade; in later epochs, operands
+/// and results are limited to [`ScriptNum::MAX_LEN`] bytes.
+#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) struct VmNum(Inner);
+
+#[cfg(feature = "bigint")]
+type Inner = BigScriptNum;
+#[cfg(not(feature = "bigint"))]
+type Inner = ScriptNum;
+
+#[cfg(feature = "bigint")]
+type BinaryOp = fn(&BigScriptNum, &BigScriptNum) -> Result<BigScriptNum, ScriptNumError>;
+#[cfg(not(feature = "bigint"))]
+type BinaryOp = fn(ScriptNum, ScriptNum) -> Result<ScriptNum, ScriptNumError>;
+
+impl VmNum {
+    /// Decode a minimally encoded number.
+    pub(crate) fn decode(bytes: &[u8]) -> Result<VmNum, ScriptNumError> {
+        Ok(VmNum(Inner::decode(bytes)?))
+    }
+
+    /// Convert to a [`ScriptNum`], failing outside of the 8-byte range.
+    #[cfg(feature = "bigint")]
+    pub(crate) fn to_script_num(&self) -> Result<ScriptNum, ScriptNumError> {
+        self.0.to_script_num()
+    }
+
+    /// Convert to a [`ScriptNum`], failing outside of the 8-byte range.
+    #[cfg(not(feature = "bigint"))]
+    pub(crate) fn to_script_num(&self) -> Result<ScriptNum, ScriptNumError> {
+        Ok(self.0)
+    }
+
+    /// Minimal encoding of this number.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        self.0.encode()
+    }
+
+    /// Number of bytes of the encoding.
+    pub(crate) fn encoded_len(&self) -> usize {
+        match self.bits() {
+            0 => 0,
+            // The sign takes one bit of the last byte
+            bits => bits as usize / 8 + 1,
+        }
+    }
+
+    /// `self + other`.
+    pub(crate) fn checked_add(&self, other: &VmNum) -> Result<VmNum, ScriptNumError> {
+        self.apply(other, Inner::checked_add)
+    }
+
+    /// `self - other`.
+    pub(crate) fn checked_sub(&self, other: &VmNum) -> Result<VmNum, ScriptNumError> {
+        self.apply(other, Inner::checked_sub)
+    }
+
+    /// `self * other`.
+    pub(crate) fn checked_mul(&self, other: &VmNum) -> Result<VmNum, ScriptNumError> {
+        self.apply(other, Inner::checked_mul)
+    }
+
+    /// `self / other`, rounded towards zero like `OP_DIV`.
+    pub(crate) fn checked_div(&self, other: &VmNum) -> Result<VmNum, ScriptNumError> {
+        self.apply(other, Inner::checked_div)
+    }
+
+    /// Remainder of `self / other`, with the sign of `self` like `OP_MOD`.
+    pub(crate) fn checked_rem(&self, other: &VmNum) -> Result<VmNum, ScriptNumError> {
+        self.apply(other, Inner::checked_rem)
+    }
+
+    #[cfg(feature = "bigint")]
+    fn apply(&self, other: &VmNum, op: BinaryOp) -> Result<VmNum, ScriptNumError> {
+        Ok(VmNum(op(&self.0, &other.0)?))
+    }
+
+    #[cfg(not(feature = "bigint"))]
+    fn apply(&self, other: &VmNum, op: BinaryOp) -> Result<VmNum, ScriptNumError> {
+        Ok(VmNum(op(self.0, other.0)?))
+    }
+
+    /// Number of bits of the magnitude.
+    #[cfg(feature = "bigint")]
+    pub(crate) fn bits(&self) -> u64 {
+        self.0 .0.bits()
+    }
+
+    /// Number of bits of the magnitude.
+    #[cfg(not(feature = "bigint"))]
+    pub(crate) fn bits(&self) -> u64 {
+        u64::from(64 - self.0 .0.unsigned_abs().leading_zeros())
+    }
+
+    /// `self * 2^shift`; callers limit the size of the result first.
+    #[cfg(feature = "bigint")]
+    pub(crate) fn checked_shl(&self, shift: usize) -> Result<VmNum, ScriptNumError> {
+        Ok(VmNum(BigScriptNum(&self.0 .0 << shift)))
+    }
+
+    /// `self * 2^shift`, failing outside of the 8-byte range.
+    #[cfg(not(feature = "bigint"))]
+    pub(crate) fn checked_shl(&self, shift: usize) -> Result<VmNum, ScriptNumError> {
+        match self.bits() {
+            0 => Ok(VmNum::default()),
+            bits if bits + shift as u64 >= 64 => Err(ScriptNumError::Overflow),
+            _ => Ok(VmNum(ScriptNum(self.0 .0 << shift))),
+        }
+    }
+
+    /// `self / 2^shift`, rounded towards negative infinity like an
+    /// arithmetic shift.
+    #[cfg(feature = "bigint")]
+    pub(crate) fn shr(&self, shift: usize) -> VmNum {
+        VmNum(BigScriptNum(&self.0 .0 >> shift))
+    }
+
+    /// `self / 2^shift`, rounded towards negative infinity like an
+    /// arithmetic shift.
+    #[cfg(not(feature = "bigint"))]
+    pub(crate) fn shr(&self, shift: usize) -> VmNum {
+        VmNum(ScriptNum(self.0 .0 >> shift.min(63)))
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl From<i64> for VmNum {
+    fn from(num: i64) -> Self {
+        VmNum(ScriptNum(num).into())
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+impl From<i64> for VmNum {
+    fn from(num: i64) -> Self {
+        VmNum(ScriptNum(num))
+    }
+}
@@ -0,0 +1,598 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use std::ops::Range;
+
+use thiserror::Error;
+
+use crate::{
+    policy::PolicyConfig,
+    script::Script,
+    ser::{BitcoinSer, CompactUint},
+    tx::{
+        CashToken, Coin, FeeRate, OutPoint, Output, Tx, TxId, UnlockingTemplate,
+        MAXIMUM_TOKEN_AMOUNT,
+    },
+    wallet::{
+        chain::{PendingTx, TxChainError, OUTPUT_COUNT_RESERVE},
+        TxBuilder, TxBuilderError, TxBuilderInput, UnsignedTx, DEFAULT_SEQUENCE,
+    },
+};
+
+/// Recipient of an [`Airdrop`].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct AirdropRecipient {
+    /// Script receiving the tokens.
+    pub script: Script,
+    /// Amount of fungible tokens sent, must be at least 1.
+    pub token_amount: u64,
+    /// Sats sent alongside the tokens; raised to the dust threshold of the
+    /// output if lower.
+    pub sats: u64,
+}
+
+/// Parameters of the txs built by [`Airdrop`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct AirdropParams {
+    /// Script receiving token and BCH change, which funds the next tx of the
+    /// chain.
+    pub change_script: Script,
+    /// How change outputs are unlocked when spent by the next tx.
+    pub change_template: UnlockingTemplate,
+    /// Fee rate of the txs.
+    pub fee_rate: FeeRate,
+    /// Maximum estimated size of each tx once signed.
+    pub max_tx_size: usize,
+}
+
+/// Costs of an [`Airdrop`], known before any tx is built.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct AirdropPlan {
+    /// Number of recipients of each tx of the chain.
+    pub batch_sizes: Vec<usize>,
+    /// Estimated fee of all txs together; an upper bound if any template
+    /// has a variable size.
+    pub total_fee: u64,
+    /// Sats sent to recipients, including `dust_sats`.
+    pub recipient_sats: u64,
+    /// Sats added to recipient outputs so they aren't dust.
+    pub dust_sats: u64,
+    /// Sats of the token change output left by the last tx, if any tokens
+    /// are left over.
+    pub token_change_sats: u64,
+    /// Sats required from the funding coins: recipients, token change and
+    /// fees.
+    pub required_sats: u64,
+    /// Tokens required from the funding coins.
+    pub required_tokens: u64,
+}
+
+/// Sends fungible tokens of a category to many recipients, over as many txs
+/// as needed to keep each one under [`AirdropParams::max_tx_size`].
+///
+/// The first tx spends all funding coins; each following tx spends the token
+/// change and BCH change of the previous one. The costs are planned when the
+/// airdrop is created, see [`Airdrop::plan`], so an airdrop that can't be
+/// funded fails before anything is built.
+///
+/// As the txid of a tx is only known once it is signed, txs are built one at
+/// a time: build the next tx with [`Airdrop::next_tx`], sign it and pass it
+/// to [`Airdrop::advance`].
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     hash::ShaRmd160,
+/// #     script::Script,
+/// #     ser::CompactUint,
+/// #     tx::{CashToken, Coin, OutPoint, Output, TxId, UnlockingTemplate},
+/// #     wallet::{Airdrop, AirdropParams, AirdropRecipient, TxBuilderInput, UnsignedTx},
+/// # };
+/// let my_script = Script::p2pkh(&ShaRmd160([1; 20]));
+/// let category = TxId::from([7; 32]);
+/// let input = |txid: u8, value: u64, token: Option<CashToken>| TxBuilderInput {
+///     prev_out: OutPoint { txid: TxId::from([txid; 32]), outpoint_index: 0 },
+///     sequence: 0xffff_ffff,
+///     coin: Coin {
+///         output: Output { value, script: my_script.clone(), token },
+///         ..Default::default()
+///     },
+///     template: UnlockingTemplate::P2pkhSchnorr,
+/// };
+/// let funding = vec![
+///     input(1, 1_000, Some(CashToken { amount: CompactUint(1_000_000), category, nft: None })),
+///     input(2, 2_000_000, None),
+/// ];
+/// let recipients = (0..1000u32)
+///     .map(|idx| AirdropRecipient {
+///         script: Script::p2pkh(&ShaRmd160([idx as u8; 20])),
+///         token_amount: 100,
+///         sats: 0,
+///     })
+///     .collect();
+/// let params = AirdropParams {
+///     max_tx_size: 10_000,
+///     ..AirdropParams::new(my_script.clone(), UnlockingTemplate::P2pkhSchnorr)
+/// };
+/// let mut airdrop = Airdrop::new(category, funding, recipients, params).unwrap();
+/// let plan = airdrop.plan().clone();
+/// assert_eq!(plan.batch_sizes.len(), 8);
+/// assert_eq!(plan.required_tokens, 100_000);
+///
+/// let sign = |_: &UnsignedTx, _: usize| Ok::<_, ()>(Script::new(vec![0; 100].into()));
+/// let mut total_fee = 0;
+/// while let Some(unsigned_tx) = airdrop.next_tx().unwrap() {
+///     total_fee += unsigned_tx.fee;
+///     let tx = unsigned_tx.sign(&sign).unwrap();
+///     airdrop.advance(&tx).unwrap();
+/// }
+/// assert_eq!(airdrop.num_remaining(), 0);
+/// assert!(total_fee <= plan.total_fee);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Airdrop {
+    category: TxId,
+    params: AirdropParams,
+    plan: AirdropPlan,
+    funding: Vec<TxBuilderInput>,
+    recipients: Vec<AirdropRecipient>,
+    num_batches_sent: usize,
+    num_sent: usize,
+    pending: PendingTx<()>,
+}
+
+/// Errors when planning or building an [`Airdrop`].
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum AirdropError {
+    /// A funding coin has an NFT or tokens of another category.
+    #[error("Funding coin {prev_out:?} has tokens other than those airdropped")]
+    InvalidFundingCoin {
+        /// Outpoint of the coin.
+        prev_out: OutPoint,
+    },
+
+    /// A recipient doesn't receive any tokens.
+    #[error("Recipient {idx} doesn't receive any tokens")]
+    NoTokens {
+        /// Index of the recipient.
+        idx: usize,
+    },
+
+    /// Not even a single recipient fits into a tx of the maximum size.
+    #[error("No recipient fits into a tx of at most {max_tx_size} bytes")]
+    TxSizeTooSmall {
+        /// Maximum size of a tx.
+        max_tx_size: usize,
+    },
+
+    /// The funding coins don't have enough sats for the airdrop.
+    #[error("Insufficient funds: have {available} sats, need {required} sats")]
+    InsufficientFunds {
+        /// Sats of the funding coins.
+        available: u64,
+        /// Sats required, see [`AirdropPlan::required_sats`].
+        required: u64,
+    },
+
+    /// The funding coins don't have enough tokens for the airdrop.
+    #[error("Insufficient tokens: have {available}, need {required}")]
+    InsufficientTokens {
+        /// Tokens of the funding coins.
+        available: u64,
+        /// Tokens sent to all recipients.
+        required: u64,
+    },
+
+    /// Building a tx failed.
+    #[error("Building tx failed: {0}")]
+    Build(TxBuilderError),
+
+    /// [`Airdrop::advance`] was called without a tx from
+    /// [`Airdrop::next_tx`].
+    #[error("No tx is waiting to be signed")]
+    NoPendingTx,
+
+    /// The signed tx doesn't match the tx built by [`Airdrop::next_tx`].
+    #[error("Signed tx {txid} doesn't match the built tx")]
+    TxMismatch {
+        /// TxId of the signed tx.
+        txid: TxId,
+    },
+}
+
+impl TxChainError for AirdropError {
+    fn no_pending_tx() -> Self {
+        AirdropError::NoPendingTx
+    }
+
+    fn tx_mismatch(txid: TxId) -> Self {
+        AirdropError::TxMismatch { txid }
+    }
+}
+
+impl AirdropParams {
+    /// Params returning change to `change_script`, at 1 sat/byte and the
+    /// standard tx size limit.
+    pub fn new(change_script: Script, change_template: UnlockingTemplate) -> Self {
+        AirdropParams {
+            change_script,
+            change_template,
+            fee_rate: FeeRate::from_sats_per_byte(1),
+            max_tx_size: PolicyConfig::default().max_standard_tx_size,
+        }
+    }
+}
+
+impl Airdrop {
+    /// Plan an airdrop of tokens of `category` to `recipients`, funded by
+    /// the `funding` coins, which may only have fungible tokens of
+    /// `category`.
+    ///
+    /// Fails if the funding coins don't have enough sats or tokens for the
+    /// planned txs.
+    pub fn new(
+        category: TxId,
+        funding: Vec<TxBuilderInput>,
+        recipients: Vec<AirdropRecipient>,
+        params: AirdropParams,
+    ) -> Result<Self, AirdropError> {
+        let mut available_sats = 0u64;
+        let mut available_tokens = 0u64;
+        for input in &funding {
+            let output = &input.coin.output;
+            available_sats += output.value;
+            if let Some(token) = &output.token {
+                if token.category != category || token.nft.is_some() {
+                    return Err(AirdropError::InvalidFundingCoin {
+                        prev_out: input.prev_out,
+                    });
+                }
+                available_tokens = available_tokens.saturating_add(token.amount.0);
+            }
+        }
+        if let Some(idx) = recipients
+            .iter()
+            .position(|recipient| recipient.token_amount == 0)
+        {
+            return Err(AirdropError::NoTokens { idx });
+        }
+
+        let mut airdrop = Airdrop {
+            category,
+            params,
+            plan: AirdropPlan::default(),
+            funding,
+            recipients,
+            num_batches_sent: 0,
+            num_sent: 0,
+            pending: PendingTx::default(),
+        };
+        let plan = airdrop.make_plan(available_tokens)?;
+        if available_tokens < plan.required_tokens {
+            return Err(AirdropError::InsufficientTokens {
+                available: available_tokens,
+                required: plan.required_tokens,
+            });
+        }
+        if available_sats < plan.required_sats {
+            return Err(AirdropError::InsufficientFunds {
+                available: available_sats,
+                required: plan.required_sats,
+            });
+        }
+        airdrop.plan = plan;
+        Ok(airdrop)
+    }
+
+    /// Planned costs of the airdrop.
+    pub fn plan(&self) -> &AirdropPlan {
+        &self.plan
+    }
+
+    /// Number of recipients not sent to yet by a signed tx.
+    pub fn num_remaining(&self) -> usize {
+        self.recipients.len() - self.num_sent
+    }
+
+    /// Build the next tx of the chain, sending to the next batch of
+    /// recipients, or `None` if all recipients have been sent to.
+    ///
+    /// Building again before calling [`Airdrop::advance`] returns the same
+    /// tx.
+    pub fn next_tx(&mut self) -> Result<Option<UnsignedTx>, AirdropError> {
+        let batch = match self.batch(self.num_batches_sent) {
+            Some(batch) => batch,
+            None => return Ok(None),
+        };
+        let mut builder = self.builder(self.funding.clone());
+        for recipient in &self.recipients[batch] {
+            builder.add_output(self.recipient_output(recipient));
+        }
+        let unsigned_tx = builder.build().map_err(AirdropError::Build)?;
+        self.pending.set(unsigned_tx.clone(), ());
+        Ok(Some(unsigned_tx))
+    }
+
+    /// Continue the chain after `tx`, the signed tx built by
+    /// [`Airdrop::next_tx`]: its token and BCH change are spent by the next
+    /// tx.
+    pub fn advance(&mut self, tx: &Tx) -> Result<(), AirdropError> {
+        self.pending.take_signed::<AirdropError>(tx)?;
+        let batch_size = self.plan.batch_sizes[self.num_batches_sent];
+        // Change outputs come after the recipients
+        self.funding = (batch_size..tx.outputs.len())
+            .map(|output_idx| {
+                TxBuilderInput::from_tx_output(tx, output_idx, self.params.change_template)
+            })
+            .collect();
+        self.num_batches_sent += 1;
+        self.num_sent += batch_size;
+        Ok(())
+    }
+
+    /// Split the recipients into batches fitting into a tx and estimate the
+    /// costs of each tx.
+    fn make_plan(&self, available_tokens: u64) -> Result<AirdropPlan, AirdropError> {
+        let mut plan = AirdropPlan::default();
+        let token_change_output = Output {
+            value: 0,
+            script: self.params.change_script.clone(),
+            token: Some(CashToken {
+                amount: CompactUint(MAXIMUM_TOKEN_AMOUNT),
+                category: self.category,
+                nft: None,
+            }),
+        };
+        let bch_change_output = Output {
+            value: 0,
+            script: self.params.change_script.clone(),
+            token: None,
+        };
+        let change_size = token_change_output.ser_len() + bch_change_output.ser_len();
+
+        let mut recipients = self.recipients.iter().peekable();
+        while recipients.peek().is_some() {
+            let inputs = if plan.batch_sizes.is_empty() {
+                self.funding.clone()
+            } else {
+                let change_input = TxBuilderInput {
+                    prev_out: OutPoint::default(),
+                    sequence: DEFAULT_SEQUENCE,
+                    coin: Coin::default(),
+                    template: self.params.change_template,
+                };
+                vec![change_input; 2]
+            };
+            let mut builder = self.builder(inputs);
+            let mut size = builder.estimate_size() + OUTPUT_COUNT_RESERVE + change_size;
+            let mut batch_size = 0;
+            while let Some(recipient) = recipients.peek() {
+                let output = self.recipient_output(recipient);
+                size += output.ser_len();
+                if size > self.params.max_tx_size {
+                    break;
+                }
+                plan.required_tokens = plan.required_tokens.saturating_add(recipient.token_amount);
+                plan.recipient_sats += output.value;
+                plan.dust_sats += output.value - recipient.sats.min(output.value);
+                builder.add_output(output);
+                batch_size += 1;
+                recipients.next();
+            }
+            if batch_size == 0 {
+                return Err(AirdropError::TxSizeTooSmall {
+                    max_tx_size: self.params.max_tx_size,
+                });
+            }
+            plan.total_fee += self
+                .params
+                .fee_rate
+                .fee(builder.estimate_size() + change_size);
+            plan.batch_sizes.push(batch_size);
+        }
+
+        if available_tokens > plan.required_tokens {
+            let mut output = token_change_output;
+            output.token = Some(CashToken {
+                amount: CompactUint(available_tokens - plan.required_tokens),
+                category: self.category,
+                nft: None,
+            });
+            plan.token_change_sats = output.min_value();
+        }
+        plan.required_sats = plan.recipient_sats + plan.token_change_sats + plan.total_fee;
+        Ok(plan)
+    }
+
+    fn batch(&self, batch_idx: usize) -> Option<Range<usize>> {
+        let batch_size = *self.plan.batch_sizes.get(batch_idx)?;
+        Some(self.num_sent..self.num_sent + batch_size)
+    }
+
+    fn builder(&self, inputs: Vec<TxBuilderInput>) -> TxBuilder {
+        TxBuilder {
+            inputs,
+            change_script: Some(self.params.change_script.clone()),
+            fee_rate: self.params.fee_rate,
+            ..Default::default()
+        }
+    }
+
+    fn recipient_output(&self, recipient: &AirdropRecipient) -> Output {
+        let mut output = Output {
+            value: recipient.sats,
+            script: recipient.script.clone(),
+            token: Some(CashToken {
+                amount: CompactUint(recipient.token_amount),
+                category: self.category,
+                nft: None,
+            }),
+        };
+        output.value = output.value.max(output.min_value());
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        hash::ShaRmd160,
+        script::Script,
+        ser::{BitcoinSer, CompactUint},
+        tx::{
+            Capability, CashToken, Commitment, NonFungibleTokenCapability, TxId, UnlockingTemplate,
+            NFT,
+        },
+        wallet::{
+            chain::PendingTx,
+            test_util::{self, script, sign},
+            Airdrop, AirdropError, AirdropParams, AirdropRecipient, TxBuilderInput,
+        },
+    };
+
+    const CATEGORY: TxId = TxId::ZERO;
+
+    fn input(txid: u8, value: u64, token_amount: u64) -> TxBuilderInput {
+        let token = (token_amount > 0).then(|| CashToken {
+            amount: CompactUint(token_amount),
+            category: CATEGORY,
+            nft: None,
+        });
+        test_util::input(txid, value, token)
+    }
+
+    fn recipients(num: u8, sats: u64) -> Vec<AirdropRecipient> {
+        (0..num)
+            .map(|idx| AirdropRecipient {
+                script: Script::p2pkh(&ShaRmd160([idx; 20])),
+                token_amount: idx as u64 + 1,
+                sats,
+            })
+            .collect()
+    }
+
+    fn params() -> AirdropParams {
+        AirdropParams {
+            max_tx_size: 2_000,
+            ..AirdropParams::new(script(), UnlockingTemplate::P2pkhSchnorr)
+        }
+    }
+
+    #[test]
+    fn test_airdrop() {
+        let funding = vec![
+            input(1, 800, 3_000),
+            input(2, 800, 3_000),
+            input(3, 200_000, 0),
+        ];
+        let recipients = recipients(100, 1_000);
+        let mut airdrop = Airdrop::new(CATEGORY, funding, recipients.clone(), params()).unwrap();
+        let plan = airdrop.plan().clone();
+        assert_eq!(plan.batch_sizes.iter().sum::<usize>(), 100);
+        assert_eq!(plan.required_tokens, 5050);
+        // P2PKH token outputs have a dust threshold above 1000 sats
+        let dust = airdrop.recipient_output(&recipients[0]).value;
+        assert_eq!(plan.dust_sats, 100 * (dust - 1_000));
+        assert_eq!(plan.recipient_sats, 100 * dust);
+
+        let mut sent = Vec::new();
+        let mut total_fee = 0;
+        while let Some(unsigned_tx) = airdrop.next_tx().unwrap() {
+            let spent_outputs = unsigned_tx.spent_outputs();
+            total_fee += unsigned_tx.fee;
+            let tx = unsigned_tx.sign(&sign).unwrap();
+            assert!(tx.ser_len() <= 2_000);
+            assert_eq!(tx.check_tokens(&spent_outputs), Ok(()));
+            let batch_size = plan.batch_sizes[airdrop.num_batches_sent];
+            sent.extend(tx.outputs[..batch_size].iter().cloned());
+            airdrop.advance(&tx).unwrap();
+            if airdrop.num_remaining() > 0 {
+                assert_eq!(airdrop.funding.len(), 2);
+                assert_eq!(airdrop.funding[0].prev_out.txid, tx.txid());
+            } else {
+                // Last tx returns the leftover tokens
+                let token_change = &airdrop.funding[0].coin.output;
+                assert_eq!(
+                    token_change.token.as_ref().unwrap().amount,
+                    CompactUint(6_000 - 5050)
+                );
+                assert_eq!(token_change.value, plan.token_change_sats);
+            }
+        }
+        assert!(total_fee <= plan.total_fee);
+        let expected = recipients
+            .iter()
+            .map(|recipient| airdrop.recipient_output(recipient))
+            .collect::<Vec<_>>();
+        assert_eq!(sent, expected);
+    }
+
+    #[test]
+    fn test_airdrop_errors() {
+        let mut nft_coin = input(1, 800, 10);
+        nft_coin.coin.output.token.as_mut().unwrap().nft = Some(NFT {
+            capability: NonFungibleTokenCapability(Capability::None),
+            commitment: Commitment(vec![].into()),
+        });
+        assert_eq!(
+            Airdrop::new(CATEGORY, vec![nft_coin], recipients(1, 0), params()),
+            Err(AirdropError::InvalidFundingCoin {
+                prev_out: input(1, 0, 0).prev_out,
+            }),
+        );
+
+        let mut recipients = recipients(3, 0);
+        recipients[2].token_amount = 0;
+        assert_eq!(
+            Airdrop::new(CATEGORY, vec![], recipients.clone(), params()),
+            Err(AirdropError::NoTokens { idx: 2 }),
+        );
+        recipients.pop();
+
+        assert_eq!(
+            Airdrop::new(
+                CATEGORY,
+                vec![input(1, 800, 2)],
+                recipients.clone(),
+                params()
+            ),
+            Err(AirdropError::InsufficientTokens {
+                available: 2,
+                required: 3,
+            }),
+        );
+        match Airdrop::new(
+            CATEGORY,
+            vec![input(1, 800, 3)],
+            recipients.clone(),
+            params(),
+        ) {
+            Err(AirdropError::InsufficientFunds {
+                available: 800,
+                required,
+            }) => assert!(required > 1_000),
+            result => panic!("Unexpected result: {result:?}"),
+        }
+
+        let mut small_params = params();
+        small_params.max_tx_size = 200;
+        assert_eq!(
+            Airdrop::new(
+                CATEGORY,
+                vec![input(1, 80_000, 3)],
+                recipients.clone(),
+                small_params
+            ),
+            Err(AirdropError::TxSizeTooSmall { max_tx_size: 200 }),
+        );
+
+        let mut airdrop =
+            Airdrop::new(CATEGORY, vec![input(1, 80_000, 3)], recipients, params()).unwrap();
+        assert_eq!(airdrop.plan().token_change_sats, 0);
+        let tx = airdrop.next_tx().unwrap().unwrap().sign(&sign).unwrap();
+        assert_eq!(airdrop.clone().advance(&tx), Ok(()));
+        airdrop.pending = PendingTx::default();
+        assert_eq!(airdrop.advance(&tx), Err(AirdropError::NoPendingTx));
+    }
+}
@@ -0,0 +1,758 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use std::collections::{BTreeMap, HashSet};
+
+use thiserror::Error;
+
+use crate::{
+    hash::Sha256d,
+    script::Script,
+    ser::CompactUint,
+    tx::{
+        Capability, CashToken, Coin, FeeRate, Input, OutPoint, Output, SigHashError, SigHashType,
+        Transaction, Tx, TxCheckError, TxId, UnlockingTemplate, NFT,
+    },
+};
+
+/// Default sequence of inputs, final without opting into any relative
+/// locktime.
+pub const DEFAULT_SEQUENCE: u32 = 0xffff_ffff;
+
+/// Height of coins of txs that aren't mined yet.
+pub const UNMINED_HEIGHT: i32 = -1;
+
+/// Builds a tx from inputs spending [`Coin`]s and outputs, adding change for
+/// BCH and for every token that isn't sent to an output, so no tokens are
+/// burned by accident.
+///
+/// The fee is estimated from the [`UnlockingTemplate`] of each input, then
+/// each input is signed by a [`Signer`].
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     hash::ShaRmd160,
+/// #     script::Script,
+/// #     ser::CompactUint,
+/// #     tx::{CashToken, Coin, OutPoint, Output, TxId, UnlockingTemplate},
+/// #     wallet::TxBuilder,
+/// # };
+/// let my_script = Script::p2pkh(&ShaRmd160([1; 20]));
+/// let category = TxId::from([7; 32]);
+/// let coin = Coin {
+///     output: Output {
+///         value: 10_000,
+///         script: my_script.clone(),
+///         token: Some(CashToken { amount: CompactUint(100), category, nft: None }),
+///     },
+///     ..Default::default()
+/// };
+/// let mut builder = TxBuilder::default();
+/// builder.change_script = Some(my_script);
+/// builder.add_input(
+///     OutPoint { txid: TxId::from([1; 32]), outpoint_index: 1 },
+///     coin,
+///     UnlockingTemplate::P2pkhSchnorr,
+/// );
+/// builder.add_output(Output {
+///     value: 1_000,
+///     script: Script::p2pkh(&ShaRmd160([2; 20])),
+///     token: Some(CashToken { amount: CompactUint(30), category, nft: None }),
+/// });
+/// let unsigned_tx = builder.build().unwrap();
+/// let outputs = &unsigned_tx.tx.outputs;
+/// assert_eq!(outputs.len(), 3);
+/// // Token change of 70 tokens, with just enough sats to not be dust
+/// assert_eq!(outputs[1].token.as_ref().unwrap().amount, CompactUint(70));
+/// assert_eq!(outputs[1].value, outputs[1].min_value());
+/// // Remaining BCH minus the fee goes to the last output
+/// assert_eq!(unsigned_tx.fee, 323);
+/// assert_eq!(outputs[2].value, 10_000 - 1_000 - outputs[1].value - 323);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TxBuilder {
+    /// nVersion of the tx.
+    pub version: i32,
+    /// Locktime of the tx.
+    pub locktime: u32,
+    /// Inputs of the tx, with the coins they spend.
+    pub inputs: Vec<TxBuilderInput>,
+    /// Outputs of the tx, without change.
+    pub outputs: Vec<Output>,
+    /// Script receiving BCH and token change. Required if there's any
+    /// change left.
+    pub change_script: Option<Script>,
+    /// Fee rate the tx pays.
+    pub fee_rate: FeeRate,
+}
+
+/// Input of a [`TxBuilder`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TxBuilderInput {
+    /// Points to the coin being spent.
+    pub prev_out: OutPoint,
+    /// nSequence of the input.
+    pub sequence: u32,
+    /// Coin being spent.
+    pub coin: Coin,
+    /// How the input will be unlocked, used to estimate the fee.
+    pub template: UnlockingTemplate,
+}
+
+/// Tx built by [`TxBuilder::build`], with empty scriptSigs.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct UnsignedTx {
+    /// The tx, with empty scriptSigs.
+    pub tx: Transaction,
+    /// Coins spent by each input.
+    pub spent_coins: Vec<Coin>,
+    /// Unlocking template of each input.
+    pub templates: Vec<UnlockingTemplate>,
+    /// Fee paid by the tx once signed.
+    pub fee: u64,
+}
+
+/// Produces the scriptSig of inputs of an [`UnsignedTx`], e.g. by signing
+/// the sighash of the input with a private key.
+///
+/// Implemented for closures, so signers can be defined inline.
+pub trait Signer {
+    /// Error when signing fails.
+    type Error;
+
+    /// Return the scriptSig for input `input_idx` of `unsigned_tx`.
+    fn sign_input(&self, unsigned_tx: &UnsignedTx, input_idx: usize)
+        -> Result<Script, Self::Error>;
+}
+
+/// Errors when building a tx with [`TxBuilder`].
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum TxBuilderError {
+    /// Inputs don't have enough sats for the outputs, token change and fee.
+    #[error("Insufficient funds: have {available} sats, need {required} sats")]
+    InsufficientFunds {
+        /// Sum of the values of all inputs.
+        available: u64,
+        /// Sum of the values of all outputs, token change and the fee.
+        required: u64,
+    },
+
+    /// There is change left, but no change script has been set.
+    #[error("Change of {sats} sats and {num_tokens} token outputs, but no change script")]
+    MissingChangeScript {
+        /// BCH change in sats (0 if there are only tokens to return).
+        sats: u64,
+        /// Number of token change outputs required.
+        num_tokens: usize,
+    },
+
+    /// Outputs have tokens that the inputs can't provide.
+    #[error("Outputs violate token conservation: {0:?}")]
+    TokenConservation(Vec<TxCheckError>),
+}
+
+/// Input NFT of a category, which may be used by an output.
+struct InputNft<'a> {
+    nft: &'a NFT,
+    is_used: bool,
+}
+
+/// Tokens of a category available in the inputs.
+#[derive(Default)]
+struct CategoryInputs<'a> {
+    amount: u64,
+    nfts: Vec<InputNft<'a>>,
+}
+
+impl Default for TxBuilder {
+    fn default() -> Self {
+        TxBuilder {
+            version: 2,
+            locktime: 0,
+            inputs: vec![],
+            outputs: vec![],
+            change_script: None,
+            fee_rate: FeeRate::from_sats_per_byte(1),
+        }
+    }
+}
+
+impl TxBuilder {
+    /// Add an input spending `coin` at `prev_out`, using the default
+    /// sequence.
+    pub fn add_input(
+        &mut self,
+        prev_out: OutPoint,
+        coin: Coin,
+        template: UnlockingTemplate,
+    ) -> &mut Self {
+        self.inputs.push(TxBuilderInput {
+            prev_out,
+            sequence: DEFAULT_SEQUENCE,
+            coin,
+            template,
+        });
+        self
+    }
+
+    /// Add an output, which may also carry tokens.
+    pub fn add_output(&mut self, output: Output) -> &mut Self {
+        self.outputs.push(output);
+        self
+    }
+
+    /// Build the tx, adding token change and BCH change outputs.
+    ///
+    /// Every token of the inputs which isn't sent to an output is returned
+    /// to [`TxBuilder::change_script`]: one output per leftover NFT, and
+    /// fungible change per category (sharing an output with a leftover NFT
+    /// of the same category, if any). Token change outputs carry the
+    /// minimum value to not be dust. BCH change is only added if it is
+    /// above the dust threshold, otherwise it's added to the fee.
+    ///
+    /// Fails if the outputs have tokens the inputs can't provide, see
+    /// [`Transaction::check_tokens`].
+    pub fn build(&self) -> Result<UnsignedTx, TxBuilderError> {
+        let mut tx = Transaction {
+            version: self.version,
+            inputs: self
+                .inputs
+                .iter()
+                .map(|input| Input {
+                    prev_out: input.prev_out,
+                    script: Script::default(),
+                    sequence: input.sequence,
+                })
+                .collect(),
+            outputs: self.outputs.clone(),
+            locktime: self.locktime,
+        };
+        let spent_coins = self
+            .inputs
+            .iter()
+            .map(|input| input.coin.clone())
+            .collect::<Vec<_>>();
+        let templates = self
+            .inputs
+            .iter()
+            .map(|input| input.template)
+            .collect::<Vec<_>>();
+
+        let spent_outputs = spent_coins
+            .iter()
+            .map(|coin| coin.output.clone())
+            .collect::<Vec<_>>();
+        tx.check_tokens(&spent_outputs)
+            .map_err(TxBuilderError::TokenConservation)?;
+
+        let token_change = self.token_change();
+        if !token_change.is_empty() {
+            let script = self
+                .change_script
+                .clone()
+                .ok_or(TxBuilderError::MissingChangeScript {
+                    sats: 0,
+                    num_tokens: token_change.len(),
+                })?;
+            for token in token_change {
+                let mut output = Output {
+                    value: 0,
+                    script: script.clone(),
+                    token: Some(token),
+                };
+                output.value = output.min_value();
+                tx.outputs.push(output);
+            }
+        }
+
+        let available = spent_coins
+            .iter()
+            .map(|coin| coin.output.value)
+            .sum::<u64>();
+        let spent = tx.outputs.iter().map(|output| output.value).sum::<u64>();
+        let fee_without_change = self.fee(&tx, &templates);
+        let required = spent + fee_without_change;
+        if available < required {
+            return Err(TxBuilderError::InsufficientFunds {
+                available,
+                required,
+            });
+        }
+
+        let mut change_output = Output {
+            value: 0,
+            script: self.change_script.clone().unwrap_or_default(),
+            token: None,
+        };
+        tx.outputs.push(change_output.clone());
+        let fee_with_change = self.fee(&tx, &templates);
+        let change = available.saturating_sub(spent + fee_with_change);
+        change_output.value = change;
+        if change > 0 && change >= change_output.min_value() {
+            if self.change_script.is_none() {
+                return Err(TxBuilderError::MissingChangeScript {
+                    sats: change,
+                    num_tokens: 0,
+                });
+            }
+            *tx.outputs.last_mut().unwrap() = change_output;
+            Ok(UnsignedTx {
+                tx,
+                spent_coins,
+                templates,
+                fee: fee_with_change,
+            })
+        } else {
+            tx.outputs.pop();
+            Ok(UnsignedTx {
+                tx,
+                spent_coins,
+                templates,
+                fee: available - spent,
+            })
+        }
+    }
+
+    /// Estimated size of the tx once signed, without any change outputs.
+    pub(crate) fn estimate_size(&self) -> usize {
+        let tx = Transaction {
+            version: self.version,
+            inputs: vec![Input::default(); self.inputs.len()],
+            outputs: self.outputs.clone(),
+            locktime: self.locktime,
+        };
+        let templates = self
+            .inputs
+            .iter()
+            .map(|input| input.template)
+            .collect::<Vec<_>>();
+        tx.estimate_size(&templates)
+            .expect("One template per input")
+            .size
+    }
+
+    fn fee(&self, tx: &Transaction, templates: &[UnlockingTemplate]) -> u64 {
+        let estimate = tx.estimate_size(templates).expect("One template per input");
+        estimate.fee(self.fee_rate)
+    }
+
+    /// Tokens of the inputs that aren't used by any output.
+    fn token_change(&self) -> Vec<CashToken> {
+        let genesis_categories = self
+            .inputs
+            .iter()
+            .filter(|input| input.prev_out.outpoint_index == 0)
+            .map(|input| input.prev_out.txid)
+            .collect::<HashSet<_>>();
+        let mut inputs = BTreeMap::<TxId, CategoryInputs<'_>>::new();
+        for token in self
+            .inputs
+            .iter()
+            .filter_map(|input| input.coin.output.token.as_ref())
+        {
+            let category = inputs.entry(token.category).or_default();
+            category.amount = category.amount.saturating_add(token.amount.0);
+            if let Some(nft) = &token.nft {
+                category.nfts.push(InputNft {
+                    nft,
+                    is_used: false,
+                });
+            }
+        }
+
+        let output_tokens = self
+            .outputs
+            .iter()
+            .filter_map(|output| output.token.as_ref())
+            .filter(|token| !genesis_categories.contains(&token.category))
+            .collect::<Vec<_>>();
+        let mut unmatched_nfts = Vec::new();
+        for token in &output_tokens {
+            if let Some(category) = inputs.get_mut(&token.category) {
+                category.amount = category.amount.saturating_sub(token.amount.0);
+            }
+            let nft = match &token.nft {
+                Some(nft) => nft,
+                None => continue,
+            };
+            // NFTs sent on unchanged use up their input NFT
+            match inputs
+                .get_mut(&token.category)
+                .and_then(|category| category.unused_nft(|input| input == nft))
+            {
+                Some(input) => input.is_used = true,
+                None => unmatched_nfts.push((token.category, nft)),
+            }
+        }
+        for (category, nft) in unmatched_nfts {
+            let category = match inputs.get_mut(&category) {
+                Some(category) => category,
+                None => continue,
+            };
+            // Other NFTs are minted by a minting NFT, or use up a mutable NFT
+            // if a minting NFT isn't available; minting NFTs use up an input
+            // minting NFT
+            let capability = nft.capability.0;
+            let has_minting = category.has_nft(Capability::Minting);
+            let used_input = match capability {
+                Capability::None if has_minting => None,
+                Capability::Mutable if has_minting => None,
+                Capability::None | Capability::Mutable => {
+                    category.unused_nft(|input| input.capability.0 == Capability::Mutable)
+                }
+                Capability::Minting => {
+                    category.unused_nft(|input| input.capability.0 == Capability::Minting)
+                }
+            };
+            if let Some(input) = used_input {
+                input.is_used = true;
+            }
+        }
+
+        let mut change = Vec::new();
+        for (category, inputs) in inputs {
+            let mut amount = inputs.amount;
+            for input in inputs.nfts.into_iter().filter(|input| !input.is_used) {
+                change.push(CashToken {
+                    amount: CompactUint(amount),
+                    category,
+                    nft: Some(input.nft.clone()),
+                });
+                amount = 0;
+            }
+            if amount > 0 {
+                change.push(CashToken {
+                    amount: CompactUint(amount),
+                    category,
+                    nft: None,
+                });
+            }
+        }
+        change
+    }
+}
+
+impl<'a> CategoryInputs<'a> {
+    fn unused_nft(&mut self, pred: impl Fn(&NFT) -> bool) -> Option<&mut InputNft<'a>> {
+        self.nfts
+            .iter_mut()
+            .find(|input| !input.is_used && pred(input.nft))
+    }
+
+    fn has_nft(&self, capability: Capability) -> bool {
+        self.nfts
+            .iter()
+            .any(|input| input.nft.capability.0 == capability)
+    }
+}
+
+impl TxBuilderInput {
+    /// Input spending output `output_idx` of `tx`, e.g. to chain txs before
+    /// they are mined.
+    ///
+    /// Panics if `tx` doesn't have that output.
+    pub fn from_tx_output(tx: &Tx, output_idx: usize, template: UnlockingTemplate) -> Self {
+        TxBuilderInput {
+            prev_out: OutPoint {
+                txid: tx.txid(),
+                outpoint_index: output_idx as u32,
+            },
+            sequence: DEFAULT_SEQUENCE,
+            coin: Coin {
+                output: tx.outputs[output_idx].clone(),
+                height: UNMINED_HEIGHT,
+                is_coinbase: false,
+            },
+            template,
+        }
+    }
+}
+
+impl UnsignedTx {
+    /// Whether `tx` is this tx with scriptSigs added, i.e. everything but
+    /// the scriptSigs is the same.
+    pub fn is_signed_by(&self, tx: &Tx) -> bool {
+        self.tx.version == tx.version
+            && self.tx.locktime == tx.locktime
+            && self.tx.outputs == tx.outputs
+            && self.tx.inputs.len() == tx.inputs.len()
+            && self
+                .tx
+                .inputs
+                .iter()
+                .zip(&tx.inputs)
+                .all(|(unsigned, signed)| {
+                    unsigned.prev_out == signed.prev_out && unsigned.sequence == signed.sequence
+                })
+    }
+
+    /// Outputs spent by each input.
+    pub fn spent_outputs(&self) -> Vec<Output> {
+        self.spent_coins
+            .iter()
+            .map(|coin| coin.output.clone())
+            .collect()
+    }
+
+    /// Sighash for input `input_idx`, see [`Transaction::sighash`].
+    pub fn sighash(
+        &self,
+        input_idx: usize,
+        script_code: &Script,
+        sig_hash_type: SigHashType,
+    ) -> Result<Sha256d, SigHashError> {
+        self.tx
+            .sighash(input_idx, &self.spent_outputs(), script_code, sig_hash_type)
+    }
+
+    /// Sign every input using the `signer`, and return the finished tx.
+    pub fn sign<S: Signer>(self, signer: &S) -> Result<Tx, S::Error> {
+        let scripts = (0..self.tx.inputs.len())
+            .map(|input_idx| signer.sign_input(&self, input_idx))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut tx = self.tx;
+        for (input, script) in tx.inputs.iter_mut().zip(scripts) {
+            input.script = script;
+        }
+        Ok(Tx::with_txid(TxId::from_tx(&tx), tx))
+    }
+}
+
+impl<F, E> Signer for F
+where
+    F: Fn(&UnsignedTx, usize) -> Result<Script, E>,
+{
+    type Error = E;
+
+    fn sign_input(&self, unsigned_tx: &UnsignedTx, input_idx: usize) -> Result<Script, E> {
+        self(unsigned_tx, input_idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        hash::ShaRmd160,
+        script::Script,
+        ser::BitcoinSer,
+        tx::{
+            Capability, CashToken, Coin, OutPoint, Output, SigHashType, TxCheckError, TxId,
+            UnlockingTemplate,
+        },
+        wallet::{
+            test_util::{category, token},
+            TxBuilder, TxBuilderError, UnsignedTx,
+        },
+    };
+
+    fn script(byte: u8) -> Script {
+        Script::p2pkh(&ShaRmd160([byte; 20]))
+    }
+
+    fn builder(coins: Vec<(u64, Option<CashToken>)>) -> TxBuilder {
+        let mut builder = TxBuilder {
+            change_script: Some(script(1)),
+            ..Default::default()
+        };
+        for (idx, (value, token)) in coins.into_iter().enumerate() {
+            builder.add_input(
+                OutPoint {
+                    txid: TxId::from([idx as u8 + 1; 32]),
+                    outpoint_index: 1,
+                },
+                Coin {
+                    output: Output {
+                        value,
+                        script: script(1),
+                        token,
+                    },
+                    ..Default::default()
+                },
+                UnlockingTemplate::P2pkhSchnorr,
+            );
+        }
+        builder
+    }
+
+    #[test]
+    fn test_build_bch_only() {
+        let mut builder = builder(vec![(10_000, None), (5_000, None)]);
+        builder.add_output(Output {
+            value: 12_000,
+            script: script(2),
+            token: None,
+        });
+        let unsigned_tx = builder.build().unwrap();
+        // 10 + 2 * 141 + 2 * 34 bytes
+        assert_eq!(unsigned_tx.fee, 360);
+        assert_eq!(unsigned_tx.tx.outputs[1].value, 15_000 - 12_000 - 360);
+
+        // Change below dust goes to the fee
+        builder.outputs[0].value = 15_000 - 326 - 545;
+        let unsigned_tx = builder.build().unwrap();
+        assert_eq!(unsigned_tx.tx.outputs.len(), 1);
+        assert_eq!(unsigned_tx.fee, 871);
+
+        builder.outputs[0].value = 15_000 - 325;
+        assert_eq!(
+            builder.build(),
+            Err(TxBuilderError::InsufficientFunds {
+                available: 15_000,
+                required: 15_001,
+            }),
+        );
+
+        builder.outputs[0].value = 10_000;
+        builder.change_script = None;
+        // Fee is estimated with an empty change script
+        assert_eq!(
+            builder.build(),
+            Err(TxBuilderError::MissingChangeScript {
+                sats: 15_000 - 10_000 - 335,
+                num_tokens: 0,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_build_token_change() {
+        use Capability::{Minting, Mutable};
+        let mut builder = builder(vec![
+            (10_000, None),
+            (1_000, token(500, Some((Minting, b"")))),
+            (1_000, token(200, Some((Mutable, b"m")))),
+            (1_000, token(0, Some((Capability::None, b"a")))),
+        ]);
+        // Mint a new NFT, send on 100 fungible tokens and the immutable NFT
+        builder.add_output(Output {
+            value: 800,
+            script: script(2),
+            token: token(100, Some((Capability::None, b"new"))),
+        });
+        builder.add_output(Output {
+            value: 800,
+            script: script(2),
+            token: token(0, Some((Capability::None, b"a"))),
+        });
+        let unsigned_tx = builder.build().unwrap();
+        let change_tokens = unsigned_tx.tx.outputs[2..]
+            .iter()
+            .map(|output| output.token.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            change_tokens,
+            vec![
+                token(600, Some((Minting, b""))),
+                token(0, Some((Mutable, b"m"))),
+                None,
+            ],
+        );
+        for output in &unsigned_tx.tx.outputs[2..] {
+            assert_eq!(output.script, script(1));
+        }
+        let spent_outputs = unsigned_tx.spent_outputs();
+        assert_eq!(unsigned_tx.tx.check_tokens(&spent_outputs), Ok(()));
+
+        // A new mutable NFT is minted, and doesn't burn the input mutable NFT
+        builder.outputs[1].token = token(0, Some((Mutable, b"n")));
+        let unsigned_tx = builder.build().unwrap();
+        assert_eq!(
+            unsigned_tx.tx.outputs[2..]
+                .iter()
+                .map(|output| output.token.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                token(600, Some((Minting, b""))),
+                token(0, Some((Mutable, b"m"))),
+                token(0, Some((Capability::None, b"a"))),
+                None,
+            ],
+        );
+        let spent_outputs = unsigned_tx.spent_outputs();
+        assert_eq!(unsigned_tx.tx.check_tokens(&spent_outputs), Ok(()));
+
+        // Without a minting NFT, modifying the mutable NFT uses it up
+        let mut mutable_builder = builder.clone();
+        mutable_builder.inputs.remove(1);
+        mutable_builder.outputs[0].token = None;
+        let unsigned_tx = mutable_builder.build().unwrap();
+        assert_eq!(
+            unsigned_tx.tx.outputs[2..]
+                .iter()
+                .map(|output| output.token.clone())
+                .collect::<Vec<_>>(),
+            vec![token(200, Some((Capability::None, b"a"))), None],
+        );
+
+        // Token change needs a change script
+        builder.change_script = None;
+        assert_eq!(
+            builder.build(),
+            Err(TxBuilderError::MissingChangeScript {
+                sats: 0,
+                num_tokens: 3,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_build_token_conservation() {
+        let mut builder = builder(vec![(10_000, None), (1_000, token(50, None))]);
+        builder.add_output(Output {
+            value: 1_000,
+            script: script(2),
+            token: token(60, Some((Capability::None, b"x"))),
+        });
+        assert_eq!(
+            builder.build(),
+            Err(TxBuilderError::TokenConservation(vec![
+                TxCheckError::NftWithoutAuthority {
+                    output_idx: 0,
+                    category: category(),
+                },
+                TxCheckError::TokenAmountInflated {
+                    category: category(),
+                    input_amount: 50,
+                    output_amount: 60,
+                },
+            ])),
+        );
+
+        // Genesis: spending output 0 of the category's txid
+        builder.inputs[0].prev_out = OutPoint {
+            txid: category(),
+            outpoint_index: 0,
+        };
+        let unsigned_tx = builder.build().unwrap();
+        // Input tokens of the same category are returned as change
+        assert_eq!(unsigned_tx.tx.outputs[1].token, token(50, None));
+    }
+
+    #[test]
+    fn test_sign() {
+        let mut builder = builder(vec![(10_000, None), (5_000, None)]);
+        builder.add_output(Output {
+            value: 12_000,
+            script: script(2),
+            token: None,
+        });
+        let unsigned_tx = builder.build().unwrap();
+        let signer = |unsigned_tx: &UnsignedTx, input_idx: usize| {
+            let script_code = &unsigned_tx.spent_coins[input_idx].output.script;
+            let sighash = unsigned_tx.sighash(input_idx, script_code, SigHashType::ALL_FORKID)?;
+            // Fake signature, just to check the sighash is passed on
+            let mut script = vec![0x41];
+            script.extend_from_slice(&[[0; 32], sighash.0].concat());
+            script.push(0x41);
+            script.push(0x21);
+            script.extend_from_slice(&[2; 33]);
+            Ok::<_, crate::tx::SigHashError>(Script::new(script.into()))
+        };
+        let tx = unsigned_tx.clone().sign(&signer).unwrap();
+        assert_eq!(tx.inputs[1].script, signer(&unsigned_tx, 1).unwrap());
+        // Estimate for Schnorr signatures is exact
+        assert_eq!(tx.ser_len() as u64, unsigned_tx.fee);
+    }
+}
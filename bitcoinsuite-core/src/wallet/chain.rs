@@ -0,0 +1,56 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use crate::{
+    tx::{Tx, TxId},
+    wallet::UnsignedTx,
+};
+
+/// Room left in a tx for the output count to grow, which is at most 9
+/// bytes as CompactSize.
+pub(crate) const OUTPUT_COUNT_RESERVE: usize = 9;
+
+/// Errors of a chain of txs where each tx spends outputs of the previous
+/// one, see [`PendingTx`].
+pub(crate) trait TxChainError {
+    /// The tx was signed without being built first.
+    fn no_pending_tx() -> Self;
+
+    /// The signed tx doesn't match the built tx.
+    fn tx_mismatch(txid: TxId) -> Self;
+}
+
+/// Tx of a chain that has been built but not signed yet, together with the
+/// `state` needed to continue the chain after it.
+///
+/// As the txid of a tx is only known once it is signed, chains are built
+/// one tx at a time: build a tx and [`PendingTx::set`] it, then pass the
+/// signed tx to [`PendingTx::take_signed`] to continue with the next one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct PendingTx<T> {
+    pending: Option<(UnsignedTx, T)>,
+}
+
+impl<T> PendingTx<T> {
+    /// Wait for `unsigned_tx` to be signed, replacing any tx built before.
+    pub(crate) fn set(&mut self, unsigned_tx: UnsignedTx, state: T) {
+        self.pending = Some((unsigned_tx, state));
+    }
+
+    /// Take the state of the pending tx if `tx` is that tx signed.
+    pub(crate) fn take_signed<E: TxChainError>(&mut self, tx: &Tx) -> Result<T, E> {
+        let (unsigned_tx, _) = self.pending.as_ref().ok_or_else(E::no_pending_tx)?;
+        if !unsigned_tx.is_signed_by(tx) {
+            return Err(E::tx_mismatch(tx.txid()));
+        }
+        let (_, state) = self.pending.take().expect("Checked above");
+        Ok(state)
+    }
+}
+
+impl<T> Default for PendingTx<T> {
+    fn default() -> Self {
+        PendingTx { pending: None }
+    }
+}
@@ -0,0 +1,792 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use crate::{
+    script::Script,
+    ser::{BitcoinSer, CompactUint},
+    tx::{CashToken, FeeRate, Output, TxId},
+    wallet::{TxBuilder, TxBuilderInput},
+};
+
+/// Maximum number of branches explored by
+/// [`CoinSelectionStrategy::BranchAndBound`] before giving up.
+const BNB_MAX_TRIES: usize = 100_000;
+
+/// Strategy to pick coins when selecting inputs with [`select_coins`].
+///
+/// Tokens are selected first, per category, then BCH from coins without
+/// tokens, using the same strategy.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CoinSelectionStrategy {
+    /// Spend the largest coins first, which keeps the number of inputs (and
+    /// therefore the fee) low.
+    LargestFirst,
+    /// Search for a set of coins matching the target exactly, so no change
+    /// is needed: for tokens no token change output, for BCH less than the
+    /// cost of adding a change output. Falls back to
+    /// [`CoinSelectionStrategy::LargestFirst`] if there's no such set.
+    BranchAndBound,
+    /// Pick random coins until the target is reached, then keep adding
+    /// random coins as long as the selection gets closer to twice the
+    /// target. This leaves change of a size similar to the payment, which
+    /// keeps the wallet from fragmenting into many small coins.
+    RandomImprove {
+        /// Seed of the random number generator, so selection is
+        /// reproducible.
+        seed: u64,
+    },
+}
+
+/// What the selected coins have to pay for: sats and amounts of fungible
+/// tokens.
+/// ```
+/// # use bitcoinsuite_core::{tx::TxId, wallet::SelectionTarget};
+/// let target = SelectionTarget::sats(10_000).with_tokens(TxId::from([1; 32]), 500);
+/// assert_eq!(target.sats, 10_000);
+/// assert_eq!(target.tokens[&TxId::from([1; 32])], 500);
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SelectionTarget {
+    /// Sats sent to the outputs, excluding the fee.
+    pub sats: u64,
+    /// Amount of fungible tokens sent to the outputs, by category.
+    pub tokens: BTreeMap<TxId, u64>,
+}
+
+/// Parameters of the tx the coins are selected for.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CoinSelectionParams {
+    /// Fee rate the tx pays.
+    pub fee_rate: FeeRate,
+    /// Serialized size of the tx before adding the selected coins, i.e.
+    /// version, locktime, input and output counts, the outputs and any inputs
+    /// already spent.
+    pub base_size: usize,
+    /// Script receiving token and BCH change, used to estimate the size and
+    /// dust value of change outputs.
+    pub change_script: Script,
+    /// Whether coins with NFTs may be spent for their fungible tokens; their
+    /// NFTs are then returned as token change. By default, coins with NFTs
+    /// are never selected.
+    pub allow_nfts: bool,
+}
+
+/// Coins picked by [`select_coins`].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CoinSelection {
+    /// Indices of the selected coins in the candidates, in ascending order.
+    pub indices: Vec<usize>,
+    /// Sum of the values of the selected coins.
+    pub input_sats: u64,
+    /// Sum of the fungible token amounts of the selected coins, by category.
+    pub tokens: BTreeMap<TxId, u64>,
+    /// Sats locked in token change outputs, at their dust value.
+    pub token_change_sats: u64,
+    /// Estimated fee of the tx including token change, but without a BCH
+    /// change output.
+    pub fee: u64,
+}
+
+/// Errors when selecting coins.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum CoinSelectionError {
+    /// The candidates don't have enough sats for the target and the fee.
+    #[error("Insufficient funds: have {available} sats, need {required} sats")]
+    InsufficientFunds {
+        /// Sats of all usable candidates, minus the fee of spending them.
+        available: u64,
+        /// Sats of the target, token change and the fee without inputs.
+        required: u64,
+    },
+
+    /// The candidates don't have enough tokens of a category.
+    #[error("Insufficient tokens of {category}: have {available}, need {required}")]
+    InsufficientTokens {
+        /// Token category.
+        category: TxId,
+        /// Tokens of the category in all usable candidates.
+        available: u64,
+        /// Tokens of the category in the target.
+        required: u64,
+    },
+
+    /// No change script is set, so the size and dust value of change outputs
+    /// can't be estimated.
+    #[error("No change script to select coins for")]
+    MissingChangeScript,
+}
+
+/// Small deterministic PRNG (SplitMix64) for
+/// [`CoinSelectionStrategy::RandomImprove`].
+struct SplitMix64(u64);
+
+impl SelectionTarget {
+    /// Target of `sats` sats and no tokens.
+    pub fn sats(sats: u64) -> Self {
+        SelectionTarget {
+            sats,
+            tokens: BTreeMap::new(),
+        }
+    }
+
+    /// Add `amount` fungible tokens of `category` to the target.
+    pub fn with_tokens(mut self, category: TxId, amount: u64) -> Self {
+        let tokens = self.tokens.entry(category).or_default();
+        *tokens = tokens.saturating_add(amount);
+        self
+    }
+}
+
+/// Select coins from `candidates` paying for `target` and the fee of the tx.
+///
+/// Tokens of each category in the target are selected first from coins of
+/// that category (skipping coins with NFTs unless
+/// [`CoinSelectionParams::allow_nfts`] is set); coins with tokens of other
+/// categories are never spent. Any excess tokens require a token change
+/// output, whose dust value and size are added to the sats to select.
+///
+/// BCH is then selected from coins without tokens by their effective value,
+/// i.e. their value minus the fee of spending them, so every input pays for
+/// itself and coins worth less than their fee are skipped.
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     hash::ShaRmd160,
+/// #     script::Script,
+/// #     tx::{Coin, FeeRate, OutPoint, Output, TxId, UnlockingTemplate},
+/// #     wallet::{
+/// #         select_coins, CoinSelectionParams, CoinSelectionStrategy,
+/// #         SelectionTarget, TxBuilderInput,
+/// #     },
+/// # };
+/// let script = Script::p2pkh(&ShaRmd160([1; 20]));
+/// let candidates = [3_000, 20_000, 8_000]
+///     .iter()
+///     .enumerate()
+///     .map(|(idx, &value)| TxBuilderInput {
+///         prev_out: OutPoint { txid: TxId::from([1; 32]), outpoint_index: idx as u32 },
+///         sequence: 0xffff_ffff,
+///         coin: Coin {
+///             output: Output { value, script: script.clone(), token: None },
+///             ..Default::default()
+///         },
+///         template: UnlockingTemplate::P2pkhSchnorr,
+///     })
+///     .collect::<Vec<_>>();
+/// let params = CoinSelectionParams {
+///     fee_rate: FeeRate::from_sats_per_byte(1),
+///     base_size: 10 + 34,
+///     change_script: script,
+///     allow_nfts: false,
+/// };
+/// let selection = select_coins(
+///     &candidates,
+///     &SelectionTarget::sats(10_000),
+///     &params,
+///     CoinSelectionStrategy::LargestFirst,
+/// )
+/// .unwrap();
+/// assert_eq!(selection.indices, vec![1]);
+/// assert_eq!(selection.input_sats, 20_000);
+/// assert_eq!(selection.fee, 10 + 34 + 141);
+/// ```
+pub fn select_coins(
+    candidates: &[TxBuilderInput],
+    target: &SelectionTarget,
+    params: &CoinSelectionParams,
+    strategy: CoinSelectionStrategy,
+) -> Result<CoinSelection, CoinSelectionError> {
+    let mut rng = match strategy {
+        CoinSelectionStrategy::RandomImprove { seed } => SplitMix64(seed),
+        _ => SplitMix64(0),
+    };
+    let mut selection = CoinSelection::default();
+    let mut size = params.base_size;
+
+    for (&category, &required) in &target.tokens {
+        if required == 0 {
+            continue;
+        }
+        let values = candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, input)| {
+                let token = input.coin.output.token.as_ref()?;
+                let is_usable = token.category == category
+                    && token.amount.0 > 0
+                    && (params.allow_nfts || token.nft.is_none());
+                is_usable.then(|| (idx, token.amount.0))
+            })
+            .collect::<Vec<_>>();
+        let indices = select_values(&values, required, 0, strategy, &mut rng).ok_or_else(|| {
+            CoinSelectionError::InsufficientTokens {
+                category,
+                available: values
+                    .iter()
+                    .fold(0u64, |sum, &(_, amount)| sum.saturating_add(amount)),
+                required,
+            }
+        })?;
+
+        let mut amount = 0u64;
+        let mut nfts = Vec::new();
+        for idx in indices {
+            let input = &candidates[idx];
+            let token = input.coin.output.token.as_ref().expect("Selected by token");
+            amount = amount.saturating_add(token.amount.0);
+            nfts.extend(token.nft.clone());
+            selection.indices.push(idx);
+            selection.input_sats += input.coin.output.value;
+            size += input.template.input_size();
+        }
+        selection.tokens.insert(category, amount);
+
+        // Excess tokens go to change, riding on returned NFTs if any
+        let mut excess = amount - required;
+        let mut change_tokens = Vec::new();
+        for nft in nfts {
+            change_tokens.push(CashToken {
+                amount: CompactUint(excess),
+                category,
+                nft: Some(nft),
+            });
+            excess = 0;
+        }
+        if excess > 0 {
+            change_tokens.push(CashToken {
+                amount: CompactUint(excess),
+                category,
+                nft: None,
+            });
+        }
+        for token in change_tokens {
+            let output = Output {
+                value: 0,
+                script: params.change_script.clone(),
+                token: Some(token),
+            };
+            selection.token_change_sats += output.min_value();
+            size += output.ser_len();
+        }
+    }
+
+    // Sats still missing once token inputs and outputs are paid for
+    let fixed_cost = target.sats + selection.token_change_sats + fee_ceil(params.fee_rate, size);
+    let required = fixed_cost.saturating_sub(selection.input_sats);
+    let values = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, input)| input.coin.output.token.is_none())
+        .filter_map(|(idx, input)| {
+            let input_fee = fee_ceil(params.fee_rate, input.template.input_size());
+            let effective_value = input.coin.output.value.checked_sub(input_fee)?;
+            (effective_value > 0).then(|| (idx, effective_value))
+        })
+        .collect::<Vec<_>>();
+    let change_output = Output {
+        value: 0,
+        script: params.change_script.clone(),
+        token: None,
+    };
+    let cost_of_change =
+        change_output.min_value() + fee_ceil(params.fee_rate, change_output.ser_len());
+    let indices =
+        select_values(&values, required, cost_of_change, strategy, &mut rng).ok_or_else(|| {
+            CoinSelectionError::InsufficientFunds {
+                available: selection.input_sats
+                    + values.iter().map(|&(_, value)| value).sum::<u64>(),
+                required: fixed_cost,
+            }
+        })?;
+    for idx in indices {
+        let input = &candidates[idx];
+        selection.indices.push(idx);
+        selection.input_sats += input.coin.output.value;
+        size += input.template.input_size();
+    }
+
+    selection.indices.sort_unstable();
+    selection.fee = params.fee_rate.fee(size);
+    Ok(selection)
+}
+
+impl TxBuilder {
+    /// Select coins from `candidates` for the outputs of this builder with
+    /// [`select_coins`] and add them as inputs.
+    ///
+    /// Inputs already added count towards the target; NFTs in the outputs
+    /// must be provided by them, only fungible tokens are selected.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::ShaRmd160,
+    /// #     script::Script,
+    /// #     ser::CompactUint,
+    /// #     tx::{CashToken, Coin, OutPoint, Output, TxId, UnlockingTemplate},
+    /// #     wallet::{CoinSelectionStrategy, TxBuilder, TxBuilderInput},
+    /// # };
+    /// let my_script = Script::p2pkh(&ShaRmd160([1; 20]));
+    /// let category = TxId::from([7; 32]);
+    /// let candidate = |idx: u32, value: u64, amount: u64| TxBuilderInput {
+    ///     prev_out: OutPoint { txid: TxId::from([1; 32]), outpoint_index: idx },
+    ///     sequence: 0xffff_ffff,
+    ///     coin: Coin {
+    ///         output: Output {
+    ///             value,
+    ///             script: my_script.clone(),
+    ///             token: (amount > 0).then(|| CashToken {
+    ///                 amount: CompactUint(amount),
+    ///                 category,
+    ///                 nft: None,
+    ///             }),
+    ///         },
+    ///         ..Default::default()
+    ///     },
+    ///     template: UnlockingTemplate::P2pkhSchnorr,
+    /// };
+    /// let candidates = [
+    ///     candidate(1, 800, 40),
+    ///     candidate(2, 800, 60),
+    ///     candidate(3, 800, 25),
+    ///     candidate(4, 50_000, 0),
+    /// ];
+    /// let mut builder = TxBuilder::default();
+    /// builder.change_script = Some(my_script);
+    /// builder.add_output(Output {
+    ///     value: 5_000,
+    ///     script: Script::p2pkh(&ShaRmd160([2; 20])),
+    ///     token: Some(CashToken { amount: CompactUint(100), category, nft: None }),
+    /// });
+    /// builder
+    ///     .select_coins(&candidates, CoinSelectionStrategy::BranchAndBound, false)
+    ///     .unwrap();
+    /// // 40 + 60 tokens match exactly, so no token change is needed
+    /// let prev_outs = builder
+    ///     .inputs
+    ///     .iter()
+    ///     .map(|input| input.prev_out.outpoint_index)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(prev_outs, vec![1, 2, 4]);
+    /// let unsigned_tx = builder.build().unwrap();
+    /// assert_eq!(unsigned_tx.tx.outputs.len(), 2);
+    /// ```
+    pub fn select_coins(
+        &mut self,
+        candidates: &[TxBuilderInput],
+        strategy: CoinSelectionStrategy,
+        allow_nfts: bool,
+    ) -> Result<CoinSelection, CoinSelectionError> {
+        let change_script = self
+            .change_script
+            .clone()
+            .ok_or(CoinSelectionError::MissingChangeScript)?;
+        let base_size = self.estimate_size();
+
+        let mut target = SelectionTarget::default();
+        for output in &self.outputs {
+            target.sats += output.value;
+            if let Some(token) = &output.token {
+                target = target.with_tokens(token.category, token.amount.0);
+            }
+        }
+        for input in &self.inputs {
+            let output = &input.coin.output;
+            target.sats = target.sats.saturating_sub(output.value);
+            if let Some(token) = &output.token {
+                if let Some(amount) = target.tokens.get_mut(&token.category) {
+                    *amount = amount.saturating_sub(token.amount.0);
+                }
+            }
+        }
+
+        let params = CoinSelectionParams {
+            fee_rate: self.fee_rate,
+            base_size,
+            change_script,
+            allow_nfts,
+        };
+        // Coins already spent by the builder can't be selected again
+        let (candidate_indices, unspent): (Vec<_>, Vec<_>) = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| {
+                !self
+                    .inputs
+                    .iter()
+                    .any(|input| input.prev_out == candidate.prev_out)
+            })
+            .map(|(idx, candidate)| (idx, candidate.clone()))
+            .unzip();
+        let mut selection = select_coins(&unspent, &target, &params, strategy)?;
+        for idx in &mut selection.indices {
+            *idx = candidate_indices[*idx];
+        }
+        self.inputs
+            .extend(selection.indices.iter().map(|&idx| candidates[idx].clone()));
+        Ok(selection)
+    }
+}
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for idx in (1..items.len()).rev() {
+            let other = (self.next_u64() % (idx as u64 + 1)) as usize;
+            items.swap(idx, other);
+        }
+    }
+}
+
+/// Fee for `size` bytes, rounded up, so fees of inputs computed one by one
+/// never add up to less than the fee of the whole tx.
+fn fee_ceil(fee_rate: FeeRate, size: usize) -> u64 {
+    (fee_rate.sats_per_kb() * size as u64 + 999) / 1000
+}
+
+/// Select `(idx, value)` pairs summing up to at least `target`, returning
+/// the selected indices, or `None` if all values together are not enough.
+fn select_values(
+    values: &[(usize, u64)],
+    target: u64,
+    tolerance: u64,
+    strategy: CoinSelectionStrategy,
+    rng: &mut SplitMix64,
+) -> Option<Vec<usize>> {
+    if target == 0 {
+        return Some(vec![]);
+    }
+    let total = values
+        .iter()
+        .fold(0u64, |sum, &(_, value)| sum.saturating_add(value));
+    if total < target {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    // Largest first, ties broken by index for deterministic results
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let positions = match strategy {
+        CoinSelectionStrategy::LargestFirst => accumulate(&sorted, target)?,
+        CoinSelectionStrategy::BranchAndBound => {
+            let mut search = BranchAndBound {
+                values: &sorted,
+                target,
+                max_sum: target.saturating_add(tolerance),
+                tries: BNB_MAX_TRIES,
+                selection: vec![],
+                best: None,
+            };
+            search.explore(0, 0, total);
+            match search.best {
+                Some((_, best)) => best,
+                None => accumulate(&sorted, target)?,
+            }
+        }
+        CoinSelectionStrategy::RandomImprove { .. } => {
+            rng.shuffle(&mut sorted);
+            let mut selected = accumulate(&sorted, target)?;
+            let mut sum = selected
+                .iter()
+                .fold(0u64, |sum, &pos| sum.saturating_add(sorted[pos].1));
+            let ideal = target.saturating_mul(2);
+            let max_sum = target.saturating_mul(3);
+            for (pos, &(_, value)) in sorted.iter().enumerate().skip(selected.len()) {
+                let new_sum = sum.saturating_add(value);
+                if new_sum <= max_sum && ideal.abs_diff(new_sum) < ideal.abs_diff(sum) {
+                    selected.push(pos);
+                    sum = new_sum;
+                }
+            }
+            selected
+        }
+    };
+    Some(positions.into_iter().map(|pos| sorted[pos].0).collect())
+}
+
+/// Take values in order until `target` is reached, returning their
+/// positions.
+fn accumulate(values: &[(usize, u64)], target: u64) -> Option<Vec<usize>> {
+    let mut sum = 0u64;
+    for (pos, &(_, value)) in values.iter().enumerate() {
+        sum = sum.saturating_add(value);
+        if sum >= target {
+            return Some((0..=pos).collect());
+        }
+    }
+    None
+}
+
+/// Depth-first search for a subset of values (sorted descending) summing up
+/// to between `target` and `max_sum`, keeping the one with the least excess.
+struct BranchAndBound<'a> {
+    values: &'a [(usize, u64)],
+    target: u64,
+    max_sum: u64,
+    tries: usize,
+    selection: Vec<usize>,
+    best: Option<(u64, Vec<usize>)>,
+}
+
+impl BranchAndBound<'_> {
+    fn explore(&mut self, pos: usize, sum: u64, remaining: u64) {
+        if self.tries == 0 || sum > self.max_sum {
+            return;
+        }
+        self.tries -= 1;
+        if sum >= self.target {
+            let excess = sum - self.target;
+            if self.best.as_ref().map_or(true, |(best, _)| excess < *best) {
+                self.best = Some((excess, self.selection.clone()));
+            }
+            return;
+        }
+        if pos == self.values.len() || sum.saturating_add(remaining) < self.target {
+            return;
+        }
+        let value = self.values[pos].1;
+        self.selection.push(pos);
+        self.explore(
+            pos + 1,
+            sum.saturating_add(value),
+            remaining.saturating_sub(value),
+        );
+        self.selection.pop();
+        self.explore(pos + 1, sum, remaining.saturating_sub(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        hash::ShaRmd160,
+        script::Script,
+        tx::{Capability, CashToken, FeeRate, Output, TxId},
+        wallet::{
+            select_coins,
+            test_util::{category, input, script, token},
+            CoinSelection, CoinSelectionError, CoinSelectionParams, CoinSelectionStrategy,
+            SelectionTarget, TxBuilder, TxBuilderInput,
+        },
+    };
+
+    fn candidate(value: u64, token: Option<CashToken>) -> TxBuilderInput {
+        input(1, value, token)
+    }
+
+    fn params() -> CoinSelectionParams {
+        CoinSelectionParams {
+            fee_rate: FeeRate::from_sats_per_byte(1),
+            base_size: 10 + 34,
+            change_script: script(),
+            allow_nfts: false,
+        }
+    }
+
+    #[test]
+    fn test_select_bch() {
+        let candidates = [
+            candidate(5_000, None),
+            candidate(100, None),
+            candidate(7_000, None),
+            candidate(3_000, None),
+        ];
+        let select = |sats, strategy| {
+            select_coins(
+                &candidates,
+                &SelectionTarget::sats(sats),
+                &params(),
+                strategy,
+            )
+        };
+
+        let selection = select(9_000, CoinSelectionStrategy::LargestFirst).unwrap();
+        assert_eq!(
+            selection,
+            CoinSelection {
+                indices: vec![0, 2],
+                input_sats: 12_000,
+                tokens: BTreeMap::new(),
+                token_change_sats: 0,
+                fee: 44 + 2 * 141,
+            },
+        );
+
+        // 5000 + 3000 just covers 7500 sats plus the fee, no change needed
+        let selection = select(7_500, CoinSelectionStrategy::BranchAndBound).unwrap();
+        assert_eq!(selection.indices, vec![0, 3]);
+        assert_eq!(selection.fee, 44 + 2 * 141);
+        // No exact match, falls back to largest first
+        let selection = select(12_000, CoinSelectionStrategy::BranchAndBound).unwrap();
+        assert_eq!(selection.indices, vec![0, 2, 3]);
+
+        // The 100 sat coin is worth less than the fee to spend it
+        assert_eq!(
+            select(15_000, CoinSelectionStrategy::LargestFirst),
+            Err(CoinSelectionError::InsufficientFunds {
+                available: 15_000 - 3 * 141,
+                required: 15_044,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_select_random_improve() {
+        let candidates = (1..=20)
+            .map(|value| candidate(value * 1_000, None))
+            .collect::<Vec<_>>();
+        let target = SelectionTarget::sats(10_000);
+        for seed in 0..20 {
+            let strategy = CoinSelectionStrategy::RandomImprove { seed };
+            let selection = select_coins(&candidates, &target, &params(), strategy).unwrap();
+            let required = target.sats + selection.fee;
+            assert!(selection.input_sats >= required);
+            assert!(selection.input_sats - selection.fee <= 3 * 10_044 + 10_000);
+            // Same seed, same selection
+            assert_eq!(
+                select_coins(&candidates, &target, &params(), strategy).unwrap(),
+                selection,
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_tokens() {
+        let category = category();
+        let other = TxId::from([8; 32]);
+        let other_token = token(1_000, None).map(|token| CashToken {
+            category: other,
+            ..token
+        });
+        let nft = Some((Capability::None, [1, 2].as_ref()));
+        let candidates = [
+            candidate(800, token(40, None)),
+            candidate(800, other_token),
+            candidate(800, token(500, nft)),
+            candidate(800, token(70, None)),
+            candidate(50_000, None),
+        ];
+        let target = SelectionTarget::sats(1_000).with_tokens(category, 100);
+
+        // NFT coin and other categories are never selected
+        let selection = select_coins(
+            &candidates,
+            &target,
+            &params(),
+            CoinSelectionStrategy::LargestFirst,
+        )
+        .unwrap();
+        let change_output = Output {
+            value: 0,
+            script: params().change_script,
+            token: token(10, None),
+        };
+        assert_eq!(selection.indices, vec![0, 3, 4]);
+        assert_eq!(selection.tokens, [(category, 110)].into_iter().collect());
+        assert_eq!(selection.token_change_sats, change_output.min_value());
+        assert_eq!(selection.fee, 44 + 3 * 141 + 69);
+
+        assert_eq!(
+            select_coins(
+                &candidates,
+                &SelectionTarget::sats(0).with_tokens(category, 200),
+                &params(),
+                CoinSelectionStrategy::LargestFirst,
+            ),
+            Err(CoinSelectionError::InsufficientTokens {
+                category,
+                available: 110,
+                required: 200,
+            }),
+        );
+
+        // When allowed, the NFT coin is used and its NFT returned as change
+        let mut params = params();
+        params.allow_nfts = true;
+        let selection = select_coins(
+            &candidates,
+            &target,
+            &params,
+            CoinSelectionStrategy::LargestFirst,
+        )
+        .unwrap();
+        let change_output = Output {
+            value: 0,
+            script: params.change_script.clone(),
+            token: token(400, nft),
+        };
+        assert_eq!(selection.indices, vec![2, 4]);
+        assert_eq!(selection.token_change_sats, change_output.min_value());
+    }
+
+    #[test]
+    fn test_builder_select_coins() {
+        let candidates = (0..3)
+            .map(|idx| {
+                let mut candidate = candidate(5_000, None);
+                candidate.prev_out.outpoint_index = idx;
+                candidate
+            })
+            .collect::<Vec<_>>();
+        let mut builder = TxBuilder::default();
+        builder.add_output(Output {
+            value: 7_000,
+            script: Script::p2pkh(&ShaRmd160([2; 20])),
+            token: None,
+        });
+        assert_eq!(
+            builder.select_coins(&candidates, CoinSelectionStrategy::LargestFirst, false),
+            Err(CoinSelectionError::MissingChangeScript),
+        );
+
+        // The coin already spent by the builder isn't selected again
+        builder.change_script = Some(params().change_script);
+        builder.inputs.push(candidates[0].clone());
+        let selection = builder
+            .select_coins(&candidates, CoinSelectionStrategy::LargestFirst, false)
+            .unwrap();
+        assert_eq!(selection.indices, vec![1]);
+        let prev_outs = builder
+            .inputs
+            .iter()
+            .map(|input| input.prev_out.outpoint_index)
+            .collect::<Vec<_>>();
+        assert_eq!(prev_outs, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_select_tokens_bnb() {
+        let category = category();
+        let candidates = [30, 45, 20, 25, 60]
+            .iter()
+            .map(|&amount| candidate(1_000, token(amount, None)))
+            .collect::<Vec<_>>();
+        let target = SelectionTarget::sats(1_000).with_tokens(category, 95);
+        let selection = select_coins(
+            &candidates,
+            &target,
+            &params(),
+            CoinSelectionStrategy::BranchAndBound,
+        )
+        .unwrap();
+        // 45 + 30 + 20 match exactly, so no token change is needed
+        assert_eq!(selection.indices, vec![0, 1, 2]);
+        assert_eq!(selection.tokens[&category], 95);
+        assert_eq!(selection.token_change_sats, 0);
+        // Sats of the token coins cover the target and fee
+        assert_eq!(selection.input_sats, 3_000);
+        assert_eq!(selection.fee, 44 + 3 * 141);
+    }
+}
@@ -0,0 +1,439 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use crate::{
+    policy::PolicyConfig,
+    script::Script,
+    ser::{BitcoinSer, CompactUint},
+    tx::{CashToken, FeeRate, Output, TxId, UnlockingTemplate, MAXIMUM_TOKEN_AMOUNT},
+    wallet::{chain::OUTPUT_COUNT_RESERVE, TxBuilder, TxBuilderError, TxBuilderInput, UnsignedTx},
+};
+
+/// Parameters of [`plan_token_consolidation`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ConsolidationParams {
+    /// Script receiving the consolidated tokens and the freed up BCH.
+    pub script: Script,
+    /// How outputs of `script` are unlocked, used to estimate the fee of
+    /// spending the consolidated outputs.
+    pub template: UnlockingTemplate,
+    /// Number of outputs each tx splits the tokens of its category into;
+    /// always fewer than the number of coins it spends, and at most one per
+    /// token.
+    pub outputs_per_category: usize,
+    /// Categories with fewer coins than this are left alone.
+    pub min_coins: usize,
+    /// Fee rate of the txs.
+    pub fee_rate: FeeRate,
+    /// Fee rate expected when the consolidated outputs are spent, used to
+    /// estimate the fee savings.
+    pub future_fee_rate: FeeRate,
+    /// Maximum estimated size of each tx once signed.
+    pub max_tx_size: usize,
+}
+
+/// Txs consolidating token coins, built by [`plan_token_consolidation`].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ConsolidationPlan {
+    /// Txs to sign and broadcast, independent of each other.
+    pub txs: Vec<UnsignedTx>,
+    /// Sum of the fees of all txs.
+    pub fee: u64,
+    /// Number of coins spent.
+    pub num_coins: usize,
+    /// Number of token outputs the coins are consolidated into.
+    pub num_outputs: usize,
+    /// Fee saved when later spending the consolidated outputs instead of
+    /// the original coins, at [`ConsolidationParams::future_fee_rate`].
+    /// Consolidation pays off if this is larger than `fee`, i.e. when
+    /// consolidating at a lower fee rate than coins are later spent at.
+    pub future_fee_savings: u64,
+}
+
+/// Errors when consolidating coins with [`plan_token_consolidation`].
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ConsolidationError {
+    /// [`ConsolidationParams::outputs_per_category`] is 0.
+    #[error("Consolidating into 0 outputs would burn tokens")]
+    NoOutputs,
+
+    /// Not even two coins fit into a tx of the maximum size.
+    #[error("No consolidation fits into a tx of at most {max_tx_size} bytes")]
+    TxSizeTooSmall {
+        /// Maximum size of a tx.
+        max_tx_size: usize,
+    },
+
+    /// Building a tx failed.
+    #[error("Building tx failed: {0}")]
+    Build(TxBuilderError),
+}
+
+impl ConsolidationParams {
+    /// Params consolidating into 1 output per category, for categories with
+    /// at least 2 coins, at 1 sat/byte (now and in the future) and the
+    /// standard tx size limit.
+    pub fn new(script: Script, template: UnlockingTemplate) -> Self {
+        ConsolidationParams {
+            script,
+            template,
+            outputs_per_category: 1,
+            min_coins: 2,
+            fee_rate: FeeRate::from_sats_per_byte(1),
+            future_fee_rate: FeeRate::from_sats_per_byte(1),
+            max_tx_size: PolicyConfig::default().max_standard_tx_size,
+        }
+    }
+}
+
+/// Plan txs merging coins with fungible tokens of the same category into
+/// [`ConsolidationParams::outputs_per_category`] outputs each.
+///
+/// Coins with NFTs or without tokens are never spent, and categories with
+/// fewer than [`ConsolidationParams::min_coins`] coins are skipped. Coins of
+/// a category that don't fit into one tx, or whose tokens add up to more
+/// than [`MAXIMUM_TOKEN_AMOUNT`], are split over several txs. Token
+/// outputs carry the minimum value to not be dust; the remaining BCH of the
+/// coins pays the fee and is returned in a BCH change output.
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     hash::ShaRmd160,
+/// #     script::Script,
+/// #     ser::CompactUint,
+/// #     tx::{CashToken, Coin, FeeRate, OutPoint, Output, TxId, UnlockingTemplate},
+/// #     wallet::{plan_token_consolidation, ConsolidationParams, TxBuilderInput},
+/// # };
+/// let my_script = Script::p2pkh(&ShaRmd160([1; 20]));
+/// let coins = (0..50u32)
+///     .map(|idx| TxBuilderInput {
+///         prev_out: OutPoint { txid: TxId::from([1; 32]), outpoint_index: idx },
+///         sequence: 0xffff_ffff,
+///         coin: Coin {
+///             output: Output {
+///                 value: 800,
+///                 script: my_script.clone(),
+///                 token: Some(CashToken {
+///                     amount: CompactUint(10),
+///                     category: TxId::from([7; 32]),
+///                     nft: None,
+///                 }),
+///             },
+///             ..Default::default()
+///         },
+///         template: UnlockingTemplate::P2pkhSchnorr,
+///     })
+///     .collect::<Vec<_>>();
+/// let params = ConsolidationParams {
+///     future_fee_rate: FeeRate::from_sats_per_byte(2),
+///     ..ConsolidationParams::new(my_script, UnlockingTemplate::P2pkhSchnorr)
+/// };
+/// let plan = plan_token_consolidation(&coins, &params).unwrap();
+/// assert_eq!(plan.txs.len(), 1);
+/// assert_eq!(plan.num_outputs, 1);
+/// let token = plan.txs[0].tx.outputs[0].token.as_ref().unwrap();
+/// assert_eq!(token.amount, CompactUint(500));
+/// // Spending 1 output instead of 50 coins later saves 49 inputs
+/// assert_eq!(plan.future_fee_savings, 2 * 49 * 141);
+/// assert!(plan.future_fee_savings > plan.fee);
+/// ```
+pub fn plan_token_consolidation(
+    coins: &[TxBuilderInput],
+    params: &ConsolidationParams,
+) -> Result<ConsolidationPlan, ConsolidationError> {
+    if params.outputs_per_category == 0 {
+        return Err(ConsolidationError::NoOutputs);
+    }
+    let mut categories = BTreeMap::<TxId, Vec<&TxBuilderInput>>::new();
+    for input in coins {
+        match &input.coin.output.token {
+            Some(token) if token.nft.is_none() => {
+                categories.entry(token.category).or_default().push(input)
+            }
+            _ => continue,
+        }
+    }
+
+    let mut plan = ConsolidationPlan::default();
+    for (category, inputs) in categories {
+        if inputs.len() < params.min_coins.max(2) {
+            continue;
+        }
+        let mut remaining = inputs.as_slice();
+        while remaining.len() >= 2 {
+            let (builder, num_outputs) = match consolidation_tx(category, remaining, params)? {
+                Some(tx) => tx,
+                // First coin has too many tokens to merge with the next one
+                None => {
+                    remaining = &remaining[1..];
+                    continue;
+                }
+            };
+            let num_coins = builder.inputs.len();
+            let unsigned_tx = builder.build().map_err(ConsolidationError::Build)?;
+            plan.fee += unsigned_tx.fee;
+            plan.num_coins += num_coins;
+            plan.num_outputs += num_outputs;
+            plan.txs.push(unsigned_tx);
+            remaining = &remaining[num_coins..];
+        }
+    }
+    let num_saved_inputs = plan.num_coins - plan.num_outputs;
+    plan.future_fee_savings = params
+        .future_fee_rate
+        .fee(num_saved_inputs * params.template.input_size());
+    Ok(plan)
+}
+
+/// Builder for a tx spending as many of `inputs` as fit into a tx, together
+/// with the number of token outputs. `None` if the tokens of the first two
+/// inputs already exceed [`MAXIMUM_TOKEN_AMOUNT`].
+fn consolidation_tx(
+    category: TxId,
+    inputs: &[&TxBuilderInput],
+    params: &ConsolidationParams,
+) -> Result<Option<(TxBuilder, usize)>, ConsolidationError> {
+    let mut builder = TxBuilder {
+        change_script: Some(params.script.clone()),
+        fee_rate: params.fee_rate,
+        ..Default::default()
+    };
+    // Reserve room for the largest possible token outputs and BCH change
+    let max_token_output = Output {
+        value: 0,
+        script: params.script.clone(),
+        token: Some(CashToken {
+            amount: CompactUint(u64::MAX),
+            category,
+            nft: None,
+        }),
+    };
+    let change_output = Output {
+        value: 0,
+        script: params.script.clone(),
+        token: None,
+    };
+    let mut size = builder.estimate_size()
+        + OUTPUT_COUNT_RESERVE
+        + params.outputs_per_category * max_token_output.ser_len()
+        + change_output.ser_len();
+    let mut amount = 0u64;
+    for &input in inputs {
+        size += input.template.input_size();
+        if size > params.max_tx_size {
+            break;
+        }
+        let token = input.coin.output.token.as_ref().expect("Coin has tokens");
+        // Outputs can't hold more, even the first one with the remainder
+        match amount.checked_add(token.amount.0) {
+            Some(sum) if sum <= MAXIMUM_TOKEN_AMOUNT => amount = sum,
+            _ => break,
+        }
+        builder.inputs.push(input.clone());
+    }
+    if builder.inputs.len() < 2 {
+        if size <= params.max_tx_size {
+            return Ok(None);
+        }
+        return Err(ConsolidationError::TxSizeTooSmall {
+            max_tx_size: params.max_tx_size,
+        });
+    }
+
+    // Split evenly, with the remainder going to the first output
+    let max_outputs = params.outputs_per_category.min(builder.inputs.len() - 1);
+    let num_outputs = (max_outputs as u64).min(amount.max(1));
+    let share = amount / num_outputs;
+    for output_idx in 0..num_outputs {
+        let output_amount = match output_idx {
+            0 => share + amount % num_outputs,
+            _ => share,
+        };
+        let mut output = Output {
+            value: 0,
+            script: params.script.clone(),
+            token: Some(CashToken {
+                amount: CompactUint(output_amount),
+                category,
+                nft: None,
+            }),
+        };
+        output.value = output.min_value();
+        builder.add_output(output);
+    }
+    Ok(Some((builder, num_outputs as usize)))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        ser::{BitcoinSer, CompactUint},
+        tx::{
+            Capability, CashToken, Commitment, NonFungibleTokenCapability, TxId, UnlockingTemplate,
+            MAXIMUM_TOKEN_AMOUNT, NFT,
+        },
+        wallet::{
+            plan_token_consolidation,
+            test_util::{input, script},
+            ConsolidationError, ConsolidationParams, TxBuilderInput, UnsignedTx,
+        },
+    };
+
+    fn coin(idx: u32, category: u8, amount: u64, nft: Option<NFT>) -> TxBuilderInput {
+        let token = CashToken {
+            amount: CompactUint(amount),
+            category: TxId::from([category; 32]),
+            nft,
+        };
+        let mut coin = input(9, 5_000, Some(token));
+        coin.prev_out.outpoint_index = idx;
+        coin
+    }
+
+    fn params() -> ConsolidationParams {
+        ConsolidationParams::new(script(), UnlockingTemplate::P2pkhSchnorr)
+    }
+
+    fn token_amounts(unsigned_tx: &UnsignedTx) -> Vec<u64> {
+        unsigned_tx
+            .tx
+            .outputs
+            .iter()
+            .filter_map(|output| Some(output.token.as_ref()?.amount.0))
+            .collect()
+    }
+
+    #[test]
+    fn test_consolidate_categories() {
+        let nft = NFT {
+            capability: NonFungibleTokenCapability(Capability::None),
+            commitment: Commitment(vec![].into()),
+        };
+        let mut coins = vec![
+            coin(0, 2, 5, None),
+            coin(1, 1, 3, None),
+            coin(2, 2, 6, Some(nft.clone())),
+            coin(3, 2, 7, None),
+            coin(4, 3, 1, None),
+            coin(5, 2, 9, Some(nft)),
+            coin(6, 1, 4, None),
+            coin(7, 2, 1, None),
+        ];
+        // Plain BCH coins are never spent either
+        coins.push(coin(8, 4, 1, None));
+        coins[8].coin.output.token = None;
+
+        let mut params = params();
+        params.outputs_per_category = 2;
+        let plan = plan_token_consolidation(&coins, &params).unwrap();
+        assert_eq!(plan.txs.len(), 2);
+        // Category 3 has a single coin and is skipped
+        let prev_outs = |unsigned_tx: &UnsignedTx| {
+            unsigned_tx
+                .tx
+                .inputs
+                .iter()
+                .map(|input| input.prev_out.outpoint_index)
+                .collect::<Vec<_>>()
+        };
+        // Category 1: 3 + 4 tokens into a single output, fewer than the coins
+        assert_eq!(prev_outs(&plan.txs[0]), vec![1, 6]);
+        assert_eq!(token_amounts(&plan.txs[0]), vec![7]);
+        // Category 2: NFTs are left alone
+        assert_eq!(prev_outs(&plan.txs[1]), vec![0, 3, 7]);
+        assert_eq!(token_amounts(&plan.txs[1]), vec![7, 6]);
+        assert_eq!(plan.num_coins, 5);
+        assert_eq!(plan.num_outputs, 3);
+        assert_eq!(plan.future_fee_savings, 2 * 141);
+        assert_eq!(plan.fee, plan.txs[0].fee + plan.txs[1].fee);
+        for unsigned_tx in &plan.txs {
+            let spent_outputs = unsigned_tx.spent_outputs();
+            assert_eq!(unsigned_tx.tx.check_tokens(&spent_outputs), Ok(()));
+            // Freed up BCH is returned as change
+            assert_eq!(unsigned_tx.tx.outputs.last().unwrap().token, None);
+        }
+
+        // Never more outputs than coins spent
+        params.outputs_per_category = 10;
+        params.min_coins = 3;
+        let plan = plan_token_consolidation(&coins, &params).unwrap();
+        assert_eq!(plan.txs.len(), 1);
+        assert_eq!(token_amounts(&plan.txs[0]), vec![7, 6]);
+
+        // Never more outputs than tokens
+        let coins = vec![
+            coin(0, 1, 1, None),
+            coin(1, 1, 1, None),
+            coin(2, 1, 1, None),
+        ];
+        let plan = plan_token_consolidation(&coins, &params).unwrap();
+        assert_eq!(token_amounts(&plan.txs[0]), vec![2, 1]);
+
+        params.outputs_per_category = 0;
+        assert_eq!(
+            plan_token_consolidation(&coins, &params),
+            Err(ConsolidationError::NoOutputs),
+        );
+    }
+
+    #[test]
+    fn test_consolidate_max_amount() {
+        let coins = vec![
+            coin(0, 1, MAXIMUM_TOKEN_AMOUNT - 10, None),
+            coin(1, 1, 10, None),
+            coin(2, 1, MAXIMUM_TOKEN_AMOUNT, None),
+            coin(3, 1, u64::MAX, None),
+            coin(4, 1, 20, None),
+            coin(5, 1, 30, None),
+        ];
+        let mut params = params();
+        params.outputs_per_category = 2;
+        let plan = plan_token_consolidation(&coins, &params).unwrap();
+        // Token sums never exceed the maximum amount, coins that can't be
+        // merged with the next one are left alone
+        assert_eq!(plan.txs.len(), 2);
+        assert_eq!(token_amounts(&plan.txs[0]), vec![MAXIMUM_TOKEN_AMOUNT]);
+        assert_eq!(token_amounts(&plan.txs[1]), vec![50]);
+        assert_eq!(plan.num_coins, 4);
+    }
+
+    #[test]
+    fn test_consolidate_size_limit() {
+        let coins = (0..100)
+            .map(|idx| coin(idx, 1, 1_000, None))
+            .collect::<Vec<_>>();
+        let mut params = params();
+        params.max_tx_size = 3_000;
+        let plan = plan_token_consolidation(&coins, &params).unwrap();
+        let num_inputs = plan
+            .txs
+            .iter()
+            .map(|unsigned_tx| unsigned_tx.tx.inputs.len())
+            .collect::<Vec<_>>();
+        // 20 Schnorr P2PKH inputs fit into 3000 bytes
+        assert_eq!(num_inputs, vec![20; 5]);
+        assert_eq!(plan.num_coins, 100);
+        assert_eq!(plan.num_outputs, 5);
+        for unsigned_tx in &plan.txs {
+            let size = unsigned_tx
+                .tx
+                .estimate_size(&unsigned_tx.templates)
+                .unwrap()
+                .size;
+            assert!(size <= 3_000);
+            assert!(unsigned_tx.tx.ser_len() < size);
+        }
+
+        params.max_tx_size = 200;
+        assert_eq!(
+            plan_token_consolidation(&coins, &params),
+            Err(ConsolidationError::TxSizeTooSmall { max_tx_size: 200 }),
+        );
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use thiserror::Error;
+
+use crate::{
+    consensus::ConsensusParams,
+    error::DataError,
+    script::Script,
+    ser::CompactUint,
+    tx::{CashToken, OutPoint, Output, TxId, NFT},
+    wallet::{TxBuilder, TxBuilderInput},
+};
+
+/// Tokens of a new category, created by [`TxBuilder::add_token_genesis`].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TokenGenesis {
+    /// Script receiving the new tokens.
+    pub script: Script,
+    /// Fungible supply of the category, 0 to not create fungible tokens.
+    pub amount: u64,
+    /// NFT created alongside the fungible supply, usually a minting NFT to
+    /// mint more NFTs of the category later.
+    pub nft: Option<NFT>,
+}
+
+/// Errors when creating a token category with
+/// [`TxBuilder::add_token_genesis`].
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum TokenGenesisError {
+    /// Only outpoints with index 0 can create a token category.
+    #[error("Outpoint {prev_out:?} can't create a token category, its index must be 0")]
+    NotGenesisOutpoint {
+        /// Outpoint of the input.
+        prev_out: OutPoint,
+    },
+
+    /// The builder already spends the outpoint, so the category is already
+    /// being created.
+    #[error("Category {category} is already created by an input of the tx")]
+    CategoryAlreadyCreated {
+        /// Category of the outpoint.
+        category: TxId,
+    },
+
+    /// The new token is invalid, e.g. it has no tokens or the commitment is
+    /// too long.
+    #[error("Invalid token: {0}")]
+    InvalidToken(DataError),
+}
+
+impl TokenGenesis {
+    /// Create `amount` fungible tokens sent to `script`.
+    pub fn fungible(script: Script, amount: u64) -> Self {
+        TokenGenesis {
+            script,
+            amount,
+            nft: None,
+        }
+    }
+
+    /// Add an NFT to the new tokens.
+    pub fn with_nft(mut self, nft: NFT) -> Self {
+        self.nft = Some(nft);
+        self
+    }
+}
+
+impl TxBuilder {
+    /// Create a new token category by spending `input`, adding the input and
+    /// an output with the new tokens, at the minimum value to not be dust.
+    /// Returns the ID of the new category.
+    ///
+    /// The category ID is the txid of the spent outpoint, with the same byte
+    /// order; only an outpoint with index 0 can create a category. The
+    /// tokens are checked against the latest consensus rules.
+    /// ```
+    /// # use bitcoinsuite_core::{
+    /// #     hash::ShaRmd160,
+    /// #     script::Script,
+    /// #     ser::CompactUint,
+    /// #     tx::{
+    /// #         Capability, Coin, Commitment, NonFungibleTokenCapability, OutPoint, Output,
+    /// #         TxId, UnlockingTemplate, NFT,
+    /// #     },
+    /// #     wallet::{TokenGenesis, TokenGenesisError, TxBuilder, TxBuilderInput},
+    /// # };
+    /// let my_script = Script::p2pkh(&ShaRmd160([1; 20]));
+    /// let txid = "cd0c0ba4a5e9e6d6e4b3c3dcb04c2e19d4a92d5b8f27b6e1c5ba84f1d9e4cc11";
+    /// let mut input = TxBuilderInput {
+    ///     prev_out: OutPoint { txid: txid.parse().unwrap(), outpoint_index: 0 },
+    ///     sequence: 0xffff_ffff,
+    ///     coin: Coin {
+    ///         output: Output { value: 10_000, script: my_script.clone(), token: None },
+    ///         ..Default::default()
+    ///     },
+    ///     template: UnlockingTemplate::P2pkhSchnorr,
+    /// };
+    /// let genesis = TokenGenesis::fungible(my_script.clone(), 1_000_000).with_nft(NFT {
+    ///     capability: NonFungibleTokenCapability(Capability::Minting),
+    ///     commitment: Commitment(vec![].into()),
+    /// });
+    ///
+    /// let mut builder = TxBuilder::default();
+    /// builder.change_script = Some(my_script);
+    /// let category = builder.add_token_genesis(input.clone(), genesis.clone()).unwrap();
+    /// // Category IDs are displayed like the txid they come from
+    /// assert_eq!(category.to_string(), txid);
+    /// let unsigned_tx = builder.build().unwrap();
+    /// let token = unsigned_tx.tx.outputs[0].token.as_ref().unwrap();
+    /// assert_eq!(token.category, category);
+    /// assert_eq!(token.amount, CompactUint(1_000_000));
+    ///
+    /// input.prev_out.outpoint_index = 1;
+    /// assert_eq!(
+    ///     TxBuilder::default().add_token_genesis(input.clone(), genesis),
+    ///     Err(TokenGenesisError::NotGenesisOutpoint { prev_out: input.prev_out }),
+    /// );
+    /// ```
+    pub fn add_token_genesis(
+        &mut self,
+        input: TxBuilderInput,
+        genesis: TokenGenesis,
+    ) -> Result<TxId, TokenGenesisError> {
+        if input.prev_out.outpoint_index != 0 {
+            return Err(TokenGenesisError::NotGenesisOutpoint {
+                prev_out: input.prev_out,
+            });
+        }
+        let category = input.prev_out.txid;
+        if self
+            .inputs
+            .iter()
+            .any(|other| other.prev_out == input.prev_out)
+        {
+            return Err(TokenGenesisError::CategoryAlreadyCreated { category });
+        }
+        let token = CashToken {
+            amount: CompactUint(genesis.amount),
+            category,
+            nft: genesis.nft,
+        };
+        token
+            .validate(&ConsensusParams::latest())
+            .map_err(TokenGenesisError::InvalidToken)?;
+        let mut output = Output {
+            value: 0,
+            script: genesis.script,
+            token: Some(token),
+        };
+        output.value = output.min_value();
+        self.inputs.push(input);
+        self.outputs.push(output);
+        Ok(category)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        error::DataError,
+        hash::ShaRmd160,
+        script::Script,
+        ser::CompactUint,
+        tx::{
+            Capability, CashToken, Coin, Commitment, NonFungibleTokenCapability, OutPoint, Output,
+            TxId, UnlockingTemplate, NFT,
+        },
+        wallet::{TokenGenesis, TokenGenesisError, TxBuilder, TxBuilderInput},
+    };
+
+    fn genesis_input(value: u64, token: Option<CashToken>) -> TxBuilderInput {
+        TxBuilderInput {
+            prev_out: OutPoint {
+                txid: TxId::from([3; 32]),
+                outpoint_index: 0,
+            },
+            sequence: 0xffff_ffff,
+            coin: Coin {
+                output: Output {
+                    value,
+                    script: Script::p2pkh(&ShaRmd160([1; 20])),
+                    token,
+                },
+                ..Default::default()
+            },
+            template: UnlockingTemplate::P2pkhSchnorr,
+        }
+    }
+
+    #[test]
+    fn test_token_genesis() {
+        let script = Script::p2pkh(&ShaRmd160([1; 20]));
+        let nft = NFT {
+            capability: NonFungibleTokenCapability(Capability::Minting),
+            commitment: Commitment(vec![1, 2, 3].into()),
+        };
+        // The genesis coin itself carries tokens of another category, which
+        // are returned as change
+        let other_token = CashToken {
+            amount: CompactUint(5),
+            category: TxId::from([9; 32]),
+            nft: None,
+        };
+        let mut builder = TxBuilder {
+            change_script: Some(script.clone()),
+            ..Default::default()
+        };
+        let category = builder
+            .add_token_genesis(
+                genesis_input(5_000, Some(other_token.clone())),
+                TokenGenesis {
+                    script: script.clone(),
+                    amount: 0,
+                    nft: Some(nft.clone()),
+                },
+            )
+            .unwrap();
+        assert_eq!(category, TxId::from([3; 32]));
+
+        let unsigned_tx = builder.build().unwrap();
+        let outputs = &unsigned_tx.tx.outputs;
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(
+            outputs[0].token,
+            Some(CashToken {
+                amount: CompactUint(0),
+                category,
+                nft: Some(nft),
+            }),
+        );
+        assert_eq!(outputs[1].token, Some(other_token));
+        assert_eq!(outputs[2].token, None);
+        let spent_outputs = [unsigned_tx.spent_coins[0].output.clone()];
+        assert_eq!(unsigned_tx.tx.check_tokens(&spent_outputs), Ok(()));
+    }
+
+    #[test]
+    fn test_token_genesis_errors() {
+        let script = Script::p2pkh(&ShaRmd160([1; 20]));
+        let mut builder = TxBuilder::default();
+        assert_eq!(
+            builder.add_token_genesis(genesis_input(5_000, None), TokenGenesis::default()),
+            Err(TokenGenesisError::InvalidToken(DataError::NoTokens {
+                error: "Invalid token prefix: must encode at least one token".to_string(),
+            })),
+        );
+        let genesis = TokenGenesis::fungible(script.clone(), 1).with_nft(NFT {
+            capability: NonFungibleTokenCapability(Capability::None),
+            commitment: Commitment(vec![0; 200].into()),
+        });
+        assert_eq!(
+            builder.add_token_genesis(genesis_input(5_000, None), genesis),
+            Err(TokenGenesisError::InvalidToken(
+                DataError::CommitmentTooLong {
+                    max_length: 128,
+                    actual: 200,
+                }
+            )),
+        );
+        assert!(builder.inputs.is_empty());
+
+        let genesis = TokenGenesis::fungible(script, 1);
+        builder
+            .add_token_genesis(genesis_input(5_000, None), genesis.clone())
+            .unwrap();
+        assert_eq!(
+            builder.add_token_genesis(genesis_input(5_000, None), genesis),
+            Err(TokenGenesisError::CategoryAlreadyCreated {
+                category: TxId::from([3; 32]),
+            }),
+        );
+    }
+}
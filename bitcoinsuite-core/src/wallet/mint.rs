@@ -0,0 +1,464 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use thiserror::Error;
+
+use crate::{
+    consensus::ConsensusParams,
+    error::DataError,
+    policy::PolicyConfig,
+    script::Script,
+    ser::{BitcoinSer, CompactUint},
+    tx::{
+        Capability, CashToken, Commitment, FeeRate, NonFungibleTokenCapability, OutPoint, Output,
+        Tx, TxId, UnlockingTemplate, NFT,
+    },
+    wallet::{
+        chain::{PendingTx, TxChainError, OUTPUT_COUNT_RESERVE},
+        TxBuilder, TxBuilderError, TxBuilderInput, UnsignedTx,
+    },
+};
+
+/// Parameters of the txs built by [`NftMinter`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct NftMintParams {
+    /// Script receiving the minted NFTs.
+    pub nft_script: Script,
+    /// Script receiving BCH change, which funds the next tx of the chain.
+    pub change_script: Script,
+    /// How BCH change outputs are unlocked when spent by the next tx.
+    pub change_template: UnlockingTemplate,
+    /// Fee rate of the txs.
+    pub fee_rate: FeeRate,
+    /// Maximum estimated size of each tx once signed.
+    pub max_tx_size: usize,
+}
+
+/// Mints a collection of immutable NFTs with a minting NFT (the "baton"),
+/// over as many txs as needed to keep each one under
+/// [`NftMintParams::max_tx_size`].
+///
+/// Every tx spends the baton and BCH to pay fees, sends the baton on
+/// unchanged in its first output, mints as many NFTs as fit and returns BCH
+/// change to fund the next tx. As the txid of a tx is only known once it is
+/// signed, txs are built one at a time: build the next tx with
+/// [`NftMinter::next_tx`], sign it and pass it to [`NftMinter::advance`].
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     hash::ShaRmd160,
+/// #     script::Script,
+/// #     ser::CompactUint,
+/// #     tx::{
+/// #         Capability, CashToken, Coin, Commitment, FeeRate, NonFungibleTokenCapability,
+/// #         OutPoint, Output, TxId, UnlockingTemplate, NFT,
+/// #     },
+/// #     wallet::{NftMintParams, NftMinter, TxBuilderInput, UnsignedTx},
+/// # };
+/// let my_script = Script::p2pkh(&ShaRmd160([1; 20]));
+/// let input = |txid: u8, value: u64, token: Option<CashToken>| TxBuilderInput {
+///     prev_out: OutPoint { txid: TxId::from([txid; 32]), outpoint_index: 0 },
+///     sequence: 0xffff_ffff,
+///     coin: Coin {
+///         output: Output { value, script: my_script.clone(), token },
+///         ..Default::default()
+///     },
+///     template: UnlockingTemplate::P2pkhSchnorr,
+/// };
+/// let baton = input(
+///     1,
+///     1_000,
+///     Some(CashToken {
+///         amount: CompactUint(0),
+///         category: TxId::from([7; 32]),
+///         nft: Some(NFT {
+///             capability: NonFungibleTokenCapability(Capability::Minting),
+///             commitment: Commitment(vec![].into()),
+///         }),
+///     }),
+/// );
+/// let commitments = (0..1000u32)
+///     .map(|idx| Commitment(idx.to_le_bytes().to_vec().into()))
+///     .collect();
+/// let params = NftMintParams {
+///     max_tx_size: 10_000,
+///     ..NftMintParams::new(my_script.clone(), my_script.clone(), UnlockingTemplate::P2pkhSchnorr)
+/// };
+/// let mut minter =
+///     NftMinter::new(baton, vec![input(2, 1_000_000, None)], commitments, params).unwrap();
+///
+/// let sign = |_: &UnsignedTx, _: usize| Ok::<_, ()>(Script::new(vec![0; 100].into()));
+/// let mut txs = Vec::new();
+/// while let Some(unsigned_tx) = minter.next_tx().unwrap() {
+///     let tx = unsigned_tx.sign(&sign).unwrap();
+///     minter.advance(&tx).unwrap();
+///     txs.push(tx);
+/// }
+/// assert_eq!(txs.len(), 8);
+/// assert_eq!(minter.num_remaining(), 0);
+/// // The baton is kept alive in the first output of the last tx
+/// assert_eq!(txs[7].outputs[0].token, txs[0].outputs[0].token);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NftMinter {
+    params: NftMintParams,
+    baton: TxBuilderInput,
+    funding: Vec<TxBuilderInput>,
+    commitments: Vec<Commitment>,
+    num_minted: usize,
+    /// Tx built by [`NftMinter::next_tx`] and the number of NFTs it mints.
+    pending: PendingTx<usize>,
+}
+
+/// Errors when minting NFTs with [`NftMinter`].
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum NftMintError {
+    /// The baton doesn't have a minting NFT.
+    #[error("Baton {prev_out:?} doesn't have a minting NFT")]
+    NotMintingBaton {
+        /// Outpoint of the baton.
+        prev_out: OutPoint,
+    },
+
+    /// A commitment is invalid, e.g. too long.
+    #[error("Invalid commitment at index {idx}: {error}")]
+    InvalidCommitment {
+        /// Index of the commitment.
+        idx: usize,
+        /// Why the commitment is invalid.
+        error: DataError,
+    },
+
+    /// Not even a single NFT fits into a tx of the maximum size.
+    #[error("No NFT fits into a tx of at most {max_tx_size} bytes")]
+    TxSizeTooSmall {
+        /// Maximum size of a tx.
+        max_tx_size: usize,
+    },
+
+    /// Building a tx failed, e.g. there's not enough BCH to pay the fee.
+    #[error("Building tx failed: {0}")]
+    Build(TxBuilderError),
+
+    /// [`NftMinter::advance`] was called without a tx from
+    /// [`NftMinter::next_tx`].
+    #[error("No tx is waiting to be signed")]
+    NoPendingTx,
+
+    /// The signed tx doesn't match the tx built by [`NftMinter::next_tx`].
+    #[error("Signed tx {txid} doesn't match the built tx")]
+    TxMismatch {
+        /// TxId of the signed tx.
+        txid: TxId,
+    },
+}
+
+impl TxChainError for NftMintError {
+    fn no_pending_tx() -> Self {
+        NftMintError::NoPendingTx
+    }
+
+    fn tx_mismatch(txid: TxId) -> Self {
+        NftMintError::TxMismatch { txid }
+    }
+}
+
+impl NftMintParams {
+    /// Params sending NFTs to `nft_script` and change to `change_script`,
+    /// at 1 sat/byte and the standard tx size limit.
+    pub fn new(
+        nft_script: Script,
+        change_script: Script,
+        change_template: UnlockingTemplate,
+    ) -> Self {
+        NftMintParams {
+            nft_script,
+            change_script,
+            change_template,
+            fee_rate: FeeRate::from_sats_per_byte(1),
+            max_tx_size: PolicyConfig::default().max_standard_tx_size,
+        }
+    }
+}
+
+impl NftMinter {
+    /// Create a minter minting one immutable NFT per commitment with the
+    /// minting NFT of `baton`, paying fees with the `funding` coins.
+    ///
+    /// Fails if `baton` doesn't have a minting NFT or if any commitment is
+    /// invalid under the latest consensus rules.
+    pub fn new(
+        baton: TxBuilderInput,
+        funding: Vec<TxBuilderInput>,
+        commitments: Vec<Commitment>,
+        params: NftMintParams,
+    ) -> Result<Self, NftMintError> {
+        let category = match &baton.coin.output.token {
+            Some(CashToken {
+                category,
+                nft: Some(nft),
+                ..
+            }) if nft.capability.0 == Capability::Minting => *category,
+            _ => {
+                return Err(NftMintError::NotMintingBaton {
+                    prev_out: baton.prev_out,
+                })
+            }
+        };
+        let consensus_params = ConsensusParams::latest();
+        for (idx, commitment) in commitments.iter().enumerate() {
+            immutable_nft(category, commitment.clone())
+                .validate(&consensus_params)
+                .map_err(|error| NftMintError::InvalidCommitment { idx, error })?;
+        }
+        Ok(NftMinter {
+            params,
+            baton,
+            funding,
+            commitments,
+            num_minted: 0,
+            pending: PendingTx::default(),
+        })
+    }
+
+    /// Number of NFTs not minted yet by a signed tx.
+    pub fn num_remaining(&self) -> usize {
+        self.commitments.len() - self.num_minted
+    }
+
+    /// Build the next tx of the chain, minting as many of the remaining NFTs
+    /// as fit, or `None` if all NFTs have been minted.
+    ///
+    /// Building again before calling [`NftMinter::advance`] returns the same
+    /// tx.
+    pub fn next_tx(&mut self) -> Result<Option<UnsignedTx>, NftMintError> {
+        if self.num_remaining() == 0 {
+            return Ok(None);
+        }
+        let category = self.category();
+        let mut builder = TxBuilder {
+            change_script: Some(self.params.change_script.clone()),
+            fee_rate: self.params.fee_rate,
+            ..Default::default()
+        };
+        builder.inputs.push(self.baton.clone());
+        builder.inputs.extend(self.funding.iter().cloned());
+        builder.add_output(self.baton.coin.output.clone());
+
+        let mut size = builder.estimate_size()
+            + OUTPUT_COUNT_RESERVE
+            + Output {
+                value: 0,
+                script: self.params.change_script.clone(),
+                token: None,
+            }
+            .ser_len();
+        let mut num_nfts = 0;
+        for commitment in &self.commitments[self.num_minted..] {
+            let mut output = Output {
+                value: 0,
+                script: self.params.nft_script.clone(),
+                token: Some(immutable_nft(category, commitment.clone())),
+            };
+            output.value = output.min_value();
+            size += output.ser_len();
+            if size > self.params.max_tx_size {
+                break;
+            }
+            builder.add_output(output);
+            num_nfts += 1;
+        }
+        if num_nfts == 0 {
+            return Err(NftMintError::TxSizeTooSmall {
+                max_tx_size: self.params.max_tx_size,
+            });
+        }
+
+        let unsigned_tx = builder.build().map_err(NftMintError::Build)?;
+        self.pending.set(unsigned_tx.clone(), num_nfts);
+        Ok(Some(unsigned_tx))
+    }
+
+    /// Continue the chain after `tx`, the signed tx built by
+    /// [`NftMinter::next_tx`]: the baton and BCH change of `tx` are spent
+    /// by the next tx.
+    pub fn advance(&mut self, tx: &Tx) -> Result<(), NftMintError> {
+        let num_nfts = self.pending.take_signed::<NftMintError>(tx)?;
+        self.baton = TxBuilderInput::from_tx_output(tx, 0, self.baton.template);
+        self.funding.clear();
+        // Change, if any, comes after the baton and the NFTs
+        let change_idx = 1 + num_nfts;
+        if change_idx < tx.outputs.len() {
+            self.funding.push(TxBuilderInput::from_tx_output(
+                tx,
+                change_idx,
+                self.params.change_template,
+            ));
+        }
+        self.num_minted += num_nfts;
+        Ok(())
+    }
+
+    fn category(&self) -> TxId {
+        self.baton
+            .coin
+            .output
+            .token
+            .as_ref()
+            .expect("Baton has a token")
+            .category
+    }
+}
+
+/// Immutable NFT of `category` with the given commitment.
+fn immutable_nft(category: TxId, commitment: Commitment) -> CashToken {
+    CashToken {
+        amount: CompactUint(0),
+        category,
+        nft: Some(NFT {
+            capability: NonFungibleTokenCapability(Capability::None),
+            commitment,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        error::DataError,
+        ser::{BitcoinSer, CompactUint},
+        tx::{
+            Capability, CashToken, Commitment, NonFungibleTokenCapability, OutPoint, Transaction,
+            Tx, TxId, UnlockingTemplate, NFT,
+        },
+        wallet::{
+            test_util::{input, script, sign},
+            NftMintError, NftMintParams, NftMinter, TxBuilderError,
+        },
+    };
+
+    fn nft_token(capability: Capability, commitment: Vec<u8>) -> Option<CashToken> {
+        Some(CashToken {
+            amount: CompactUint(50),
+            category: TxId::from([7; 32]),
+            nft: Some(NFT {
+                capability: NonFungibleTokenCapability(capability),
+                commitment: Commitment(commitment.into()),
+            }),
+        })
+    }
+
+    fn params() -> NftMintParams {
+        NftMintParams {
+            max_tx_size: 3_000,
+            ..NftMintParams::new(script(), script(), UnlockingTemplate::P2pkhSchnorr)
+        }
+    }
+
+    #[test]
+    fn test_mint_nfts() {
+        let baton = input(1, 2_000, nft_token(Capability::Minting, vec![]));
+        let commitments = (0..100u8)
+            .map(|idx| Commitment(vec![idx; 40].into()))
+            .collect::<Vec<_>>();
+        let funding = vec![input(2, 50_000, None), input(3, 50_000, None)];
+        let mut minter =
+            NftMinter::new(baton.clone(), funding, commitments.clone(), params()).unwrap();
+
+        let mut minted = Vec::new();
+        let mut prev_baton = baton.prev_out;
+        while let Some(unsigned_tx) = minter.next_tx().unwrap() {
+            // Building again gives the same tx
+            assert_eq!(minter.next_tx().unwrap(), Some(unsigned_tx.clone()));
+            let spent_outputs = unsigned_tx.spent_outputs();
+            let tx = unsigned_tx.sign(&sign).unwrap();
+            assert!(tx.ser_len() <= 3_000);
+            assert_eq!(tx.check_tokens(&spent_outputs), Ok(()));
+            assert_eq!(tx.inputs[0].prev_out, prev_baton);
+            assert_eq!(tx.outputs[0], baton.coin.output);
+            minted.extend(
+                tx.outputs[1..]
+                    .iter()
+                    .filter_map(|output| output.token.as_ref()?.nft.as_ref())
+                    .map(|nft| nft.commitment.clone()),
+            );
+            minter.advance(&tx).unwrap();
+            prev_baton = OutPoint {
+                txid: tx.txid(),
+                outpoint_index: 0,
+            };
+            assert_eq!(minter.num_remaining(), 100 - minted.len());
+        }
+        assert_eq!(minted, commitments);
+    }
+
+    #[test]
+    fn test_mint_nfts_errors() {
+        let baton = input(1, 2_000, nft_token(Capability::Mutable, vec![]));
+        assert_eq!(
+            NftMinter::new(baton.clone(), vec![], vec![], params()),
+            Err(NftMintError::NotMintingBaton {
+                prev_out: baton.prev_out,
+            }),
+        );
+
+        let baton = input(1, 2_000, nft_token(Capability::Minting, vec![]));
+        let commitments = vec![Commitment(vec![1].into()), Commitment(vec![2; 200].into())];
+        assert_eq!(
+            NftMinter::new(baton.clone(), vec![], commitments, params()),
+            Err(NftMintError::InvalidCommitment {
+                idx: 1,
+                error: DataError::CommitmentTooLong {
+                    max_length: 128,
+                    actual: 200,
+                },
+            }),
+        );
+
+        let commitments = vec![Commitment(vec![1].into())];
+        let mut minter =
+            NftMinter::new(baton.clone(), vec![], commitments.clone(), params()).unwrap();
+        assert!(matches!(
+            minter.next_tx(),
+            Err(NftMintError::Build(
+                TxBuilderError::InsufficientFunds { .. }
+            )),
+        ));
+
+        let mut minter = NftMinter::new(
+            baton.clone(),
+            vec![input(2, 10_000, None)],
+            commitments.clone(),
+            params(),
+        )
+        .unwrap();
+        let other_minter = minter.clone();
+        let tx = minter.next_tx().unwrap().unwrap().sign(&sign).unwrap();
+        assert_eq!(
+            other_minter.clone().advance(&tx),
+            Err(NftMintError::NoPendingTx),
+        );
+        let mut other_tx = Transaction::clone(&tx);
+        other_tx.outputs[1].value += 1;
+        let other_tx = Tx::with_txid(TxId::from_tx(&other_tx), other_tx);
+        assert_eq!(
+            minter.advance(&other_tx),
+            Err(NftMintError::TxMismatch {
+                txid: other_tx.txid(),
+            }),
+        );
+        assert_eq!(minter.advance(&tx), Ok(()));
+        assert_eq!(minter.next_tx(), Ok(None));
+
+        let mut params = params();
+        params.max_tx_size = 300;
+        let mut minter =
+            NftMinter::new(baton, vec![input(2, 10_000, None)], commitments, params).unwrap();
+        assert_eq!(
+            minter.next_tx(),
+            Err(NftMintError::TxSizeTooSmall { max_tx_size: 300 }),
+        );
+    }
+}
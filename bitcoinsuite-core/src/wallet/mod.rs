@@ -0,0 +1,27 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+//! Module for building and signing txs, e.g. [`TxBuilder`], and selecting
+//! the coins they spend, see [`select_coins`].
+
+mod airdrop;
+mod builder;
+mod chain;
+mod coin_select;
+mod consolidate;
+mod genesis;
+mod mint;
+mod partial_tx;
+mod swap;
+#[cfg(test)]
+pub(crate) mod test_util;
+
+pub use self::airdrop::*;
+pub use self::builder::*;
+pub use self::coin_select::*;
+pub use self::consolidate::*;
+pub use self::genesis::*;
+pub use self::mint::*;
+pub use self::partial_tx::*;
+pub use self::swap::*;
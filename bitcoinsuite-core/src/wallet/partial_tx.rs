@@ -0,0 +1,929 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use thiserror::Error;
+
+use crate::{
+    bytes::read_bytes,
+    error::DataError,
+    hash::{Hashed, Sha256d, ShaRmd160},
+    script::{MultisigMode, PubKey, PubKeyVariant, Script, ScriptMut, ScriptVariant},
+    ser::{read_compact_size, write_compact_size, BitcoinSer, BitcoinSerializer},
+    tx::{
+        Coin, Output, SigHashError, SigHashType, SignatureError, Transaction, Tx, TxId, TxSignature,
+    },
+    wallet::UnsignedTx,
+};
+
+/// Magic bytes at the start of a serialized [`PartialTx`], ending with the
+/// version of the format.
+pub const PARTIAL_TX_MAGIC: [u8; 5] = *b"bcpt\x01";
+
+/// Tx shared between parties that sign it independently, e.g. the keyholders
+/// of a multisig wallet, together with everything needed to sign and
+/// finalize each input.
+///
+/// BCH has no standard format for this, so [`PartialTx`] uses its own:
+/// [`PARTIAL_TX_MAGIC`] followed by the fields with [`BitcoinSer`]. With the
+/// `serde` feature, it can also be (de)serialized as JSON, with scripts and
+/// signatures as hex.
+///
+/// Each signer adds signatures with [`PartialTx::add_signature`], signed
+/// copies are merged with [`PartialTx::combine`] and once enough signatures
+/// are collected, [`PartialTx::finalize`] builds the scriptSigs.
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     hash::{Hashed, ShaRmd160},
+/// #     script::{PubKey, Script},
+/// #     ser::BitcoinSer,
+/// #     tx::{Coin, OutPoint, Output, SigHashType, TxId, UnlockingTemplate},
+/// #     wallet::{PartialTx, TxBuilder},
+/// # };
+/// let pubkey = PubKey([2; 33]);
+/// let my_script = Script::p2pkh(&ShaRmd160::digest(pubkey.as_slice()));
+/// let mut builder = TxBuilder::default();
+/// builder.change_script = Some(my_script.clone());
+/// builder.add_input(
+///     OutPoint { txid: TxId::from([1; 32]), outpoint_index: 0 },
+///     Coin {
+///         output: Output { value: 10_000, script: my_script, token: None },
+///         ..Default::default()
+///     },
+///     UnlockingTemplate::P2pkhSchnorr,
+/// );
+/// let mut partial_tx = PartialTx::new(&builder.build().unwrap(), SigHashType::ALL_FORKID);
+///
+/// // Pass around as bytes
+/// let mut received = PartialTx::deser(&mut partial_tx.ser()).unwrap();
+/// let sighash = received.sighash(0).unwrap();
+/// // Sign `sighash` with the key of `pubkey`, then add the signature
+/// let sig = [[3; 64].as_ref(), &[0x41]].concat();
+/// received.add_signature(0, pubkey, sig.clone().into()).unwrap();
+///
+/// partial_tx.combine(&received).unwrap();
+/// let tx = partial_tx.finalize().unwrap();
+/// assert_eq!(
+///     tx.inputs[0].script.bytecode().as_ref(),
+///     [[65].as_ref(), &sig, &[33], &[2; 33]].concat(),
+/// );
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "json::PartialTxJson", try_from = "json::PartialTxJson")
+)]
+pub struct PartialTx {
+    /// Tx being signed, with empty scriptSigs.
+    pub tx: Transaction,
+    /// Signing data of each input of `tx`.
+    pub inputs: Vec<PartialInput>,
+}
+
+/// Signing data of an input of a [`PartialTx`].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PartialInput {
+    /// Coin spent by the input.
+    pub coin: Coin,
+    /// Redeem script, if the coin is P2SH or P2SH32.
+    pub redeem_script: Option<Script>,
+    /// Sighash type all signatures of the input must use.
+    pub sig_hash_type: SigHashType,
+    /// Signatures collected so far, including the sighash byte, by pubkey.
+    pub partial_sigs: BTreeMap<PubKey, Bytes>,
+    /// Complete scriptSig, for inputs which can't be finalized from
+    /// signatures alone, e.g. covenants.
+    pub final_script_sig: Option<Script>,
+}
+
+/// Errors when signing, combining or finalizing a [`PartialTx`].
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum PartialTxError {
+    /// There's no input with that index.
+    #[error("Input {input_idx} out of range, tx has {num_inputs} inputs")]
+    InputIndexOutOfRange {
+        /// Index of the input.
+        input_idx: usize,
+        /// Number of inputs of the tx.
+        num_inputs: usize,
+    },
+
+    /// The number of partial inputs doesn't match the inputs of the tx.
+    #[error("Partial tx has {num_partial_inputs} inputs, but the tx has {num_tx_inputs}")]
+    InputCountMismatch {
+        /// Number of inputs of the tx.
+        num_tx_inputs: usize,
+        /// Number of [`PartialInput`]s.
+        num_partial_inputs: usize,
+    },
+
+    /// Partial txs with different txs can't be combined.
+    #[error("Can't combine partial txs of different txs: {txid} and {other_txid}")]
+    TxMismatch {
+        /// TxId of the unsigned tx.
+        txid: TxId,
+        /// TxId of the other unsigned tx.
+        other_txid: TxId,
+    },
+
+    /// Two partial txs disagree on the data of an input.
+    #[error("Partial txs disagree on {field} of input {input_idx}")]
+    InputMismatch {
+        /// Index of the input.
+        input_idx: usize,
+        /// Field of the input that differs.
+        field: &'static str,
+    },
+
+    /// A pubkey has two different signatures for the same input.
+    #[error("Conflicting signatures of {pubkey:?} for input {input_idx}")]
+    ConflictingSignature {
+        /// Index of the input.
+        input_idx: usize,
+        /// Pubkey of the signatures.
+        pubkey: PubKey,
+    },
+
+    /// The signature doesn't end with the sighash type of the input.
+    #[error("Signature for input {input_idx} must have sighash type {expected:?}, got {actual:?}")]
+    WrongSigHashType {
+        /// Index of the input.
+        input_idx: usize,
+        /// Sighash type of the input.
+        expected: SigHashType,
+        /// Sighash type of the signature, `None` if it's empty.
+        actual: Option<SigHashType>,
+    },
+
+    /// The signature is not a valid ECDSA or Schnorr signature.
+    #[error("Invalid signature for input {input_idx}: {error}")]
+    InvalidSignature {
+        /// Index of the input.
+        input_idx: usize,
+        /// Why the signature is invalid.
+        error: SignatureError,
+    },
+
+    /// The coin is P2SH or P2SH32, but the redeem script is missing.
+    #[error("Input {input_idx} is missing its redeem script")]
+    MissingRedeemScript {
+        /// Index of the input.
+        input_idx: usize,
+    },
+
+    /// The redeem script doesn't hash to the script of the coin.
+    #[error("Redeem script of input {input_idx} doesn't match the coin")]
+    RedeemScriptMismatch {
+        /// Index of the input.
+        input_idx: usize,
+    },
+
+    /// The signatures of a multisig input mix Schnorr and ECDSA, which no
+    /// multisig mode accepts.
+    #[error("Input {input_idx} mixes Schnorr and ECDSA multisig signatures")]
+    MixedMultisigSignatures {
+        /// Index of the input.
+        input_idx: usize,
+    },
+
+    /// Not enough signatures have been collected to finalize the input.
+    #[error("Input {input_idx} needs {required} signatures, has {actual}")]
+    MissingSignatures {
+        /// Index of the input.
+        input_idx: usize,
+        /// Number of signatures required.
+        required: usize,
+        /// Number of usable signatures.
+        actual: usize,
+    },
+
+    /// The input can't be finalized from signatures, it needs a
+    /// [`PartialInput::final_script_sig`].
+    #[error("Can't finalize input {input_idx}, unsupported script")]
+    UnsupportedScript {
+        /// Index of the input.
+        input_idx: usize,
+    },
+
+    /// Computing the sighash failed.
+    #[error("Computing sighash failed: {0}")]
+    SigHash(SigHashError),
+}
+
+impl PartialTx {
+    /// Partial tx to sign `unsigned_tx`, with every input using
+    /// `sig_hash_type`.
+    pub fn new(unsigned_tx: &UnsignedTx, sig_hash_type: SigHashType) -> Self {
+        PartialTx {
+            tx: unsigned_tx.tx.clone(),
+            inputs: unsigned_tx
+                .spent_coins
+                .iter()
+                .map(|coin| PartialInput {
+                    coin: coin.clone(),
+                    redeem_script: None,
+                    sig_hash_type,
+                    partial_sigs: BTreeMap::new(),
+                    final_script_sig: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Sighash to sign for input `input_idx`, using the redeem script (or
+    /// the script of the coin) as script code.
+    pub fn sighash(&self, input_idx: usize) -> Result<Sha256d, PartialTxError> {
+        self.check_input_count()?;
+        let input = self.input(input_idx)?;
+        let script_code = input
+            .redeem_script
+            .as_ref()
+            .unwrap_or(&input.coin.output.script);
+        self.tx
+            .sighash(
+                input_idx,
+                &self.spent_outputs(),
+                script_code,
+                input.sig_hash_type,
+            )
+            .map_err(PartialTxError::SigHash)
+    }
+
+    /// Add a signature of `pubkey` for input `input_idx`. The signature must
+    /// be a valid [`TxSignature`] with the sighash type of the input.
+    pub fn add_signature(
+        &mut self,
+        input_idx: usize,
+        pubkey: PubKey,
+        sig: Bytes,
+    ) -> Result<(), PartialTxError> {
+        let input = self.input_mut(input_idx)?;
+        let actual = sig.last().map(|&byte| SigHashType(byte));
+        if actual != Some(input.sig_hash_type) {
+            return Err(PartialTxError::WrongSigHashType {
+                input_idx,
+                expected: input.sig_hash_type,
+                actual,
+            });
+        }
+        TxSignature::try_from(sig.as_ref())
+            .map_err(|error| PartialTxError::InvalidSignature { input_idx, error })?;
+        match input.partial_sigs.get(&pubkey) {
+            Some(existing) if *existing != sig => {
+                Err(PartialTxError::ConflictingSignature { input_idx, pubkey })
+            }
+            _ => {
+                input.partial_sigs.insert(pubkey, sig);
+                Ok(())
+            }
+        }
+    }
+
+    /// Merge the signatures, redeem scripts and final scriptSigs of `other`
+    /// into this partial tx. Both must be for the same tx and agree on the
+    /// coins and sighash types.
+    pub fn combine(&mut self, other: &PartialTx) -> Result<(), PartialTxError> {
+        if self.tx != other.tx || self.inputs.len() != other.inputs.len() {
+            return Err(PartialTxError::TxMismatch {
+                txid: TxId::from_tx(&self.tx),
+                other_txid: TxId::from_tx(&other.tx),
+            });
+        }
+        // Check everything first, so a failed combine leaves self untouched
+        for (input_idx, (input, other)) in self.inputs.iter().zip(&other.inputs).enumerate() {
+            let mismatch = |field| PartialTxError::InputMismatch { input_idx, field };
+            if input.coin != other.coin {
+                return Err(mismatch("coin"));
+            }
+            if input.sig_hash_type != other.sig_hash_type {
+                return Err(mismatch("sig_hash_type"));
+            }
+            if is_conflict(&input.redeem_script, &other.redeem_script) {
+                return Err(mismatch("redeem_script"));
+            }
+            if is_conflict(&input.final_script_sig, &other.final_script_sig) {
+                return Err(mismatch("final_script_sig"));
+            }
+            for (pubkey, sig) in &other.partial_sigs {
+                if matches!(input.partial_sigs.get(pubkey), Some(existing) if existing != sig) {
+                    return Err(PartialTxError::ConflictingSignature {
+                        input_idx,
+                        pubkey: *pubkey,
+                    });
+                }
+            }
+        }
+        for (input, other) in self.inputs.iter_mut().zip(&other.inputs) {
+            if input.redeem_script.is_none() {
+                input.redeem_script = other.redeem_script.clone();
+            }
+            if input.final_script_sig.is_none() {
+                input.final_script_sig = other.final_script_sig.clone();
+            }
+            for (pubkey, sig) in &other.partial_sigs {
+                input.partial_sigs.insert(*pubkey, sig.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the scriptSig of every input and return the signed tx.
+    ///
+    /// Inputs with a [`PartialInput::final_script_sig`] use it as is. P2PKH,
+    /// P2PK and P2SH/P2SH32 multisig inputs are built from the collected
+    /// signatures; multisig inputs with Schnorr signatures use the bitfield
+    /// dummy element, with the signatures in pubkey order.
+    pub fn finalize(&self) -> Result<Tx, PartialTxError> {
+        self.check_input_count()?;
+        let mut tx = self.tx.clone();
+        for (input_idx, input) in self.inputs.iter().enumerate() {
+            tx.inputs[input_idx].script = finalize_input(input_idx, input)?;
+        }
+        Ok(Tx::with_txid(TxId::from_tx(&tx), tx))
+    }
+
+    /// Outputs spent by each input.
+    pub fn spent_outputs(&self) -> Vec<Output> {
+        self.inputs
+            .iter()
+            .map(|input| input.coin.output.clone())
+            .collect()
+    }
+
+    fn check_input_count(&self) -> Result<(), PartialTxError> {
+        if self.inputs.len() != self.tx.inputs.len() {
+            return Err(PartialTxError::InputCountMismatch {
+                num_tx_inputs: self.tx.inputs.len(),
+                num_partial_inputs: self.inputs.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn input(&self, input_idx: usize) -> Result<&PartialInput, PartialTxError> {
+        let num_inputs = self.inputs.len();
+        self.inputs
+            .get(input_idx)
+            .ok_or(PartialTxError::InputIndexOutOfRange {
+                input_idx,
+                num_inputs,
+            })
+    }
+
+    fn input_mut(&mut self, input_idx: usize) -> Result<&mut PartialInput, PartialTxError> {
+        let num_inputs = self.inputs.len();
+        self.inputs
+            .get_mut(input_idx)
+            .ok_or(PartialTxError::InputIndexOutOfRange {
+                input_idx,
+                num_inputs,
+            })
+    }
+}
+
+impl BitcoinSer for PartialTx {
+    fn ser_to<S: BitcoinSerializer>(&self, bytes: &mut S) {
+        bytes.put(&PARTIAL_TX_MAGIC);
+        self.tx.ser_to(bytes);
+        self.inputs.ser_to(bytes);
+    }
+
+    fn deser(data: &mut Bytes) -> Result<Self, DataError> {
+        let magic = read_bytes(data, PARTIAL_TX_MAGIC.len())?;
+        if magic.as_ref() != PARTIAL_TX_MAGIC {
+            return Err(DataError::InvalidMagic {
+                expected: PARTIAL_TX_MAGIC.to_vec(),
+                actual: magic.to_vec(),
+            });
+        }
+        let tx = Transaction::deser(data)?;
+        let inputs = Vec::<PartialInput>::deser(data)?;
+        if inputs.len() != tx.inputs.len() {
+            return Err(DataError::InvalidLength {
+                expected: tx.inputs.len(),
+                actual: inputs.len(),
+            });
+        }
+        Ok(PartialTx { tx, inputs })
+    }
+}
+
+impl BitcoinSer for PartialInput {
+    fn ser_to<S: BitcoinSerializer>(&self, bytes: &mut S) {
+        self.coin.output.ser_to(bytes);
+        self.coin.height.ser_to(bytes);
+        self.coin.is_coinbase.ser_to(bytes);
+        ser_option(&self.redeem_script, bytes);
+        self.sig_hash_type.0.ser_to(bytes);
+        write_compact_size(bytes, self.partial_sigs.len() as u64);
+        for (pubkey, sig) in &self.partial_sigs {
+            pubkey.0.ser_to(bytes);
+            sig.ser_to(bytes);
+        }
+        ser_option(&self.final_script_sig, bytes);
+    }
+
+    fn deser(data: &mut Bytes) -> Result<Self, DataError> {
+        let coin = Coin {
+            output: BitcoinSer::deser(data)?,
+            height: BitcoinSer::deser(data)?,
+            is_coinbase: BitcoinSer::deser(data)?,
+        };
+        let redeem_script = deser_option(data)?;
+        let sig_hash_type = SigHashType(BitcoinSer::deser(data)?);
+        let num_sigs = read_compact_size(data)?;
+        let mut partial_sigs = BTreeMap::new();
+        for _ in 0..num_sigs {
+            let pubkey = PubKey(BitcoinSer::deser(data)?);
+            partial_sigs.insert(pubkey, BitcoinSer::deser(data)?);
+        }
+        Ok(PartialInput {
+            coin,
+            redeem_script,
+            sig_hash_type,
+            partial_sigs,
+            final_script_sig: deser_option(data)?,
+        })
+    }
+}
+
+fn ser_option<S: BitcoinSerializer>(script: &Option<Script>, bytes: &mut S) {
+    script.is_some().ser_to(bytes);
+    if let Some(script) = script {
+        script.ser_to(bytes);
+    }
+}
+
+fn deser_option(data: &mut Bytes) -> Result<Option<Script>, DataError> {
+    match bool::deser(data)? {
+        true => Ok(Some(Script::deser(data)?)),
+        false => Ok(None),
+    }
+}
+
+/// Whether both are set, but to different values.
+fn is_conflict<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a != b)
+}
+
+fn finalize_input(input_idx: usize, input: &PartialInput) -> Result<Script, PartialTxError> {
+    if let Some(script_sig) = &input.final_script_sig {
+        return Ok(script_sig.clone());
+    }
+    let missing_sig = PartialTxError::MissingSignatures {
+        input_idx,
+        required: 1,
+        actual: 0,
+    };
+    match ScriptVariant::from_script(&input.coin.output.script) {
+        ScriptVariant::P2PKH(hash) => {
+            let (pubkey, sig) = input
+                .partial_sigs
+                .iter()
+                .find(|(pubkey, _)| ShaRmd160::digest(pubkey.as_slice()) == hash)
+                .ok_or(missing_sig)?;
+            let mut script = ScriptMut::with_capacity(0);
+            script.push_data(sig);
+            script.push_data(pubkey.as_slice());
+            Ok(script.freeze())
+        }
+        ScriptVariant::P2PK(pubkey) => {
+            let sig = match &pubkey {
+                PubKeyVariant::Compressed(pubkey) => input.partial_sigs.get(pubkey),
+                PubKeyVariant::Uncompressed(_) => None,
+            };
+            let sig = sig.ok_or(missing_sig)?;
+            let mut script = ScriptMut::with_capacity(0);
+            script.push_data(sig);
+            Ok(script.freeze())
+        }
+        ScriptVariant::Multisig { m, pubkeys } => {
+            multisig_script_sig(input_idx, input, m, &pubkeys)
+        }
+        ScriptVariant::P2SH(_) | ScriptVariant::P2SH32(_) => {
+            let redeem_script = input
+                .redeem_script
+                .as_ref()
+                .ok_or(PartialTxError::MissingRedeemScript { input_idx })?;
+            let expected_script = match ScriptVariant::from_script(&input.coin.output.script) {
+                ScriptVariant::P2SH(_) => Script::p2sh(&ShaRmd160::digest(redeem_script)),
+                _ => Script::p2sh32(&Sha256d::digest(redeem_script)),
+            };
+            if expected_script != input.coin.output.script {
+                return Err(PartialTxError::RedeemScriptMismatch { input_idx });
+            }
+            let script_sig = match ScriptVariant::from_script(redeem_script) {
+                ScriptVariant::Multisig { m, pubkeys } => {
+                    multisig_script_sig(input_idx, input, m, &pubkeys)?
+                }
+                _ => return Err(PartialTxError::UnsupportedScript { input_idx }),
+            };
+            Ok(Script::p2sh_script_sig(&script_sig, redeem_script))
+        }
+        ScriptVariant::P2S(_) | ScriptVariant::Other(_) => {
+            Err(PartialTxError::UnsupportedScript { input_idx })
+        }
+    }
+}
+
+/// Multisig scriptSig (without redeem script) from the first `m` signatures
+/// of the pubkeys, using the Schnorr mode if all of them are Schnorr and the
+/// legacy mode if none are.
+fn multisig_script_sig(
+    input_idx: usize,
+    input: &PartialInput,
+    m: usize,
+    pubkeys: &[PubKeyVariant],
+) -> Result<Script, PartialTxError> {
+    let sigs = pubkeys
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, pubkey)| match pubkey {
+            PubKeyVariant::Compressed(pubkey) => Some((idx, input.partial_sigs.get(pubkey)?)),
+            PubKeyVariant::Uncompressed(_) => None,
+        })
+        .take(m)
+        .collect::<Vec<_>>();
+    if sigs.len() < m {
+        return Err(PartialTxError::MissingSignatures {
+            input_idx,
+            required: m,
+            actual: sigs.len(),
+        });
+    }
+    let is_schnorr = |sig: &Bytes| match TxSignature::try_from(sig.as_ref()) {
+        Ok(sig) => sig.sig.is_schnorr(),
+        Err(_) => false,
+    };
+    let num_schnorr = sigs.iter().filter(|(_, sig)| is_schnorr(sig)).count();
+    let mode = match num_schnorr {
+        0 => MultisigMode::Legacy,
+        _ if num_schnorr == sigs.len() => MultisigMode::Schnorr,
+        _ => return Err(PartialTxError::MixedMultisigSignatures { input_idx }),
+    };
+    Ok(Script::multisig_script_sig(mode, pubkeys.len(), &sigs))
+}
+
+#[cfg(feature = "serde")]
+mod json {
+    use std::collections::BTreeMap;
+
+    use bytes::Bytes;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        error::DataError,
+        script::{PubKey, Script},
+        ser::BitcoinSer,
+        tx::{Coin, Output, SigHashType, Transaction},
+        wallet::{PartialInput, PartialTx},
+    };
+
+    /// JSON representation of [`PartialTx`].
+    #[derive(Debug, Deserialize, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PartialTxJson {
+        tx: String,
+        inputs: Vec<PartialInputJson>,
+    }
+
+    /// JSON representation of [`PartialInput`].
+    #[derive(Debug, Deserialize, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PartialInputJson {
+        output: String,
+        height: i32,
+        is_coinbase: bool,
+        redeem_script: Option<String>,
+        sig_hash_type: u8,
+        partial_sigs: BTreeMap<String, String>,
+        final_script_sig: Option<String>,
+    }
+
+    impl From<PartialTx> for PartialTxJson {
+        fn from(partial_tx: PartialTx) -> Self {
+            PartialTxJson {
+                tx: hex::encode(partial_tx.tx.ser()),
+                inputs: partial_tx
+                    .inputs
+                    .into_iter()
+                    .map(|input| PartialInputJson {
+                        output: hex::encode(input.coin.output.ser()),
+                        height: input.coin.height,
+                        is_coinbase: input.coin.is_coinbase,
+                        redeem_script: input.redeem_script.map(|script| script.hex()),
+                        sig_hash_type: input.sig_hash_type.0,
+                        partial_sigs: input
+                            .partial_sigs
+                            .into_iter()
+                            .map(|(pubkey, sig)| (pubkey.hex(), hex::encode(sig)))
+                            .collect(),
+                        final_script_sig: input.final_script_sig.map(|script| script.hex()),
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    impl TryFrom<PartialTxJson> for PartialTx {
+        type Error = DataError;
+
+        fn try_from(json: PartialTxJson) -> Result<Self, Self::Error> {
+            let inputs = json
+                .inputs
+                .into_iter()
+                .map(|input| {
+                    let partial_sigs = input
+                        .partial_sigs
+                        .iter()
+                        .map(|(pubkey, sig)| Ok((pubkey.parse::<PubKey>()?, from_hex(sig)?)))
+                        .collect::<Result<_, DataError>>()?;
+                    Ok(PartialInput {
+                        coin: Coin {
+                            output: Output::deser(&mut from_hex(&input.output)?)?,
+                            height: input.height,
+                            is_coinbase: input.is_coinbase,
+                        },
+                        redeem_script: input.redeem_script.as_deref().map(script).transpose()?,
+                        sig_hash_type: SigHashType(input.sig_hash_type),
+                        partial_sigs,
+                        final_script_sig: input
+                            .final_script_sig
+                            .as_deref()
+                            .map(script)
+                            .transpose()?,
+                    })
+                })
+                .collect::<Result<Vec<_>, DataError>>()?;
+            let tx = Transaction::deser(&mut from_hex(&json.tx)?)?;
+            if inputs.len() != tx.inputs.len() {
+                return Err(DataError::InvalidLength {
+                    expected: tx.inputs.len(),
+                    actual: inputs.len(),
+                });
+            }
+            Ok(PartialTx { tx, inputs })
+        }
+    }
+
+    fn from_hex(hex: &str) -> Result<Bytes, DataError> {
+        Ok(hex::decode(hex).map_err(DataError::InvalidHex)?.into())
+    }
+
+    fn script(hex: &str) -> Result<Script, DataError> {
+        Ok(Script::new(from_hex(hex)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        error::DataError,
+        hash::{Hashed, ShaRmd160},
+        script::{opcode::*, PubKey, Script, ScriptMut},
+        ser::BitcoinSer,
+        tx::{Coin, OutPoint, Output, SigHashType, SignatureError, TxId, UnlockingTemplate},
+        wallet::{PartialTx, PartialTxError, TxBuilder, PARTIAL_TX_MAGIC},
+    };
+
+    const PUBKEYS: [PubKey; 3] = [PubKey([2; 33]), PubKey([3; 33]), PubKey([4; 33])];
+
+    fn multisig_script() -> Script {
+        let mut script = ScriptMut::with_capacity(0);
+        script.put_opcodes([OP_2]);
+        for pubkey in &PUBKEYS {
+            script.put_bytecode(&[33]);
+            script.put_bytecode(pubkey.as_slice());
+        }
+        script.put_opcodes([OP_3, OP_CHECKMULTISIG]);
+        script.freeze()
+    }
+
+    fn multisig_partial_tx() -> PartialTx {
+        let redeem_script = multisig_script();
+        let p2sh = Script::p2sh(&ShaRmd160::digest(&redeem_script));
+        let mut builder = TxBuilder {
+            change_script: Some(p2sh.clone()),
+            ..Default::default()
+        };
+        builder.add_input(
+            OutPoint {
+                txid: TxId::from([1; 32]),
+                outpoint_index: 1,
+            },
+            Coin {
+                output: Output {
+                    value: 20_000,
+                    script: p2sh,
+                    token: None,
+                },
+                ..Default::default()
+            },
+            UnlockingTemplate::P2shMultisig {
+                m: 2,
+                n: 3,
+                schnorr: true,
+            },
+        );
+        let mut partial_tx = PartialTx::new(&builder.build().unwrap(), SigHashType::ALL_FORKID);
+        partial_tx.inputs[0].redeem_script = Some(redeem_script);
+        partial_tx
+    }
+
+    /// Schnorr signature if `len` is 65, DER encoded ECDSA signature
+    /// otherwise.
+    fn sig(byte: u8, len: usize) -> Bytes {
+        let mut sig = match len {
+            65 => vec![byte; 64],
+            _ => {
+                let len_s = (len - 7) / 2;
+                let len_r = len - 7 - len_s;
+                let mut der = vec![0x30, (len - 3) as u8, 0x02, len_r as u8];
+                der.extend(vec![byte & 0x7f; len_r]);
+                der.extend([0x02, len_s as u8]);
+                der.extend(vec![byte & 0x7f; len_s]);
+                der
+            }
+        };
+        sig.push(SigHashType::ALL_FORKID.0);
+        sig.into()
+    }
+
+    #[test]
+    fn test_partial_tx_multisig() {
+        let unsigned = multisig_partial_tx();
+        let mut alice = unsigned.clone();
+        let mut carol = PartialTx::deser(&mut unsigned.ser()).unwrap();
+        assert_eq!(carol, unsigned);
+        alice.add_signature(0, PUBKEYS[0], sig(0xaa, 65)).unwrap();
+        carol.add_signature(0, PUBKEYS[2], sig(0xcc, 65)).unwrap();
+
+        assert_eq!(
+            alice.finalize(),
+            Err(PartialTxError::MissingSignatures {
+                input_idx: 0,
+                required: 2,
+                actual: 1,
+            }),
+        );
+        alice.combine(&carol).unwrap();
+        let tx = alice.finalize().unwrap();
+        let redeem_script = multisig_script();
+        let mut expected = ScriptMut::with_capacity(0);
+        // Bitfield 0b101 pushed as OP_5, then the sigs in pubkey order
+        expected.put_opcodes([OP_5]);
+        expected.put_bytecode(&[65]);
+        expected.put_bytecode(&sig(0xaa, 65));
+        expected.put_bytecode(&[65]);
+        expected.put_bytecode(&sig(0xcc, 65));
+        expected.put_opcodes([OP_PUSHDATA1]);
+        expected.put_bytecode(&[redeem_script.bytecode().len() as u8]);
+        expected.put_bytecode(redeem_script.bytecode());
+        assert_eq!(tx.inputs[0].script, expected.freeze());
+
+        // ECDSA sigs use the OP_0 dummy
+        let mut partial_tx = unsigned.clone();
+        partial_tx.add_signature(0, PUBKEYS[1], sig(1, 71)).unwrap();
+        partial_tx.add_signature(0, PUBKEYS[2], sig(2, 72)).unwrap();
+        let tx = partial_tx.finalize().unwrap();
+        assert_eq!(tx.inputs[0].script.bytecode()[0], OP_0::N);
+        assert_eq!(tx.inputs[0].script.bytecode()[1], 71);
+
+        // No multisig mode accepts both Schnorr and ECDSA sigs
+        let mut partial_tx = unsigned;
+        partial_tx
+            .add_signature(0, PUBKEYS[0], sig(0xaa, 65))
+            .unwrap();
+        partial_tx.add_signature(0, PUBKEYS[1], sig(1, 71)).unwrap();
+        assert_eq!(
+            partial_tx.finalize(),
+            Err(PartialTxError::MixedMultisigSignatures { input_idx: 0 }),
+        );
+    }
+
+    #[test]
+    fn test_partial_tx_p2pk() {
+        let mut partial_tx = multisig_partial_tx();
+        partial_tx.inputs[0].coin.output.script = Script::p2pk(&PUBKEYS[1]);
+        // Only the signature of the output's pubkey can spend it
+        partial_tx.add_signature(0, PUBKEYS[0], sig(1, 65)).unwrap();
+        assert_eq!(
+            partial_tx.finalize(),
+            Err(PartialTxError::MissingSignatures {
+                input_idx: 0,
+                required: 1,
+                actual: 0,
+            }),
+        );
+        partial_tx.add_signature(0, PUBKEYS[1], sig(2, 65)).unwrap();
+        let tx = partial_tx.finalize().unwrap();
+        let mut expected = ScriptMut::with_capacity(0);
+        expected.put_bytecode(&[65]);
+        expected.put_bytecode(&sig(2, 65));
+        assert_eq!(tx.inputs[0].script, expected.freeze());
+    }
+
+    #[test]
+    fn test_partial_tx_errors() {
+        let mut partial_tx = multisig_partial_tx();
+        assert_eq!(
+            partial_tx.add_signature(1, PUBKEYS[0], sig(1, 65)),
+            Err(PartialTxError::InputIndexOutOfRange {
+                input_idx: 1,
+                num_inputs: 1,
+            }),
+        );
+        assert_eq!(
+            partial_tx.add_signature(0, PUBKEYS[0], vec![1; 65].into()),
+            Err(PartialTxError::WrongSigHashType {
+                input_idx: 0,
+                expected: SigHashType::ALL_FORKID,
+                actual: Some(SigHashType(1)),
+            }),
+        );
+        assert_eq!(
+            partial_tx.add_signature(0, PUBKEYS[0], vec![0x41; 72].into()),
+            Err(PartialTxError::InvalidSignature {
+                input_idx: 0,
+                error: SignatureError::InvalidDer("Must start with 0x30"),
+            }),
+        );
+        partial_tx.add_signature(0, PUBKEYS[0], sig(1, 65)).unwrap();
+        let conflict = PartialTxError::ConflictingSignature {
+            input_idx: 0,
+            pubkey: PUBKEYS[0],
+        };
+        assert_eq!(
+            partial_tx.add_signature(0, PUBKEYS[0], sig(2, 65)),
+            Err(conflict.clone()),
+        );
+
+        let mut other = multisig_partial_tx();
+        other.add_signature(0, PUBKEYS[0], sig(2, 65)).unwrap();
+        other.add_signature(0, PUBKEYS[1], sig(2, 65)).unwrap();
+        assert_eq!(partial_tx.combine(&other), Err(conflict));
+        // Failed combine leaves the partial tx unchanged
+        assert_eq!(partial_tx.inputs[0].partial_sigs.len(), 1);
+
+        other.inputs[0].redeem_script = Some(Script::new(vec![OP_1::N].into()));
+        assert_eq!(
+            other.finalize(),
+            Err(PartialTxError::RedeemScriptMismatch { input_idx: 0 }),
+        );
+        other.inputs[0].redeem_script = None;
+        assert_eq!(
+            other.finalize(),
+            Err(PartialTxError::MissingRedeemScript { input_idx: 0 }),
+        );
+        let mut extra_input = other.clone();
+        extra_input.inputs.push(other.inputs[0].clone());
+        let mismatch = PartialTxError::InputCountMismatch {
+            num_tx_inputs: 1,
+            num_partial_inputs: 2,
+        };
+        assert_eq!(extra_input.finalize(), Err(mismatch.clone()));
+        assert_eq!(extra_input.sighash(1), Err(mismatch));
+        other.tx.locktime = 1;
+        assert!(matches!(
+            partial_tx.combine(&other),
+            Err(PartialTxError::TxMismatch { .. }),
+        ));
+
+        assert_eq!(
+            PartialTx::deser(&mut partial_tx.ser()),
+            Ok(partial_tx.clone())
+        );
+        let mut data = Bytes::from([b"psbt".as_ref(), &partial_tx.ser()[4..]].concat());
+        assert_eq!(
+            PartialTx::deser(&mut data),
+            Err(DataError::InvalidMagic {
+                expected: PARTIAL_TX_MAGIC.to_vec(),
+                actual: b"psbt\x01".to_vec(),
+            }),
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_partial_tx_json() {
+        let mut partial_tx = multisig_partial_tx();
+        partial_tx.add_signature(0, PUBKEYS[1], sig(1, 65)).unwrap();
+        let json = serde_json::to_value(&partial_tx).unwrap();
+        assert_eq!(json["inputs"][0]["sigHashType"], 0x41);
+        assert_eq!(
+            json["inputs"][0]["partialSigs"][PUBKEYS[1].hex()],
+            hex::encode(sig(1, 65)),
+        );
+        assert_eq!(
+            serde_json::from_value::<PartialTx>(json).unwrap(),
+            partial_tx
+        );
+    }
+}
@@ -0,0 +1,611 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use bytes::Bytes;
+use thiserror::Error;
+
+use crate::{
+    error::DataError,
+    hash::{Hashed, Sha256d, ShaRmd160},
+    script::{Op, PubKey, Script, ScriptVariant},
+    ser::{BitcoinSer, BitcoinSerializer},
+    tx::{
+        CashToken, Coin, Input, OutPoint, Output, SigHashError, SigHashType, SignatureError,
+        Transaction, Tx, TxSignature, UnlockingTemplate,
+    },
+    wallet::{Signer, TxBuilder, TxBuilderError, TxBuilderInput, UnsignedTx, DEFAULT_SEQUENCE},
+};
+
+/// Sighash type the maker of a [`SwapOffer`] signs with,
+/// `SIGHASH_SINGLE | SIGHASH_ANYONECANPAY | SIGHASH_FORKID`.
+///
+/// The signature only covers the offered input and the payment output at the
+/// same index, so the taker can add any other inputs and outputs.
+pub const SWAP_SIG_HASH_TYPE: SigHashType = SigHashType(0xc3);
+
+/// Offer to trade tokens peer-to-peer: the maker signs an input spending a
+/// coin with tokens and the output paying them, with
+/// [`SWAP_SIG_HASH_TYPE`]. The taker accepts by adding inputs paying for the
+/// output and an output receiving the tokens, see [`SwapOffer::accept`].
+///
+/// The offer is a tx with exactly one input and one output, which stay at
+/// index 0 of the completed tx. The maker claims the spent coin, but the
+/// signature commits to its value and token, so a wrong coin only makes the
+/// completed tx invalid; the taker should still check that it's unspent.
+/// ```
+/// # use bitcoinsuite_core::{
+/// #     hash::{Hashed, ShaRmd160},
+/// #     script::{PubKey, Script, ScriptMut},
+/// #     ser::{BitcoinSer, CompactUint},
+/// #     tx::{CashToken, Coin, OutPoint, Output, TxId, UnlockingTemplate},
+/// #     wallet::{SwapOffer, SwapTerms, TxBuilder},
+/// # };
+/// let maker_pubkey = PubKey([2; 33]);
+/// let maker_script = Script::p2pkh(&ShaRmd160::digest(maker_pubkey.as_slice()));
+/// let token = CashToken { amount: CompactUint(1_000), category: TxId::from([7; 32]), nft: None };
+/// let coin = Coin {
+///     output: Output { value: 800, script: maker_script.clone(), token: Some(token.clone()) },
+///     ..Default::default()
+/// };
+/// let prev_out = OutPoint { txid: TxId::from([1; 32]), outpoint_index: 1 };
+/// let payment = Output { value: 50_000, script: maker_script.clone(), token: None };
+///
+/// // Maker signs the sighash and publishes the offer as bytes
+/// let mut offer = SwapOffer::new(prev_out, coin, payment);
+/// let _sighash = offer.sighash(&maker_script).unwrap();
+/// let sig = [[3; 64].as_ref(), &[0xc3]].concat();
+/// let mut script_sig = ScriptMut::with_capacity(100);
+/// script_sig.put_bytecode(&[65]);
+/// script_sig.put_bytecode(&sig);
+/// script_sig.put_bytecode(&[33]);
+/// script_sig.put_bytecode(maker_pubkey.as_slice());
+/// offer.tx.inputs[0].script = script_sig.freeze();
+/// let data = offer.ser();
+///
+/// // Taker checks the terms and completes the tx with their own coins
+/// let offer = SwapOffer::deser(&mut data.clone()).unwrap();
+/// let terms = SwapTerms { token, max_payment: 50_000 };
+/// let taker_script = Script::p2pkh(&ShaRmd160([4; 20]));
+/// let mut builder = TxBuilder::default();
+/// builder.change_script = Some(taker_script.clone());
+/// builder.add_input(
+///     OutPoint { txid: TxId::from([5; 32]), outpoint_index: 1 },
+///     Coin {
+///         output: Output { value: 100_000, script: taker_script.clone(), token: None },
+///         ..Default::default()
+///     },
+///     UnlockingTemplate::P2pkhSchnorr,
+/// );
+/// let unsigned_tx = offer.accept(&terms, builder, taker_script).unwrap();
+/// assert_eq!(&unsigned_tx.tx.outputs[0], offer.payment().unwrap());
+/// assert_eq!(unsigned_tx.tx.outputs[1].token, Some(terms.token));
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SwapOffer {
+    /// Tx with the offered input and the payment output, signed by the
+    /// maker.
+    pub tx: Transaction,
+    /// Coin spent by the offered input, holding the offered tokens.
+    pub coin: Coin,
+}
+
+/// Terms the taker expects from a [`SwapOffer`], see
+/// [`SwapOffer::validate`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SwapTerms {
+    /// Tokens the offer must sell, exactly.
+    pub token: CashToken,
+    /// Maximum sats the payment output may request.
+    pub max_payment: u64,
+}
+
+/// Errors when validating or accepting a [`SwapOffer`].
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum SwapError {
+    /// The offer tx must have exactly one input and one output.
+    #[error("Offer must have 1 input and 1 output, got {num_inputs} and {num_outputs}")]
+    InvalidOfferTx {
+        /// Number of inputs of the offer tx.
+        num_inputs: usize,
+        /// Number of outputs of the offer tx.
+        num_outputs: usize,
+    },
+
+    /// The offered coin doesn't have any tokens.
+    #[error("Offered coin has no tokens")]
+    NoOfferedToken,
+
+    /// The offered tokens are not the ones in the terms.
+    #[error("Offer sells {actual:?}, expected {expected:?}")]
+    TokenMismatch {
+        /// Tokens in the terms.
+        expected: Box<CashToken>,
+        /// Tokens of the offered coin.
+        actual: Box<CashToken>,
+    },
+
+    /// The payment output requests more than the terms allow.
+    #[error("Offer requests {actual} sats, at most {max_payment} allowed")]
+    PaymentTooHigh {
+        /// Maximum payment of the terms.
+        max_payment: u64,
+        /// Value of the payment output.
+        actual: u64,
+    },
+
+    /// Payments must be in sats only.
+    #[error("Payment output must not request tokens")]
+    PaymentHasToken,
+
+    /// The payment output is below the dust limit, so the tx wouldn't relay.
+    #[error("Payment of {value} sats is dust, at least {min_value} sats required")]
+    DustPayment {
+        /// Value of the payment output.
+        value: u64,
+        /// Minimum value to not be dust.
+        min_value: u64,
+    },
+
+    /// The offered input has no scriptSig.
+    #[error("Offer is not signed")]
+    UnsignedOffer,
+
+    /// The maker's signature doesn't use [`SWAP_SIG_HASH_TYPE`], so adding
+    /// inputs or outputs would invalidate it.
+    #[error("Offer must be signed with {SWAP_SIG_HASH_TYPE:?}, got {actual:?}")]
+    WrongSigHashType {
+        /// Sighash type of the signature, `None` if there's no signature.
+        actual: Option<SigHashType>,
+    },
+
+    /// The maker's signature is not a valid ECDSA or Schnorr signature.
+    #[error("Invalid offer signature: {0}")]
+    InvalidSignature(SignatureError),
+
+    /// The scriptSig doesn't have the pubkey of the offered P2PKH coin.
+    #[error("ScriptSig of the offer doesn't match the offered coin")]
+    ScriptSigMismatch,
+
+    /// Building the completed tx failed, e.g. the taker's inputs don't have
+    /// enough sats.
+    #[error("Building swap tx failed: {0}")]
+    Build(TxBuilderError),
+}
+
+/// Errors when signing a completed swap with [`SwapOffer::sign`].
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum SwapSignError<E> {
+    /// The offer tx doesn't have exactly one input and one output.
+    #[error("Invalid offer: {0}")]
+    Offer(SwapError),
+
+    /// Signing the taker's inputs failed.
+    #[error("Signing failed: {0}")]
+    Signer(E),
+}
+
+impl SwapOffer {
+    /// Unsigned offer selling the tokens of `coin` at `prev_out` for
+    /// `payment`. The maker signs [`SwapOffer::sighash`] and sets the
+    /// scriptSig of the input.
+    pub fn new(prev_out: OutPoint, coin: Coin, payment: Output) -> Self {
+        SwapOffer {
+            tx: Transaction {
+                version: 2,
+                inputs: vec![Input {
+                    prev_out,
+                    script: Script::default(),
+                    sequence: DEFAULT_SEQUENCE,
+                }],
+                outputs: vec![payment],
+                locktime: 0,
+            },
+            coin,
+        }
+    }
+
+    /// Output paying the maker.
+    ///
+    /// Fails if the offer tx doesn't have exactly one input and one output.
+    pub fn payment(&self) -> Result<&Output, SwapError> {
+        let (_, payment) = self.offer_tx()?;
+        Ok(payment)
+    }
+
+    /// Sighash the maker signs, with [`SWAP_SIG_HASH_TYPE`]. It's the same
+    /// for the completed tx, as long as the offered input and the payment
+    /// output stay at index 0.
+    pub fn sighash(&self, script_code: &Script) -> Result<Sha256d, SigHashError> {
+        self.tx.sighash(
+            0,
+            std::slice::from_ref(&self.coin.output),
+            script_code,
+            SWAP_SIG_HASH_TYPE,
+        )
+    }
+
+    /// Check that the offer is well-formed, signed with
+    /// [`SWAP_SIG_HASH_TYPE`] and sells exactly the tokens of `terms`, for
+    /// at most [`SwapTerms::max_payment`] sats.
+    ///
+    /// This doesn't verify the signature itself; a bad signature makes the
+    /// completed tx invalid, but can't lose the taker any funds.
+    pub fn validate(&self, terms: &SwapTerms) -> Result<(), SwapError> {
+        let (_, payment) = self.offer_tx()?;
+        let token = self
+            .coin
+            .output
+            .token
+            .as_ref()
+            .ok_or(SwapError::NoOfferedToken)?;
+        if *token != terms.token {
+            return Err(SwapError::TokenMismatch {
+                expected: Box::new(terms.token.clone()),
+                actual: Box::new(token.clone()),
+            });
+        }
+        if payment.value > terms.max_payment {
+            return Err(SwapError::PaymentTooHigh {
+                max_payment: terms.max_payment,
+                actual: payment.value,
+            });
+        }
+        if payment.token.is_some() {
+            return Err(SwapError::PaymentHasToken);
+        }
+        if payment.value < payment.min_value() {
+            return Err(SwapError::DustPayment {
+                value: payment.value,
+                min_value: payment.min_value(),
+            });
+        }
+        self.validate_script_sig()
+    }
+
+    /// Complete the offer: validate it against `terms`, then build a tx with
+    /// the offered input and payment output first, followed by the inputs
+    /// and outputs of `builder` and an output sending the tokens to
+    /// `token_script`. The change and fee settings of `builder` are used,
+    /// its version and locktime are replaced with the ones the maker signed.
+    ///
+    /// Sign the taker's inputs of the result with [`SwapOffer::sign`].
+    pub fn accept(
+        &self,
+        terms: &SwapTerms,
+        mut builder: TxBuilder,
+        token_script: Script,
+    ) -> Result<UnsignedTx, SwapError> {
+        self.validate(terms)?;
+        let (input, payment) = self.offer_tx()?;
+        builder.version = self.tx.version;
+        builder.locktime = self.tx.locktime;
+        builder.inputs.insert(
+            0,
+            TxBuilderInput {
+                prev_out: input.prev_out,
+                sequence: input.sequence,
+                coin: self.coin.clone(),
+                template: UnlockingTemplate::Custom {
+                    script_sig_size: input.script.bytecode().len(),
+                },
+            },
+        );
+        let mut token_output = Output {
+            value: 0,
+            script: token_script,
+            token: self.coin.output.token.clone(),
+        };
+        token_output.value = token_output.min_value();
+        builder.outputs.insert(0, payment.clone());
+        builder.outputs.insert(1, token_output);
+        builder.build().map_err(SwapError::Build)
+    }
+
+    /// Sign the taker's inputs of `unsigned_tx` (built by
+    /// [`SwapOffer::accept`]) with `signer`, and add the maker's scriptSig
+    /// to the offered input.
+    pub fn sign<S: Signer>(
+        &self,
+        unsigned_tx: UnsignedTx,
+        signer: &S,
+    ) -> Result<Tx, SwapSignError<S::Error>> {
+        let (input, _) = self.offer_tx().map_err(SwapSignError::Offer)?;
+        unsigned_tx
+            .sign(&|unsigned_tx: &UnsignedTx, input_idx| match input_idx {
+                0 => Ok(input.script.clone()),
+                _ => signer.sign_input(unsigned_tx, input_idx),
+            })
+            .map_err(SwapSignError::Signer)
+    }
+
+    /// The offered input and the payment output, the only ones of the
+    /// offer tx.
+    fn offer_tx(&self) -> Result<(&Input, &Output), SwapError> {
+        match (self.tx.inputs.as_slice(), self.tx.outputs.as_slice()) {
+            ([input], [payment]) => Ok((input, payment)),
+            _ => Err(SwapError::InvalidOfferTx {
+                num_inputs: self.tx.inputs.len(),
+                num_outputs: self.tx.outputs.len(),
+            }),
+        }
+    }
+
+    fn validate_script_sig(&self) -> Result<(), SwapError> {
+        let (input, _) = self.offer_tx()?;
+        let script_sig = &input.script;
+        if script_sig.bytecode().is_empty() {
+            return Err(SwapError::UnsignedOffer);
+        }
+        let pushes = script_sig
+            .iter_ops()
+            .map(|op| match op {
+                Ok(Op::Push(_, data)) => Some(data),
+                _ => None,
+            })
+            .collect::<Option<Vec<Bytes>>>()
+            .ok_or(SwapError::ScriptSigMismatch)?;
+        let sig = match ScriptVariant::from_script(&self.coin.output.script) {
+            ScriptVariant::P2PKH(hash) => match pushes.as_slice() {
+                [sig, pubkey] if pubkey.len() == PubKey::SIZE => {
+                    if ShaRmd160::digest(pubkey) != hash {
+                        return Err(SwapError::ScriptSigMismatch);
+                    }
+                    Some(sig)
+                }
+                _ => return Err(SwapError::ScriptSigMismatch),
+            },
+            ScriptVariant::P2PK(_) => pushes.first(),
+            // Signatures of other scripts can't be located, e.g. a
+            // multisig signs with several sighash types.
+            _ => return Ok(()),
+        };
+        let sig = sig.ok_or(SwapError::WrongSigHashType { actual: None })?;
+        let sig = TxSignature::try_from(sig.as_ref()).map_err(SwapError::InvalidSignature)?;
+        if sig.sig_hash_type != SWAP_SIG_HASH_TYPE {
+            return Err(SwapError::WrongSigHashType {
+                actual: Some(sig.sig_hash_type),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl BitcoinSer for SwapOffer {
+    fn ser_to<S: BitcoinSerializer>(&self, bytes: &mut S) {
+        self.tx.ser_to(bytes);
+        self.coin.output.ser_to(bytes);
+        self.coin.height.ser_to(bytes);
+        self.coin.is_coinbase.ser_to(bytes);
+    }
+
+    fn deser(data: &mut Bytes) -> Result<Self, DataError> {
+        Ok(SwapOffer {
+            tx: BitcoinSer::deser(data)?,
+            coin: Coin {
+                output: BitcoinSer::deser(data)?,
+                height: BitcoinSer::deser(data)?,
+                is_coinbase: BitcoinSer::deser(data)?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        hash::{Hashed, ShaRmd160},
+        script::{PubKey, Script, ScriptMut},
+        ser::{BitcoinSer, CompactUint},
+        tx::{CashToken, Coin, OutPoint, Output, SigHashType, TxId, UnlockingTemplate},
+        wallet::{
+            SwapError, SwapOffer, SwapSignError, SwapTerms, TxBuilder, UnsignedTx,
+            SWAP_SIG_HASH_TYPE,
+        },
+    };
+
+    const MAKER_PUBKEY: PubKey = PubKey([2; 33]);
+
+    fn maker_script() -> Script {
+        Script::p2pkh(&ShaRmd160::digest(MAKER_PUBKEY.as_slice()))
+    }
+
+    fn token() -> CashToken {
+        CashToken {
+            amount: CompactUint(500),
+            category: TxId::from([7; 32]),
+            nft: None,
+        }
+    }
+
+    fn script_sig(sig_hash_type: SigHashType) -> Script {
+        let mut script = ScriptMut::with_capacity(100);
+        script.put_bytecode(&[65]);
+        script.put_bytecode(&[3; 64]);
+        script.put_bytecode(&[sig_hash_type.0, 33]);
+        script.put_bytecode(MAKER_PUBKEY.as_slice());
+        script.freeze()
+    }
+
+    fn signed_offer() -> SwapOffer {
+        let mut offer = SwapOffer::new(
+            OutPoint {
+                txid: TxId::from([1; 32]),
+                outpoint_index: 2,
+            },
+            Coin {
+                output: Output {
+                    value: 1_000,
+                    script: maker_script(),
+                    token: Some(token()),
+                },
+                ..Default::default()
+            },
+            Output {
+                value: 20_000,
+                script: maker_script(),
+                token: None,
+            },
+        );
+        offer.tx.inputs[0].script = script_sig(SWAP_SIG_HASH_TYPE);
+        offer
+    }
+
+    fn taker_builder(value: u64) -> TxBuilder {
+        let taker_script = Script::p2pkh(&ShaRmd160([4; 20]));
+        let mut builder = TxBuilder {
+            change_script: Some(taker_script.clone()),
+            ..Default::default()
+        };
+        builder.add_input(
+            OutPoint {
+                txid: TxId::from([5; 32]),
+                outpoint_index: 1,
+            },
+            Coin {
+                output: Output {
+                    value,
+                    script: taker_script,
+                    token: None,
+                },
+                ..Default::default()
+            },
+            UnlockingTemplate::P2pkhSchnorr,
+        );
+        builder
+    }
+
+    #[test]
+    fn test_swap_accept() {
+        let offer = SwapOffer::deser(&mut signed_offer().ser()).unwrap();
+        assert_eq!(offer, signed_offer());
+        let terms = SwapTerms {
+            token: token(),
+            max_payment: 20_000,
+        };
+        let taker_script = Script::p2pkh(&ShaRmd160([4; 20]));
+        let unsigned_tx = offer
+            .accept(&terms, taker_builder(50_000), taker_script.clone())
+            .unwrap();
+        assert_eq!(unsigned_tx.tx.inputs.len(), 2);
+        assert_eq!(unsigned_tx.tx.outputs.len(), 3);
+        assert_eq!(unsigned_tx.tx.outputs[1].script, taker_script);
+        assert_eq!(unsigned_tx.tx.outputs[1].token, Some(token()));
+        assert_eq!(
+            unsigned_tx.tx.check_tokens(&unsigned_tx.spent_outputs()),
+            Ok(()),
+        );
+
+        // The maker's signature stays valid in the completed tx
+        assert_eq!(
+            unsigned_tx.sighash(0, &maker_script(), SWAP_SIG_HASH_TYPE),
+            offer.sighash(&maker_script()),
+        );
+        let taker_script_sig = Script::new(vec![1, 2].into());
+        let tx = offer
+            .sign(unsigned_tx.clone(), &|_: &UnsignedTx, input_idx| {
+                assert_eq!(input_idx, 1);
+                Ok::<_, Infallible>(taker_script_sig.clone())
+            })
+            .unwrap();
+        assert_eq!(tx.inputs[0].script, offer.tx.inputs[0].script);
+        assert_eq!(tx.inputs[1].script, taker_script_sig);
+        assert!(unsigned_tx.is_signed_by(&tx));
+
+        assert!(matches!(
+            offer.accept(&terms, taker_builder(10_000), taker_script),
+            Err(SwapError::Build(_)),
+        ));
+
+        // Malformed offers fail instead of panicking
+        let mut no_output = offer;
+        no_output.tx.outputs.clear();
+        let invalid = SwapError::InvalidOfferTx {
+            num_inputs: 1,
+            num_outputs: 0,
+        };
+        assert_eq!(no_output.payment(), Err(invalid.clone()));
+        let mut no_input = signed_offer();
+        no_input.tx.inputs.clear();
+        assert_eq!(
+            no_input.sign(unsigned_tx, &|_: &UnsignedTx, _| Ok::<_, Infallible>(
+                Script::default()
+            )),
+            Err(SwapSignError::Offer(SwapError::InvalidOfferTx {
+                num_inputs: 0,
+                num_outputs: 1,
+            })),
+        );
+        assert_eq!(no_output.validate(&terms), Err(invalid));
+    }
+
+    #[test]
+    fn test_swap_validate() {
+        let offer = signed_offer();
+        let terms = SwapTerms {
+            token: token(),
+            max_payment: 20_000,
+        };
+        assert_eq!(offer.validate(&terms), Ok(()));
+
+        let cheaper = SwapTerms {
+            max_payment: 19_999,
+            ..terms.clone()
+        };
+        assert_eq!(
+            offer.validate(&cheaper),
+            Err(SwapError::PaymentTooHigh {
+                max_payment: 19_999,
+                actual: 20_000,
+            }),
+        );
+        let more_tokens = SwapTerms {
+            token: CashToken {
+                amount: CompactUint(501),
+                ..token()
+            },
+            ..terms.clone()
+        };
+        assert_eq!(
+            offer.validate(&more_tokens),
+            Err(SwapError::TokenMismatch {
+                expected: Box::new(more_tokens.token.clone()),
+                actual: Box::new(token()),
+            }),
+        );
+
+        let mut wrong_type = offer.clone();
+        wrong_type.tx.inputs[0].script = script_sig(SigHashType::ALL_FORKID);
+        assert_eq!(
+            wrong_type.validate(&terms),
+            Err(SwapError::WrongSigHashType {
+                actual: Some(SigHashType::ALL_FORKID),
+            }),
+        );
+        let mut unsigned = offer.clone();
+        unsigned.tx.inputs[0].script = Script::default();
+        assert_eq!(unsigned.validate(&terms), Err(SwapError::UnsignedOffer));
+        let mut other_coin = offer.clone();
+        other_coin.coin.output.script = Script::p2pkh(&ShaRmd160([9; 20]));
+        assert_eq!(
+            other_coin.validate(&terms),
+            Err(SwapError::ScriptSigMismatch),
+        );
+        let mut dust = offer.clone();
+        dust.tx.outputs[0].value = 100;
+        assert_eq!(
+            dust.validate(&terms),
+            Err(SwapError::DustPayment {
+                value: 100,
+                min_value: 546,
+            }),
+        );
+        let mut no_token = offer;
+        no_token.coin.output.token = None;
+        assert_eq!(no_token.validate(&terms), Err(SwapError::NoOfferedToken));
+    }
+}
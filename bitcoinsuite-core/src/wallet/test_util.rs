@@ -0,0 +1,85 @@
+// Copyright (c) 2023 The Bitcoin developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+//! Fixtures shared by tests building txs, e.g. of the wallet.
+
+use bytes::Bytes;
+
+use crate::{
+    hash::ShaRmd160,
+    script::Script,
+    ser::CompactUint,
+    tx::{
+        Capability, CashToken, Coin, Commitment, Input, NonFungibleTokenCapability, OutPoint,
+        Output, Transaction, TxId, UnlockingTemplate, NFT,
+    },
+    wallet::{TxBuilderInput, UnsignedTx},
+};
+
+/// P2PKH script of the test wallet.
+pub(crate) fn script() -> Script {
+    Script::p2pkh(&ShaRmd160([1; 20]))
+}
+
+/// Token category of the tests.
+pub(crate) fn category() -> TxId {
+    TxId::from([7; 32])
+}
+
+/// Token of [`category`] with `amount` fungible tokens and an optional NFT
+/// with the given capability and commitment.
+pub(crate) fn token(amount: u64, nft: Option<(Capability, &[u8])>) -> Option<CashToken> {
+    Some(CashToken {
+        amount: CompactUint(amount),
+        category: category(),
+        nft: nft.map(|(capability, commitment)| NFT {
+            capability: NonFungibleTokenCapability(capability),
+            commitment: Commitment(Bytes::copy_from_slice(commitment)),
+        }),
+    })
+}
+
+/// Version 2 tx with the given inputs and outputs and no locktime.
+pub(crate) fn tx(inputs: Vec<Input>, outputs: Vec<Output>) -> Transaction {
+    Transaction {
+        version: 2,
+        inputs,
+        outputs,
+        locktime: 0,
+    }
+}
+
+/// Output of `value` sats to `script`, without a token.
+pub(crate) fn output(value: u64, script: Script) -> Output {
+    Output {
+        value,
+        script,
+        token: None,
+    }
+}
+
+/// Coin of the test wallet, the first output of tx `[txid; 32]`.
+pub(crate) fn input(txid: u8, value: u64, token: Option<CashToken>) -> TxBuilderInput {
+    TxBuilderInput {
+        prev_out: OutPoint {
+            txid: TxId::from([txid; 32]),
+            outpoint_index: 0,
+        },
+        sequence: 0xffff_ffff,
+        coin: Coin {
+            output: Output {
+                value,
+                script: script(),
+                token,
+            },
+            ..Default::default()
+        },
+        template: UnlockingTemplate::P2pkhSchnorr,
+    }
+}
+
+/// Signer with a dummy 100-byte scriptSig for every input.
+pub(crate) fn sign(_: &UnsignedTx, _: usize) -> Result<Script, ()> {
+    Ok(Script::new(vec![0; 100].into()))
+}
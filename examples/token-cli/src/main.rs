@@ -1,10 +1,16 @@
-use bitcoinsuite_core::{ser::BitcoinSer, tx::*};
+use bitcoinsuite_core::tx::*;
 use bytes::Bytes;
 use clap::{command, Arg, ArgAction};
 
 fn main() {
     let matches = command!() // requires `cargo` feature
         .arg(Arg::new("txtokens").action(ArgAction::Append))
+        .arg(
+            Arg::new("lenient")
+                .long("lenient")
+                .action(ArgAction::SetTrue)
+                .help("Decode malformed txs as far as possible and print warnings"),
+        )
         .get_matches();
 
     let args = matches
@@ -12,14 +18,21 @@ fn main() {
         .unwrap_or_default()
         .map(|v| v.as_str())
         .collect::<Vec<_>>();
-    let tx_hex = hex::decode(&args[1]).unwrap();
-    let bytes = &mut Bytes::from(tx_hex);
-    let res = Transaction::deser(bytes);
-    let tx = res.unwrap();
+    let tx_hex = hex::decode(args[1]).unwrap();
+    let ctx = if matches.get_flag("lenient") {
+        DecodeContext::lenient()
+    } else {
+        DecodeContext::default()
+    };
+    let decoded = Transaction::decode(Bytes::from(tx_hex), &ctx).unwrap();
+    for warning in &decoded.warnings {
+        eprintln!("warning: {:?}", warning);
+    }
+    let tx = decoded.value;
 
     tx.outputs.iter().for_each(|x| {
-        if x.token.is_some() {
-            print!("\n{:#?}\n", x.token.as_ref().unwrap());
+        if let Some(token) = &x.token {
+            print!("\n{:#?}\n", token);
         } else {
             print!("");
         }
@@ -28,73 +28,66 @@ mod tests {
 
         for item in token_data.iter() {
             for (key, value) in item.as_object().unwrap() {
-                match key.as_str() {
-                    "prefix" => {
-                        assert!(&value.is_string());
-                        //Decodes a hex string into raw bytes
-                        let token_bytes = hex::decode(value.as_str().unwrap());
-                        let token_prefix = Vec::from(token_bytes.unwrap());
-                        tokens_prefix_vec.push(token_prefix);
-                    }
-                    _ => (),
+                if key.as_str() == "prefix" {
+                    assert!(&value.is_string());
+                    //Decodes a hex string into raw bytes
+                    let token_bytes = hex::decode(value.as_str().unwrap());
+                    let token_prefix = token_bytes.unwrap();
+                    tokens_prefix_vec.push(token_prefix);
                 };
-                match key.as_str() {
-                    "data" => {
-                        let amount: u64 = value["amount"].as_str().unwrap().parse().unwrap();
-
-                        let category = value["category"].clone().as_str().unwrap().to_string();
-                        let category = Sha256d::from_be_hex(&category);
-                        let nft = value["nft"].clone();
-                        let commitment = nft["commitment"].clone();
-                        let commitment = match commitment {
-                            Value::String(data) => data,
-                            _ => "".to_string(),
+                if key.as_str() == "data" {
+                    let amount: u64 = value["amount"].as_str().unwrap().parse().unwrap();
+
+                    let category = value["category"].clone().as_str().unwrap().to_string();
+                    let category = Sha256d::from_be_hex(&category);
+                    let nft = value["nft"].clone();
+                    let commitment = nft["commitment"].clone();
+                    let commitment = match commitment {
+                        Value::String(data) => data,
+                        _ => "".to_string(),
+                    };
+
+                    let commitment = hex::decode(commitment);
+                    let commitment =
+                        Commitment(Bytes::copy_from_slice(commitment.unwrap().as_ref()));
+
+                    let capability = nft["capability"].clone();
+
+                    let capability = match capability.as_str() {
+                        Some("none") => NonFungibleTokenCapability(Capability::None),
+                        Some("mutable") => NonFungibleTokenCapability(Capability::Mutable),
+                        Some("minting") => NonFungibleTokenCapability(Capability::Minting),
+
+                        //This Works for now.
+                        _ => NonFungibleTokenCapability(Capability::None),
+                    };
+
+                    if nft.is_null() {
+                        let cashtoken = CashToken {
+                            amount: CompactUint(amount),
+                            category: TxId::from(category.unwrap()),
+                            nft: None,
                         };
-
-                        let commitment = hex::decode(commitment);
-                        let commitment =
-                            Commitment(Bytes::copy_from_slice(commitment.unwrap().as_ref()));
-
-                        let capability = nft["capability"].clone();
-
-                        let capability = match capability.as_str() {
-                            Some("none") => NonFungibleTokenCapability(Capability::None),
-                            Some("mutable") => NonFungibleTokenCapability(Capability::Mutable),
-                            Some("minting") => NonFungibleTokenCapability(Capability::Minting),
-
-                            //This Works for now.
-                            _ => NonFungibleTokenCapability(Capability::None),
+                        cashtoken_vec.push(cashtoken.clone());
+                        tokens_data_vec.push(cashtoken.ser().to_vec());
+                        
+                    } else {
+                        let nft = NFT {
+                            commitment,
+                            capability,
                         };
-
-                        if nft.is_null() == true {
-                            let cashtoken = CashToken {
-                                amount: CompactUint(amount),
-                                category: TxId::from(category.unwrap()),
-                                nft: None,
-                            };
-                            cashtoken_vec.push(cashtoken.clone());
-                            tokens_data_vec.push(cashtoken.ser().to_vec());
-                            
-                        } else {
-                            let nft = NFT {
-                                commitment,
-                                capability,
-                            };
-                            let cashtoken = CashToken {
-                                amount: CompactUint(amount),
-                                category: TxId::from(category.unwrap()),
-                                nft: Some(nft),
-                            };
-                            cashtoken_vec.push(cashtoken.clone());
-                            tokens_data_vec.push(cashtoken.ser().to_vec());
-                        }
+                        let cashtoken = CashToken {
+                            amount: CompactUint(amount),
+                            category: TxId::from(category.unwrap()),
+                            nft: Some(nft),
+                        };
+                        cashtoken_vec.push(cashtoken.clone());
+                        tokens_data_vec.push(cashtoken.ser().to_vec());
                     }
-
-                    _ => (),
                 };
             }
         }
-        assert_eq!(tokens_prefix_vec.iter().eq(tokens_data_vec.iter()), true);
+        assert!(tokens_prefix_vec.iter().eq(tokens_data_vec.iter()));
 
         assert_eq!(tokens_data_vec[60], tokens_prefix_vec[60]);
 
@@ -123,16 +116,13 @@ mod tests {
         for item in token_data.iter() {
             for (key, value) in item.as_object().unwrap() {
                 /* let _prefixes =  */
-                match key.as_str() {
-                    "prefix" => {
-                        assert!(&value.is_string());
+                if key.as_str() == "prefix" {
+                    assert!(&value.is_string());
 
-                        let token_bytes = hex::decode(value.as_str().unwrap());
-                        let token_prefix = Vec::from(token_bytes.unwrap());
+                    let token_bytes = hex::decode(value.as_str().unwrap());
+                    let token_prefix = token_bytes.unwrap();
 
-                        tokens_prefix_vec.push(token_prefix);
-                    }
-                    _ => (),
+                    tokens_prefix_vec.push(token_prefix);
                 };
             }
         }
@@ -142,7 +132,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[0];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::NoTokens {
@@ -157,7 +147,7 @@ mod tests {
 
         let token = &tokens_prefix_vec[1];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err()
@@ -172,7 +162,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[2];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err()
@@ -187,7 +177,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[3];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err()
@@ -202,7 +192,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[4];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidPrefixEncoding {
@@ -215,7 +205,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[5];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidPrefixEncoding {
@@ -228,7 +218,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[6];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err()
@@ -242,7 +232,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[7];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
                 expected:
@@ -258,7 +248,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[8];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -274,7 +264,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[9];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -290,7 +280,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[10];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -306,7 +296,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[11];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -322,7 +312,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[12];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -339,7 +329,7 @@ mod tests {
         { */
         let token = &tokens_prefix_vec[13];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -355,7 +345,7 @@ mod tests {
         },*/
         let token = &tokens_prefix_vec[14];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -371,7 +361,7 @@ mod tests {
         },*/
         let token = &tokens_prefix_vec[15];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -387,7 +377,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[16];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -403,7 +393,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[17];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -419,7 +409,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[18];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -435,7 +425,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[19];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err() == DataError::InvalidCapability {
@@ -451,7 +441,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[20];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(res.unwrap_err() == DataError::CapabilityWithoutNft);
         /*   {
@@ -460,7 +450,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[21];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(res.unwrap_err() == DataError::CapabilityWithoutNft);
         /* {
           "prefix": "efbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb40",
@@ -468,7 +458,7 @@ mod tests {
         }, */
         let token = &tokens_prefix_vec[22];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::CommitmentWithoutNft {
@@ -482,7 +472,7 @@ mod tests {
 
         let token = &tokens_prefix_vec[23];
 
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::CommitmentWithoutNft {
@@ -494,7 +484,7 @@ mod tests {
           "error": "Invalid token prefix: invalid non-fungible token commitment. Error reading CompactUint-prefixed bin: invalid CompactUint. Error reading CompactUint: requires at least one byte."
         }, */
         let token = &tokens_prefix_vec[24];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         // May need to update to CompactUint Error
         assert!(
@@ -508,7 +498,7 @@ mod tests {
           "error": "Invalid token prefix: invalid non-fungible token commitment. Error reading CompactUint-prefixed bin: invalid CompactUint. Error reading CompactUint: requires at least one byte."
         }, */
         let token = &tokens_prefix_vec[25];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::CommitmentLengthZero {
@@ -520,7 +510,7 @@ mod tests {
           "error": "Invalid token prefix: invalid non-fungible token commitment. Error reading CompactUint-prefixed bin: invalid CompactUint. Error reading CompactUint: requires at least one byte."
         }, */
         let token = &tokens_prefix_vec[26];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err()
@@ -533,7 +523,7 @@ mod tests {
           "error": "Invalid token prefix: invalid non-fungible token commitment. Error reading CompactUint-prefixed bin: invalid CompactUint. Error reading CompactUint: CompactUint is not minimally encoded. Value: 1, encoded length: 3, canonical length: 1"
         }, */
         let token = &tokens_prefix_vec[27];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidCompactUint {
@@ -547,7 +537,7 @@ mod tests {
           "error": "Invalid token prefix: invalid non-fungible token commitment. Error reading CompactUint-prefixed bin: invalid CompactUint. Error reading CompactUint: CompactUint is not minimally encoded. Value: 1, encoded length: 5, canonical length: 1"
         }, */
         let token = &tokens_prefix_vec[28];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         
         assert!(
             res.unwrap_err()
@@ -561,7 +551,7 @@ mod tests {
           "error": "Invalid token prefix: invalid non-fungible token commitment. Error reading CompactUint-prefixed bin: invalid CompactUint. Error reading CompactUint: CompactUint is not minimally encoded. Value: 1, encoded length: 9, canonical length: 1"
         }, */
         let token = &tokens_prefix_vec[29];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
 
         assert!(
             res.unwrap_err()
@@ -575,7 +565,7 @@ mod tests {
           "error": "Invalid token prefix: if encoded, commitment length must be greater than 0."
         }, */
         let token = &tokens_prefix_vec[30];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::CommitmentLengthZero {
@@ -589,7 +579,7 @@ mod tests {
           "error": "Invalid token prefix: if encoded, commitment length must be greater than 0."
         }, */
         let token = &tokens_prefix_vec[31];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::CommitmentLengthZero {
@@ -603,7 +593,7 @@ mod tests {
           "error": "Invalid token prefix: invalid non-fungible token commitment. Error reading CompactUint-prefixed bin: insufficient bytes. Required bytes: 1, remaining bytes: 0"
         }, */
         let token = &tokens_prefix_vec[32];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -616,7 +606,7 @@ mod tests {
           "error": "Invalid token prefix: invalid non-fungible token commitment. Error reading CompactUint-prefixed bin: insufficient bytes. Required bytes: 1, remaining bytes: 0"
         }, */
         let token = &tokens_prefix_vec[33];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -629,7 +619,7 @@ mod tests {
           "error": "Invalid token prefix: invalid non-fungible token commitment. Error reading CompactUint-prefixed bin: insufficient bytes. Required bytes: 2, remaining bytes: 1"
         }, */
         let token = &tokens_prefix_vec[34];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -643,7 +633,7 @@ mod tests {
         }, */
 
         let token = &tokens_prefix_vec[35];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -656,7 +646,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: requires at least one byte."
         }, */
         let token = &tokens_prefix_vec[36];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidAmountEncoding {
@@ -668,7 +658,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: insufficient bytes. CompactUint prefix 253 requires at least 3 bytes. Remaining bytes: 2"
         }, */
         let token = &tokens_prefix_vec[37];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -681,7 +671,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: insufficient bytes. CompactUint prefix 254 requires at least 5 bytes. Remaining bytes: 4"
         }, */
         let token = &tokens_prefix_vec[38];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -694,7 +684,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: insufficient bytes. CompactUint prefix 255 requires at least 9 bytes. Remaining bytes: 8"
         }, */
         let token = &tokens_prefix_vec[39];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -707,7 +697,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: requires at least one byte."
         }, */
         let token = &tokens_prefix_vec[40];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -720,7 +710,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: insufficient bytes. CompactUint prefix 253 requires at least 3 bytes. Remaining bytes: 2"
         }, */
         let token = &tokens_prefix_vec[41];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -733,7 +723,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: insufficient bytes. CompactUint prefix 254 requires at least 5 bytes. Remaining bytes: 4"
         }, */
         let token = &tokens_prefix_vec[42];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -746,7 +736,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: insufficient bytes. CompactUint prefix 255 requires at least 9 bytes. Remaining bytes: 8"
         }, */
         let token = &tokens_prefix_vec[43];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidLength {
@@ -759,7 +749,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: requires at least one byte."
         }, */
         let token = &tokens_prefix_vec[44];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidAmountEncoding { error: "Error reading CompactUint: requires at least one byte".to_string() });
@@ -768,7 +758,7 @@ mod tests {
           "error": "Invalid token prefix: if encoded, fungible token amount must be greater than 0."
         }, */
         let token = &tokens_prefix_vec[45];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidAmountEncoding {
@@ -780,7 +770,7 @@ mod tests {
           "error": "Invalid token prefix: if encoded, fungible token amount must be greater than 0."
         }, */
         let token = &tokens_prefix_vec[46];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidAmountEncoding {
@@ -792,7 +782,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: CompactUint is not minimally encoded. Value: 1, encoded length: 3, canonical length: 1"
         }, */
         let token = &tokens_prefix_vec[47];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidCompactUint {
@@ -805,7 +795,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: CompactUint is not minimally encoded. Value: 1, encoded length: 5, canonical length: 1"
         }, */
         let token = &tokens_prefix_vec[48];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidCompactUint {
@@ -818,7 +808,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: CompactUint is not minimally encoded. Value: 1, encoded length: 9, canonical length: 1"
         }, */
         let token = &tokens_prefix_vec[49];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidCompactUint {
@@ -831,7 +821,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: CompactUint is not minimally encoded. Value: 1, encoded length: 3, canonical length: 1"
         }, */
         let token = &tokens_prefix_vec[50];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidCompactUint {
@@ -844,7 +834,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: CompactUint is not minimally encoded. Value: 1, encoded length: 5, canonical length: 1"
         }, */
         let token = &tokens_prefix_vec[51];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidCompactUint {
@@ -857,7 +847,7 @@ mod tests {
           "error": "Invalid token prefix: invalid fungible token amount encoding. Error reading CompactUint: CompactUint is not minimally encoded. Value: 1, encoded length: 9, canonical length: 1"
         }, */
         let token = &tokens_prefix_vec[52];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidCompactUint {
@@ -870,7 +860,7 @@ mod tests {
           "error": "Invalid token prefix: exceeds maximum fungible token amount of 9223372036854775807. Encoded amount: 9223372036854775808"
         }, */
         let token = &tokens_prefix_vec[53];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidAmountEncoding { 
@@ -882,7 +872,7 @@ mod tests {
         "error": "Invalid token prefix: exceeds maximum fungible token amount of 9223372036854775807. Encoded amount: 9223372036854775808"
         } */
         let token = &tokens_prefix_vec[54];
-        let res = CashToken::deser(&mut Bytes::copy_from_slice(&token));
+        let res = CashToken::deser(&mut Bytes::copy_from_slice(token));
         assert!(
             res.unwrap_err()
                 == DataError::InvalidAmountEncoding { 